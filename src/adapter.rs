@@ -1,7 +1,7 @@
 use anyhow::Result;
 use std::sync::Arc;
 
-use crate::nm::NMClient;
+use crate::backend::Backend;
 use ratatui::{
     Frame,
     layout::{Alignment, Constraint, Direction, Flex, Layout},
@@ -24,7 +24,7 @@ pub struct Adapter {
 
 impl Adapter {
     pub async fn new(
-        client: Arc<NMClient>,
+        client: Arc<dyn Backend>,
         device_path: String,
         config: Arc<Config>,
     ) -> Result<Self> {
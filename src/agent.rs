@@ -1,15 +1,47 @@
 use async_channel::{Receiver, Sender};
-use std::sync::{Arc, atomic::AtomicBool};
-use tokio::sync::mpsc::UnboundedSender;
+use std::sync::{Arc, Mutex, atomic::AtomicBool};
+use tokio::sync::{mpsc::UnboundedSender, oneshot};
 
 use crate::event::Event;
 
+/// Where the answer to one password prompt goes. Every VPN secret request
+/// gets its own, so answering or cancelling one prompt can never reach a
+/// request queued behind it, or the WiFi flows' shared channels.
+#[derive(Debug, Clone)]
+pub struct SecretReply(Arc<Mutex<Option<oneshot::Sender<String>>>>);
+
+impl SecretReply {
+    /// A reply and the receiver its answer arrives on. The receiver fails
+    /// once the reply is cancelled or dropped unanswered.
+    pub fn new() -> (Self, oneshot::Receiver<String>) {
+        let (tx, rx) = oneshot::channel();
+        (Self(Arc::new(Mutex::new(Some(tx)))), rx)
+    }
+
+    /// Answers the request; later calls do nothing.
+    pub fn send(&self, secret: String) {
+        if let Some(tx) = self.take() {
+            let _ = tx.send(secret);
+        }
+    }
+
+    /// Answers the request with nothing.
+    pub fn cancel(&self) {
+        drop(self.take());
+    }
+
+    fn take(&self) -> Option<oneshot::Sender<String>> {
+        self.0.lock().map_or(None, |mut tx| tx.take())
+    }
+}
+
 /// Authentication agent for handling credential requests
 ///
-/// In NetworkManager, unlike iwd, we don't need to implement a D-Bus agent interface.
-/// Instead, credentials are collected from the user and passed to NetworkManager
-/// when creating/activating connections. This agent struct provides the coordination
-/// mechanism for the UI to collect and provide credentials.
+/// WiFi credentials are collected from the user and passed to NetworkManager
+/// when creating/activating connections. VPN secrets are asked for by
+/// NetworkManager itself, through the secret agent in `nm::agent`. This agent
+/// struct provides the coordination mechanism for the UI to collect and
+/// provide credentials either way.
 #[derive(Debug, Clone)]
 pub struct AuthAgent {
     pub tx_cancel: Sender<()>,
@@ -82,22 +114,40 @@ impl AuthAgent {
         Ok(())
     }
 
-    /// Request password from user (with optional pre-filled username)
+    /// Request one secret from user (with optional pre-filled username, and a
+    /// message such as the server's one-time code challenge). The answer goes
+    /// to `reply` rather than the shared passphrase channel.
     pub fn request_password(
         &self,
         network_name: String,
         user_name: Option<String>,
+        message: Option<String>,
+        label: String,
+        reply: SecretReply,
     ) -> anyhow::Result<()> {
         self.password_required
             .store(true, std::sync::atomic::Ordering::Relaxed);
 
         self.event_sender
-            .send(Event::AuthRequestPassword((network_name, user_name)))
+            .send(Event::AuthRequestPassword {
+                network_name,
+                user_name,
+                message,
+                label,
+                reply,
+            })
             .map_err(|e| anyhow::anyhow!("Failed to send auth event: {}", e))?;
 
         Ok(())
     }
 
+    /// Close the password prompt after the request behind it was withdrawn
+    pub fn close_password_prompt(&self) {
+        self.password_required
+            .store(false, std::sync::atomic::Ordering::Relaxed);
+        let _ = self.event_sender.send(Event::AuthRequestWithdrawn);
+    }
+
     /// Wait for passphrase response with cancellation support
     pub async fn wait_for_passphrase(&self) -> Option<String> {
         tokio::select! {
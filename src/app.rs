@@ -1,10 +1,9 @@
+use crate::backend::{self, Backend, BackendKind, Snapshot, Watch};
+use crate::nm::{EthernetInfo, Mode, PrimaryLink};
 use anyhow::{Result, anyhow};
 use ratatui::widgets::Row;
 use std::sync::Arc;
 use tokio::sync::mpsc::UnboundedSender;
-use zbus::zvariant::OwnedObjectPath;
-
-use crate::nm::{EthernetInfo, Mode, NMClient, NmSnapshot, PrimaryLink};
 
 use crate::{
     adapter::Adapter, agent::AuthAgent, config::Config, device::Device, doctor::DoctorModal,
@@ -36,25 +35,25 @@ pub enum FocusedBlock {
     Vpn,
 }
 
-/// A lightweight handle to a WiFi adapter known to NetworkManager.
+/// A lightweight handle to a WiFi adapter known to the backend.
 ///
 /// Holding the path + human name avoids re-fetching the interface name every
 /// render and lets the Device block list adapters without instantiating a full
 /// `Device` per row.
 #[derive(Debug, Clone)]
 pub struct AdapterSummary {
-    pub path: OwnedObjectPath,
+    pub path: String,
     pub name: String,
 }
 
 impl AdapterSummary {
-    /// Every WiFi adapter described by `snapshot`, in NetworkManager's order.
-    fn all(snapshot: &NmSnapshot) -> Vec<Self> {
+    /// Every WiFi adapter described by `snapshot`, in the backend's order.
+    fn all(snapshot: &dyn Snapshot) -> Vec<Self> {
         snapshot
             .wifi_devices()
             .into_iter()
             .map(|path| {
-                let name = snapshot.device_interface(path.as_str()).unwrap_or_default();
+                let name = snapshot.device_interface(&path).unwrap_or_default();
                 Self { path, name }
             })
             .collect()
@@ -139,7 +138,10 @@ pub struct App {
     pub running: bool,
     pub focused_block: FocusedBlock,
     pub notifications: Vec<Notification>,
-    pub client: Arc<NMClient>,
+    pub client: Arc<dyn Backend>,
+    /// The backend's state, kept current by its change notifications. Each
+    /// change queues an `Event::NmChanged`, which drives [`App::tick`].
+    pub watcher: Box<dyn Watch>,
     pub adapter: Adapter,
     pub device: Device,
     pub adapters: Vec<AdapterSummary>,
@@ -177,27 +179,50 @@ impl App {
         config: Arc<Config>,
         mode: Mode,
     ) -> Result<Self> {
-        let client = {
-            match NMClient::new().await {
-                Ok(client) => Arc::new(client),
-                Err(e) => {
-                    return Err(anyhow!(
-                        "Can not access the NetworkManager service.
-Error: {}",
-                        e
-                    ));
-                }
+        let kind = BackendKind::try_from(config.backend.as_str())?;
+        let client = match backend::connect(kind).await {
+            Ok(client) => client,
+            Err(e) => {
+                return Err(anyhow!(
+                    "Can not access the network service.
+Error: {:#}",
+                    e
+                ));
             }
         };
 
+        Self::with_backend(sender, client, config, mode).await
+    }
+
+    /// Builds the app on top of `client`. [`App::new`] passes the configured
+    /// service; tests pass an in-memory backend.
+    pub async fn with_backend(
+        sender: UnboundedSender<Event>,
+        client: Arc<dyn Backend>,
+        config: Arc<Config>,
+        mode: Mode,
+    ) -> Result<Self> {
+        let watcher = {
+            let sender = sender.clone();
+            client
+                .watch(Box::new(move || {
+                    let _ = sender.send(Event::NmChanged);
+                }))
+                .await?
+        };
+
         // One snapshot answers the adapter list and the wired-link state, so
         // the station is built already knowing both.
-        let snapshot = NmSnapshot::fetch(&client).await?;
-        let adapters = AdapterSummary::all(&snapshot);
+        let snapshot = watcher.snapshot().await;
+        let adapters = AdapterSummary::all(&*snapshot);
         if adapters.is_empty() {
             return Err(anyhow!("No WiFi device found"));
         }
-        let ethernet = client.active_ethernet(&snapshot).await.unwrap_or(None);
+        let ethernet = client
+            .active_ethernet(snapshot.clone())
+            .await
+            .unwrap_or(None);
+        drop(snapshot);
 
         let active_index = 0;
         let mut device = Device::new(
@@ -211,13 +236,22 @@ Error: {}",
             match Adapter::new(client.clone(), device.device_path.clone(), config.clone()).await {
                 Ok(v) => v,
                 Err(e) => {
-                    return Err(anyhow!("Can not access the NetworkManager service: {}", e));
+                    return Err(anyhow!("Can not access the network service: {}", e));
                 }
             };
 
         device.set_mode(mode, ethernet.is_some()).await?;
 
         let agent = AuthAgent::new(sender);
+        // Without the agent, VPN profiles that need a password or a one-time
+        // code fail to come up, but everything else still works.
+        if let Err(e) = client.register_secret_agent(agent.clone()).await {
+            Notification::send(
+                format!("VPN password prompts are unavailable: {e}"),
+                crate::notification::NotificationLevel::Warning,
+                &agent.event_sender,
+            )?;
+        }
 
         let focused_block = Self::default_focus_for(&device);
 
@@ -228,6 +262,7 @@ Error: {}",
             focused_block,
             notifications: Vec::new(),
             client,
+            watcher,
             adapter,
             agent,
             reset,
@@ -253,6 +288,16 @@ Error: {}",
         Self::default_focus_for(&self.device)
     }
 
+    /// Focus to return to when a password prompt closes: the VPN modal while
+    /// it is open, as that is where VPN activations ask for secrets.
+    pub fn focus_after_prompt(&self) -> FocusedBlock {
+        if self.vpn.is_some() {
+            FocusedBlock::Vpn
+        } else {
+            FocusedBlock::KnownNetworks
+        }
+    }
+
     pub fn adapter_count(&self) -> usize {
         self.adapters.len()
     }
@@ -305,7 +350,7 @@ Error: {}",
     // after this returns Ok, so `self` stays consistent on failure.
     async fn activate_device(
         &mut self,
-        path: OwnedObjectPath,
+        path: String,
         preserve_mode: Mode,
         is_ethernet_connected: bool,
     ) -> Result<()> {
@@ -327,12 +372,10 @@ Error: {}",
         Ok(())
     }
 
-    pub async fn reset(mode: Mode) -> Result<()> {
-        let client = {
-            match NMClient::new().await {
-                Ok(client) => Arc::new(client),
-                Err(e) => return Err(anyhow!("Can not access the NetworkManager service: {}", e)),
-            }
+    pub async fn reset(mode: Mode, kind: BackendKind) -> Result<()> {
+        let client = match backend::connect(kind).await {
+            Ok(client) => client,
+            Err(e) => return Err(anyhow!("Can not access the network service: {:#}", e)),
         };
 
         let device_paths = client.get_wifi_devices().await?;
@@ -341,16 +384,16 @@ Error: {}",
             .next()
             .ok_or_else(|| anyhow!("No WiFi device found"))?;
 
-        let snapshot = NmSnapshot::fetch(&client).await?;
+        let snapshot = client.snapshot().await?;
         let is_ethernet_connected = client
-            .active_ethernet(&snapshot)
+            .active_ethernet(snapshot.clone())
             .await
             .unwrap_or(None)
             .is_some();
 
         let mut device = match Device::new(client.clone(), path, is_ethernet_connected).await {
             Ok(v) => v,
-            Err(e) => return Err(anyhow!("Can not access the NetworkManager service: {}", e)),
+            Err(e) => return Err(anyhow!("Can not access the network service: {}", e)),
         };
 
         device.set_mode(mode, is_ethernet_connected).await?;
@@ -366,25 +409,28 @@ Error: {}",
         self.notifications.retain(|n| !n.is_expired());
     }
 
+    /// Re-derives every view from the backend's current state. Runs
+    /// when the watcher reports a change rather than on a timer, so an idle
+    /// machine costs nothing.
     pub async fn tick(&mut self) -> Result<()> {
-        // One read of NetworkManager's object graph serves every refresh below,
-        // so they all describe the same instant and cost one round trip between
-        // them instead of one per object.
-        let snapshot = NmSnapshot::fetch(&self.client).await?;
+        // One read of the watched state serves every refresh below, so
+        // they all describe the same instant. It is an immutable copy:
+        // changes arriving during the awaits below go into the next one.
+        let snapshot = self.watcher.snapshot().await;
 
         // Resolved before anything below can build a station, so a station born
         // during this tick starts with the right wired state instead of a
         // default it would carry until the next one.
         let ethernet = self
             .client
-            .active_ethernet(&snapshot)
+            .active_ethernet(snapshot.clone())
             .await
             .unwrap_or_else(|_| self.ethernet.clone());
 
         // Refresh the adapter list; if the active path disappeared, fall back to
         // the first remaining device. `adapters` is only committed after any
         // fallible activation so `self` stays consistent on error.
-        let current = AdapterSummary::all(&snapshot);
+        let current = AdapterSummary::all(&*snapshot);
         let paths_changed = current.len() != self.adapters.len()
             || current
                 .iter()
@@ -400,7 +446,7 @@ Error: {}",
                 .map(|a| a.path.clone());
 
             let active_path = self.device.device_path.clone();
-            match current.iter().position(|a| a.path.as_str() == active_path) {
+            match current.iter().position(|a| a.path == active_path) {
                 Some(idx) => {
                     self.active_index = idx;
                     self.adapters = current;
@@ -423,22 +469,22 @@ Error: {}",
                 .unwrap_or(self.active_index);
         }
 
-        self.device.refresh(&snapshot, ethernet.is_some()).await?;
+        self.device.refresh(&*snapshot, ethernet.is_some()).await?;
 
         // Keep the VPN modal's on/off state live while it's open.
         if let Some(modal) = &mut self.vpn {
-            modal.refresh(&self.client).await?;
+            modal.refresh(self.client.as_ref()).await?;
         }
 
         // Refresh the always-on VPN badge.
-        self.active_vpns = self.client.active_vpn_names(&snapshot);
+        self.active_vpns = snapshot.active_vpn_names();
 
         // Wired status is tracked here rather than on the WiFi device so it
         // stays visible when the radio is off.
         self.set_ethernet(ethernet);
 
         // Refresh which link owns the default route (the live internet path).
-        self.primary_link = self.client.primary_link(&snapshot);
+        self.primary_link = snapshot.primary_link();
 
         Ok(())
     }
@@ -457,3 +503,51 @@ Error: {}",
         self.running = false;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::fake::FakeBackend;
+    use crate::nm::SecurityType;
+    use tokio::sync::mpsc::unbounded_channel;
+
+    fn new_networks(app: &App) -> Vec<&str> {
+        let station = app.device.station.as_ref().expect("station mode");
+        station
+            .new_networks
+            .iter()
+            .map(|(n, _)| n.name.as_str())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn runs_on_an_in_memory_backend() {
+        let fake = FakeBackend::default();
+        let device = fake.add_wifi_device("wlan0");
+        fake.add_access_point(&device, "cafe", 50, SecurityType::Open);
+        let (tx, mut rx) = unbounded_channel();
+
+        let mut app = App::with_backend(
+            tx,
+            Arc::new(fake.clone()),
+            Arc::new(Config::default()),
+            Mode::Station,
+        )
+        .await
+        .unwrap();
+        assert_eq!(app.device.name, "wlan0");
+        assert_eq!(new_networks(&app), ["cafe"]);
+
+        // Changes reach the app the way NetworkManager's would: a notification,
+        // then a tick.
+        while rx.try_recv().is_ok() {}
+        fake.add_access_point(&device, "library", 70, SecurityType::WPA2);
+        fake.add_wifi_device("wlan1");
+        assert!(matches!(rx.try_recv(), Ok(Event::NmChanged)));
+        app.tick().await.unwrap();
+
+        assert_eq!(new_networks(&app), ["library", "cafe"]);
+        let adapters: Vec<&str> = app.adapters.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(adapters, ["wlan0", "wlan1"]);
+    }
+}
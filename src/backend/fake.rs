@@ -0,0 +1,581 @@
+//! An in-memory [`Backend`] for unit tests. Activations succeed at once, and
+//! every change is announced to watchers synchronously, so a test can build a
+//! `Station`, `VpnModal` or `App` and assert on it without a bus.
+
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+
+use super::{Backend, EnterpriseCredentials, SharedSnapshot, Snapshot, Watch};
+use crate::nm::{
+    AccessPointInfo, ActivationOutcome, ActiveConnectionInfo, ActiveConnectionState,
+    ConnectionInfo, DeviceState, LinkKind, OvpnConfig, SecurityType, VpnConnectionInfo, VpnKind,
+    WgConfig, WifiMode,
+};
+
+#[derive(Clone)]
+struct Device {
+    path: String,
+    interface: String,
+    state: DeviceState,
+    access_points: Vec<AccessPointInfo>,
+    active_access_point: Option<String>,
+}
+
+/// Everything the fake knows. Cloned whole to serve as a snapshot.
+#[derive(Clone)]
+struct State {
+    wireless_enabled: bool,
+    devices: Vec<Device>,
+    wifi: Vec<ConnectionInfo>,
+    psks: Vec<(String, String)>,
+    vpns: Vec<VpnConnectionInfo>,
+    /// The config each imported WireGuard profile was saved with.
+    tunnels: Vec<(String, WgConfig)>,
+    active: Vec<ActiveConnectionInfo>,
+    hotspots: Vec<String>,
+    scans: usize,
+    next_id: usize,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            wireless_enabled: true,
+            devices: Vec::new(),
+            wifi: Vec::new(),
+            psks: Vec::new(),
+            vpns: Vec::new(),
+            tunnels: Vec::new(),
+            active: Vec::new(),
+            hotspots: Vec::new(),
+            scans: 0,
+            next_id: 0,
+        }
+    }
+}
+
+impl State {
+    fn next_path(&mut self, kind: &str) -> String {
+        self.next_id += 1;
+        format!("/fake/{}/{}", kind, self.next_id)
+    }
+
+    fn device(&self, path: &str) -> Option<&Device> {
+        self.devices.iter().find(|d| d.path == path)
+    }
+
+    fn device_mut(&mut self, path: &str) -> Result<&mut Device> {
+        self.devices
+            .iter_mut()
+            .find(|d| d.path == path)
+            .with_context(|| format!("No device {}", path))
+    }
+
+    fn add_wifi_profile(&mut self, ssid: &str, security: SecurityType, hidden: bool) -> String {
+        let path = self.next_path("profile");
+        self.wifi.push(ConnectionInfo {
+            path: path.clone(),
+            id: ssid.to_string(),
+            uuid: path.clone(),
+            ssid: ssid.to_string(),
+            autoconnect: true,
+            timestamp: 0,
+            hidden,
+            security,
+        });
+        path
+    }
+
+    /// Brings `profile` up on `device` (`/` for none) and returns the active
+    /// connection.
+    fn activate(&mut self, profile: &str, device: &str) -> Result<String> {
+        let (id, uuid, kind) = if let Some(wifi) = self.wifi.iter().find(|c| c.path == profile) {
+            (wifi.id.clone(), wifi.uuid.clone(), "802-11-wireless")
+        } else if let Some(vpn) = self.vpns.iter().find(|c| c.path == profile) {
+            (vpn.id.clone(), vpn.uuid.clone(), "wireguard")
+        } else {
+            anyhow::bail!("No profile {}", profile);
+        };
+
+        let path = self.next_path("active");
+        let devices = if device == "/" {
+            Vec::new()
+        } else {
+            vec![device.to_string()]
+        };
+        self.active.push(ActiveConnectionInfo {
+            path: path.clone(),
+            id: id.clone(),
+            uuid,
+            connection_type: kind.to_string(),
+            state: ActiveConnectionState::Activated,
+            connection_path: profile.to_string(),
+            devices,
+        });
+
+        if device != "/" {
+            let dev = self.device_mut(device)?;
+            dev.state = DeviceState::Activated;
+            dev.active_access_point = dev
+                .access_points
+                .iter()
+                .find(|ap| ap.ssid == id)
+                .map(|ap| ap.path.clone());
+        }
+        Ok(path)
+    }
+
+    fn deactivate(&mut self, active: &str) -> Result<()> {
+        let index = self
+            .active
+            .iter()
+            .position(|a| a.path == active)
+            .with_context(|| format!("No active connection {}", active))?;
+        let removed = self.active.remove(index);
+        self.hotspots.retain(|h| h != active);
+        for device in &removed.devices {
+            let dev = self.device_mut(device)?;
+            dev.state = DeviceState::Disconnected;
+            dev.active_access_point = None;
+        }
+        Ok(())
+    }
+}
+
+impl Snapshot for State {
+    fn wireless_enabled(&self) -> Option<bool> {
+        Some(self.wireless_enabled)
+    }
+
+    fn wifi_devices(&self) -> Vec<String> {
+        self.devices.iter().map(|d| d.path.clone()).collect()
+    }
+
+    fn device_interface(&self, device: &str) -> Option<String> {
+        self.device(device).map(|d| d.interface.clone())
+    }
+
+    fn device_state(&self, device: &str) -> Option<DeviceState> {
+        self.device(device).map(|d| d.state)
+    }
+
+    fn access_points(&self, device: &str) -> Vec<AccessPointInfo> {
+        self.device(device)
+            .map(|d| d.access_points.clone())
+            .unwrap_or_default()
+    }
+
+    fn bitrate(&self, device: &str) -> Option<u32> {
+        self.active_access_point(device).map(|_| 54_000)
+    }
+
+    fn active_access_point(&self, device: &str) -> Option<AccessPointInfo> {
+        let device = self.device(device)?;
+        let path = device.active_access_point.as_ref()?;
+        device
+            .access_points
+            .iter()
+            .find(|ap| &ap.path == path)
+            .cloned()
+    }
+
+    fn active_connections(&self) -> Vec<ActiveConnectionInfo> {
+        self.active.clone()
+    }
+
+    fn primary_connection(&self) -> Option<ActiveConnectionInfo> {
+        self.active
+            .iter()
+            .find(|a| !a.devices.is_empty() && a.state == ActiveConnectionState::Activated)
+            .cloned()
+    }
+
+    fn profile_versions(&self) -> Vec<(String, Option<u64>)> {
+        let mut versions: Vec<(String, Option<u64>)> = self
+            .wifi
+            .iter()
+            .map(|c| c.path.clone())
+            .chain(self.vpns.iter().map(|c| c.path.clone()))
+            .map(|path| (path, Some(0)))
+            .collect();
+        versions.sort();
+        versions
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({ "devices": self.wifi_devices() })
+    }
+}
+
+#[derive(Default)]
+struct Shared {
+    state: Mutex<State>,
+    watchers: Mutex<Vec<Box<dyn Fn() + Send + Sync>>>,
+}
+
+/// The fake itself. Clones share state, so a test keeps one to arrange and
+/// inspect while the code under test holds another as `Arc<dyn Backend>`.
+#[derive(Clone, Default)]
+pub(crate) struct FakeBackend {
+    shared: Arc<Shared>,
+}
+
+impl std::fmt::Debug for FakeBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("FakeBackend")
+    }
+}
+
+impl FakeBackend {
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.shared.state.lock().unwrap()
+    }
+
+    /// Runs `edit` on the state, then tells every watcher.
+    fn update<T>(&self, edit: impl FnOnce(&mut State) -> T) -> T {
+        let out = edit(&mut self.state());
+        for notify in self.shared.watchers.lock().unwrap().iter() {
+            notify();
+        }
+        out
+    }
+
+    pub(crate) fn add_wifi_device(&self, interface: &str) -> String {
+        self.update(|state| {
+            let path = state.next_path("device");
+            state.devices.push(Device {
+                path: path.clone(),
+                interface: interface.to_string(),
+                state: DeviceState::Disconnected,
+                access_points: Vec::new(),
+                active_access_point: None,
+            });
+            path
+        })
+    }
+
+    pub(crate) fn add_access_point(
+        &self,
+        device: &str,
+        ssid: &str,
+        strength: u8,
+        security: SecurityType,
+    ) -> String {
+        self.update(|state| {
+            let path = state.next_path("ap");
+            let ap = AccessPointInfo {
+                path: path.clone(),
+                ssid: ssid.to_string(),
+                strength,
+                frequency: 2412,
+                hw_address: "00:11:22:33:44:55".to_string(),
+                security,
+                mode: WifiMode::Infrastructure,
+            };
+            state.device_mut(device).unwrap().access_points.push(ap);
+            path
+        })
+    }
+
+    pub(crate) fn add_wifi_profile(&self, ssid: &str, psk: Option<&str>) -> String {
+        self.update(|state| {
+            let security = match psk {
+                Some(_) => SecurityType::WPA,
+                None => SecurityType::Open,
+            };
+            let path = state.add_wifi_profile(ssid, security, false);
+            if let Some(psk) = psk {
+                state.psks.push((path.clone(), psk.to_string()));
+            }
+            path
+        })
+    }
+
+    pub(crate) fn add_wireguard_profile(&self, id: &str) -> String {
+        self.update(|state| {
+            let path = state.next_path("profile");
+            state.vpns.push(VpnConnectionInfo {
+                path: path.clone(),
+                id: id.to_string(),
+                uuid: path.clone(),
+                kind: VpnKind::WireGuard,
+                interface_name: format!("wg-{}", id),
+                autoconnect: false,
+                timestamp: 0,
+            });
+            path
+        })
+    }
+
+    /// Activates a saved profile as if another client had.
+    pub(crate) fn activate_now(&self, profile: &str, device: &str) {
+        self.update(|state| state.activate(profile, device).unwrap());
+    }
+
+    pub(crate) fn scans(&self) -> usize {
+        self.state().scans
+    }
+
+    pub(crate) fn active_connections(&self) -> Vec<ActiveConnectionInfo> {
+        self.state().active.clone()
+    }
+}
+
+struct FakeWatch {
+    backend: FakeBackend,
+}
+
+#[async_trait]
+impl Watch for FakeWatch {
+    async fn snapshot(&self) -> SharedSnapshot {
+        Arc::new(self.backend.state().clone())
+    }
+}
+
+#[async_trait]
+impl Backend for FakeBackend {
+    async fn get_wifi_devices(&self) -> Result<Vec<String>> {
+        Ok(self.state().wifi_devices())
+    }
+
+    async fn get_device_interface(&self, device: &str) -> Result<String> {
+        self.state()
+            .device_interface(device)
+            .with_context(|| format!("No device {}", device))
+    }
+
+    async fn get_device_hw_address(&self, _device: &str) -> Result<String> {
+        Ok("02:00:00:00:00:01".to_string())
+    }
+
+    async fn get_device_state(&self, device: &str) -> Result<DeviceState> {
+        self.state()
+            .device_state(device)
+            .with_context(|| format!("No device {}", device))
+    }
+
+    async fn is_wireless_enabled(&self) -> Result<bool> {
+        Ok(self.state().wireless_enabled)
+    }
+
+    async fn set_wireless_enabled(&self, enabled: bool) -> Result<()> {
+        self.update(|state| state.wireless_enabled = enabled);
+        Ok(())
+    }
+
+    async fn snapshot(&self) -> Result<SharedSnapshot> {
+        Ok(Arc::new(self.state().clone()))
+    }
+
+    async fn watch(&self, notify: Box<dyn Fn() + Send + Sync>) -> Result<Box<dyn Watch>> {
+        self.shared.watchers.lock().unwrap().push(notify);
+        Ok(Box::new(FakeWatch {
+            backend: self.clone(),
+        }))
+    }
+
+    async fn request_scan(&self, _device: &str) -> Result<()> {
+        self.state().scans += 1;
+        Ok(())
+    }
+
+    async fn get_active_access_point(&self, device: &str) -> Result<Option<String>> {
+        Ok(self.state().active_access_point(device).map(|ap| ap.path))
+    }
+
+    async fn get_access_point_info(&self, access_point: &str) -> Result<AccessPointInfo> {
+        self.state()
+            .devices
+            .iter()
+            .flat_map(|d| d.access_points.iter())
+            .find(|ap| ap.path == access_point)
+            .cloned()
+            .with_context(|| format!("No access point {}", access_point))
+    }
+
+    async fn wifi_connections(&self, _snapshot: &dyn Snapshot) -> Result<Arc<[ConnectionInfo]>> {
+        Ok(self.state().wifi.clone().into())
+    }
+
+    async fn get_wifi_psk(&self, profile: &str) -> Result<Option<String>> {
+        Ok(self
+            .state()
+            .psks
+            .iter()
+            .find(|(path, _)| path == profile)
+            .map(|(_, psk)| psk.clone()))
+    }
+
+    async fn get_vpn_connections(&self) -> Result<Vec<VpnConnectionInfo>> {
+        let mut vpns = self.state().vpns.clone();
+        vpns.sort_by_key(|v| v.id.to_lowercase());
+        Ok(vpns)
+    }
+
+    async fn profile_json(&self, profile: &str) -> Result<serde_json::Value> {
+        Ok(serde_json::json!({ "path": profile }))
+    }
+
+    async fn wireguard_config(&self, profile: &str) -> Result<WgConfig> {
+        self.state()
+            .tunnels
+            .iter()
+            .find(|(path, _)| path == profile)
+            .map(|(_, cfg)| cfg.clone())
+            .with_context(|| format!("No WireGuard config for {}", profile))
+    }
+
+    async fn add_wireguard_connection(
+        &self,
+        id: &str,
+        interface: &str,
+        cfg: &WgConfig,
+    ) -> Result<String> {
+        let path = self.add_wireguard_profile(id);
+        self.update(|state| {
+            if let Some(vpn) = state.vpns.iter_mut().find(|v| v.path == path) {
+                vpn.interface_name = interface.to_string();
+            }
+            state.tunnels.push((path.clone(), cfg.clone()));
+        });
+        Ok(path)
+    }
+
+    async fn add_openvpn_connection(&self, id: &str, cfg: &OvpnConfig) -> Result<String> {
+        Ok(self.update(|state| {
+            let path = state.next_path("profile");
+            state.vpns.push(VpnConnectionInfo {
+                path: path.clone(),
+                id: id.to_string(),
+                uuid: cfg.uuid.clone(),
+                kind: VpnKind::Vpn,
+                interface_name: String::new(),
+                autoconnect: false,
+                timestamp: 0,
+            });
+            path
+        }))
+    }
+
+    async fn add_enterprise_connection(
+        &self,
+        ssid: &str,
+        _credentials: EnterpriseCredentials<'_>,
+    ) -> Result<String> {
+        Ok(self.update(|state| state.add_wifi_profile(ssid, SecurityType::Enterprise, false)))
+    }
+
+    async fn set_connection_autoconnect(&self, profile: &str, autoconnect: bool) -> Result<()> {
+        self.update(|state| {
+            if let Some(wifi) = state.wifi.iter_mut().find(|c| c.path == profile) {
+                wifi.autoconnect = autoconnect;
+            } else if let Some(vpn) = state.vpns.iter_mut().find(|c| c.path == profile) {
+                vpn.autoconnect = autoconnect;
+            } else {
+                anyhow::bail!("No profile {}", profile);
+            }
+            Ok(())
+        })
+    }
+
+    async fn delete_connection(&self, profile: &str) -> Result<()> {
+        self.update(|state| {
+            state.wifi.retain(|c| c.path != profile);
+            state.vpns.retain(|c| c.path != profile);
+            state.psks.retain(|(path, _)| path != profile);
+            state.tunnels.retain(|(path, _)| path != profile);
+        });
+        Ok(())
+    }
+
+    async fn activate_connection(&self, profile: &str, device: &str) -> Result<String> {
+        self.update(|state| state.activate(profile, device))
+    }
+
+    async fn add_and_activate_connection(
+        &self,
+        device: &str,
+        access_point: &str,
+        password: Option<&str>,
+    ) -> Result<String> {
+        let ap = self.get_access_point_info(access_point).await?;
+        self.update(|state| {
+            let profile = state.add_wifi_profile(&ap.ssid, ap.security, false);
+            if let Some(psk) = password {
+                state.psks.push((profile.clone(), psk.to_string()));
+            }
+            state.activate(&profile, device)
+        })
+    }
+
+    async fn add_and_activate_hidden_connection(
+        &self,
+        device: &str,
+        ssid: &str,
+        security: SecurityType,
+        password: Option<&str>,
+    ) -> Result<String> {
+        self.update(|state| {
+            let profile = state.add_wifi_profile(ssid, security, true);
+            if let Some(psk) = password {
+                state.psks.push((profile.clone(), psk.to_string()));
+            }
+            state.activate(&profile, device)
+        })
+    }
+
+    async fn await_activation(&self, _active: &str, _device: &str) -> Result<ActivationOutcome> {
+        Ok(ActivationOutcome::Activated)
+    }
+
+    async fn disconnect_device(&self, device: &str) -> Result<()> {
+        self.update(|state| {
+            let on_device: Vec<String> = state
+                .active
+                .iter()
+                .filter(|a| a.devices.iter().any(|d| d == device))
+                .map(|a| a.path.clone())
+                .collect();
+            for active in on_device {
+                state.deactivate(&active)?;
+            }
+            Ok(())
+        })
+    }
+
+    async fn deactivate_connection(&self, active: &str) -> Result<()> {
+        self.update(|state| state.deactivate(active))
+    }
+
+    async fn get_active_connections(&self) -> Result<Vec<ActiveConnectionInfo>> {
+        Ok(self.state().active.clone())
+    }
+
+    async fn active_connection_ipv4(&self, _active: &str) -> Result<Option<String>> {
+        Ok(Some("10.0.0.2/24".to_string()))
+    }
+
+    async fn create_hotspot(&self, device: &str, ssid: &str, _password: &str) -> Result<String> {
+        self.update(|state| {
+            let profile = state.add_wifi_profile(ssid, SecurityType::WPA2, false);
+            let active = state.activate(&profile, device)?;
+            state.hotspots.push(active.clone());
+            Ok(active)
+        })
+    }
+
+    async fn active_hotspot(&self, device: &str) -> Result<Option<ActiveConnectionInfo>> {
+        let state = self.state();
+        Ok(state
+            .active
+            .iter()
+            .find(|a| state.hotspots.contains(&a.path) && a.devices.iter().any(|d| d == device))
+            .cloned())
+    }
+
+    async fn prefer_internet(&self, _kind: LinkKind) -> Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,411 @@
+//! The seam between the UI and whatever manages the WiFi hardware.
+//!
+//! `Station`, `AccessPoint`, `Device`, `VpnModal` and the doctor checks hold an
+//! `Arc<dyn Backend>` and read state through a [`Snapshot`]; none of them talk
+//! to a network service directly. [`NMClient`](crate::nm::NMClient) and
+//! [`IwdClient`](crate::iwd::IwdClient) implement both traits, [`connect`]
+//! picks one, and unit tests drive the UI with the in-memory fake in `fake`.
+//!
+//! Devices, access points, profiles and active connections are named by
+//! strings the backend hands out and takes back unchanged; both services use
+//! D-Bus object paths.
+
+use std::fmt;
+use std::net::Ipv4Addr;
+use std::sync::Arc;
+
+use anyhow::{Context, Result, bail};
+use async_trait::async_trait;
+use zbus::Connection;
+use zbus::fdo::DBusProxy;
+use zbus::names::BusName;
+
+use crate::agent::AuthAgent;
+use crate::iwd::IwdClient;
+use crate::nm::{
+    AccessPointInfo, ActivationOutcome, ActiveConnectionInfo, ActiveConnectionState,
+    ConnectionInfo, Connectivity, DeviceState, Dhcp4Lease, EthernetInfo, Ip4Info, Ip6Info,
+    LinkKind, NMClient, OvpnConfig, PrimaryLink, SecurityType, VpnConnectionInfo, WgConfig,
+};
+
+#[cfg(test)]
+pub(crate) mod fake;
+
+/// Which service to drive, from `--backend` or the `backend` config key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BackendKind {
+    /// NetworkManager when it is running, iwd otherwise.
+    #[default]
+    Auto,
+    NetworkManager,
+    Iwd,
+}
+
+impl TryFrom<&str> for BackendKind {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "auto" => Ok(BackendKind::Auto),
+            "networkmanager" | "nm" => Ok(BackendKind::NetworkManager),
+            "iwd" => Ok(BackendKind::Iwd),
+            _ => Err(anyhow::anyhow!("Invalid backend: {}", value)),
+        }
+    }
+}
+
+/// Connects to the service `kind` names.
+///
+/// `Auto` asks the bus which of the two is running. NetworkManager wins when
+/// both are: configured with `wifi.backend=iwd` it runs iwd underneath and
+/// owns the devices, so talking to iwd directly would fight it.
+pub async fn connect(kind: BackendKind) -> Result<Arc<dyn Backend>> {
+    let kind = match kind {
+        BackendKind::Auto => detect().await?,
+        kind => kind,
+    };
+
+    Ok(match kind {
+        BackendKind::Iwd => Arc::new(
+            IwdClient::new()
+                .await
+                .context("Could not reach iwd over D-Bus")?,
+        ),
+        _ => Arc::new(
+            NMClient::new()
+                .await
+                .context("Could not reach NetworkManager over D-Bus")?,
+        ),
+    })
+}
+
+async fn detect() -> Result<BackendKind> {
+    let connection = Connection::system()
+        .await
+        .context("Failed to connect to system D-Bus")?;
+    let bus = DBusProxy::new(&connection).await?;
+
+    for (name, kind) in [
+        (
+            "org.freedesktop.NetworkManager",
+            BackendKind::NetworkManager,
+        ),
+        ("net.connman.iwd", BackendKind::Iwd),
+    ] {
+        if bus.name_has_owner(BusName::try_from(name)?).await? {
+            return Ok(kind);
+        }
+    }
+    bail!("Neither NetworkManager nor iwd is running")
+}
+
+/// A read of the backend's state that never changes under its holder. It is
+/// shared, not locked: holding it never holds back incoming changes, which
+/// go into the next one.
+pub type SharedSnapshot = Arc<dyn Snapshot>;
+
+/// Everything a refresh draws, read at one instant so every view agrees.
+pub trait Snapshot: Send + Sync {
+    /// Whether the WiFi radio is enabled.
+    fn wireless_enabled(&self) -> Option<bool>;
+
+    /// WiFi devices, in a stable order so the adapter table keeps its rows.
+    fn wifi_devices(&self) -> Vec<String>;
+
+    /// A device's kernel interface name, e.g. `wlan0`.
+    fn device_interface(&self, device: &str) -> Option<String>;
+
+    fn device_state(&self, device: &str) -> Option<DeviceState>;
+
+    /// Every access point (BSSID) the device can currently see, hidden ones
+    /// included.
+    fn access_points(&self, device: &str) -> Vec<AccessPointInfo>;
+
+    /// The device's current link rate in kbit/s, `None` while not associated.
+    fn bitrate(&self, device: &str) -> Option<u32>;
+
+    /// The access point the device is currently associated with.
+    fn active_access_point(&self, device: &str) -> Option<AccessPointInfo>;
+
+    /// Every active connection, in no particular order.
+    fn active_connections(&self) -> Vec<ActiveConnectionInfo>;
+
+    /// The connection carrying the default route, when there is one.
+    fn primary_connection(&self) -> Option<ActiveConnectionInfo>;
+
+    /// `(profile, revision)` for every saved profile, sorted. The revision
+    /// changes on every edit, so an equal list means no profile was added,
+    /// removed or changed; an unknown revision is `None`.
+    fn profile_versions(&self) -> Vec<(String, Option<u64>)>;
+
+    /// The raw state as JSON for diagnostics bundles, secrets redacted.
+    fn to_json(&self) -> serde_json::Value;
+
+    /// Access points the device can currently see: deduplicated by SSID
+    /// keeping the strongest signal, then sorted strongest first.
+    fn visible_networks(&self, device: &str) -> Vec<AccessPointInfo> {
+        let mut networks: Vec<AccessPointInfo> = Vec::new();
+        for ap in self.access_points(device) {
+            // A hidden network has no SSID until one is observed, and has
+            // nothing to show in a network list.
+            if ap.ssid.is_empty() {
+                continue;
+            }
+
+            match networks.iter_mut().find(|known| known.ssid == ap.ssid) {
+                Some(weaker) if ap.strength > weaker.strength => *weaker = ap,
+                Some(_) => {}
+                None => networks.push(ap),
+            }
+        }
+
+        networks.sort_by_key(|n| std::cmp::Reverse(n.strength));
+        networks
+    }
+
+    /// Names of the VPN / WireGuard profiles that are currently activated.
+    /// Drives the always-on VPN indicator without opening the modal.
+    fn active_vpn_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .active_connections()
+            .into_iter()
+            .filter(|info| info.state == ActiveConnectionState::Activated)
+            .filter(|info| info.connection_type == "vpn" || info.connection_type == "wireguard")
+            .map(|info| info.id)
+            .collect();
+
+        names.sort_by_key(|n| n.to_lowercase());
+        names
+    }
+
+    /// The link currently carrying internet traffic. `None` when nothing is
+    /// online.
+    fn primary_link(&self) -> Option<PrimaryLink> {
+        self.primary_connection().map(|info| PrimaryLink {
+            id: info.id,
+            kind: LinkKind::from_nm_type(&info.connection_type),
+        })
+    }
+}
+
+/// A snapshot kept current by the backend's change notifications; see
+/// [`Backend::watch`].
+#[async_trait]
+pub trait Watch: Send + Sync {
+    /// The current state. Taking it re-arms the change notification: anything
+    /// that changes after this call is announced again.
+    async fn snapshot(&self) -> SharedSnapshot;
+}
+
+/// 802.1X credentials for a new enterprise profile. Paths are absolute file
+/// names; empty ones are left out.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EnterpriseCredentials<'a> {
+    pub eap_method: &'a str,
+    pub identity: &'a str,
+    pub password: Option<&'a str>,
+    pub phase2_auth: Option<&'a str>,
+    pub ca_cert: Option<&'a str>,
+    pub client_cert: Option<&'a str>,
+    pub private_key: Option<&'a str>,
+    pub private_key_password: Option<&'a str>,
+}
+
+fn unsupported(what: &str) -> Result<std::convert::Infallible> {
+    bail!("{} is not supported by this backend", what)
+}
+
+/// The operations the UI needs from a network service: devices and the radio,
+/// state snapshots, scanning, saved profiles and activation.
+///
+/// Methods under "Diagnostics" have default implementations that report the
+/// feature as unsupported, so a backend can start without them; the doctor
+/// checks that need them skip.
+#[async_trait]
+pub trait Backend: Send + Sync + fmt::Debug {
+    // Devices and the radio.
+
+    async fn get_wifi_devices(&self) -> Result<Vec<String>>;
+
+    async fn get_device_interface(&self, device: &str) -> Result<String>;
+
+    async fn get_device_hw_address(&self, device: &str) -> Result<String>;
+
+    async fn get_device_state(&self, device: &str) -> Result<DeviceState>;
+
+    async fn is_wireless_enabled(&self) -> Result<bool>;
+
+    async fn set_wireless_enabled(&self, enabled: bool) -> Result<()>;
+
+    // State.
+
+    /// A one-off read of the current state.
+    async fn snapshot(&self) -> Result<SharedSnapshot>;
+
+    /// Follows the backend's state, calling `notify` when it changes. Bursts
+    /// are coalesced: `notify` fires once, and not again until a reader has
+    /// taken a fresh [`Watch::snapshot`].
+    async fn watch(&self, notify: Box<dyn Fn() + Send + Sync>) -> Result<Box<dyn Watch>>;
+
+    // Scanning.
+
+    async fn request_scan(&self, device: &str) -> Result<()>;
+
+    /// The access point the device is associated with, read live.
+    async fn get_active_access_point(&self, device: &str) -> Result<Option<String>>;
+
+    async fn get_access_point_info(&self, access_point: &str) -> Result<AccessPointInfo>;
+
+    // Saved profiles.
+
+    /// Saved WiFi profiles, most recently used first, as of `snapshot`.
+    async fn wifi_connections(&self, snapshot: &dyn Snapshot) -> Result<Arc<[ConnectionInfo]>>;
+
+    /// The stored passphrase of a WiFi profile, if it has one.
+    async fn get_wifi_psk(&self, profile: &str) -> Result<Option<String>>;
+
+    /// Saved VPN and WireGuard profiles, sorted by name.
+    async fn get_vpn_connections(&self) -> Result<Vec<VpnConnectionInfo>>;
+
+    /// A profile's settings as JSON for diagnostics bundles, secrets redacted.
+    async fn profile_json(&self, profile: &str) -> Result<serde_json::Value>;
+
+    /// A saved WireGuard profile read back as a config, keys included.
+    async fn wireguard_config(&self, profile: &str) -> Result<WgConfig>;
+
+    /// Saves a WireGuard profile without activating it. Returns the profile.
+    async fn add_wireguard_connection(
+        &self,
+        id: &str,
+        interface: &str,
+        cfg: &WgConfig,
+    ) -> Result<String>;
+
+    /// Saves an OpenVPN profile without activating it. Returns the profile.
+    async fn add_openvpn_connection(&self, id: &str, cfg: &OvpnConfig) -> Result<String>;
+
+    /// Saves an 802.1X profile for `ssid` without activating it. Returns the
+    /// profile.
+    async fn add_enterprise_connection(
+        &self,
+        ssid: &str,
+        credentials: EnterpriseCredentials<'_>,
+    ) -> Result<String>;
+
+    async fn set_connection_autoconnect(&self, profile: &str, autoconnect: bool) -> Result<()>;
+
+    async fn delete_connection(&self, profile: &str) -> Result<()>;
+
+    // Activation.
+
+    /// Starts activating `profile` on `device` (`/` for none, as VPNs use).
+    /// Returns the active connection; see [`Backend::await_activation`].
+    async fn activate_connection(&self, profile: &str, device: &str) -> Result<String>;
+
+    /// Creates a profile for a scanned network and starts activating it.
+    async fn add_and_activate_connection(
+        &self,
+        device: &str,
+        access_point: &str,
+        password: Option<&str>,
+    ) -> Result<String>;
+
+    /// Creates a profile for a network that does not broadcast its SSID and
+    /// starts activating it.
+    async fn add_and_activate_hidden_connection(
+        &self,
+        device: &str,
+        ssid: &str,
+        security: SecurityType,
+        password: Option<&str>,
+    ) -> Result<String>;
+
+    /// Answers the service's requests for VPN secrets, such as a password or
+    /// a one-time code, by prompting through `agent`. Backends without VPNs
+    /// are never asked.
+    async fn register_secret_agent(&self, _agent: AuthAgent) -> Result<()> {
+        Ok(())
+    }
+
+    /// Waits for an activation to succeed or fail.
+    async fn await_activation(&self, active: &str, device: &str) -> Result<ActivationOutcome>;
+
+    async fn disconnect_device(&self, device: &str) -> Result<()>;
+
+    async fn deactivate_connection(&self, active: &str) -> Result<()>;
+
+    /// Every active connection, read live.
+    async fn get_active_connections(&self) -> Result<Vec<ActiveConnectionInfo>>;
+
+    /// First IPv4 address (as `addr/prefix`) of an active connection.
+    async fn active_connection_ipv4(&self, active: &str) -> Result<Option<String>>;
+
+    /// Starts a WPA2 hotspot on `device`. Returns the active connection.
+    async fn create_hotspot(&self, device: &str, ssid: &str, password: &str) -> Result<String>;
+
+    /// The hotspot running on `device`, if any.
+    async fn active_hotspot(&self, device: &str) -> Result<Option<ActiveConnectionInfo>>;
+
+    /// Makes the active link of `kind` carry the default route while leaving
+    /// the others up.
+    async fn prefer_internet(&self, kind: LinkKind) -> Result<()>;
+
+    /// The first activated wired connection, with its interface and IPv4
+    /// address when they can be read. Takes its own handle on the snapshot,
+    /// so the caller's read can end before the address lookup goes out.
+    async fn active_ethernet(&self, snapshot: SharedSnapshot) -> Result<Option<EthernetInfo>> {
+        let Some(info) = snapshot.active_connections().into_iter().find(|info| {
+            info.state == ActiveConnectionState::Activated
+                && info.connection_type == "802-3-ethernet"
+        }) else {
+            return Ok(None);
+        };
+
+        let device = info.devices.first();
+        let interface = device.and_then(|device| snapshot.device_interface(device));
+        let ipv4 = match device {
+            Some(device) => self
+                .get_ip4_info(device)
+                .await
+                .ok()
+                .flatten()
+                .and_then(|ip| ip.addresses.into_iter().next().map(|(addr, _)| addr)),
+            None => None,
+        };
+
+        Ok(Some(EthernetInfo {
+            id: info.id,
+            interface,
+            ipv4,
+        }))
+    }
+
+    // Diagnostics.
+
+    async fn get_ip4_info(&self, _device: &str) -> Result<Option<Ip4Info>> {
+        unsupported("Reading IPv4 configuration").map(|_| None)
+    }
+
+    async fn get_ip6_info(&self, _device: &str) -> Result<Option<Ip6Info>> {
+        unsupported("Reading IPv6 configuration").map(|_| None)
+    }
+
+    async fn get_dhcp4_lease(&self, _device: &str) -> Result<Option<Dhcp4Lease>> {
+        unsupported("Reading the DHCP lease").map(|_| None)
+    }
+
+    /// Asks the backend to re-run its connectivity check.
+    async fn check_connectivity(&self) -> Result<Connectivity> {
+        unsupported("Checking connectivity").map(|_| Connectivity::Unknown)
+    }
+
+    /// The URL the connectivity check probes, when one is configured.
+    async fn connectivity_check_uri(&self) -> Result<Option<String>> {
+        unsupported("Reading the connectivity check URL").map(|_| None)
+    }
+
+    /// Overrides the device's IPv4 nameservers until it next reconnects.
+    async fn set_runtime_dns(&self, _device: &str, _servers: &[Ipv4Addr]) -> Result<()> {
+        unsupported("Overriding DNS servers").map(|_| ())
+    }
+}
@@ -2,10 +2,10 @@ use clap::{Command, arg, crate_version};
 
 pub fn cli() -> Command {
     Command::new("wlctl")
-        .about("TUI for managing WiFi using NetworkManager")
+        .about("TUI for managing WiFi using NetworkManager or iwd")
         .version(crate_version!())
-        // Root-level args (--mode) are for launching the TUI; they do not apply
-        // when a subcommand like `doctor` is used.
+        // Root-level args (--mode, --replay) are for launching the TUI; they do
+        // not apply when a subcommand like `doctor` or `connect` is used.
         .args_conflicts_with_subcommands(true)
         .arg(
             arg!(--mode <mode>)
@@ -14,8 +14,116 @@ pub fn cli() -> Command {
                 .help("Device mode")
                 .value_parser(["station", "ap"]),
         )
+        .arg(
+            arg!(--replay <file>)
+                .required(false)
+                .value_parser(clap::value_parser!(std::path::PathBuf))
+                .help("Run on a recording made with `wlctl record` instead of the live network"),
+        )
+        // Global so every subcommand takes it too, written after the
+        // subcommand's name: `wlctl doctor --backend iwd`.
+        .arg(
+            arg!(--backend <backend>)
+                .required(false)
+                .global(true)
+                .help("Network service to drive (default: NetworkManager if running, else iwd)")
+                .value_parser(["auto", "networkmanager", "iwd"]),
+        )
         .subcommand(
             Command::new("doctor")
-                .about("Diagnose why your WiFi isn't working (rfkill, driver, DHCP, DNS, ...)"),
+                .about("Diagnose why your WiFi isn't working (rfkill, driver, DHCP, DNS, ...)")
+                .arg(
+                    arg!(--interface <interface>)
+                        .short('i')
+                        .required(false)
+                        .help("WiFi interface to diagnose (default: the first one)"),
+                )
+                .arg(
+                    arg!(--only <checks>)
+                        .required(false)
+                        .value_delimiter(',')
+                        .help("Run only these checks, e.g. dns,gateway"),
+                )
+                .arg(
+                    arg!(--skip <checks>)
+                        .required(false)
+                        .value_delimiter(',')
+                        .help("Leave out these checks, e.g. portal"),
+                )
+                .arg(
+                    arg!(--watch <secs>)
+                        .required(false)
+                        .value_parser(clap::value_parser!(u64).range(1..))
+                        .conflicts_with("format")
+                        .help("Rerun every <secs> seconds and print only status changes"),
+                )
+                .arg(
+                    arg!(--fix)
+                        .conflicts_with_all(["watch", "format"])
+                        .help("Offer the fixes the report suggests, asking before each one"),
+                )
+                .arg(
+                    arg!(--bundle <path>)
+                        .required(false)
+                        .value_parser(clap::value_parser!(std::path::PathBuf))
+                        .conflicts_with("watch")
+                        .help("Also write a redacted diagnostics archive (tar) for a support ticket"),
+                )
+                .arg(
+                    arg!(--history)
+                        .conflicts_with_all(["only", "skip", "watch", "fix", "bundle", "format"])
+                        .help("List past runs instead of running the checks"),
+                )
+                .arg(
+                    arg!(--format <format>)
+                        .required(false)
+                        .help("Output format")
+                        .value_parser(["text", "json", "junit"])
+                        .default_value("text"),
+                )
+                .after_help(
+                    "Checks: rfkill, driver, interface, association, logs, link, dhcp, ip, ip6, dns, dns6, \
+                     gateway, route6, internet, internet6, portal\n\n\
+                     Exit status: 0 all checks passed, 1 error, 3 warning, 4 failure",
+                ),
+        )
+        .subcommand(
+            Command::new("connect")
+                .about("Connect to a network and wait until NetworkManager reports the result")
+                .arg(arg!(<ssid> "Network name"))
+                .arg(
+                    arg!(--"password-stdin").help("Read the password from the first line of stdin"),
+                )
+                .after_help(
+                    "Exit status: 0 connected, 1 error, 3 wrong or missing password, \
+                     4 network not found, 5 timed out, 6 other failure",
+                ),
+        )
+        .subcommand(
+            Command::new("record")
+                .about("Record NetworkManager's state and changes to a file for a bug report")
+                .arg(
+                    arg!(<file> "Where to write the recording")
+                        .value_parser(clap::value_parser!(std::path::PathBuf)),
+                )
+                .arg(
+                    arg!(--duration <secs>)
+                        .required(false)
+                        .value_parser(clap::value_parser!(u64).range(1..))
+                        .help("Stop after <secs> seconds instead of at Ctrl-C"),
+                )
+                .after_help(
+                    "Secrets are redacted; network names and addresses are kept. \
+                     Replay with `wlctl --replay <file>`.",
+                ),
+        )
+        .subcommand(Command::new("disconnect").about("Disconnect the WiFi device"))
+        .subcommand(
+            Command::new("list")
+                .about("List visible and saved networks")
+                .after_help(
+                    "One tab-separated line per network: SSID, signal, security, and status \
+                     (connected, saved, new, out-of-range)",
+                ),
         )
 }
@@ -0,0 +1,211 @@
+//! Non-interactive `connect` / `disconnect` / `list` subcommands, for scripts
+//! that would otherwise shell out to nmcli. They drive the same `Backend`
+//! calls as the TUI and report through stdout, stderr and the exit status.
+
+use std::io::BufRead;
+
+use anyhow::{Context, Result, anyhow, bail};
+
+use crate::backend::{self, Backend, BackendKind};
+use crate::nm::{ActivationFailureReason, ActivationOutcome, DeviceState, StationState};
+
+/// Exit status when `connect` ends in `ActivationOutcome::Failed`. Each reason
+/// gets its own code so a script can tell a wrong password from a network that
+/// is out of range without parsing stderr. `1` stays the generic error and `2`
+/// belongs to clap's usage errors.
+pub fn failure_exit_code(reason: ActivationFailureReason) -> i32 {
+    match reason {
+        ActivationFailureReason::BadSecrets => 3,
+        ActivationFailureReason::SsidNotFound => 4,
+        ActivationFailureReason::Timeout => 5,
+        ActivationFailureReason::Other(_) => 6,
+        ActivationFailureReason::NoAgent => 7,
+    }
+}
+
+/// Connects the first WiFi device to `ssid` and blocks until the backend
+/// reports the outcome. Returns the process exit status.
+///
+/// A saved profile for the SSID is activated as-is, exactly as the TUI does for
+/// a known network; the password is only used when a new profile is created.
+pub async fn connect(backend: BackendKind, ssid: &str, password_stdin: bool) -> Result<i32> {
+    let password = if password_stdin {
+        Some(read_password()?)
+    } else {
+        None
+    };
+
+    let client = backend::connect(backend).await?;
+    let device_path = wifi_device(client.as_ref()).await?;
+
+    let snapshot = client.snapshot().await?;
+    let saved = client.wifi_connections(&*snapshot).await?;
+
+    let active_path = if let Some(profile) = saved.iter().find(|c| c.ssid == ssid) {
+        client
+            .activate_connection(&profile.path, &device_path)
+            .await?
+    } else {
+        let Some(ap) = snapshot
+            .visible_networks(&device_path)
+            .into_iter()
+            .find(|ap| ap.ssid == ssid)
+        else {
+            eprintln!("No saved or visible network named {ssid}");
+            return Ok(failure_exit_code(ActivationFailureReason::SsidNotFound));
+        };
+
+        if ap.security.is_enterprise() {
+            bail!("{ssid} uses 802.1X; configure it once from the TUI, then connect by name");
+        }
+        if ap.security.requires_password() {
+            let Some(password) = &password else {
+                eprintln!("{ssid} is secured ({}); pass --password-stdin", ap.security);
+                return Ok(failure_exit_code(ActivationFailureReason::BadSecrets));
+            };
+            if let Err(msg) = ap.security.validate_psk(password) {
+                eprintln!("{msg}");
+                return Ok(failure_exit_code(ActivationFailureReason::BadSecrets));
+            }
+        }
+
+        client
+            .add_and_activate_connection(&device_path, &ap.path, password.as_deref())
+            .await?
+    };
+
+    match client.await_activation(&active_path, &device_path).await? {
+        ActivationOutcome::Activated => {
+            println!("Connected to {ssid}");
+            Ok(0)
+        }
+        ActivationOutcome::Failed(reason) => {
+            eprintln!("{}", failure_message(ssid, reason));
+            Ok(failure_exit_code(reason))
+        }
+    }
+}
+
+/// Disconnects the first WiFi device. Succeeds quietly when it is already idle.
+pub async fn disconnect(backend: BackendKind) -> Result<i32> {
+    let client = backend::connect(backend).await?;
+    let device_path = wifi_device(client.as_ref()).await?;
+
+    let state = client.get_device_state(&device_path).await?;
+    if StationState::from(state) == StationState::Disconnected {
+        println!("Not connected");
+        return Ok(0);
+    }
+
+    client.disconnect_device(&device_path).await?;
+    println!("Disconnected");
+    Ok(0)
+}
+
+/// Prints one tab-separated line per network: SSID, signal, security and
+/// status (`connected`, `saved`, `new`, or `out-of-range` for a saved profile
+/// that is not currently visible). Visible networks come first, strongest
+/// first; out-of-range profiles follow, most recently used first.
+pub async fn list(backend: BackendKind) -> Result<i32> {
+    let client = backend::connect(backend).await?;
+    let device_path = wifi_device(client.as_ref()).await?;
+
+    let snapshot = client.snapshot().await?;
+    let visible = snapshot.visible_networks(&device_path);
+    let saved = client.wifi_connections(&*snapshot).await?;
+    let connected = snapshot
+        .active_access_point(&device_path)
+        .filter(|_| snapshot.device_state(&device_path) == Some(DeviceState::Activated))
+        .map(|ap| ap.ssid);
+
+    for ap in &visible {
+        let status = if connected.as_deref() == Some(ap.ssid.as_str()) {
+            "connected"
+        } else if saved.iter().any(|c| c.ssid == ap.ssid) {
+            "saved"
+        } else {
+            "new"
+        };
+        println!("{}\t{}\t{}\t{}", ap.ssid, ap.strength, ap.security, status);
+    }
+
+    for profile in saved
+        .iter()
+        .filter(|c| !visible.iter().any(|ap| ap.ssid == c.ssid))
+    {
+        println!("{}\t-\t{}\tout-of-range", profile.ssid, profile.security);
+    }
+
+    Ok(0)
+}
+
+/// The same wording the TUI uses for its connect notifications.
+fn failure_message(ssid: &str, reason: ActivationFailureReason) -> String {
+    match reason {
+        ActivationFailureReason::BadSecrets => format!("Wrong password for {ssid}"),
+        ActivationFailureReason::SsidNotFound => {
+            format!("Could not connect to {ssid} — wrong password or network out of range")
+        }
+        ActivationFailureReason::Timeout => format!("Connection to {ssid} timed out"),
+        ActivationFailureReason::NoAgent => {
+            format!("Nothing could ask for the password of {ssid}; pass it with --password-stdin")
+        }
+        ActivationFailureReason::Other(code) => {
+            format!("Failed to connect to {ssid} (reason {code})")
+        }
+    }
+}
+
+/// Reads the passphrase from the first line of stdin, without its newline.
+fn read_password() -> Result<String> {
+    let mut line = String::new();
+    std::io::stdin()
+        .lock()
+        .read_line(&mut line)
+        .context("Could not read the password from stdin")?;
+    let password = line.trim_end_matches(['\r', '\n']).to_string();
+    if password.is_empty() {
+        return Err(anyhow!("--password-stdin was given but stdin was empty"));
+    }
+    Ok(password)
+}
+
+async fn wifi_device(client: &dyn Backend) -> Result<String> {
+    client
+        .get_wifi_devices()
+        .await?
+        .into_iter()
+        .next()
+        .context("No WiFi device found")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Scripts branch on these, so every reason needs its own code and none
+    /// may collide with the generic (1) or usage (2) statuses.
+    #[test]
+    fn failure_exit_codes_are_distinct_and_reserved_codes_are_free() {
+        let codes = [
+            failure_exit_code(ActivationFailureReason::BadSecrets),
+            failure_exit_code(ActivationFailureReason::SsidNotFound),
+            failure_exit_code(ActivationFailureReason::Timeout),
+            failure_exit_code(ActivationFailureReason::Other(42)),
+            failure_exit_code(ActivationFailureReason::NoAgent),
+        ];
+
+        for (i, code) in codes.iter().enumerate() {
+            assert!(*code > 2, "code {code} collides with a reserved status");
+            assert!(!codes[i + 1..].contains(code), "code {code} is reused");
+        }
+    }
+
+    #[test]
+    fn other_reasons_share_one_code() {
+        assert_eq!(
+            failure_exit_code(ActivationFailureReason::Other(1)),
+            failure_exit_code(ActivationFailureReason::Other(99))
+        );
+    }
+}
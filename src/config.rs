@@ -1,5 +1,7 @@
+use std::net::Ipv4Addr;
 use std::time::Duration;
 
+use anyhow::{Context, Result};
 use toml;
 
 use dirs;
@@ -13,15 +15,20 @@ pub struct Config {
     #[serde(default = "default_device_mode")]
     pub mode: String,
 
+    /// Network service to drive: `auto`, `networkmanager` or `iwd`.
+    /// Overridden by `--backend`.
+    #[serde(default = "default_backend")]
+    pub backend: String,
+
     #[serde(default = "default_esc_quit")]
     pub esc_quit: bool,
 
     #[serde(default = "default_vpn")]
     pub vpn: char,
 
-    /// How often to re-read NetworkManager state, in milliseconds. Raising this
-    /// trades how quickly changes made outside wlctl show up for less work per
-    /// second on machines with many saved profiles.
+    /// How often to redraw while NetworkManager is idle, in milliseconds. State
+    /// changes are pushed by NetworkManager as they happen; this only paces
+    /// time-based output such as tunnel uptimes.
     #[serde(default = "default_refresh_interval_ms")]
     pub refresh_interval_ms: u64,
 
@@ -33,6 +40,9 @@ pub struct Config {
 
     #[serde(default)]
     pub ap: AccessPoint,
+
+    #[serde(default)]
+    pub doctor: Doctor,
 }
 
 fn default_switch_mode() -> char {
@@ -43,6 +53,10 @@ fn default_device_mode() -> String {
     "station".to_string()
 }
 
+fn default_backend() -> String {
+    "auto".to_string()
+}
+
 fn default_esc_quit() -> bool {
     false
 }
@@ -211,27 +225,184 @@ fn default_ap_stop() -> char {
     'x'
 }
 
+// Doctor
+/// Probe targets for `wlctl doctor` and the TUI doctor modal. The defaults
+/// reach public anycast resolvers, which restricted networks often block.
+#[derive(Deserialize, Debug)]
+pub struct Doctor {
+    /// Name the DNS checks resolve. It has to fit in a DNS query, so a
+    /// malformed name is refused.
+    #[serde(
+        default = "default_doctor_dns_probe_host",
+        deserialize_with = "dns_name"
+    )]
+    pub dns_probe_host: String,
+
+    /// `host:port` endpoints the internet check dials; any one answering passes.
+    #[serde(default = "default_doctor_internet_hosts")]
+    pub internet_hosts: Vec<String>,
+
+    /// Same, over IPv6.
+    #[serde(default = "default_doctor_internet6_hosts")]
+    pub internet6_hosts: Vec<String>,
+
+    /// TCP port knocked on to prove the gateway is up. A refusal counts.
+    #[serde(default = "default_doctor_gateway_port")]
+    pub gateway_port: u16,
+
+    /// How long each DNS query or TCP connect may take, in milliseconds.
+    /// Zero would fail every probe, so it is refused.
+    #[serde(
+        default = "default_doctor_probe_timeout_ms",
+        deserialize_with = "non_zero_ms"
+    )]
+    pub probe_timeout_ms: u64,
+
+    /// Check ids never run, e.g. `["portal", "internet6"]`. An explicit
+    /// `wlctl doctor --only` still runs them.
+    #[serde(default)]
+    pub disabled: Vec<String>,
+
+    /// IPv4 nameservers the DNS fix switches to until the next reconnect.
+    #[serde(default = "default_doctor_fallback_dns")]
+    pub fallback_dns: Vec<Ipv4Addr>,
+}
+
+impl Default for Doctor {
+    fn default() -> Self {
+        Self {
+            dns_probe_host: default_doctor_dns_probe_host(),
+            internet_hosts: default_doctor_internet_hosts(),
+            internet6_hosts: default_doctor_internet6_hosts(),
+            gateway_port: default_doctor_gateway_port(),
+            probe_timeout_ms: default_doctor_probe_timeout_ms(),
+            disabled: Vec::new(),
+            fallback_dns: default_doctor_fallback_dns(),
+        }
+    }
+}
+
+impl Doctor {
+    pub fn probe_timeout(&self) -> Duration {
+        Duration::from_millis(self.probe_timeout_ms)
+    }
+}
+
+fn default_doctor_dns_probe_host() -> String {
+    "one.one.one.one".to_string()
+}
+
+fn default_doctor_internet_hosts() -> Vec<String> {
+    vec!["1.1.1.1:443".to_string(), "8.8.8.8:443".to_string()]
+}
+
+fn default_doctor_internet6_hosts() -> Vec<String> {
+    vec![
+        "[2606:4700:4700::1111]:443".to_string(),
+        "[2001:4860:4860::8888]:443".to_string(),
+    ]
+}
+
+fn default_doctor_gateway_port() -> u16 {
+    80
+}
+
+fn default_doctor_probe_timeout_ms() -> u64 {
+    3_000
+}
+
+fn non_zero_ms<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    match u64::deserialize(deserializer)? {
+        0 => Err(serde::de::Error::custom("must be more than 0 milliseconds")),
+        ms => Ok(ms),
+    }
+}
+
+fn dns_name<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    let name = String::deserialize(deserializer)?;
+    match dns_name_problem(&name) {
+        Some(problem) => Err(serde::de::Error::custom(format!("'{name}' {problem}"))),
+        None => Ok(name),
+    }
+}
+
+/// Why `name` cannot go into a DNS query, if it cannot: every label 1 to 63
+/// bytes, the whole name at most 253. One trailing dot is allowed.
+pub fn dns_name_problem(name: &str) -> Option<&'static str> {
+    let name = name.strip_suffix('.').unwrap_or(name);
+    if name.is_empty() {
+        Some("is empty")
+    } else if name.len() > 253 {
+        Some("is longer than 253 bytes")
+    } else if name.split('.').any(str::is_empty) {
+        Some("has an empty label")
+    } else if name.split('.').any(|label| label.len() > 63) {
+        Some("has a label longer than 63 bytes")
+    } else {
+        None
+    }
+}
+
+fn default_doctor_fallback_dns() -> Vec<Ipv4Addr> {
+    vec![Ipv4Addr::new(1, 1, 1, 1), Ipv4Addr::new(9, 9, 9, 9)]
+}
+
 impl Config {
-    /// How often to re-read NetworkManager state.
+    /// How often to redraw while NetworkManager is idle.
     pub fn refresh_interval(&self) -> Duration {
         Duration::from_millis(self.refresh_interval_ms.max(MIN_REFRESH_INTERVAL_MS))
     }
 
-    pub fn new() -> Self {
-        let conf_path = dirs::config_dir()
-            .unwrap()
-            .join("wlctl")
-            .join("config.toml");
-
-        let config = std::fs::read_to_string(conf_path).unwrap_or_default();
-        let app_config: Config = toml::from_str(&config).unwrap();
-
-        app_config
+    /// Reads `~/.config/wlctl/config.toml`. A missing file means the
+    /// defaults; one that cannot be read or parsed is an error.
+    pub fn new() -> Result<Self> {
+        let Some(dir) = dirs::config_dir() else {
+            return Ok(Self::default());
+        };
+        let conf_path = dir.join("wlctl").join("config.toml");
+
+        let config = match std::fs::read_to_string(&conf_path) {
+            Ok(config) => config,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => {
+                return Err(e).with_context(|| format!("Cannot read {}", conf_path.display()));
+            }
+        };
+        toml::from_str(&config).with_context(|| format!("Invalid config {}", conf_path.display()))
     }
 }
 
+/// Every setting at its default, whatever the config file says.
 impl Default for Config {
     fn default() -> Self {
-        Self::new()
+        toml::from_str("").expect("every setting has a default")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_zero_probe_timeout_is_refused() {
+        let config: Config = toml::from_str("[doctor]\nprobe_timeout_ms = 500\n").unwrap();
+        assert_eq!(config.doctor.probe_timeout(), Duration::from_millis(500));
+
+        let err = toml::from_str::<Config>("[doctor]\nprobe_timeout_ms = 0\n").unwrap_err();
+        assert!(err.to_string().contains("more than 0"), "{err}");
+    }
+
+    #[test]
+    fn a_malformed_dns_probe_host_is_refused() {
+        let config: Config =
+            toml::from_str("[doctor]\ndns_probe_host = \"example.com.\"\n").unwrap();
+        assert_eq!(config.doctor.dns_probe_host, "example.com.");
+
+        let long = "a".repeat(64);
+        for bad in ["example..com", "", ".", long.as_str()] {
+            let toml = format!("[doctor]\ndns_probe_host = \"{bad}\"\n");
+            assert!(toml::from_str::<Config>(&toml).is_err(), "{bad:?}");
+        }
+        assert!(dns_name_problem(&["a"; 128].join(".")).is_some());
     }
 }
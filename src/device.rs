@@ -1,8 +1,8 @@
 use anyhow::Result;
 use std::sync::Arc;
-use zbus::zvariant::OwnedObjectPath;
 
-use crate::nm::{EthernetInfo, Mode, NMClient, NmSnapshot};
+use crate::backend::{Backend, Snapshot};
+use crate::nm::{EthernetInfo, Mode};
 
 use ratatui::{
     Frame,
@@ -20,7 +20,7 @@ use crate::{
 
 #[derive(Clone)]
 pub struct Device {
-    client: Arc<NMClient>,
+    client: Arc<dyn Backend>,
     pub device_path: String,
     pub name: String,
     pub address: String,
@@ -32,14 +32,12 @@ pub struct Device {
 
 impl Device {
     pub async fn new(
-        client: Arc<NMClient>,
-        device_path: OwnedObjectPath,
+        client: Arc<dyn Backend>,
+        device_path: String,
         is_ethernet_connected: bool,
     ) -> Result<Self> {
-        let device_path_str = device_path.as_str().to_string();
-
-        let name = client.get_device_interface(&device_path_str).await?;
-        let address = client.get_device_hw_address(&device_path_str).await?;
+        let name = client.get_device_interface(&device_path).await?;
+        let address = client.get_device_hw_address(&device_path).await?;
         let is_powered = client.is_wireless_enabled().await?;
 
         // Default to Station mode - NetworkManager doesn't have explicit mode switching
@@ -49,12 +47,9 @@ impl Device {
         let (station, ap) = if is_powered {
             match mode {
                 Mode::Station => {
-                    if let Ok(station) = Station::new(
-                        client.clone(),
-                        device_path_str.clone(),
-                        is_ethernet_connected,
-                    )
-                    .await
+                    if let Ok(station) =
+                        Station::new(client.clone(), device_path.clone(), is_ethernet_connected)
+                            .await
                     {
                         (Some(station), None)
                     } else {
@@ -62,8 +57,7 @@ impl Device {
                     }
                 }
                 Mode::Ap => {
-                    if let Ok(ap) = AccessPoint::new(client.clone(), device_path_str.clone()).await
-                    {
+                    if let Ok(ap) = AccessPoint::new(client.clone(), device_path.clone()).await {
                         (None, Some(ap))
                     } else {
                         (None, None)
@@ -76,7 +70,7 @@ impl Device {
 
         Ok(Self {
             client,
-            device_path: device_path_str,
+            device_path,
             name,
             address,
             mode,
@@ -132,7 +126,7 @@ impl Device {
 
     pub async fn refresh(
         &mut self,
-        snapshot: &NmSnapshot,
+        snapshot: &dyn Snapshot,
         is_ethernet_connected: bool,
     ) -> Result<()> {
         self.is_powered = snapshot.wireless_enabled().unwrap_or(self.is_powered);
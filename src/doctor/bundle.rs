@@ -0,0 +1,226 @@
+//! `wlctl doctor --bundle`: everything someone else needs to diagnose the
+//! connection, in one tar archive that is safe to attach to a ticket. Secrets
+//! are redacted; every piece that cannot be read is noted in its place rather
+//! than failing the whole bundle.
+
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::Local;
+use tokio::fs;
+use tokio::process::Command;
+
+use super::CheckEntry;
+use super::context::DoctorContext;
+use super::report::Report;
+
+/// Files the bundle copies verbatim when they exist.
+const RESOLVER_FILES: [(&str, &str); 2] = [
+    ("/etc/resolv.conf", "resolv.conf"),
+    ("/run/systemd/resolve/resolv.conf", "resolved-upstream.conf"),
+];
+
+/// Where the TUI saves a bundle: the downloads folder, else the home
+/// directory, named after the current time.
+pub(super) fn default_path() -> PathBuf {
+    let dir = dirs::download_dir()
+        .or_else(dirs::home_dir)
+        .unwrap_or_else(|| PathBuf::from("."));
+    dir.join(format!(
+        "wlctl-doctor-{}.tar",
+        Local::now().format("%Y%m%d-%H%M%S")
+    ))
+}
+
+/// Collects the bundle for `results` and writes it to `path`.
+pub(super) async fn write(path: &Path, ctx: &DoctorContext, results: &[CheckEntry]) -> Result<()> {
+    let report = Report { entries: results };
+    let mut text = Vec::new();
+    report.write_text(&mut text)?;
+    let mut json = Vec::new();
+    report.write_json(&mut json)?;
+
+    let files = vec![
+        ("info.txt", info(ctx).await.into_bytes()),
+        ("doctor.txt", text),
+        ("doctor.json", json),
+        ("networkmanager.json", networkmanager(ctx).await),
+        ("connection.json", connection(ctx).await),
+        ("device.txt", device(&ctx.interface).await.into_bytes()),
+        ("rfkill.txt", rfkill().await.into_bytes()),
+        ("routes.txt", routes().await.into_bytes()),
+    ];
+    let mut resolver = Vec::new();
+    for (source, name) in RESOLVER_FILES {
+        if let Ok(contents) = fs::read(source).await {
+            resolver.push((name, contents));
+        }
+    }
+
+    // tar writes through blocking file I/O; keep it off the runtime's threads.
+    let path = path.to_path_buf();
+    let files: Vec<_> = files.into_iter().chain(resolver).collect();
+    tokio::task::spawn_blocking(move || write_archive(&path, files))
+        .await
+        .context("Writing the bundle stopped")?
+}
+
+/// Writes `files` into a tar archive at `path`, under a directory named after
+/// the archive.
+fn write_archive(path: &Path, files: Vec<(&str, Vec<u8>)>) -> Result<()> {
+    let root = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "wlctl-doctor".to_string());
+    let file =
+        File::create(path).with_context(|| format!("Could not create {}", path.display()))?;
+    let mut archive = tar::Builder::new(file);
+    let mtime = Local::now().timestamp().max(0) as u64;
+
+    for (name, contents) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(mtime);
+        header.set_cksum();
+        archive
+            .append_data(
+                &mut header,
+                format!("{}/{}", root, name),
+                contents.as_slice(),
+            )
+            .with_context(|| format!("Could not write {}", path.display()))?;
+    }
+    archive
+        .into_inner()
+        .with_context(|| format!("Could not write {}", path.display()))?;
+    Ok(())
+}
+
+async fn info(ctx: &DoctorContext) -> String {
+    let kernel = fs::read_to_string("/proc/sys/kernel/osrelease")
+        .await
+        .unwrap_or_default();
+    format!(
+        "wlctl {}\ncollected {}\ninterface {}\ndevice {}\nkernel {}\n",
+        env!("CARGO_PKG_VERSION"),
+        Local::now().to_rfc3339(),
+        ctx.interface,
+        ctx.device_path,
+        kernel.trim()
+    )
+}
+
+async fn networkmanager(ctx: &DoctorContext) -> Vec<u8> {
+    match ctx.backend.snapshot().await {
+        Ok(snapshot) => pretty(&snapshot.to_json()),
+        Err(e) => pretty(&serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+/// Settings of the connection active on the diagnosed device.
+async fn connection(ctx: &DoctorContext) -> Vec<u8> {
+    let active = match ctx.backend.snapshot().await {
+        Ok(snapshot) => snapshot
+            .active_connections()
+            .into_iter()
+            .find(|c| c.devices.contains(&ctx.device_path)),
+        Err(e) => return pretty(&serde_json::json!({ "error": e.to_string() })),
+    };
+    let Some(active) = active else {
+        return pretty(&serde_json::json!({ "error": "no active connection on the device" }));
+    };
+
+    match ctx.backend.profile_json(&active.connection_path).await {
+        Ok(settings) => pretty(&settings),
+        Err(e) => pretty(&serde_json::json!({ "error": e.to_string() })),
+    }
+}
+
+/// The interface's sysfs attributes and what is known about its driver.
+async fn device(interface: &str) -> String {
+    let base = PathBuf::from("/sys/class/net").join(interface);
+    let mut out = String::new();
+
+    for attr in ["address", "operstate", "mtu", "carrier"] {
+        let value = fs::read_to_string(base.join(attr)).await;
+        out.push_str(&format!("{}: {}\n", attr, read_or_note(value)));
+    }
+
+    let driver = fs::read_link(base.join("device/driver"))
+        .await
+        .ok()
+        .and_then(|target| Some(target.file_name()?.to_string_lossy().into_owned()));
+    out.push_str(&format!(
+        "driver: {}\n",
+        driver.as_deref().unwrap_or("<none bound>")
+    ));
+    if let Some(driver) = &driver {
+        let version = fs::read_to_string(format!("/sys/module/{}/version", driver)).await;
+        out.push_str(&format!("driver version: {}\n", read_or_note(version)));
+    }
+    let phy = fs::read_to_string(base.join("phy80211/name")).await;
+    out.push_str(&format!("phy: {}\n", read_or_note(phy)));
+
+    let uevent = fs::read_to_string(base.join("device/uevent")).await;
+    out.push_str("\n[device/uevent]\n");
+    out.push_str(&read_or_note(uevent));
+    out.push('\n');
+    out
+}
+
+async fn rfkill() -> String {
+    let Ok(mut entries) = fs::read_dir("/sys/class/rfkill").await else {
+        return "rfkill sysfs not available\n".to_string();
+    };
+
+    let mut out = String::new();
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let path = entry.path();
+        let mut fields = Vec::new();
+        for attr in ["name", "type", "soft", "hard"] {
+            let value = fs::read_to_string(path.join(attr)).await;
+            fields.push(format!("{}={}", attr, read_or_note(value)));
+        }
+        out.push_str(&format!(
+            "{}: {}\n",
+            entry.file_name().to_string_lossy(),
+            fields.join(" ")
+        ));
+    }
+    out
+}
+
+/// IPv4 and IPv6 routes from `ip`, or the raw kernel tables without it.
+async fn routes() -> String {
+    let mut out = String::new();
+    for (family, table) in [("-4", "/proc/net/route"), ("-6", "/proc/net/ipv6_route")] {
+        out.push_str(&format!("[ip {} route]\n", family));
+        match Command::new("ip")
+            .args([family, "route", "show", "table", "all"])
+            .output()
+            .await
+        {
+            Ok(output) if output.status.success() => {
+                out.push_str(&String::from_utf8_lossy(&output.stdout));
+            }
+            _ => out.push_str(&read_or_note(fs::read_to_string(table).await)),
+        }
+        out.push('\n');
+    }
+    out
+}
+
+fn read_or_note(value: std::io::Result<String>) -> String {
+    match value {
+        Ok(v) => v.trim().to_string(),
+        Err(e) => format!("<unreadable: {}>", e),
+    }
+}
+
+fn pretty(value: &serde_json::Value) -> Vec<u8> {
+    let mut out = serde_json::to_vec_pretty(value).unwrap_or_default();
+    out.push(b'\n');
+    out
+}
@@ -0,0 +1,301 @@
+//! Captive portal login helper: finds where a portal wants the user to log in
+//! by fetching NetworkManager's own connectivity-check URI and following the
+//! hijacked response, then hands that page to a browser.
+
+use std::io::{self, IsTerminal, Write};
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::process::Command;
+use tokio::time::timeout;
+
+use super::context::DoctorContext;
+use super::report::{Format, Report};
+use super::{CheckEntry, Doctor};
+
+/// Responses beyond this are a portal's full login page; the redirect and any
+/// meta refresh sit well inside it.
+const MAX_RESPONSE: usize = 64 * 1024;
+
+/// Fetches `check_uri` and returns the portal's login page: the redirect
+/// target, or the URL of an HTML meta refresh. `None` when the portal serves
+/// its page in place, in which case opening `check_uri` itself shows it.
+pub async fn login_url(check_uri: &str, limit: Duration) -> Result<Option<String>> {
+    let (host, port, path) = parse_http_url(check_uri)
+        .with_context(|| format!("Unsupported connectivity-check URI {}", check_uri))?;
+
+    let exchange = async {
+        let mut stream = TcpStream::connect((host.as_str(), port)).await?;
+        let request = format!(
+            "GET {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: wlctl\r\nConnection: close\r\n\r\n",
+            path, host
+        );
+        stream.write_all(request.as_bytes()).await?;
+
+        let mut response = Vec::new();
+        stream
+            .take(MAX_RESPONSE as u64)
+            .read_to_end(&mut response)
+            .await?;
+        Ok::<_, std::io::Error>(response)
+    };
+
+    let response = match timeout(limit, exchange).await {
+        Ok(result) => result.context("Could not fetch the connectivity-check URI")?,
+        Err(_) => bail!("Connectivity-check URI did not answer within {:?}", limit),
+    };
+
+    Ok(login_url_from_response(
+        &host,
+        port,
+        &String::from_utf8_lossy(&response),
+    ))
+}
+
+/// Opens `url` in the desktop's browser via `xdg-open`. Returns once the
+/// launcher has started; it is reaped in the background.
+pub fn open_in_browser(url: &str) -> Result<()> {
+    let mut child = Command::new("xdg-open")
+        .arg(url)
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .context("Could not run xdg-open")?;
+    tokio::spawn(async move {
+        let _ = child.wait().await;
+    });
+    Ok(())
+}
+
+/// Whether a graphical session is around for `xdg-open` to open a browser in.
+pub fn has_display() -> bool {
+    ["DISPLAY", "WAYLAND_DISPLAY"]
+        .iter()
+        .any(|var| std::env::var_os(var).is_some_and(|v| !v.is_empty()))
+}
+
+/// Whether the CLI can ask the user about a login page and wait for them.
+pub(super) fn interactive() -> bool {
+    io::stdin().is_terminal() && io::stdout().is_terminal()
+}
+
+/// CLI follow-up to a report with a captive portal in it: offers to open the
+/// login page (or prints it when there is no browser to open it in), waits
+/// for the user to log in, then re-runs the connectivity check and swaps its
+/// row in `results`. Repeats until the portal is gone or stdin closes.
+pub(super) async fn assist(ctx: &DoctorContext, results: &mut [CheckEntry]) -> Result<()> {
+    while let Some(entry) = results.iter_mut().find(|e| e.outcome.link.is_some()) {
+        let url = entry.outcome.link.clone().unwrap_or_default();
+        println!();
+        if has_display() {
+            if ask("Open the login page in a browser? [Y/n] ")?
+                .is_some_and(|answer| !answer.eq_ignore_ascii_case("n"))
+                && let Err(e) = open_in_browser(&url)
+            {
+                println!("{}; open {} yourself.", e, url);
+            }
+        } else {
+            println!("Open {} in a browser on any device to log in.", url);
+        }
+
+        if ask("Press Enter once you have logged in (Ctrl-D to stop) ")?.is_none() {
+            println!();
+            break;
+        }
+
+        let recheck = Doctor::select(&["portal".to_string()], &[])?.run(ctx).await;
+        Report { entries: &recheck }.print(Format::Text)?;
+        if let Some(fresh) = recheck.into_iter().next() {
+            *entry = fresh;
+        }
+    }
+    Ok(())
+}
+
+/// Prints `prompt` and reads one line, trimmed; `None` on end of input.
+pub(super) fn ask(prompt: &str) -> Result<Option<String>> {
+    print!("{}", prompt);
+    io::stdout().flush()?;
+    let mut line = String::new();
+    let read = io::stdin()
+        .read_line(&mut line)
+        .context("Could not read from stdin")?;
+    Ok((read > 0).then(|| line.trim().to_string()))
+}
+
+/// `(host, port, path)` of a plain `http://` URL. Connectivity checks are
+/// deliberately unencrypted so portals can intercept them; anything else is
+/// refused.
+fn parse_http_url(url: &str) -> Option<(String, u16, String)> {
+    let rest = url.strip_prefix("http://")?;
+    let (authority, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/"),
+    };
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) if !host.ends_with(']') || authority.starts_with('[') => {
+            (host, port.parse().ok()?)
+        }
+        _ => (authority, 80),
+    };
+    if host.is_empty() {
+        return None;
+    }
+    Some((host.to_string(), port, path.to_string()))
+}
+
+fn login_url_from_response(host: &str, port: u16, response: &str) -> Option<String> {
+    let (head, body) = response.split_once("\r\n\r\n").unwrap_or((response, ""));
+    let mut lines = head.lines();
+    let status: u16 = lines.next()?.split_whitespace().nth(1)?.parse().ok()?;
+
+    let target = if (300..400).contains(&status) {
+        lines.find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.trim()
+                .eq_ignore_ascii_case("location")
+                .then(|| value.trim().to_string())
+        })?
+    } else {
+        meta_refresh(body)?
+    };
+
+    Some(absolute(host, port, &target))
+}
+
+/// The URL of `<meta http-equiv="refresh" content="0; url=...">`, the other
+/// way portals bounce a browser to their login page. The content may be
+/// quoted or not; the page may hold any text around the tag.
+fn meta_refresh(body: &str) -> Option<String> {
+    let (_, tag) = split_once_ignore_case(body, "http-equiv=\"refresh\"")?;
+    let (_, content) = split_once_ignore_case(tag, "content=")?;
+    let value = match content.chars().next()? {
+        quote @ ('"' | '\'') => content[1..].split_once(quote)?.0,
+        _ => content.split(['>', ' ', '\t', '\n']).next()?,
+    };
+    let (_, url) = split_once_ignore_case(value, "url=")?;
+    let url = url.trim().trim_matches(['\'', '"']);
+    (!url.is_empty()).then(|| url.to_string())
+}
+
+/// `haystack` after the first ASCII-case-insensitive match of `needle`, along
+/// with what came before it.
+fn split_once_ignore_case<'a>(haystack: &'a str, needle: &str) -> Option<(&'a str, &'a str)> {
+    haystack.char_indices().find_map(|(i, _)| {
+        let rest = &haystack[i..];
+        rest.get(..needle.len())
+            .is_some_and(|head| head.eq_ignore_ascii_case(needle))
+            .then(|| (&haystack[..i], &rest[needle.len()..]))
+    })
+}
+
+/// Resolves a redirect target against the host it came from.
+fn absolute(host: &str, port: u16, target: &str) -> String {
+    if target.contains("://") {
+        return target.to_string();
+    }
+    let authority = if port == 80 {
+        host.to_string()
+    } else {
+        format!("{}:{}", host, port)
+    };
+    if let Some(rest) = target.strip_prefix("//") {
+        format!("http://{}", rest)
+    } else if target.starts_with('/') {
+        format!("http://{}{}", authority, target)
+    } else {
+        format!("http://{}/{}", authority, target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_http_urls_only() {
+        assert_eq!(
+            parse_http_url("http://nmcheck.gnome.org/check_network_status.txt"),
+            Some((
+                "nmcheck.gnome.org".into(),
+                80,
+                "/check_network_status.txt".into()
+            ))
+        );
+        assert_eq!(
+            parse_http_url("http://10.0.0.1:8080"),
+            Some(("10.0.0.1".into(), 8080, "/".into()))
+        );
+        assert_eq!(parse_http_url("https://example.com/"), None);
+    }
+
+    #[test]
+    fn redirect_location_is_the_login_page() {
+        let response = "HTTP/1.1 302 Found\r\nContent-Length: 0\r\n\
+                        location: https://portal.example/login?orig=x\r\n\r\n";
+        assert_eq!(
+            login_url_from_response("check.example", 80, response).as_deref(),
+            Some("https://portal.example/login?orig=x")
+        );
+
+        let relative = "HTTP/1.1 307 Temporary Redirect\r\nLocation: /guest\r\n\r\n";
+        assert_eq!(
+            login_url_from_response("10.0.0.1", 8080, relative).as_deref(),
+            Some("http://10.0.0.1:8080/guest")
+        );
+    }
+
+    #[test]
+    fn meta_refresh_is_followed() {
+        let response = "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\n\r\n\
+                        <html><head><META HTTP-EQUIV=\"Refresh\" \
+                        content=\"0; URL='http://192.168.3.1/login'\"></head></html>";
+        assert_eq!(
+            login_url_from_response("check.example", 80, response).as_deref(),
+            Some("http://192.168.3.1/login")
+        );
+    }
+
+    #[test]
+    fn meta_refresh_survives_odd_pages() {
+        let unquoted = "<meta http-equiv=\"refresh\" content=5;url=/login>";
+        assert_eq!(meta_refresh(unquoted).as_deref(), Some("/login"));
+
+        let multibyte = "<p>Willkommen im Café – bitte anmelden</p>\
+                         <meta http-equiv=\"refresh\" content=\"0; url=/anmelden\">";
+        assert_eq!(meta_refresh(multibyte).as_deref(), Some("/anmelden"));
+
+        assert_eq!(
+            meta_refresh("<meta http-equiv=\"refresh\" content=\"5\">"),
+            None
+        );
+        assert_eq!(meta_refresh("«http-equiv=\"refresh\" content=»"), None);
+    }
+
+    #[tokio::test]
+    async fn fetches_the_check_uri_and_follows_the_redirect() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = [0; 1024];
+            let _ = stream.read(&mut request).await;
+            let _ = stream
+                .write_all(b"HTTP/1.1 302 Found\r\nLocation: /login\r\n\r\n")
+                .await;
+        });
+
+        let uri = format!("http://127.0.0.1:{}/check", port);
+        let login = login_url(&uri, Duration::from_secs(2)).await.unwrap();
+        assert_eq!(login, Some(format!("http://127.0.0.1:{}/login", port)));
+    }
+
+    #[test]
+    fn page_served_in_place_has_no_separate_url() {
+        let response = "HTTP/1.1 200 OK\r\n\r\nNetworkManager is online\n";
+        assert_eq!(login_url_from_response("check.example", 80, response), None);
+    }
+}
@@ -1,9 +1,13 @@
+use std::time::Duration;
+
 use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
 
 use super::context::DoctorContext;
 
 /// The verdict of a single diagnostic step.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Status {
     Ok,
     Warn,
@@ -11,12 +15,58 @@ pub enum Status {
     Skip,
 }
 
+impl Status {
+    /// How bad this status is, for picking the worst of a run. A skipped check
+    /// proves nothing either way, so it ranks with `Ok`.
+    pub fn severity(self) -> u8 {
+        match self {
+            Status::Ok | Status::Skip => 0,
+            Status::Warn => 1,
+            Status::Fail => 2,
+        }
+    }
+}
+
+/// A remediation the doctor can apply itself, offered alongside a verdict.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fix {
+    /// Turn the radio back on, lifting a soft rfkill block.
+    EnableWireless,
+    /// Activate the device's connection again (or the best available one).
+    Reconnect,
+    /// Ask the device for a fresh scan.
+    Rescan,
+    /// Bring the device's connection up again for a fresh DHCP lease.
+    RenewDhcp,
+    /// Override the nameservers with `[doctor] fallback_dns` until the next
+    /// reconnect.
+    FallbackDns,
+}
+
+impl Fix {
+    /// Short imperative label for prompts and key hints.
+    pub fn label(self) -> &'static str {
+        match self {
+            Fix::EnableWireless => "Turn WiFi on",
+            Fix::Reconnect => "Reconnect",
+            Fix::Rescan => "Rescan",
+            Fix::RenewDhcp => "Reconnect to renew the DHCP lease",
+            Fix::FallbackDns => "Use fallback DNS",
+        }
+    }
+}
+
 /// Result of one diagnostic check.
 #[derive(Debug, Clone)]
 pub struct Outcome {
     pub status: Status,
     pub summary: String,
     pub verdict: Option<String>,
+    /// A page the user has to visit to resolve the problem, such as a captive
+    /// portal's login form.
+    pub link: Option<String>,
+    /// Something the doctor can do about the problem on request.
+    pub fix: Option<Fix>,
 }
 
 impl Outcome {
@@ -25,6 +75,8 @@ impl Outcome {
             status: Status::Ok,
             summary: summary.into(),
             verdict: None,
+            link: None,
+            fix: None,
         }
     }
 
@@ -33,6 +85,8 @@ impl Outcome {
             status: Status::Warn,
             summary: summary.into(),
             verdict: None,
+            link: None,
+            fix: None,
         }
     }
 
@@ -41,6 +95,8 @@ impl Outcome {
             status: Status::Fail,
             summary: summary.into(),
             verdict: Some(verdict.into()),
+            link: None,
+            fix: None,
         }
     }
 
@@ -49,15 +105,56 @@ impl Outcome {
             status: Status::Skip,
             summary: summary.into(),
             verdict: None,
+            link: None,
+            fix: None,
         }
     }
+
+    /// Attaches advice to an outcome that does not carry one by default, e.g. a
+    /// warning whose cause is clear enough to suggest a fix.
+    pub fn with_verdict(mut self, verdict: impl Into<String>) -> Self {
+        self.verdict = Some(verdict.into());
+        self
+    }
+
+    pub fn with_link(mut self, link: impl Into<String>) -> Self {
+        self.link = Some(link.into());
+        self
+    }
+
+    pub fn with_fix(mut self, fix: Fix) -> Self {
+        self.fix = Some(fix);
+        self
+    }
 }
 
+/// Generous enough for every built-in check's own network timeouts to fire
+/// first; it only catches a check wedged on something unexpected.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
 /// A single diagnostic check. Each implementation owns exactly one concern —
 /// rfkill, driver presence, DHCP lease, DNS resolution, etc.
 #[async_trait]
 pub trait DiagnosticCheck: Send + Sync {
     fn name(&self) -> &'static str;
+
+    /// Key used by `--only` / `--skip`. Defaults to the display name; checks
+    /// whose name has spaces or reads differently on the command line override it.
+    fn id(&self) -> &'static str {
+        self.name()
+    }
+
+    /// Ids of the checks that must pass before this one is worth running. If
+    /// any of them fails, this check is skipped instead of run.
+    fn depends_on(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// How long the check may take before it is reported as a warning.
+    fn timeout(&self) -> Duration {
+        DEFAULT_TIMEOUT
+    }
+
     async fn run(&self, ctx: &DoctorContext) -> Outcome;
 }
 
@@ -80,6 +177,13 @@ mod tests {
         assert_eq!(o.verdict.as_deref(), Some("do the thing"));
     }
 
+    #[test]
+    fn severity_ranks_skip_with_ok() {
+        assert_eq!(Status::Skip.severity(), Status::Ok.severity());
+        assert!(Status::Warn.severity() > Status::Ok.severity());
+        assert!(Status::Fail.severity() > Status::Warn.severity());
+    }
+
     #[test]
     fn warn_and_skip_have_no_verdict() {
         assert!(Outcome::warn("warn").verdict.is_none());
@@ -1,6 +1,6 @@
 use async_trait::async_trait;
 
-use crate::doctor::check::{DiagnosticCheck, Outcome};
+use crate::doctor::check::{DiagnosticCheck, Fix, Outcome};
 use crate::doctor::context::DoctorContext;
 
 /// Looks up the currently-associated access point and reports its SSID.
@@ -12,16 +12,20 @@ impl DiagnosticCheck for AssociationCheck {
         "association"
     }
 
+    fn depends_on(&self) -> &'static [&'static str] {
+        &["interface"]
+    }
+
     async fn run(&self, ctx: &DoctorContext) -> Outcome {
-        let ap_path = match ctx.nm.get_active_access_point(&ctx.device_path).await {
+        let ap_path = match ctx.backend.get_active_access_point(&ctx.device_path).await {
             Ok(Some(p)) => p,
             Ok(None) => {
-                return Outcome::warn("not associated with any AP");
+                return Outcome::warn("not associated with any AP").with_fix(Fix::Rescan);
             }
             Err(e) => return Outcome::skip(format!("could not read active AP: {}", e)),
         };
 
-        match ctx.nm.get_access_point_info(ap_path.as_str()).await {
+        match ctx.backend.get_access_point_info(&ap_path).await {
             Ok(info) => {
                 let ssid = if info.ssid.is_empty() {
                     "<hidden>"
@@ -1,9 +1,15 @@
 use async_trait::async_trait;
 
-use crate::doctor::check::{DiagnosticCheck, Outcome};
+use crate::doctor::CheckEntry;
+use crate::doctor::check::{DiagnosticCheck, Fix, Outcome, Status};
 use crate::doctor::context::DoctorContext;
 use crate::nm::DeviceState;
 
+/// Said of a FAILED device, unless the logs check ran to explain it; see
+/// `point_to_logs`.
+const FAILED_VERDICT: &str =
+    "Last connection attempt failed; `journalctl -u NetworkManager` shows why.";
+
 /// Reports the NetworkManager device state (DISCONNECTED, CONNECTING, ACTIVATED...).
 pub struct DeviceStateCheck;
 
@@ -13,8 +19,12 @@ impl DiagnosticCheck for DeviceStateCheck {
         "interface"
     }
 
+    fn depends_on(&self) -> &'static [&'static str] {
+        &["rfkill", "driver"]
+    }
+
     async fn run(&self, ctx: &DoctorContext) -> Outcome {
-        match ctx.nm.get_device_state(&ctx.device_path).await {
+        match ctx.backend.get_device_state(&ctx.device_path).await {
             Ok(state) => match state {
                 DeviceState::Activated => Outcome::ok(format!("{} is ACTIVATED", ctx.interface)),
                 DeviceState::Config | DeviceState::IpConfig | DeviceState::IpCheck => {
@@ -23,18 +33,47 @@ impl DiagnosticCheck for DeviceStateCheck {
                 DeviceState::Disconnected | DeviceState::Deactivating => Outcome::warn(format!(
                     "{} is {:?} — no active connection",
                     ctx.interface, state
-                )),
-                DeviceState::Unavailable => Outcome::fail(
-                    format!("{} is UNAVAILABLE", ctx.interface),
-                    "NetworkManager cannot manage this device. Check rfkill and driver state above.",
-                ),
-                DeviceState::Failed => Outcome::fail(
-                    format!("{} is FAILED", ctx.interface),
-                    "Last connection attempt failed. Check `journalctl -u NetworkManager -n 50`.",
-                ),
+                ))
+                .with_fix(Fix::Reconnect),
+                DeviceState::Unavailable => {
+                    let outcome = Outcome::fail(
+                        format!("{} is UNAVAILABLE", ctx.interface),
+                        "NetworkManager cannot manage this device. Check rfkill and driver state above.",
+                    );
+                    // Turning WiFi off in NetworkManager also leaves the device
+                    // unavailable, and that much the doctor can undo.
+                    match ctx.backend.is_wireless_enabled().await {
+                        Ok(false) => outcome.with_fix(Fix::EnableWireless),
+                        _ => outcome,
+                    }
+                }
+                DeviceState::Failed => {
+                    Outcome::fail(format!("{} is FAILED", ctx.interface), FAILED_VERDICT)
+                        .with_fix(Fix::Reconnect)
+                }
                 _ => Outcome::warn(format!("{} state: {:?}", ctx.interface, state)),
             },
             Err(e) => Outcome::skip(format!("could not read device state: {}", e)),
         }
     }
 }
+
+/// Points a FAILED device's verdict at the logs check, which runs alongside
+/// it, once that check has read the journal. Left as is when the check was
+/// left out or could not read it.
+pub fn point_to_logs(results: &mut [CheckEntry]) {
+    let logs_ran = results
+        .iter()
+        .any(|e| e.name == "logs" && e.outcome.status != Status::Skip);
+    if !logs_ran {
+        return;
+    }
+    for entry in results.iter_mut().filter(|e| e.name == "interface") {
+        if entry.outcome.verdict.as_deref() == Some(FAILED_VERDICT) {
+            entry.outcome.verdict = Some(
+                "Last connection attempt failed; the logs check explains why from the journal."
+                    .to_string(),
+            );
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use std::net::Ipv4Addr;
+
+use async_trait::async_trait;
+
+use crate::doctor::check::{DiagnosticCheck, Fix, Outcome};
+use crate::doctor::context::DoctorContext;
+use crate::vpn::format_duration;
+
+const NO_ANSWER: &str = "The DHCP server did not answer. Reconnect, or check that the router's DHCP service is running and its pool is not exhausted.";
+
+/// Inspects the DHCPv4 lease: who granted it, how long it has left, and what
+/// it handed out.
+pub struct DhcpCheck;
+
+#[async_trait]
+impl DiagnosticCheck for DhcpCheck {
+    fn name(&self) -> &'static str {
+        "dhcp"
+    }
+
+    fn depends_on(&self) -> &'static [&'static str] {
+        &["association"]
+    }
+
+    async fn run(&self, ctx: &DoctorContext) -> Outcome {
+        // A self-assigned address means DHCP gave up, whatever the lease says.
+        if let Ok(Some(info)) = ctx.backend.get_ip4_info(&ctx.device_path).await
+            && let Some((addr, _)) = info
+                .addresses
+                .iter()
+                .find(|(a, _)| a.parse::<Ipv4Addr>().is_ok_and(|ip| ip.is_link_local()))
+        {
+            return Outcome::fail(format!("link-local address {}", addr), NO_ANSWER)
+                .with_fix(Fix::RenewDhcp);
+        }
+
+        let lease = match ctx.backend.get_dhcp4_lease(&ctx.device_path).await {
+            Ok(Some(lease)) => lease,
+            Ok(None) => return Outcome::skip("no DHCP lease (static or not configured)"),
+            Err(e) => return Outcome::skip(format!("could not read DHCP lease: {}", e)),
+        };
+
+        let now = chrono::Local::now().timestamp();
+        let server = lease.server.as_deref().unwrap_or("unknown server");
+
+        if lease.is_expiring(now) {
+            let left = lease.remaining(now).unwrap_or(0);
+            return Outcome::fail(
+                format!("lease from {} expires in {}", server, format_duration(left)),
+                "The DHCP server is not answering renewals, so the address will be dropped soon. Check the router's DHCP service, or reconnect to request a fresh lease.",
+            )
+            .with_fix(Fix::RenewDhcp);
+        }
+
+        let mut parts = vec![format!("from {}", server)];
+        match lease.remaining(now) {
+            Some(left) => parts.push(format!("{} left", format_duration(left))),
+            None => parts.push("no expiry".to_string()),
+        }
+        if let Some(routers) = lease.options.get("routers") {
+            parts.push(format!("router {}", routers));
+        }
+        if let Some(dns) = lease.options.get("domain_name_servers") {
+            parts.push(format!("dns {}", dns));
+        }
+        if let Some(domain) = lease.options.get("domain_name") {
+            parts.push(format!("domain {}", domain));
+        }
+        Outcome::ok(parts.join(", "))
+    }
+}
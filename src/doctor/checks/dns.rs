@@ -1,16 +1,23 @@
-use std::time::Duration;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
-use tokio::net::lookup_host;
+use futures::future::join_all;
+use tokio::fs;
+use tokio::net::{UdpSocket, lookup_host};
 use tokio::time::timeout;
 
-use crate::doctor::check::{DiagnosticCheck, Outcome};
+use crate::config::dns_name_problem;
+use crate::doctor::check::{DiagnosticCheck, Fix, Outcome};
 use crate::doctor::context::DoctorContext;
 
-const PROBE_HOST: &str = "one.one.one.one:80";
-const DNS_TIMEOUT: Duration = Duration::from_secs(3);
+const RESOLV_CONF: &str = "/etc/resolv.conf";
+/// Where systemd-resolved lists the upstream servers behind its 127.0.0.53 stub.
+const RESOLVED_UPSTREAM: &str = "/run/systemd/resolve/resolv.conf";
 
-/// Resolves a well-known host to confirm DNS is working.
+/// Queries every nameserver NetworkManager handed out directly over UDP, then
+/// resolves through the system resolver, and checks the two agree on which
+/// servers are in use.
 pub struct DnsCheck;
 
 #[async_trait]
@@ -19,23 +26,339 @@ impl DiagnosticCheck for DnsCheck {
         "dns"
     }
 
-    async fn run(&self, _ctx: &DoctorContext) -> Outcome {
-        match timeout(DNS_TIMEOUT, lookup_host(PROBE_HOST)).await {
-            Ok(Ok(mut addrs)) => match addrs.next() {
-                Some(addr) => Outcome::ok(format!("resolves ({})", addr.ip())),
-                None => Outcome::fail(
-                    "DNS returned no records",
-                    "Resolver reachable but empty response. Check /etc/resolv.conf.",
-                ),
-            },
-            Ok(Err(e)) => Outcome::fail(
-                format!("DNS lookup failed: {}", e),
-                "Check your DNS servers (nmcli -g IP4.DNS device show) or try overriding with 1.1.1.1.",
-            ),
-            Err(_) => Outcome::fail(
-                format!("DNS lookup timed out after {}s", DNS_TIMEOUT.as_secs()),
-                "DNS server is unreachable. Check /etc/resolv.conf or the router's DNS.",
-            ),
+    fn depends_on(&self) -> &'static [&'static str] {
+        &["ip"]
+    }
+
+    async fn run(&self, ctx: &DoctorContext) -> Outcome {
+        let assigned: Vec<IpAddr> = match ctx.backend.get_ip4_info(&ctx.device_path).await {
+            Ok(Some(info)) => info
+                .nameservers
+                .iter()
+                .filter_map(|s| s.parse().ok())
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        let settings = &ctx.config.doctor;
+        let name = settings.dns_probe_host.as_str();
+        let limit = settings.probe_timeout();
+
+        let (probes, system, resolver) = tokio::join!(
+            join_all(assigned.iter().map(|&server| probe(server, name, limit))),
+            system_lookup(name, limit),
+            system_nameservers(),
+        );
+
+        assess(&assigned, &probes, system, limit, resolver.as_deref())
+    }
+}
+
+/// How one nameserver responded to a direct query.
+#[derive(Debug, Clone, PartialEq)]
+enum Probe {
+    Answered(Duration),
+    /// Replied with an error code such as SERVFAIL or REFUSED.
+    Rejected(&'static str),
+    TimedOut,
+    Failed(String),
+}
+
+impl Probe {
+    fn answered(&self) -> bool {
+        matches!(self, Probe::Answered(_))
+    }
+
+    fn describe(&self, server: IpAddr) -> String {
+        match self {
+            Probe::Answered(latency) => format!("{} {}ms", server, latency.as_millis()),
+            Probe::Rejected(rcode) => format!("{} {}", server, rcode),
+            Probe::TimedOut => format!("{} no answer", server),
+            Probe::Failed(e) => format!("{} {}", server, e),
         }
     }
 }
+
+/// Outcome of resolving through libc, i.e. whatever resolv.conf points at.
+#[derive(Debug, Clone, PartialEq)]
+enum SystemLookup {
+    Resolved(IpAddr),
+    Empty,
+    Failed(String),
+    TimedOut,
+}
+
+fn assess(
+    assigned: &[IpAddr],
+    probes: &[Probe],
+    system: SystemLookup,
+    limit: Duration,
+    resolver: Option<&[IpAddr]>,
+) -> Outcome {
+    let servers = assigned
+        .iter()
+        .zip(probes)
+        .map(|(&server, probe)| probe.describe(server))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let answering = probes.iter().filter(|p| p.answered()).count();
+
+    // resolv.conf naming none of NM's servers means something else rewrote it.
+    // A loopback entry is a local cache (resolved without its upstream file,
+    // dnsmasq, ...) whose upstreams cannot be compared, so it gets no say.
+    let mismatch = resolver.filter(|r| {
+        !assigned.is_empty()
+            && !r.is_empty()
+            && !r.iter().any(IpAddr::is_loopback)
+            && !r.iter().any(|s| assigned.contains(s))
+    });
+
+    if let Some(resolver) = mismatch {
+        let pointed = resolver
+            .iter()
+            .map(IpAddr::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+        let summary = format!(
+            "resolv.conf uses {}, NetworkManager gave {}",
+            pointed, servers
+        );
+        let verdict = "Something other than NetworkManager rewrote /etc/resolv.conf (a VPN client, dhclient, or a manual edit). Point it back: `ln -sf /run/NetworkManager/resolv.conf /etc/resolv.conf`, or restart systemd-resolved.";
+        return match system {
+            SystemLookup::Resolved(_) => Outcome::warn(summary).with_verdict(verdict),
+            _ => Outcome::fail(summary, verdict),
+        };
+    }
+
+    match system {
+        SystemLookup::Resolved(addr) if assigned.is_empty() => {
+            Outcome::ok(format!("resolves ({})", addr))
+        }
+        SystemLookup::Resolved(_) if answering == assigned.len() => Outcome::ok(servers),
+        SystemLookup::Resolved(_) => Outcome::warn(servers)
+            .with_verdict(
+                "Some nameservers NetworkManager handed out do not answer, so lookups stall whenever they are tried first. Check the router's DNS settings or override the servers on this connection.",
+            )
+            .with_fix(Fix::FallbackDns),
+        SystemLookup::Empty => Outcome::fail(
+            "DNS returned no records",
+            "Resolver reachable but empty response. Check /etc/resolv.conf.",
+        ),
+        _ if !assigned.is_empty() && answering == 0 => Outcome::fail(
+            format!("no nameserver answered ({})", servers),
+            "DNS server is unreachable. Check the router's DNS, or override with 1.1.1.1 on this connection.",
+        )
+        .with_fix(Fix::FallbackDns),
+        SystemLookup::Failed(e) => Outcome::fail(
+            format!("DNS lookup failed: {}", e),
+            "Check your DNS servers (nmcli -g IP4.DNS device show) or try overriding with 1.1.1.1.",
+        )
+        .with_fix(Fix::FallbackDns),
+        SystemLookup::TimedOut => Outcome::fail(
+            format!("DNS lookup timed out after {}s", limit.as_secs_f32()),
+            "DNS server is unreachable. Check /etc/resolv.conf or the router's DNS.",
+        )
+        .with_fix(Fix::FallbackDns),
+    }
+}
+
+async fn system_lookup(name: &str, limit: Duration) -> SystemLookup {
+    match timeout(limit, lookup_host((name, 80))).await {
+        Ok(Ok(mut addrs)) => match addrs.next() {
+            Some(addr) => SystemLookup::Resolved(addr.ip()),
+            None => SystemLookup::Empty,
+        },
+        Ok(Err(e)) => SystemLookup::Failed(e.to_string()),
+        Err(_) => SystemLookup::TimedOut,
+    }
+}
+
+/// The servers the system resolver actually uses. Behind systemd-resolved's
+/// stub that is its upstream list, not the stub address. `None` when
+/// resolv.conf cannot be read.
+async fn system_nameservers() -> Option<Vec<IpAddr>> {
+    let servers = parse_resolv_conf(&fs::read_to_string(RESOLV_CONF).await.ok()?);
+    let stub = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 53));
+    if servers.contains(&stub)
+        && let Ok(upstream) = fs::read_to_string(RESOLVED_UPSTREAM).await
+    {
+        return Some(parse_resolv_conf(&upstream));
+    }
+    Some(servers)
+}
+
+fn parse_resolv_conf(contents: &str) -> Vec<IpAddr> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let mut words = line.split_whitespace();
+            (words.next()? == "nameserver").then(|| words.next())?
+        })
+        // Scoped IPv6 entries (fe80::1%wlan0) cannot be parsed as an IpAddr.
+        .filter_map(|addr| addr.split('%').next()?.parse().ok())
+        .collect()
+}
+
+/// Sends one A query for `name` straight to `server` and waits for its reply.
+async fn probe(server: IpAddr, name: &str, limit: Duration) -> Probe {
+    let bind: SocketAddr = match server {
+        IpAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+        IpAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+    };
+    let id = query_id();
+    let query = match build_query(id, name) {
+        Ok(query) => query,
+        Err(e) => return Probe::Failed(e),
+    };
+
+    let exchange = async {
+        let socket = UdpSocket::bind(bind).await?;
+        socket.connect((server, 53)).await?;
+        let started = Instant::now();
+        socket.send(&query).await?;
+        let mut buf = [0u8; 512];
+        loop {
+            let len = socket.recv(&mut buf).await?;
+            // A stray datagram with another id is not our answer; keep waiting.
+            if let Some(rcode) = response_rcode(id, &buf[..len]) {
+                return Ok::<_, std::io::Error>((rcode, started.elapsed()));
+            }
+        }
+    };
+
+    match timeout(limit, exchange).await {
+        Ok(Ok((0 | 3, latency))) => Probe::Answered(latency),
+        Ok(Ok((rcode, _))) => Probe::Rejected(rcode_name(rcode)),
+        Ok(Err(e)) => Probe::Failed(e.to_string()),
+        Err(_) => Probe::TimedOut,
+    }
+}
+
+/// Not security-sensitive: the id only pairs a reply with its query.
+fn query_id() -> u16 {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or_default();
+    (nanos ^ std::process::id()) as u16
+}
+
+/// A recursive query for the A record of `name`, or why `name` does not fit
+/// in one.
+fn build_query(id: u16, name: &str) -> Result<Vec<u8>, String> {
+    if let Some(problem) = dns_name_problem(name) {
+        return Err(format!("'{}' {}", name, problem));
+    }
+    let mut packet = Vec::with_capacity(18 + name.len());
+    packet.extend_from_slice(&id.to_be_bytes());
+    packet.extend_from_slice(&0x0100u16.to_be_bytes()); // RD
+    packet.extend_from_slice(&1u16.to_be_bytes()); // QDCOUNT
+    packet.extend_from_slice(&[0; 6]); // AN/NS/ARCOUNT
+    for label in name.trim_end_matches('.').split('.') {
+        packet.push(label.len() as u8);
+        packet.extend_from_slice(label.as_bytes());
+    }
+    packet.push(0);
+    packet.extend_from_slice(&1u16.to_be_bytes()); // QTYPE A
+    packet.extend_from_slice(&1u16.to_be_bytes()); // QCLASS IN
+    Ok(packet)
+}
+
+/// The response code of `packet` if it is a reply to query `id`.
+fn response_rcode(id: u16, packet: &[u8]) -> Option<u8> {
+    if packet.len() < 12 || u16::from_be_bytes([packet[0], packet[1]]) != id {
+        return None;
+    }
+    let is_response = packet[2] & 0x80 != 0;
+    is_response.then_some(packet[3] & 0x0f)
+}
+
+fn rcode_name(rcode: u8) -> &'static str {
+    match rcode {
+        1 => "FORMERR",
+        2 => "SERVFAIL",
+        4 => "NOTIMP",
+        5 => "REFUSED",
+        _ => "error reply",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn query_is_well_formed() {
+        let q = build_query(0xbeef, "one.one.one.one").unwrap();
+        assert_eq!(&q[..4], &[0xbe, 0xef, 0x01, 0x00]);
+        assert_eq!(&q[12..16], &[3, b'o', b'n', b'e']);
+        assert_eq!(&q[q.len() - 5..], &[0, 0, 1, 0, 1]);
+
+        assert!(build_query(0xbeef, "example..com").is_err());
+        assert!(build_query(0xbeef, &"a".repeat(256)).is_err());
+    }
+
+    #[test]
+    fn rcode_needs_matching_id_and_qr_bit() {
+        let reply = [0xbe, 0xef, 0x81, 0x82, 0, 1, 0, 0, 0, 0, 0, 0];
+        assert_eq!(response_rcode(0xbeef, &reply), Some(2));
+        assert_eq!(response_rcode(0x1234, &reply), None);
+
+        let query = [0xbe, 0xef, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0];
+        assert_eq!(response_rcode(0xbeef, &query), None);
+    }
+
+    #[test]
+    fn resolv_conf_nameservers() {
+        let conf = "# generated\nsearch lan\nnameserver 192.168.1.1\nnameserver fe80::1%wlan0\n\
+                    nameserver   1.1.1.1 \noptions edns0\n";
+        assert_eq!(
+            parse_resolv_conf(conf),
+            [ip("192.168.1.1"), ip("fe80::1"), ip("1.1.1.1")]
+        );
+    }
+
+    #[test]
+    fn dead_secondary_server_warns() {
+        let assigned = [ip("192.168.1.1"), ip("10.0.0.1")];
+        let probes = [Probe::Answered(Duration::from_millis(4)), Probe::TimedOut];
+        let outcome = assess(
+            &assigned,
+            &probes,
+            SystemLookup::Resolved(ip("1.1.1.1")),
+            Duration::from_secs(3),
+            Some(&assigned),
+        );
+        assert_eq!(outcome.status, crate::doctor::Status::Warn);
+        assert_eq!(outcome.summary, "192.168.1.1 4ms, 10.0.0.1 no answer");
+    }
+
+    #[test]
+    fn resolv_conf_pointing_elsewhere_is_flagged() {
+        let assigned = [ip("192.168.1.1")];
+        let probes = [Probe::Answered(Duration::from_millis(4))];
+        let outcome = assess(
+            &assigned,
+            &probes,
+            SystemLookup::TimedOut,
+            Duration::from_secs(3),
+            Some(&[ip("10.8.0.1")]),
+        );
+        assert_eq!(outcome.status, crate::doctor::Status::Fail);
+        assert!(outcome.summary.contains("resolv.conf uses 10.8.0.1"));
+
+        // A local cache hides its upstreams, so no comparison is made.
+        let outcome = assess(
+            &assigned,
+            &probes,
+            SystemLookup::Resolved(ip("1.1.1.1")),
+            Duration::from_secs(3),
+            Some(&[ip("127.0.0.1")]),
+        );
+        assert_eq!(outcome.status, crate::doctor::Status::Ok);
+    }
+}
@@ -0,0 +1,43 @@
+use std::net::SocketAddr;
+
+use async_trait::async_trait;
+use tokio::net::lookup_host;
+use tokio::time::timeout;
+
+use crate::doctor::check::{DiagnosticCheck, Outcome};
+use crate::doctor::context::DoctorContext;
+
+/// Checks the resolver hands back AAAA records. Some home routers filter them,
+/// which leaves a dual-stack host quietly on IPv4 only.
+pub struct AaaaCheck;
+
+#[async_trait]
+impl DiagnosticCheck for AaaaCheck {
+    fn name(&self) -> &'static str {
+        "dns aaaa"
+    }
+
+    fn id(&self) -> &'static str {
+        "dns6"
+    }
+
+    fn depends_on(&self) -> &'static [&'static str] {
+        &["dns"]
+    }
+
+    async fn run(&self, ctx: &DoctorContext) -> Outcome {
+        let settings = &ctx.config.doctor;
+        let limit = settings.probe_timeout();
+        match timeout(limit, lookup_host((settings.dns_probe_host.as_str(), 80))).await {
+            Ok(Ok(mut addrs)) => match addrs.find(SocketAddr::is_ipv6) {
+                Some(addr) => Outcome::ok(format!("resolves ({})", addr.ip())),
+                None => Outcome::warn("resolver returned no AAAA records"),
+            },
+            Ok(Err(e)) => Outcome::warn(format!("AAAA lookup failed: {}", e)),
+            Err(_) => Outcome::warn(format!(
+                "AAAA lookup timed out after {}s",
+                limit.as_secs_f32()
+            )),
+        }
+    }
+}
@@ -1,13 +1,17 @@
 use std::io::ErrorKind;
+use std::path::Path;
 
 use async_trait::async_trait;
 use tokio::fs;
+use tokio::process::Command;
 
 use crate::doctor::check::{DiagnosticCheck, Outcome};
 use crate::doctor::context::DoctorContext;
 
 /// Reads /sys/class/net/<iface>/device/driver to confirm a kernel driver is
-/// bound to the wireless interface.
+/// bound to the wireless interface, then reports what it says about itself
+/// (module and firmware version, bus ID, power save) and scans this boot's
+/// kernel log for firmware load failures and crashes.
 pub struct DriverCheck;
 
 #[async_trait]
@@ -17,24 +21,395 @@ impl DiagnosticCheck for DriverCheck {
     }
 
     async fn run(&self, ctx: &DoctorContext) -> Outcome {
-        let link = format!("/sys/class/net/{}/device/driver", ctx.interface);
-
-        match fs::read_link(&link).await {
-            Ok(target) => {
-                let name = target
-                    .file_name()
-                    .map(|n| n.to_string_lossy().into_owned())
-                    .unwrap_or_else(|| "unknown".into());
-                Outcome::ok(format!("{} loaded", name))
+        let device = Path::new("/sys/class/net")
+            .join(&ctx.interface)
+            .join("device");
+
+        let driver = match fs::read_link(device.join("driver")).await {
+            Ok(target) => target
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "unknown".into()),
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                return Outcome::fail(
+                    "no driver bound",
+                    format!(
+                        "No kernel driver for {}. Check `dmesg | grep -i {}` for firmware errors.",
+                        ctx.interface, ctx.interface
+                    ),
+                );
+            }
+            Err(e) => return Outcome::skip(format!("could not read driver link: {}", e)),
+        };
+
+        let interface = ctx.interface.clone();
+        let (module, uevent, drvinfo, power_save, kernel_log) = tokio::join!(
+            fs::read_link(device.join("driver/module")),
+            fs::read_to_string(device.join("uevent")),
+            tokio::task::spawn_blocking(move || ethtool_drvinfo(&interface)),
+            power_save(&ctx.interface),
+            kernel_log(),
+        );
+
+        let module = module
+            .ok()
+            .and_then(|m| Some(m.file_name()?.to_string_lossy().into_owned()))
+            .unwrap_or_else(|| driver.clone());
+        let version = fs::read_to_string(format!("/sys/module/{}/version", module))
+            .await
+            .ok()
+            .map(|v| v.trim().to_string());
+
+        let info = DriverInfo {
+            firmware: drvinfo.ok().flatten().map(|d| d.firmware),
+            hardware: uevent.ok().as_deref().and_then(hardware_id),
+            driver,
+            module,
+            version,
+            power_save,
+        };
+        let issues = scan_kernel_log(&kernel_log, &info.driver, &ctx.interface);
+        assess(&info, &issues)
+    }
+}
+
+/// What the driver reports about itself and the adapter.
+#[derive(Debug, Default)]
+struct DriverInfo {
+    driver: String,
+    module: String,
+    /// Module version, only set by out-of-tree (e.g. DKMS) drivers.
+    version: Option<String>,
+    firmware: Option<String>,
+    /// `"PCI 8086:2723"`, `"USB 0bda:8179"`, ...
+    hardware: Option<String>,
+    power_save: Option<bool>,
+}
+
+/// A firmware problem found in the kernel log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum FirmwareIssue {
+    /// A firmware or NVRAM file the driver asked for and did not get.
+    Missing(String),
+    Crashed,
+}
+
+/// The `PCI_ID`, USB `PRODUCT` or `SDIO_ID` in a device's uevent file, as a
+/// bus name and `vendor:device` pair.
+fn hardware_id(uevent: &str) -> Option<String> {
+    let field = |key: &str| {
+        uevent
+            .lines()
+            .find_map(|line| line.strip_prefix(key)?.strip_prefix('='))
+    };
+
+    if let Some(id) = field("PCI_ID") {
+        return Some(format!("PCI {}", id.to_ascii_lowercase()));
+    }
+    if let Some(product) = field("PRODUCT") {
+        // `vendor/product/bcdDevice` in unpadded hex.
+        let mut parts = product.split('/');
+        let vendor = u16::from_str_radix(parts.next()?, 16).ok()?;
+        let device = u16::from_str_radix(parts.next()?, 16).ok()?;
+        return Some(format!("USB {:04x}:{:04x}", vendor, device));
+    }
+    field("SDIO_ID").map(|id| format!("SDIO {}", id.to_ascii_lowercase()))
+}
+
+/// Driver details from `ETHTOOL_GDRVINFO`, what `ethtool -i` prints.
+struct DrvInfo {
+    firmware: String,
+}
+
+/// Asks the kernel for the interface's driver info over `SIOCETHTOOL`.
+/// `None` when the driver does not implement it or reports no firmware.
+fn ethtool_drvinfo(interface: &str) -> Option<DrvInfo> {
+    const ETHTOOL_GDRVINFO: u32 = 0x3;
+
+    /// `struct ethtool_drvinfo` from linux/ethtool.h.
+    #[repr(C)]
+    struct EthtoolDrvinfo {
+        cmd: u32,
+        driver: [u8; 32],
+        version: [u8; 32],
+        fw_version: [u8; 32],
+        bus_info: [u8; 32],
+        erom_version: [u8; 32],
+        reserved2: [u8; 12],
+        n_priv_flags: u32,
+        n_stats: u32,
+        testinfo_len: u32,
+        eedump_len: u32,
+        regdump_len: u32,
+    }
+
+    if interface.len() >= libc::IFNAMSIZ {
+        return None;
+    }
+
+    // SAFETY: both structs are plain old data for which all-zero is valid,
+    // the name is NUL-terminated (checked length, zeroed buffer), and
+    // `info` outlives the ioctl that writes through the pointer to it.
+    unsafe {
+        let mut info: EthtoolDrvinfo = std::mem::zeroed();
+        info.cmd = ETHTOOL_GDRVINFO;
+
+        let mut request: libc::ifreq = std::mem::zeroed();
+        for (dst, src) in request.ifr_name.iter_mut().zip(interface.bytes()) {
+            *dst = src as libc::c_char;
+        }
+        request.ifr_ifru.ifru_data = (&mut info as *mut EthtoolDrvinfo).cast();
+
+        let fd = libc::socket(libc::AF_INET, libc::SOCK_DGRAM | libc::SOCK_CLOEXEC, 0);
+        if fd < 0 {
+            return None;
+        }
+        let rc = libc::ioctl(fd, libc::SIOCETHTOOL as _, &mut request);
+        libc::close(fd);
+        if rc < 0 {
+            return None;
+        }
+
+        let end = info
+            .fw_version
+            .iter()
+            .position(|&b| b == 0)
+            .unwrap_or(info.fw_version.len());
+        let firmware = String::from_utf8_lossy(&info.fw_version[..end])
+            .trim()
+            .to_string();
+        (!firmware.is_empty() && firmware != "N/A").then_some(DrvInfo { firmware })
+    }
+}
+
+/// Whether 802.11 power save is on, from `iw`. `None` when `iw` is missing
+/// or does not say.
+async fn power_save(interface: &str) -> Option<bool> {
+    let output = Command::new("iw")
+        .args(["dev", interface, "get", "power_save"])
+        .output()
+        .await
+        .ok()?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    let state = text.split_once("Power save:")?.1.trim();
+    Some(state.starts_with("on"))
+}
+
+/// This boot's kernel messages: from the journal, which group membership can
+/// open up, else `dmesg`, which `kernel.dmesg_restrict` often closes.
+async fn kernel_log() -> String {
+    let journal = Command::new("journalctl")
+        .args(["-k", "-b", "--output=cat", "--no-pager"])
+        .output()
+        .await;
+    if let Ok(output) = journal
+        && output.status.success()
+        && !output.stdout.is_empty()
+    {
+        return String::from_utf8_lossy(&output.stdout).into_owned();
+    }
+
+    match Command::new("dmesg").output().await {
+        Ok(output) if output.status.success() => {
+            String::from_utf8_lossy(&output.stdout).into_owned()
+        }
+        _ => String::new(),
+    }
+}
+
+/// Firmware failures and crashes logged by `driver` or for `interface`.
+fn scan_kernel_log(log: &str, driver: &str, interface: &str) -> Vec<FirmwareIssue> {
+    let mut issues = Vec::new();
+    for line in log.lines() {
+        if !line.contains(driver) && !line.contains(interface) {
+            continue;
+        }
+        let lower = line.to_ascii_lowercase();
+
+        const LOAD_FAILED: &str = "direct firmware load for ";
+        if let Some(at) = lower.find(LOAD_FAILED)
+            && lower[at..].contains("failed")
+        {
+            // Firmware paths are case-sensitive: take the name from the line
+            // as logged. ASCII lowercasing keeps every byte where it was.
+            let rest = &line[at + LOAD_FAILED.len()..];
+            let file = rest.split_whitespace().next().unwrap_or_default();
+            let issue = FirmwareIssue::Missing(file.to_string());
+            if !issues.contains(&issue) {
+                issues.push(issue);
             }
-            Err(e) if e.kind() == ErrorKind::NotFound => Outcome::fail(
-                "no driver bound",
-                format!(
-                    "No kernel driver for {}. Check `dmesg | grep -i {}` for firmware errors.",
-                    ctx.interface, ctx.interface
-                ),
-            ),
-            Err(e) => Outcome::skip(format!("could not read driver link: {}", e)),
+        } else if lower.contains("microcode sw error")
+            || lower.contains("firmware crashed")
+            || lower.contains("firmware crash")
+            || lower.contains("fw crash")
+            || lower.contains("firmware error")
+        {
+            issues.push(FirmwareIssue::Crashed);
+        }
+    }
+    issues
+}
+
+/// Realtek drivers, in- and out-of-tree, that are known to stall or drop the
+/// link with power save on.
+const POWER_SAVE_SENSITIVE: [&str; 4] = ["rtw88", "rtw89", "rtl8", "r8188eu"];
+
+fn assess(info: &DriverInfo, issues: &[FirmwareIssue]) -> Outcome {
+    let mut summary = info.driver.clone();
+    if let Some(version) = &info.version {
+        summary.push_str(&format!(" {}", version));
+    }
+    if let Some(firmware) = &info.firmware {
+        summary.push_str(&format!(" (fw {})", firmware));
+    }
+    if let Some(hardware) = &info.hardware {
+        summary.push_str(&format!(", {}", hardware));
+    }
+    match info.power_save {
+        Some(true) => summary.push_str(", power save on"),
+        Some(false) => summary.push_str(", power save off"),
+        None => {}
+    }
+
+    let crashes = issues
+        .iter()
+        .filter(|i| **i == FirmwareIssue::Crashed)
+        .count();
+    if crashes > 0 {
+        summary.push_str(&format!(", firmware crashed {}x this boot", crashes));
+        return Outcome::warn(summary).with_verdict(crash_advice(&info.module));
+    }
+
+    // Drivers like iwlwifi try newer firmware files first and log a failure
+    // for each one missing; that is only a problem if nothing loaded.
+    let missing: Vec<&str> = issues
+        .iter()
+        .filter_map(|i| match i {
+            FirmwareIssue::Missing(file) => Some(file.as_str()),
+            FirmwareIssue::Crashed => None,
+        })
+        .collect();
+    if info.firmware.is_none() && !missing.is_empty() {
+        summary.push_str(", firmware failed to load");
+        return Outcome::warn(summary).with_verdict(missing_advice(&info.module, &missing));
+    }
+
+    if info.power_save == Some(true)
+        && POWER_SAVE_SENSITIVE
+            .iter()
+            .any(|prefix| info.module.starts_with(prefix) || info.driver.starts_with(prefix))
+    {
+        return Outcome::warn(summary).with_verdict(
+            "This Realtek driver is known to drop the connection with power save on. Turn it off: `iw dev <iface> set power_save off`, or set `wifi.powersave = 2` in /etc/NetworkManager/conf.d/.",
+        );
+    }
+
+    Outcome::ok(summary)
+}
+
+fn crash_advice(module: &str) -> String {
+    let specific = match module {
+        m if m.starts_with("iwlwifi") || m.starts_with("iwlmvm") => {
+            " If crashes continue, add `options iwlwifi power_save=0` to /etc/modprobe.d/iwlwifi.conf."
+        }
+        m if m.starts_with("ath11k") => {
+            " ath11k firmware commonly crashes around suspend; a newer kernel usually helps."
+        }
+        m if m.starts_with("mt79") => " Disabling ASPM for the card (pcie_aspm=off) often helps.",
+        _ => "",
+    };
+    format!(
+        "The adapter's firmware crashed this boot, which drops the connection. Update the linux-firmware package; `journalctl -k -b` shows the trace.{}",
+        specific
+    )
+}
+
+fn missing_advice(module: &str, files: &[&str]) -> String {
+    let specific = match module {
+        m if m.starts_with("ath10k") || m.starts_with("ath11k") => {
+            " Atheros cards also need the board-2.bin file next to the firmware."
+        }
+        m if m.starts_with("brcmfmac") => {
+            " brcmfmac also needs a board-specific NVRAM file (brcmfmac*.txt), usually from the vendor image."
+        }
+        _ => "",
+    };
+    format!(
+        "The driver could not load {}. Install or update the linux-firmware package and reboot.{}",
+        files.join(", "),
+        specific
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::doctor::Status;
+
+    fn info(driver: &str, firmware: Option<&str>) -> DriverInfo {
+        DriverInfo {
+            driver: driver.into(),
+            module: driver.into(),
+            firmware: firmware.map(Into::into),
+            ..Default::default()
         }
     }
+
+    #[test]
+    fn hardware_ids_from_uevent() {
+        let pci = "DRIVER=iwlwifi\nPCI_CLASS=28000\nPCI_ID=8086:2723\nPCI_SUBSYS_ID=8086:0084\n";
+        assert_eq!(hardware_id(pci).as_deref(), Some("PCI 8086:2723"));
+
+        let usb = "DEVTYPE=usb_interface\nDRIVER=rtl8xxxu\nPRODUCT=bda/8179/0\n";
+        assert_eq!(hardware_id(usb).as_deref(), Some("USB 0bda:8179"));
+    }
+
+    #[test]
+    fn fallback_firmware_misses_are_ignored_once_one_loads() {
+        let log = "iwlwifi 0000:00:14.3: Direct firmware load for iwlwifi-so-a0-gf-a0-86.ucode failed with error -2\n\
+                   iwlwifi 0000:00:14.3: loaded firmware version 83.e8f84e98.0 so-a0-gf-a0-83.ucode\n\
+                   e1000e 0000:00:1f.6: Direct firmware load for e1000e.bin failed with error -2\n";
+        let issues = scan_kernel_log(log, "iwlwifi", "wlan0");
+        assert_eq!(
+            issues,
+            [FirmwareIssue::Missing(
+                "iwlwifi-so-a0-gf-a0-86.ucode".into()
+            )]
+        );
+
+        assert_eq!(
+            assess(&info("iwlwifi", Some("83.e8f84e98.0")), &issues).status,
+            Status::Ok
+        );
+        let outcome = assess(&info("iwlwifi", None), &issues);
+        assert_eq!(outcome.status, Status::Warn);
+        assert!(outcome.verdict.unwrap().contains("so-a0-gf-a0-86"));
+
+        let ath = "ath10k_pci 0000:02:00.0: Direct firmware load for ath10k/QCA6174/hw3.0/firmware-6.bin failed with error -2\n";
+        assert_eq!(
+            scan_kernel_log(ath, "ath10k_pci", "wlan0"),
+            [FirmwareIssue::Missing(
+                "ath10k/QCA6174/hw3.0/firmware-6.bin".into()
+            )]
+        );
+    }
+
+    #[test]
+    fn crashes_and_realtek_power_save_are_flagged() {
+        let log = "iwlwifi 0000:00:14.3: Microcode SW error detected. Restarting 0x0.\n";
+        let outcome = assess(
+            &info("iwlwifi", Some("83.0")),
+            &scan_kernel_log(log, "iwlwifi", "wlan0"),
+        );
+        assert_eq!(outcome.status, Status::Warn);
+        assert!(outcome.summary.contains("crashed 1x"));
+        assert!(outcome.verdict.unwrap().contains("power_save=0"));
+
+        let mut realtek = info("rtw_8822ce", None);
+        realtek.module = "rtw88_8822ce".into();
+        realtek.power_save = Some(true);
+        let outcome = assess(&realtek, &[]);
+        assert_eq!(outcome.status, Status::Warn);
+        assert!(outcome.summary.ends_with("power save on"));
+    }
 }
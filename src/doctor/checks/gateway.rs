@@ -1,15 +1,12 @@
 use std::io::ErrorKind;
-use std::time::Duration;
 
 use async_trait::async_trait;
 use tokio::net::TcpStream;
 use tokio::time::timeout;
 
-use crate::doctor::check::{DiagnosticCheck, Outcome};
+use crate::doctor::check::{DiagnosticCheck, Fix, Outcome};
 use crate::doctor::context::DoctorContext;
 
-const GATEWAY_TIMEOUT: Duration = Duration::from_secs(2);
-
 /// Probes the default gateway with a short TCP SYN to confirm L3 reachability.
 pub struct GatewayCheck;
 
@@ -19,8 +16,12 @@ impl DiagnosticCheck for GatewayCheck {
         "gateway"
     }
 
+    fn depends_on(&self) -> &'static [&'static str] {
+        &["ip"]
+    }
+
     async fn run(&self, ctx: &DoctorContext) -> Outcome {
-        let gateway = match ctx.nm.get_ip4_info(&ctx.device_path).await {
+        let gateway = match ctx.backend.get_ip4_info(&ctx.device_path).await {
             Ok(Some(info)) => info.gateway,
             Ok(None) => None,
             Err(e) => return Outcome::skip(format!("could not read IP config: {}", e)),
@@ -30,22 +31,25 @@ impl DiagnosticCheck for GatewayCheck {
             return Outcome::warn("no default gateway configured");
         };
 
-        // Port 80 picked arbitrarily; a RST from a closed port still proves
-        // the host is up. ICMP would need raw sockets / CAP_NET_RAW.
-        let addr = format!("{}:80", gw);
-        match timeout(GATEWAY_TIMEOUT, TcpStream::connect(&addr)).await {
+        // Any port will do (80 by default); a RST from a closed port still
+        // proves the host is up. ICMP would need raw sockets / CAP_NET_RAW.
+        let port = ctx.config.doctor.gateway_port;
+        let addr = format!("{}:{}", gw, port);
+        match timeout(ctx.config.doctor.probe_timeout(), TcpStream::connect(&addr)).await {
             Ok(Ok(_)) => Outcome::ok(format!("{} reachable", gw)),
             Ok(Err(e)) if e.kind() == ErrorKind::ConnectionRefused => {
-                Outcome::ok(format!("{} reachable (refused port 80)", gw))
+                Outcome::ok(format!("{} reachable (refused port {})", gw, port))
             }
             Ok(Err(e)) => Outcome::fail(
                 format!("{} unreachable ({})", gw, e),
                 "Router-side issue. Try power-cycling the AP or moving closer.",
-            ),
+            )
+            .with_fix(Fix::Reconnect),
             Err(_) => Outcome::fail(
                 format!("{} unreachable (timeout)", gw),
                 "Router-side issue. Try power-cycling the AP or moving closer.",
-            ),
+            )
+            .with_fix(Fix::Reconnect),
         }
     }
 }
@@ -1,15 +1,13 @@
 use std::time::Duration;
 
 use async_trait::async_trait;
+use futures::future::select_ok;
 use tokio::net::TcpStream;
 use tokio::time::timeout;
 
 use crate::doctor::check::{DiagnosticCheck, Outcome};
 use crate::doctor::context::DoctorContext;
 
-const INTERNET_HOSTS: &[&str] = &["1.1.1.1:443", "8.8.8.8:443"];
-const INTERNET_TIMEOUT: Duration = Duration::from_secs(3);
-
 /// TCP-connects to well-known public endpoints to confirm end-to-end reachability.
 pub struct InternetCheck;
 
@@ -19,11 +17,19 @@ impl DiagnosticCheck for InternetCheck {
         "internet"
     }
 
-    async fn run(&self, _ctx: &DoctorContext) -> Outcome {
-        for host in INTERNET_HOSTS {
-            if let Ok(Ok(_)) = timeout(INTERNET_TIMEOUT, TcpStream::connect(host)).await {
-                return Outcome::ok(format!("reachable via {}", host));
-            }
+    fn depends_on(&self) -> &'static [&'static str] {
+        &["ip"]
+    }
+
+    async fn run(&self, ctx: &DoctorContext) -> Outcome {
+        let settings = &ctx.config.doctor;
+        if settings.internet_hosts.is_empty() {
+            return Outcome::skip("no probe endpoints configured");
+        }
+        if let Some(host) =
+            first_reachable(&settings.internet_hosts, settings.probe_timeout()).await
+        {
+            return Outcome::ok(format!("reachable via {}", host));
         }
 
         Outcome::fail(
@@ -32,3 +38,17 @@ impl DiagnosticCheck for InternetCheck {
         )
     }
 }
+
+/// Dials every endpoint at once and returns the first to accept a TCP
+/// connection, so the check costs one probe timeout however many are listed.
+pub(super) async fn first_reachable(hosts: &[String], limit: Duration) -> Option<&str> {
+    let dials = hosts.iter().map(|host| {
+        Box::pin(async move {
+            match timeout(limit, TcpStream::connect(host.as_str())).await {
+                Ok(Ok(_)) => Ok(host.as_str()),
+                _ => Err(()),
+            }
+        })
+    });
+    select_ok(dials).await.ok().map(|(host, _)| host)
+}
@@ -0,0 +1,53 @@
+use async_trait::async_trait;
+
+use crate::doctor::check::{DiagnosticCheck, Outcome};
+use crate::doctor::context::DoctorContext;
+
+use super::internet::first_reachable;
+
+/// TCP-connects to well-known public endpoints over IPv6. A host with a v6
+/// route that goes nowhere is the classic dual-stack failure: apps that prefer
+/// IPv6 stall before falling back.
+pub struct Internet6Check;
+
+#[async_trait]
+impl DiagnosticCheck for Internet6Check {
+    fn name(&self) -> &'static str {
+        "internet v6"
+    }
+
+    fn id(&self) -> &'static str {
+        "internet6"
+    }
+
+    fn depends_on(&self) -> &'static [&'static str] {
+        &["route6"]
+    }
+
+    async fn run(&self, ctx: &DoctorContext) -> Outcome {
+        match ctx.backend.get_ip6_info(&ctx.device_path).await {
+            Ok(Some(info)) if info.global_addresses().next().is_some() => {
+                if info.gateway.is_none() {
+                    return Outcome::skip("no IPv6 default route");
+                }
+            }
+            Ok(_) => return Outcome::skip("no global IPv6 address"),
+            Err(e) => return Outcome::skip(format!("could not read IPv6 config: {}", e)),
+        }
+
+        let settings = &ctx.config.doctor;
+        if settings.internet6_hosts.is_empty() {
+            return Outcome::skip("no probe endpoints configured");
+        }
+        if let Some(host) =
+            first_reachable(&settings.internet6_hosts, settings.probe_timeout()).await
+        {
+            return Outcome::ok(format!("reachable via {}", host));
+        }
+
+        Outcome::fail(
+            "no public endpoint reachable over IPv6",
+            "IPv6 is configured but goes nowhere, so apps preferring it will stall. Fix the router's IPv6 uplink, or set ipv6.method to disabled on this connection.",
+        )
+    }
+}
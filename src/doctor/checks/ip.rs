@@ -1,6 +1,6 @@
 use async_trait::async_trait;
 
-use crate::doctor::check::{DiagnosticCheck, Outcome};
+use crate::doctor::check::{DiagnosticCheck, Fix, Outcome};
 use crate::doctor::context::DoctorContext;
 
 /// Reports the active IPv4 address acquired via DHCP or static config.
@@ -12,8 +12,16 @@ impl DiagnosticCheck for IpAddressCheck {
         "ip address"
     }
 
+    fn id(&self) -> &'static str {
+        "ip"
+    }
+
+    fn depends_on(&self) -> &'static [&'static str] {
+        &["association"]
+    }
+
     async fn run(&self, ctx: &DoctorContext) -> Outcome {
-        match ctx.nm.get_ip4_info(&ctx.device_path).await {
+        match ctx.backend.get_ip4_info(&ctx.device_path).await {
             Ok(Some(info)) if !info.addresses.is_empty() => {
                 let (addr, prefix) = &info.addresses[0];
                 Outcome::ok(format!("{}/{}", addr, prefix))
@@ -21,7 +29,8 @@ impl DiagnosticCheck for IpAddressCheck {
             Ok(_) => Outcome::fail(
                 "no IPv4 address",
                 "DHCP did not assign an address. Try reconnecting, or check the router's DHCP pool.",
-            ),
+            )
+            .with_fix(Fix::RenewDhcp),
             Err(e) => Outcome::skip(format!("could not read IP config: {}", e)),
         }
     }
@@ -0,0 +1,34 @@
+use async_trait::async_trait;
+
+use crate::doctor::check::{DiagnosticCheck, Outcome};
+use crate::doctor::context::DoctorContext;
+
+/// Reports the global IPv6 address acquired via SLAAC, DHCPv6 or static config.
+/// An IPv4-only network is normal, so a missing address is a skip, not a fault.
+pub struct Ip6AddressCheck;
+
+#[async_trait]
+impl DiagnosticCheck for Ip6AddressCheck {
+    fn name(&self) -> &'static str {
+        "ipv6 address"
+    }
+
+    fn id(&self) -> &'static str {
+        "ip6"
+    }
+
+    fn depends_on(&self) -> &'static [&'static str] {
+        &["association"]
+    }
+
+    async fn run(&self, ctx: &DoctorContext) -> Outcome {
+        match ctx.backend.get_ip6_info(&ctx.device_path).await {
+            Ok(Some(info)) => match info.global_addresses().next() {
+                Some((addr, prefix)) => Outcome::ok(format!("{}/{}", addr, prefix)),
+                None => Outcome::skip("link-local only — network is IPv4-only"),
+            },
+            Ok(None) => Outcome::skip("IPv6 disabled on this connection"),
+            Err(e) => Outcome::skip(format!("could not read IPv6 config: {}", e)),
+        }
+    }
+}
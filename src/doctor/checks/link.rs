@@ -0,0 +1,159 @@
+use async_trait::async_trait;
+
+use crate::doctor::check::{DiagnosticCheck, Outcome};
+use crate::doctor::context::DoctorContext;
+use crate::nm::AccessPointInfo;
+
+/// Below this strength (percent) the link drops packets and falls back to low
+/// rates; roughly -75 dBm.
+const WEAK_SIGNAL: u8 = 40;
+/// Other networks sharing the channel before airtime contention shows.
+const CROWDED_CHANNEL: usize = 3;
+/// A 5 GHz BSSID needs at least this strength to be worth suggesting.
+const USABLE_SIGNAL: u8 = 30;
+/// Link rate (kbit/s) below which throughput is noticeably poor.
+const SLOW_BITRATE: u32 = 20_000;
+
+/// Rates the radio link beyond "associated": signal, negotiated bitrate, and
+/// how many neighbours share the channel.
+pub struct LinkQualityCheck;
+
+#[async_trait]
+impl DiagnosticCheck for LinkQualityCheck {
+    fn name(&self) -> &'static str {
+        "link quality"
+    }
+
+    fn id(&self) -> &'static str {
+        "link"
+    }
+
+    fn depends_on(&self) -> &'static [&'static str] {
+        &["association"]
+    }
+
+    async fn run(&self, ctx: &DoctorContext) -> Outcome {
+        let snapshot = match ctx.backend.snapshot().await {
+            Ok(s) => s,
+            Err(e) => return Outcome::skip(format!("could not read network state: {}", e)),
+        };
+        let Some(active) = snapshot.active_access_point(&ctx.device_path) else {
+            return Outcome::skip("not associated with any AP");
+        };
+
+        assess(
+            &active,
+            snapshot.bitrate(&ctx.device_path),
+            &snapshot.visible_networks(&ctx.device_path),
+            &snapshot.access_points(&ctx.device_path),
+        )
+    }
+}
+
+/// Scores the link from the active AP, its bitrate, the visible networks (one
+/// per SSID) and every BSSID in range.
+fn assess(
+    active: &AccessPointInfo,
+    bitrate: Option<u32>,
+    networks: &[AccessPointInfo],
+    bssids: &[AccessPointInfo],
+) -> Outcome {
+    let on_channel = networks
+        .iter()
+        .filter(|n| {
+            n.ssid != active.ssid && n.band() == active.band() && n.channel() == active.channel()
+        })
+        .count();
+
+    let mut summary = format!(
+        "{}% signal, {} ch {}",
+        active.strength,
+        active.band(),
+        active.channel()
+    );
+    if let Some(rate) = bitrate {
+        summary.push_str(&format!(", {} Mb/s", rate / 1000));
+    }
+    match on_channel {
+        0 => {}
+        1 => summary.push_str(", 1 other network on channel"),
+        n => summary.push_str(&format!(", {} other networks on channel", n)),
+    }
+
+    let weak = active.strength < WEAK_SIGNAL;
+    let crowded = on_channel >= CROWDED_CHANNEL;
+    let slow = bitrate.is_some_and(|rate| rate < SLOW_BITRATE);
+
+    let problem = match (weak, crowded) {
+        (true, true) => format!("weak signal on a crowded {} channel", active.band()),
+        (true, false) => "weak signal".to_string(),
+        (false, true) => format!("crowded {} channel", active.band()),
+        (false, false) if slow => "low link rate".to_string(),
+        (false, false) => return Outcome::ok(summary),
+    };
+
+    // `band a` locks a profile to 5 GHz only; 6 GHz has no such setting.
+    let better_band = active.frequency < 3000
+        && bssids.iter().any(|ap| {
+            ap.ssid == active.ssid && ap.band() == "5 GHz" && ap.strength >= USABLE_SIGNAL
+        });
+    let advice = if better_band {
+        "a 5 GHz BSSID of the same SSID is available; lock the profile to it with \
+         `nmcli connection modify <name> 802-11-wireless.band a`"
+    } else if weak {
+        "move closer to the access point or away from walls and metal"
+    } else {
+        "pick a less busy channel on the router if you control it"
+    };
+
+    let mut verdict = format!("{}; {}", problem, advice);
+    verdict[..1].make_ascii_uppercase();
+    Outcome::warn(summary).with_verdict(format!("{}.", verdict))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::doctor::Status;
+    use crate::nm::{SecurityType, WifiMode};
+
+    fn ap(ssid: &str, strength: u8, frequency: u32) -> AccessPointInfo {
+        AccessPointInfo {
+            path: String::new(),
+            ssid: ssid.into(),
+            strength,
+            frequency,
+            hw_address: String::new(),
+            security: SecurityType::WPA2,
+            mode: WifiMode::Infrastructure,
+        }
+    }
+
+    #[test]
+    fn strong_quiet_link_is_ok() {
+        let active = ap("home", 80, 5180);
+        let outcome = assess(&active, Some(433_000), std::slice::from_ref(&active), &[]);
+        assert_eq!(outcome.status, Status::Ok);
+        assert!(outcome.summary.contains("433 Mb/s"));
+    }
+
+    #[test]
+    fn weak_crowded_24_suggests_same_ssid_on_5ghz() {
+        let active = ap("home", 25, 2437);
+        let networks = [
+            active.clone(),
+            ap("a", 60, 2437),
+            ap("b", 50, 2437),
+            ap("c", 40, 2437),
+            ap("d", 40, 2412),
+        ];
+        let bssids = [active.clone(), ap("home", 45, 5180)];
+
+        let outcome = assess(&active, Some(6_000), &networks, &bssids);
+        assert_eq!(outcome.status, Status::Warn);
+        assert!(outcome.summary.contains("3 other networks"));
+        let verdict = outcome.verdict.unwrap();
+        assert!(verdict.starts_with("Weak signal on a crowded 2.4 GHz channel"));
+        assert!(verdict.contains("5 GHz BSSID"));
+    }
+}
@@ -0,0 +1,396 @@
+use std::io::ErrorKind;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Local};
+use tokio::process::Command;
+
+use crate::doctor::check::{DiagnosticCheck, Outcome};
+use crate::doctor::context::DoctorContext;
+
+/// How far back to look. Long enough to cover the attempt the user is asking
+/// about, short enough that last week's hotel WiFi does not show up.
+const SINCE: &str = "-1h";
+
+/// Reads the last hour of NetworkManager and wpa_supplicant journal entries
+/// for the interface and explains the most recent connection attempt.
+pub struct LogsCheck;
+
+#[async_trait]
+impl DiagnosticCheck for LogsCheck {
+    fn name(&self) -> &'static str {
+        "logs"
+    }
+
+    async fn run(&self, ctx: &DoctorContext) -> Outcome {
+        let output = Command::new("journalctl")
+            .args([
+                "--output=json",
+                "--no-pager",
+                "--since",
+                SINCE,
+                "-n",
+                "1000",
+                "-u",
+                "NetworkManager",
+                "-u",
+                "wpa_supplicant",
+            ])
+            .output()
+            .await;
+
+        let output = match output {
+            Ok(o) => o,
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                return Outcome::skip("journalctl not available");
+            }
+            Err(e) => return Outcome::skip(format!("could not run journalctl: {}", e)),
+        };
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        if stdout.trim().is_empty() {
+            // Without journal access journalctl only shows the caller's own
+            // entries and says so on stderr.
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            if stderr.contains("not seeing messages") || stderr.contains("permission") {
+                return Outcome::skip(
+                    "no permission to read the system journal (join the systemd-journal group)",
+                );
+            }
+        }
+
+        assess(&parse_journal(&stdout, &ctx.interface))
+    }
+}
+
+/// What a journal line says about a connection attempt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Finding {
+    Connected,
+    WrongKey,
+    HandshakeTimeout,
+    SupplicantTimeout,
+    AssocReject(u16),
+    AuthReject(u16),
+    DhcpTimeout,
+    NoSecrets,
+}
+
+impl Finding {
+    fn describe(self) -> String {
+        match self {
+            Finding::Connected => "connected".to_string(),
+            Finding::WrongKey => "password rejected".to_string(),
+            Finding::HandshakeTimeout => "4-way handshake timed out".to_string(),
+            Finding::SupplicantTimeout => "authentication timed out".to_string(),
+            Finding::AssocReject(code) => format!("association rejected (status {})", code),
+            Finding::AuthReject(code) => format!("authentication rejected (status {})", code),
+            Finding::DhcpTimeout => "DHCP timed out".to_string(),
+            Finding::NoSecrets => "no password available".to_string(),
+        }
+    }
+
+    fn verdict(self) -> String {
+        match self {
+            Finding::Connected => String::new(),
+            Finding::WrongKey => "The access point rejected the password. Forget the network \
+                                  and connect again to re-enter it."
+                .to_string(),
+            Finding::HandshakeTimeout => "The key exchange never completed: almost always a \
+                                          wrong password, otherwise a signal too weak to carry \
+                                          it. Re-enter the password, then try closer to the AP."
+                .to_string(),
+            Finding::SupplicantTimeout => "The access point stopped answering while \
+                                           authenticating. It may be out of range or \
+                                           overloaded; try again closer to it."
+                .to_string(),
+            Finding::AssocReject(code) | Finding::AuthReject(code) => {
+                let (reason, advice) = status_code(code);
+                format!(
+                    "The access point refused the connection: {}. {}",
+                    reason, advice
+                )
+            }
+            Finding::DhcpTimeout => "Associated, but no DHCP server answered. Reconnect; if it \
+                                     keeps happening the router's DHCP server is down or out \
+                                     of leases."
+                .to_string(),
+            Finding::NoSecrets => "NetworkManager has no password for this network. Connect \
+                                   from wlctl to enter one."
+                .to_string(),
+        }
+    }
+}
+
+/// IEEE 802.11 status codes an AP puts in an association or authentication
+/// rejection, with what to do about them.
+fn status_code(code: u16) -> (&'static str, &'static str) {
+    match code {
+        12 => (
+            "denied by access control",
+            "The AP may filter by MAC address; a randomised MAC (wifi.cloned-mac-address) \
+             will not be on its list.",
+        ),
+        13 | 15 => (
+            "authentication method not accepted",
+            "Forget the network and reconnect so the profile matches the AP's security.",
+        ),
+        17 => (
+            "too many clients connected",
+            "Try another access point or again later.",
+        ),
+        18 => (
+            "unsupported data rates",
+            "The AP requires rates this adapter does not offer; check its legacy-rate settings.",
+        ),
+        30 | 31 => (
+            "management frame protection mismatch",
+            "Set the profile's PMF to optional: `nmcli connection modify <name> \
+             802-11-wireless-security.pmf optional`.",
+        ),
+        34 => (
+            "poor channel conditions",
+            "Move closer to the access point.",
+        ),
+        40..=45 | 53 => (
+            "security settings mismatch",
+            "The AP's security changed (e.g. to WPA3); forget the network and reconnect.",
+        ),
+        _ => (
+            "unspecified reason",
+            "Retry; if it persists, check the access point's own logs.",
+        ),
+    }
+}
+
+/// One classified journal entry.
+#[derive(Debug, Clone, PartialEq)]
+struct LogEvent {
+    at: Option<DateTime<Local>>,
+    finding: Finding,
+}
+
+/// Classified events for `interface` from `journalctl --output=json`, oldest
+/// first. Lines about other interfaces and unrecognised messages are dropped.
+fn parse_journal(output: &str, interface: &str) -> Vec<LogEvent> {
+    output
+        .lines()
+        .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+        .filter_map(|entry| {
+            let message = message_text(&entry["MESSAGE"])?;
+            if !is_about(&entry, &message, interface) {
+                return None;
+            }
+            let finding = classify(&message)?;
+            let at = entry["__REALTIME_TIMESTAMP"]
+                .as_str()
+                .and_then(|usec| usec.parse().ok())
+                .and_then(DateTime::from_timestamp_micros)
+                .map(|t| t.with_timezone(&Local));
+            Some(LogEvent { at, finding })
+        })
+        .collect()
+}
+
+/// Whether a journal entry concerns `interface`. NetworkManager tags its
+/// entries with the device; wpa_supplicant only names it in the text, where
+/// `wlan0` must not match `wlan0mon`.
+fn is_about(entry: &serde_json::Value, message: &str, interface: &str) -> bool {
+    if let Some(device) = ["NM_DEVICE", "INTERFACE"]
+        .iter()
+        .find_map(|field| entry[field].as_str())
+    {
+        return device == interface;
+    }
+    let part_of_name = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '-';
+    message.match_indices(interface).any(|(at, _)| {
+        let before = message[..at].chars().next_back();
+        let after = message[at + interface.len()..].chars().next();
+        !before.is_some_and(part_of_name) && !after.is_some_and(part_of_name)
+    })
+}
+
+/// journald stores messages that are not valid UTF-8 as a byte array.
+fn message_text(value: &serde_json::Value) -> Option<String> {
+    if let Some(text) = value.as_str() {
+        return Some(text.to_string());
+    }
+    let bytes: Vec<u8> = value
+        .as_array()?
+        .iter()
+        .filter_map(|b| b.as_u64().map(|b| b as u8))
+        .collect();
+    Some(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+fn classify(message: &str) -> Option<Finding> {
+    let field = |name: &str| -> Option<u16> {
+        let start = message.find(name)? + name.len();
+        let digits: String = message[start..]
+            .chars()
+            .take_while(|c| c.is_ascii_digit())
+            .collect();
+        digits.parse().ok()
+    };
+
+    if message.contains("CTRL-EVENT-CONNECTED") || message.contains("-> activated") {
+        Some(Finding::Connected)
+    } else if message.contains("4-Way Handshake failed") || message.contains("reason=WRONG_KEY") {
+        Some(Finding::WrongKey)
+    } else if message.contains("CTRL-EVENT-DISCONNECTED") && field("reason=") == Some(15) {
+        Some(Finding::HandshakeTimeout)
+    } else if message.contains("CTRL-EVENT-ASSOC-REJECT") {
+        Some(Finding::AssocReject(field("status_code=").unwrap_or(1)))
+    } else if message.contains("CTRL-EVENT-AUTH-REJECT") {
+        Some(Finding::AuthReject(field("status_code=").unwrap_or(1)))
+    } else if message.contains("reason 'supplicant-timeout'") {
+        Some(Finding::SupplicantTimeout)
+    } else if message.contains("reason 'no-secrets'") {
+        Some(Finding::NoSecrets)
+    } else if message.contains("reason 'ip-config-unavailable'")
+        || (message.contains("dhcp4") && message.contains("timed out"))
+    {
+        Some(Finding::DhcpTimeout)
+    } else {
+        None
+    }
+}
+
+/// Reports the most recent event: a success with the failures that led up to
+/// it, or the failure that ended the last attempt.
+fn assess(events: &[LogEvent]) -> Outcome {
+    let Some(last) = events.last() else {
+        return Outcome::ok("no connection events in the last hour");
+    };
+    let time = |event: &LogEvent| {
+        event
+            .at
+            .map(|t| t.format("%H:%M:%S").to_string())
+            .unwrap_or_else(|| "recently".to_string())
+    };
+
+    // The run of events since the previous successful connection.
+    let attempt: Vec<&LogEvent> = events[..events.len() - 1]
+        .iter()
+        .rev()
+        .take_while(|e| e.finding != Finding::Connected)
+        .collect();
+
+    if last.finding == Finding::Connected {
+        return match attempt.len() {
+            0 => Outcome::ok(format!("connected at {}", time(last))),
+            n => Outcome::ok(format!(
+                "connected at {} after {} failed attempt{}",
+                time(last),
+                n,
+                if n == 1 { "" } else { "s" }
+            )),
+        };
+    }
+
+    let repeats = 1 + attempt
+        .iter()
+        .take_while(|e| e.finding == last.finding)
+        .count();
+    let mut summary = format!("{} {}", time(last), last.finding.describe());
+    if repeats > 1 {
+        summary.push_str(&format!(" (x{})", repeats));
+    }
+    Outcome::warn(summary).with_verdict(last.finding.verdict())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::doctor::Status;
+
+    fn line(usec: u64, message: &str) -> String {
+        serde_json::json!({
+            "__REALTIME_TIMESTAMP": usec.to_string(),
+            "MESSAGE": message,
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn classifies_supplicant_and_nm_messages() {
+        assert_eq!(
+            classify("wlan0: CTRL-EVENT-ASSOC-REJECT bssid=aa:bb:cc:dd:ee:ff status_code=17"),
+            Some(Finding::AssocReject(17))
+        );
+        assert_eq!(
+            classify("wlan0: CTRL-EVENT-DISCONNECTED bssid=aa:bb:cc:dd:ee:ff reason=15"),
+            Some(Finding::HandshakeTimeout)
+        );
+        assert_eq!(
+            classify("wlan0: WPA: 4-Way Handshake failed - pre-shared key may be incorrect"),
+            Some(Finding::WrongKey)
+        );
+        assert_eq!(
+            classify(
+                "<warn>  device (wlan0): state change: ip-config -> failed \
+                 (reason 'ip-config-unavailable', sys-iface-state: 'managed')"
+            ),
+            Some(Finding::DhcpTimeout)
+        );
+        assert_eq!(classify("wlan0: CTRL-EVENT-SCAN-RESULTS"), None);
+    }
+
+    #[test]
+    fn last_failure_is_explained_and_counted() {
+        let journal = [
+            line(
+                1,
+                "wlan0: CTRL-EVENT-CONNECTED - Connection to aa:bb completed",
+            ),
+            line(2, "wlan1: CTRL-EVENT-ASSOC-REJECT status_code=1"),
+            line(3, "wlan0: CTRL-EVENT-ASSOC-REJECT status_code=31"),
+            line(4, "wlan0: CTRL-EVENT-ASSOC-REJECT status_code=31"),
+        ]
+        .join("\n");
+        let events = parse_journal(&journal, "wlan0");
+        assert_eq!(events.len(), 3);
+
+        let outcome = assess(&events);
+        assert_eq!(outcome.status, Status::Warn);
+        assert!(
+            outcome
+                .summary
+                .ends_with("association rejected (status 31) (x2)")
+        );
+        assert!(outcome.verdict.unwrap().contains("pmf optional"));
+    }
+
+    #[test]
+    fn only_entries_about_the_interface_count() {
+        let tagged = serde_json::json!({
+            "NM_DEVICE": "wlan0mon",
+            "MESSAGE": "device (wlan0): state change: config -> failed (reason 'no-secrets')",
+        })
+        .to_string();
+        let journal = [
+            line(1, "wlan0mon: CTRL-EVENT-ASSOC-REJECT status_code=17"),
+            line(2, "p2p-dev-wlan0: CTRL-EVENT-ASSOC-REJECT status_code=17"),
+            tagged,
+            line(3, "wlan0: CTRL-EVENT-ASSOC-REJECT status_code=31"),
+        ]
+        .join("\n");
+        let events = parse_journal(&journal, "wlan0");
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].finding, Finding::AssocReject(31));
+    }
+
+    #[test]
+    fn success_after_failures_is_ok() {
+        let journal = [
+            line(1, "wlan0: CTRL-EVENT-DISCONNECTED bssid=aa reason=15"),
+            line(
+                2,
+                "wlan0: CTRL-EVENT-CONNECTED - Connection to aa completed",
+            ),
+        ]
+        .join("\n");
+        let outcome = assess(&parse_journal(&journal, "wlan0"));
+        assert_eq!(outcome.status, Status::Ok);
+        assert!(outcome.summary.ends_with("after 1 failed attempt"));
+    }
+}
@@ -1,19 +1,33 @@
 mod association;
 mod device_state;
+mod dhcp;
 mod dns;
+mod dns6;
 mod driver;
 mod gateway;
 mod internet;
+mod internet6;
 mod ip;
+mod ip6;
+mod link;
+mod logs;
 mod portal;
 mod rfkill;
+mod route6;
 
 pub use association::AssociationCheck;
-pub use device_state::DeviceStateCheck;
+pub use device_state::{DeviceStateCheck, point_to_logs};
+pub use dhcp::DhcpCheck;
 pub use dns::DnsCheck;
+pub use dns6::AaaaCheck;
 pub use driver::DriverCheck;
 pub use gateway::GatewayCheck;
 pub use internet::InternetCheck;
+pub use internet6::Internet6Check;
 pub use ip::IpAddressCheck;
+pub use ip6::Ip6AddressCheck;
+pub use link::LinkQualityCheck;
+pub use logs::LogsCheck;
 pub use portal::PortalCheck;
 pub use rfkill::RfkillCheck;
+pub use route6::Ip6RouteCheck;
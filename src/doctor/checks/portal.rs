@@ -1,10 +1,13 @@
 use async_trait::async_trait;
 
+use crate::doctor::captive;
 use crate::doctor::check::{DiagnosticCheck, Outcome};
 use crate::doctor::context::DoctorContext;
 use crate::nm::Connectivity;
 
 /// Asks NetworkManager to re-run its connectivity probe and reports the result.
+/// Behind a captive portal it fetches the probe URI itself to find the login
+/// page, which the CLI and the TUI then offer to open.
 pub struct PortalCheck;
 
 #[async_trait]
@@ -13,13 +16,18 @@ impl DiagnosticCheck for PortalCheck {
         "connectivity"
     }
 
+    fn id(&self) -> &'static str {
+        "portal"
+    }
+
+    fn depends_on(&self) -> &'static [&'static str] {
+        &["ip"]
+    }
+
     async fn run(&self, ctx: &DoctorContext) -> Outcome {
-        match ctx.nm.check_connectivity().await {
+        match ctx.backend.check_connectivity().await {
             Ok(Connectivity::Full) => Outcome::ok("full internet access"),
-            Ok(Connectivity::Portal) => Outcome::fail(
-                "captive portal detected",
-                "Open a browser and complete the login. (See issue #61 for auto-login.)",
-            ),
+            Ok(Connectivity::Portal) => portal_outcome(ctx).await,
             Ok(Connectivity::Limited) => Outcome::warn("limited — network reachable, no internet"),
             Ok(Connectivity::None) => Outcome::warn("no connectivity"),
             Ok(Connectivity::Unknown) => Outcome::skip("connectivity state unknown"),
@@ -27,3 +35,34 @@ impl DiagnosticCheck for PortalCheck {
         }
     }
 }
+
+async fn portal_outcome(ctx: &DoctorContext) -> Outcome {
+    let check_uri = match ctx.backend.connectivity_check_uri().await {
+        Ok(Some(uri)) => uri,
+        _ => {
+            return Outcome::fail(
+                "captive portal detected",
+                "Open a browser and complete the login.",
+            );
+        }
+    };
+
+    // A portal that serves its page in place of the probe shows it at the
+    // probe URI itself.
+    let login = captive::login_url(&check_uri, ctx.config.doctor.probe_timeout())
+        .await
+        .ok()
+        .flatten()
+        .unwrap_or(check_uri);
+
+    Outcome::fail(
+        format!("captive portal at {}", host_of(&login)),
+        format!("Log in at {}, then re-check.", login),
+    )
+    .with_link(login)
+}
+
+fn host_of(url: &str) -> &str {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    rest.split(['/', '?', '#']).next().unwrap_or(rest)
+}
@@ -1,7 +1,7 @@
 use async_trait::async_trait;
 use tokio::fs;
 
-use crate::doctor::check::{DiagnosticCheck, Outcome};
+use crate::doctor::check::{DiagnosticCheck, Fix, Outcome};
 use crate::doctor::context::DoctorContext;
 
 /// Reads /sys/class/rfkill to detect soft- or hard-blocked wireless radios.
@@ -39,7 +39,8 @@ impl DiagnosticCheck for RfkillCheck {
                 );
             }
             if read_flag(&path, "soft").await {
-                return Outcome::fail("wireless soft-blocked", "Run: rfkill unblock wlan");
+                return Outcome::fail("wireless soft-blocked", "Run: rfkill unblock wlan")
+                    .with_fix(Fix::EnableWireless);
             }
         }
 
@@ -0,0 +1,41 @@
+use async_trait::async_trait;
+
+use crate::doctor::check::{DiagnosticCheck, Outcome};
+use crate::doctor::context::DoctorContext;
+
+/// Confirms a host with a global IPv6 address also has an IPv6 default route.
+pub struct Ip6RouteCheck;
+
+#[async_trait]
+impl DiagnosticCheck for Ip6RouteCheck {
+    fn name(&self) -> &'static str {
+        "ipv6 route"
+    }
+
+    fn id(&self) -> &'static str {
+        "route6"
+    }
+
+    fn depends_on(&self) -> &'static [&'static str] {
+        &["ip6"]
+    }
+
+    async fn run(&self, ctx: &DoctorContext) -> Outcome {
+        let info = match ctx.backend.get_ip6_info(&ctx.device_path).await {
+            Ok(Some(info)) => info,
+            Ok(None) => return Outcome::skip("IPv6 disabled on this connection"),
+            Err(e) => return Outcome::skip(format!("could not read IPv6 config: {}", e)),
+        };
+
+        if info.global_addresses().next().is_none() {
+            return Outcome::skip("no global IPv6 address");
+        }
+
+        match info.gateway {
+            Some(gw) => Outcome::ok(format!("default via {}", gw)),
+            // Without a route the kernel refuses v6 connects immediately, so
+            // apps fall back to IPv4 quickly; worth knowing, not a breakage.
+            None => Outcome::warn("global address but no IPv6 default route"),
+        }
+    }
+}
@@ -1,11 +1,14 @@
 use std::sync::Arc;
 
-use crate::nm::NMClient;
+use crate::backend::Backend;
+use crate::config::Config;
 
 /// Shared state passed to every diagnostic check. Checks are read-only — they
-/// must not mutate the NM state while running.
+/// must not mutate the network state while running.
 pub struct DoctorContext {
-    pub nm: Arc<NMClient>,
+    pub backend: Arc<dyn Backend>,
     pub device_path: String,
     pub interface: String,
+    /// Probe targets and timeouts come from its `[doctor]` section.
+    pub config: Arc<Config>,
 }
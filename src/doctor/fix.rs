@@ -0,0 +1,159 @@
+//! Applies the remediations checks attach to their outcomes (`Outcome::fix`),
+//! then gives NetworkManager a moment to settle before anything is re-checked.
+
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+
+use super::captive::ask;
+use super::check::Fix;
+use super::context::DoctorContext;
+use super::report::{Format, Report};
+use super::{CheckEntry, Doctor};
+use crate::nm::{ActivationOutcome, ActiveConnectionInfo};
+
+/// How long to let the network settle after a fix whose effect NetworkManager
+/// does not report back: autoconnect after turning WiFi on, scan results, a
+/// DHCP exchange.
+const SETTLE: Duration = Duration::from_secs(5);
+
+/// Applies `fix` to the device in `ctx` and returns a one-line account of
+/// what changed. Returns once the effect should be visible to the checks.
+pub(super) async fn apply(fix: Fix, ctx: &DoctorContext) -> Result<String> {
+    let backend = &ctx.backend;
+    match fix {
+        Fix::EnableWireless => {
+            backend.set_wireless_enabled(true).await?;
+            tokio::time::sleep(SETTLE).await;
+            Ok("WiFi turned on".to_string())
+        }
+        Fix::Reconnect => {
+            // Activating "/" lets NetworkManager pick the best profile for the
+            // device when nothing is active on it.
+            let active = active_connection(ctx).await?;
+            reactivate(ctx, active.as_ref(), "Reconnect").await?;
+            Ok(format!("{} reconnected", ctx.interface))
+        }
+        Fix::Rescan => {
+            backend.request_scan(&ctx.device_path).await?;
+            tokio::time::sleep(SETTLE).await;
+            Ok("scan requested".to_string())
+        }
+        Fix::RenewDhcp => {
+            // NetworkManager has no call to renew a lease, and reapplying
+            // unchanged settings can leave DHCP alone. Taking the connection
+            // down and up again always starts a fresh exchange.
+            let active = active_connection(ctx)
+                .await?
+                .context("Nothing is connected to renew a lease for")?;
+            reactivate(ctx, Some(&active), "Renewing the lease").await?;
+            Ok(format!("{} reconnected with a fresh lease", ctx.interface))
+        }
+        Fix::FallbackDns => {
+            let servers = &ctx.config.doctor.fallback_dns;
+            if servers.is_empty() {
+                bail!("No fallback_dns servers configured");
+            }
+            backend.set_runtime_dns(&ctx.device_path, servers).await?;
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            let list = servers
+                .iter()
+                .map(|s| s.to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            Ok(format!("DNS switched to {} until the next reconnect", list))
+        }
+    }
+}
+
+/// The connection active on the device in `ctx`, if any.
+async fn active_connection(ctx: &DoctorContext) -> Result<Option<ActiveConnectionInfo>> {
+    Ok(ctx
+        .backend
+        .snapshot()
+        .await?
+        .active_connections()
+        .into_iter()
+        .find(|c| c.devices.contains(&ctx.device_path)))
+}
+
+/// Takes `active` down, when there is one, and brings its profile up again on
+/// the device in `ctx`, then waits for the outcome. Without one, activating
+/// "/" lets the backend pick the best profile for the device. `what` names
+/// the attempt in the error.
+async fn reactivate(
+    ctx: &DoctorContext,
+    active: Option<&ActiveConnectionInfo>,
+    what: &str,
+) -> Result<()> {
+    let backend = &ctx.backend;
+    let connection = match active {
+        Some(active) => {
+            backend.deactivate_connection(&active.path).await?;
+            active.connection_path.as_str()
+        }
+        None => "/",
+    };
+    let activating = backend
+        .activate_connection(connection, &ctx.device_path)
+        .await?;
+    match backend
+        .await_activation(&activating, &ctx.device_path)
+        .await?
+    {
+        ActivationOutcome::Activated => Ok(()),
+        ActivationOutcome::Failed(reason) => bail!("{} failed: {:?}", what, reason),
+    }
+}
+
+/// CLI `--fix`: offers each distinct fix in `results` in stack order, and
+/// after applying one re-runs the checks that suggested it and everything
+/// above them, replacing their rows. A fix surfaced by a re-check is offered
+/// in turn; one already offered is not asked about again.
+pub(super) async fn offer(ctx: &DoctorContext, results: &mut [CheckEntry]) -> Result<()> {
+    let mut offered = Vec::new();
+
+    while let Some((name, fix)) = results.iter().find_map(|e| {
+        let fix = e.outcome.fix.filter(|f| !offered.contains(f))?;
+        Some((e.name, fix))
+    }) {
+        offered.push(fix);
+        println!();
+        match ask(&format!("{}: {}? [y/N] ", name, fix.label()))? {
+            None => break,
+            Some(answer) if answer.eq_ignore_ascii_case("y") => {}
+            Some(_) => continue,
+        }
+
+        match apply(fix, ctx).await {
+            Ok(done) => println!("-> {}", done),
+            Err(e) => {
+                println!("-> {}", e);
+                continue;
+            }
+        }
+
+        let suggested: Vec<&str> = results
+            .iter()
+            .filter(|e| e.outcome.fix == Some(fix))
+            .map(|e| e.name)
+            .collect();
+        let mut doctor = Doctor::default().affected(&suggested);
+        // Stay within what the user asked to run.
+        doctor
+            .checks
+            .retain(|c| results.iter().any(|e| e.name == c.name()));
+
+        let rechecked = doctor.run(ctx).await;
+        Report {
+            entries: &rechecked,
+        }
+        .print(Format::Text)?;
+        for entry in rechecked {
+            if let Some(slot) = results.iter_mut().find(|e| e.name == entry.name) {
+                *slot = entry;
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,357 @@
+//! Doctor history: every run is kept as a small JSON record under
+//! `$XDG_STATE_HOME/wlctl/doctor`, so an intermittent problem can be compared
+//! with the last time everything passed.
+
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use chrono::{Local, TimeZone};
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+use super::CheckEntry;
+use super::check::Status;
+use super::context::DoctorContext;
+use super::report::Report;
+use super::watch::label;
+use crate::nm::frequency_band;
+
+/// Records kept before the oldest are pruned.
+const KEEP: usize = 200;
+
+/// A signal change smaller than this (percentage points) is noise.
+const SIGNAL_DELTA: i16 = 15;
+
+/// One persisted doctor run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(super) struct Record {
+    /// Unix time of the run, in seconds.
+    pub at: i64,
+    pub interface: String,
+    pub status: Status,
+    pub checks: Vec<RecordCheck>,
+    pub facts: Facts,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(super) struct RecordCheck {
+    pub name: String,
+    pub status: Status,
+    pub summary: String,
+}
+
+/// The network as it was during a run, for spotting what moved between runs.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub(super) struct Facts {
+    pub ssid: Option<String>,
+    pub bssid: Option<String>,
+    pub signal: Option<u8>,
+    pub frequency: Option<u32>,
+    /// Link rate in kbit/s.
+    pub bitrate: Option<u32>,
+    pub address: Option<String>,
+    pub gateway: Option<String>,
+    #[serde(default)]
+    pub dns: Vec<String>,
+}
+
+/// What changed since the last healthy run, for the report and the modal.
+#[derive(Debug, Clone)]
+pub struct Changes {
+    /// When the healthy run happened, already formatted.
+    pub since: String,
+    pub lines: Vec<String>,
+}
+
+fn dir() -> Option<PathBuf> {
+    Some(dirs::state_dir()?.join("wlctl").join("doctor"))
+}
+
+/// Reads the network facts the history compares.
+pub(super) async fn facts(ctx: &DoctorContext) -> Facts {
+    let mut facts = Facts::default();
+
+    if let Ok(snapshot) = ctx.backend.snapshot().await {
+        if let Some(ap) = snapshot.active_access_point(&ctx.device_path) {
+            facts.ssid = Some(ap.ssid);
+            facts.bssid = Some(ap.hw_address);
+            facts.signal = Some(ap.strength);
+            facts.frequency = Some(ap.frequency);
+        }
+        facts.bitrate = snapshot.bitrate(&ctx.device_path);
+    }
+    if let Ok(Some(ip4)) = ctx.backend.get_ip4_info(&ctx.device_path).await {
+        facts.address = ip4
+            .addresses
+            .first()
+            .map(|(addr, prefix)| format!("{}/{}", addr, prefix));
+        facts.gateway = ip4.gateway;
+        facts.dns = ip4.nameservers;
+    }
+    facts
+}
+
+/// Whether a run with this overall status is a baseline to compare later
+/// runs with: warnings such as a weak signal come and go, failures do not.
+pub(super) fn healthy(status: Status) -> bool {
+    status != Status::Fail
+}
+
+/// Stores `results` as a new record, prunes the oldest beyond `KEEP`, and
+/// returns what changed since the last healthy run before it. A `partial` run,
+/// one that left checks out, is compared but not stored: it could pass only
+/// because the failing check was not in it.
+pub(super) async fn save(
+    ctx: &DoctorContext,
+    results: &[CheckEntry],
+    partial: bool,
+) -> Result<Option<Changes>> {
+    let dir = dir().context("No state directory to keep doctor history in")?;
+    fs::create_dir_all(&dir)
+        .await
+        .with_context(|| format!("Could not create {}", dir.display()))?;
+
+    let previous = load(&dir).await;
+    let record = Record {
+        at: Local::now().timestamp(),
+        interface: ctx.interface.clone(),
+        status: Report { entries: results }.worst(),
+        checks: results
+            .iter()
+            .map(|e| RecordCheck {
+                name: e.name.to_string(),
+                status: e.outcome.status,
+                summary: e.outcome.summary.clone(),
+            })
+            .collect(),
+        facts: facts(ctx).await,
+    };
+
+    if !partial {
+        let name = Local::now().format("%Y%m%d-%H%M%S%.3f.json").to_string();
+        fs::write(dir.join(&name), serde_json::to_vec_pretty(&record)?)
+            .await
+            .with_context(|| format!("Could not write {}", dir.join(&name).display()))?;
+
+        // Names sort by time, so the oldest come first.
+        if previous.len() + 1 > KEEP {
+            for (path, _) in previous.iter().take(previous.len() + 1 - KEEP) {
+                let _ = fs::remove_file(path).await;
+            }
+        }
+    }
+
+    let healthy = previous
+        .iter()
+        .rev()
+        .map(|(_, r)| r)
+        .find(|r| healthy(r.status) && r.interface == record.interface);
+    Ok(healthy.map(|old| Changes {
+        since: timestamp(old.at),
+        lines: diff(old, &record),
+    }))
+}
+
+/// `wlctl doctor --history`: one line per recorded run, oldest first, naming
+/// the checks that did not pass.
+pub(super) async fn list() {
+    let records = match dir() {
+        Some(dir) => load(&dir).await,
+        None => Vec::new(),
+    };
+    if records.is_empty() {
+        println!("No doctor runs recorded yet");
+        return;
+    }
+
+    for (_, record) in records {
+        let problems = record
+            .checks
+            .iter()
+            .filter(|c| c.status.severity() > 0)
+            .map(|c| format!("{} ({})", c.name, label(c.status)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        println!(
+            "{}  {:<8} {:<4}  {}",
+            timestamp(record.at),
+            record.interface,
+            label(record.status),
+            problems
+        );
+    }
+}
+
+async fn load(dir: &std::path::Path) -> Vec<(PathBuf, Record)> {
+    let Ok(mut entries) = fs::read_dir(dir).await else {
+        return Vec::new();
+    };
+    let mut records = Vec::new();
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let path = entry.path();
+        if path.extension().is_none_or(|ext| ext != "json") {
+            continue;
+        }
+        // A record that no longer parses (hand-edited, older format) is
+        // skipped rather than hiding the rest.
+        if let Ok(bytes) = fs::read(&path).await
+            && let Ok(record) = serde_json::from_slice::<Record>(&bytes)
+        {
+            records.push((path, record));
+        }
+    }
+    records.sort_by(|a, b| a.0.cmp(&b.0));
+    records
+}
+
+pub(super) fn timestamp(at: i64) -> String {
+    Local
+        .timestamp_opt(at, 0)
+        .single()
+        .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|| at.to_string())
+}
+
+/// Human-readable differences between a healthy run and a later one: checks
+/// that stopped passing, then the network facts that moved.
+fn diff(old: &Record, new: &Record) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    for check in &new.checks {
+        if let Some(was) = old.checks.iter().find(|c| c.name == check.name)
+            && was.status != check.status
+        {
+            lines.push(format!(
+                "{}: {} -> {} ({})",
+                check.name,
+                label(was.status),
+                label(check.status),
+                check.summary
+            ));
+        }
+    }
+
+    let (a, b) = (&old.facts, &new.facts);
+    let show = |v: &Option<String>| v.clone().unwrap_or_else(|| "none".to_string());
+    if a.ssid != b.ssid {
+        lines.push(format!(
+            "network changed: {} -> {}",
+            show(&a.ssid),
+            show(&b.ssid)
+        ));
+    } else if a.bssid != b.bssid {
+        lines.push(format!(
+            "access point changed: {} -> {}",
+            show(&a.bssid),
+            show(&b.bssid)
+        ));
+    }
+    if let (Some(was), Some(now)) = (a.signal, b.signal) {
+        let delta = now as i16 - was as i16;
+        if delta <= -SIGNAL_DELTA {
+            lines.push(format!("signal dropped {}%: {}% -> {}%", -delta, was, now));
+        } else if delta >= SIGNAL_DELTA {
+            lines.push(format!("signal rose {}%: {}% -> {}%", delta, was, now));
+        }
+    }
+    if let (Some(was), Some(now)) = (a.frequency, b.frequency)
+        && frequency_band(was) != frequency_band(now)
+    {
+        lines.push(format!(
+            "band changed: {} -> {}",
+            frequency_band(was),
+            frequency_band(now)
+        ));
+    }
+    if a.address != b.address {
+        lines.push(format!(
+            "address changed: {} -> {}",
+            show(&a.address),
+            show(&b.address)
+        ));
+    }
+    if a.gateway != b.gateway {
+        lines.push(format!(
+            "gateway changed: {} -> {}",
+            show(&a.gateway),
+            show(&b.gateway)
+        ));
+    }
+    if a.dns != b.dns {
+        let list = |dns: &[String]| {
+            if dns.is_empty() {
+                "none".to_string()
+            } else {
+                dns.join(", ")
+            }
+        };
+        lines.push(format!(
+            "DNS servers changed: {} -> {}",
+            list(&a.dns),
+            list(&b.dns)
+        ));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(status: Status, checks: &[(&str, Status)], facts: Facts) -> Record {
+        Record {
+            at: 0,
+            interface: "wlan0".into(),
+            status,
+            checks: checks
+                .iter()
+                .map(|(name, status)| RecordCheck {
+                    name: name.to_string(),
+                    status: *status,
+                    summary: "summary".into(),
+                })
+                .collect(),
+            facts,
+        }
+    }
+
+    #[test]
+    fn diff_lists_failed_checks_then_moved_facts() {
+        let healthy = Facts {
+            ssid: Some("home".into()),
+            bssid: Some("aa".into()),
+            signal: Some(80),
+            gateway: Some("192.168.1.1".into()),
+            dns: vec!["192.168.1.1".into()],
+            ..Default::default()
+        };
+        let now = Facts {
+            signal: Some(40),
+            gateway: Some("10.0.0.1".into()),
+            dns: vec!["10.0.0.1".into()],
+            ..healthy.clone()
+        };
+        let old = record(Status::Ok, &[("dns", Status::Ok)], healthy);
+        let new = record(Status::Fail, &[("dns", Status::Fail)], now);
+
+        let lines = diff(&old, &new);
+        assert_eq!(lines[0], "dns: ok -> fail (summary)");
+        assert!(lines.contains(&"signal dropped 40%: 80% -> 40%".to_string()));
+        assert!(lines.contains(&"gateway changed: 192.168.1.1 -> 10.0.0.1".to_string()));
+        assert!(lines.iter().any(|l| l.starts_with("DNS servers changed")));
+        assert!(!lines.iter().any(|l| l.starts_with("network")));
+    }
+
+    #[test]
+    fn small_signal_moves_are_ignored() {
+        let a = Facts {
+            signal: Some(70),
+            ..Default::default()
+        };
+        let b = Facts {
+            signal: Some(60),
+            ..Default::default()
+        };
+        assert!(diff(&record(Status::Ok, &[], a), &record(Status::Warn, &[], b)).is_empty());
+    }
+}
@@ -1,38 +1,95 @@
 //! Network diagnostic runner — walks the stack (rfkill → driver → interface →
 //! association → DHCP → DNS → gateway → internet → captive portal) and prints
-//! an interpreted verdict instead of raw logs.
+//! an interpreted verdict instead of raw logs. Checks run concurrently as soon
+//! as the layers they depend on have passed.
 
+mod bundle;
+mod captive;
 mod check;
 mod checks;
 mod context;
+mod fix;
+mod history;
 mod render;
 mod report;
+mod schedule;
+mod watch;
 
+pub use captive::{has_display, open_in_browser};
+pub use history::Changes;
 pub use render::render_modal;
 
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
-use anyhow::{Context as _, Result};
+use anyhow::{Context as _, Result, bail};
 
-use crate::nm::NMClient;
+use crate::backend::{self, Backend, BackendKind};
+use crate::config::Config;
 
-pub use check::{Outcome, Status};
+pub use check::{Fix, Outcome, Status};
 
 use check::DiagnosticCheck;
 use checks::{
-    AssociationCheck, DeviceStateCheck, DnsCheck, DriverCheck, GatewayCheck, InternetCheck,
-    IpAddressCheck, PortalCheck, RfkillCheck,
+    AaaaCheck, AssociationCheck, DeviceStateCheck, DhcpCheck, DnsCheck, DriverCheck, GatewayCheck,
+    Internet6Check, InternetCheck, Ip6AddressCheck, Ip6RouteCheck, IpAddressCheck,
+    LinkQualityCheck, LogsCheck, PortalCheck, RfkillCheck,
 };
 use context::DoctorContext;
 use report::Report;
 
+pub use report::Format;
+
 /// One row in a completed diagnostic report.
-pub type CheckEntry = (&'static str, Outcome);
+#[derive(Debug, Clone)]
+pub struct CheckEntry {
+    pub name: &'static str,
+    pub outcome: Outcome,
+    /// Wall-clock time the check took, reported by `--format json`/`junit`.
+    pub elapsed: Duration,
+}
 
 /// TUI-visible state of the doctor modal.
 pub enum DoctorModal {
-    Running,
-    Ready(Vec<CheckEntry>),
+    /// One row per check in stack order; `None` until that check reports.
+    Running(Vec<(&'static str, Option<CheckEntry>)>),
+    /// The results, and what changed since the last healthy run when this
+    /// one is not healthy.
+    Ready(Vec<CheckEntry>, Option<Changes>),
+    /// A finished run waiting for the user to agree to its fix.
+    ConfirmFix(Vec<CheckEntry>, Option<Changes>, Fix),
+}
+
+impl DoctorModal {
+    /// A running modal with a pending row for every check `check_now` runs.
+    pub fn running(config: &Config) -> Self {
+        let rows = Doctor::default()
+            .without(&config.doctor.disabled)
+            .checks
+            .iter()
+            .map(|c| (c.name(), None))
+            .collect();
+        DoctorModal::Running(rows)
+    }
+
+    /// The first fix a finished run offers, in stack order.
+    pub fn fix(&self) -> Option<Fix> {
+        match self {
+            DoctorModal::Running(_) | DoctorModal::ConfirmFix(..) => None,
+            DoctorModal::Ready(entries, _) => entries.iter().find_map(|e| e.outcome.fix),
+        }
+    }
+
+    /// The captive portal login page found by a finished run, if any.
+    pub fn login_url(&self) -> Option<&str> {
+        match self {
+            DoctorModal::Running(_) | DoctorModal::ConfirmFix(..) => None,
+            DoctorModal::Ready(entries, _) => {
+                entries.iter().find_map(|e| e.outcome.link.as_deref())
+            }
+        }
+    }
 }
 
 /// Composes the default set of diagnostic checks in stack order.
@@ -48,10 +105,17 @@ impl Default for Doctor {
                 Box::new(DriverCheck),
                 Box::new(DeviceStateCheck),
                 Box::new(AssociationCheck),
+                Box::new(LogsCheck),
+                Box::new(LinkQualityCheck),
+                Box::new(DhcpCheck),
                 Box::new(IpAddressCheck),
+                Box::new(Ip6AddressCheck),
                 Box::new(DnsCheck),
+                Box::new(AaaaCheck),
                 Box::new(GatewayCheck),
+                Box::new(Ip6RouteCheck),
                 Box::new(InternetCheck),
+                Box::new(Internet6Check),
                 Box::new(PortalCheck),
             ],
         }
@@ -59,58 +123,359 @@ impl Default for Doctor {
 }
 
 impl Doctor {
-    /// Runs every check sequentially and returns an ordered list of results.
-    async fn run(&self, ctx: &DoctorContext) -> Vec<CheckEntry> {
-        let mut results = Vec::with_capacity(self.checks.len());
-        for check in &self.checks {
-            let outcome = check.run(ctx).await;
-            results.push((check.name(), outcome));
+    /// Narrows the default suite to the checks named in `only` (all of them when
+    /// empty), minus those in `skip`. Stack order is kept regardless of the order
+    /// the ids were given in. Unknown ids are an error rather than silently
+    /// matching nothing.
+    pub fn select(only: &[String], skip: &[String]) -> Result<Self> {
+        let all = Self::default();
+        let known: Vec<&str> = all.checks.iter().map(|c| c.id()).collect();
+        if let Some(unknown) = only
+            .iter()
+            .chain(skip)
+            .find(|id| !known.contains(&id.as_str()))
+        {
+            bail!(
+                "Unknown check '{}' (expected one of: {})",
+                unknown,
+                known.join(", ")
+            );
         }
+
+        let checks: Vec<_> = all
+            .checks
+            .into_iter()
+            .filter(|c| only.is_empty() || only.iter().any(|id| id == c.id()))
+            .filter(|c| !skip.iter().any(|id| id == c.id()))
+            .collect();
+        if checks.is_empty() {
+            bail!("--only and --skip leave no checks to run");
+        }
+        Ok(Self { checks })
+    }
+
+    /// Drops the checks in `ids`. Unlike `select`, unknown ids are ignored: a
+    /// stale entry in the config file should not stop the doctor from running.
+    pub fn without(mut self, ids: &[String]) -> Self {
+        self.checks.retain(|c| !ids.iter().any(|id| id == c.id()));
+        self
+    }
+
+    /// Keeps the checks named in `names` and every check that depends on one
+    /// of them, directly or through another: what fixing them can change.
+    fn affected(mut self, names: &[&str]) -> Self {
+        let mut keep: Vec<&str> = self
+            .checks
+            .iter()
+            .filter(|c| names.contains(&c.name()))
+            .map(|c| c.id())
+            .collect();
+        loop {
+            let before = keep.len();
+            for check in &self.checks {
+                if !keep.contains(&check.id())
+                    && check.depends_on().iter().any(|d| keep.contains(d))
+                {
+                    keep.push(check.id());
+                }
+            }
+            if keep.len() == before {
+                break;
+            }
+        }
+        self.checks.retain(|c| keep.contains(&c.id()));
+        self
+    }
+
+    /// Runs the checks concurrently, respecting their dependencies, and
+    /// returns the results in stack order.
+    async fn run(&self, ctx: &DoctorContext) -> Vec<CheckEntry> {
+        self.run_streaming(ctx, |_, _| {}).await
+    }
+
+    /// Like `run`, but hands each result to `on_result` (with its index in
+    /// stack order) as soon as it completes.
+    async fn run_streaming(
+        &self,
+        ctx: &DoctorContext,
+        on_result: impl FnMut(usize, &CheckEntry),
+    ) -> Vec<CheckEntry> {
+        // Leave room for two probe timeouts (e.g. DNS then a fallback dial), so
+        // a raised `probe_timeout_ms` is not cut short by the per-check guard.
+        let floor = ctx.config.doctor.probe_timeout() * 2;
+        let mut results =
+            schedule::run_all(&self.checks, floor, |i| self.checks[i].run(ctx), on_result).await;
+        checks::point_to_logs(&mut results);
         results
     }
 }
 
-/// Runs the default check suite against the given NM device and returns
-/// results. Used by the TUI modal; the CLI uses `run()` below.
+/// Runs the default check suite against the given device and returns
+/// results, reporting each row through `on_result` as it completes. The run
+/// is added to the history, and when it is not healthy the changes since the
+/// last healthy run come back with it. Used by the TUI modal; the CLI uses
+/// `run()` below.
 pub async fn check_now(
-    nm: Arc<NMClient>,
+    backend: Arc<dyn Backend>,
     device_path: String,
     interface: String,
+    config: Arc<Config>,
+    mut on_result: impl FnMut(usize, CheckEntry),
+) -> (Vec<CheckEntry>, Option<Changes>) {
+    let doctor = Doctor::default().without(&config.doctor.disabled);
+    let ctx = DoctorContext {
+        backend,
+        device_path,
+        interface,
+        config,
+    };
+    let results = doctor
+        .run_streaming(&ctx, |i, entry| on_result(i, entry.clone()))
+        .await;
+    // History is a convenience; a run that cannot be saved still shows.
+    let changes = remember(&ctx, &results, false).await.unwrap_or_default();
+    (results, changes)
+}
+
+/// Runs only the captive portal check again, as after logging in to the
+/// portal, and swaps its row in `results`.
+pub async fn recheck_portal(
+    backend: Arc<dyn Backend>,
+    device_path: String,
+    interface: String,
+    config: Arc<Config>,
+    mut results: Vec<CheckEntry>,
 ) -> Vec<CheckEntry> {
     let ctx = DoctorContext {
-        nm,
+        backend,
         device_path,
         interface,
+        config,
+    };
+    let doctor = Doctor {
+        checks: vec![Box::new(PortalCheck)],
     };
-    Doctor::default().run(&ctx).await
+    for fresh in doctor.run(&ctx).await {
+        if let Some(row) = results.iter_mut().find(|e| e.name == fresh.name) {
+            *row = fresh;
+        }
+    }
+    results
 }
 
-/// Entry point invoked by the CLI. Builds a context for the first WiFi device
-/// and prints a formatted report to stdout.
-pub async fn run() -> Result<()> {
-    let nm = Arc::new(
-        NMClient::new()
-            .await
-            .context("Could not reach NetworkManager over D-Bus")?,
-    );
+/// Saves the run to the history, unless it is `partial`. Returns the changes
+/// since the last healthy run, if this one is not healthy and something
+/// changed.
+async fn remember(
+    ctx: &DoctorContext,
+    results: &[CheckEntry],
+    partial: bool,
+) -> Result<Option<Changes>> {
+    let changes = history::save(ctx, results, partial).await?;
+    let healthy = history::healthy(Report { entries: results }.worst());
+    Ok(changes.filter(|c| !healthy && !c.lines.is_empty()))
+}
 
-    let device_path = nm.get_wifi_device().await.context("No WiFi device found")?;
-    let device_path_str = device_path.as_str().to_string();
-    let interface = nm
-        .get_device_interface(&device_path_str)
-        .await
-        .context("Could not read interface name for WiFi device")?;
+/// Writes a diagnostics bundle for a finished TUI run to the downloads folder
+/// (see `bundle::default_path`) and returns where it went.
+pub async fn save_bundle(
+    backend: Arc<dyn Backend>,
+    device_path: String,
+    interface: String,
+    config: Arc<Config>,
+    results: &[CheckEntry],
+) -> Result<PathBuf> {
+    let ctx = DoctorContext {
+        backend,
+        device_path,
+        interface,
+        config,
+    };
+    let path = bundle::default_path();
+    bundle::write(&path, &ctx, results).await?;
+    Ok(path)
+}
 
+/// Applies a fix offered in the TUI modal and returns what it changed. The
+/// caller re-runs the doctor afterwards.
+pub async fn apply_fix(
+    backend: Arc<dyn Backend>,
+    device_path: String,
+    interface: String,
+    config: Arc<Config>,
+    fix: Fix,
+) -> Result<String> {
     let ctx = DoctorContext {
-        nm,
-        device_path: device_path_str,
+        backend,
+        device_path,
         interface,
+        config,
     };
+    fix::apply(fix, &ctx).await
+}
 
-    let doctor = Doctor::default();
-    let results = doctor.run(&ctx).await;
+/// What the `doctor` subcommand was asked to do.
+#[derive(Debug, Default)]
+pub struct Options {
+    /// Interface name to diagnose; the first WiFi device when `None`.
+    pub interface: Option<String>,
+    pub only: Vec<String>,
+    pub skip: Vec<String>,
+    /// Rerun the suite at this interval and print only status transitions.
+    pub watch: Option<Duration>,
+    pub format: Format,
+    /// Also write a diagnostics bundle (tar) here.
+    pub bundle: Option<PathBuf>,
+    /// Offer each fix the report suggests, and re-check after applying one.
+    pub fix: bool,
+    /// List past runs instead of running the checks.
+    pub history: bool,
+    /// Network service to diagnose through.
+    pub backend: BackendKind,
+}
 
-    Report { entries: &results }.print();
+/// Entry point invoked by the CLI. Builds a context for the requested WiFi
+/// device, prints the report to stdout in the requested format, and returns
+/// the process exit status for the worst result (see `Report::exit_code`).
+pub async fn run(options: Options, config: Config) -> Result<i32> {
+    if options.history {
+        history::list().await;
+        return Ok(0);
+    }
+    if options.fix && !captive::interactive() {
+        bail!("--fix asks before every change and needs a terminal");
+    }
+    let config = Arc::new(config);
+
+    let mut doctor = Doctor::select(&options.only, &options.skip)?;
+    // Naming a check with --only overrides it being disabled in the config.
+    if options.only.is_empty() {
+        doctor = doctor.without(&config.doctor.disabled);
+    }
+
+    let backend = backend::connect(options.backend).await?;
+    let (device_path, interface) =
+        wifi_device(backend.as_ref(), options.interface.as_deref()).await?;
+
+    let ctx = DoctorContext {
+        backend,
+        device_path,
+        interface,
+        config,
+    };
+
+    // A run narrowed on the command line is not the whole picture, and
+    // cannot stand in for one in the history.
+    let partial = !options.only.is_empty() || !options.skip.is_empty();
+
+    if let Some(interval) = options.watch {
+        return watch::run(&doctor, &ctx, interval, partial).await;
+    }
+
+    let mut results = doctor.run(&ctx).await;
+    Report { entries: &results }.print(options.format)?;
+
+    if let Some(path) = &options.bundle {
+        bundle::write(path, &ctx, &results).await?;
+        eprintln!("Wrote diagnostics bundle to {}", path.display());
+    }
+
+    if options.format == Format::Text && captive::interactive() {
+        captive::assist(&ctx, &mut results).await?;
+    }
+    if options.fix {
+        fix::offer(&ctx, &mut results).await?;
+    }
+
+    match remember(&ctx, &results, partial).await {
+        Ok(Some(changes)) if options.format == Format::Text => {
+            println!();
+            println!("Since the last healthy run ({}):", changes.since);
+            for line in &changes.lines {
+                println!("   {}", line);
+            }
+        }
+        Ok(_) => {}
+        Err(e) => eprintln!("Could not save this run to the history: {:#}", e),
+    }
+
+    Ok(Report { entries: &results }.exit_code())
+}
 
-    Ok(())
+/// Resolves `interface` to a WiFi device path, or picks the first WiFi device
+/// when no interface was asked for. Returns `(device_path, interface)`.
+async fn wifi_device(backend: &dyn Backend, interface: Option<&str>) -> Result<(String, String)> {
+    let devices = backend.get_wifi_devices().await?;
+    let mut names = Vec::with_capacity(devices.len());
+
+    for path in devices {
+        let name = backend
+            .get_device_interface(&path)
+            .await
+            .context("Could not read interface name for WiFi device")?;
+        if interface.is_none_or(|wanted| wanted == name) {
+            return Ok((path, name));
+        }
+        names.push(name);
+    }
+
+    match interface {
+        Some(wanted) if !names.is_empty() => bail!(
+            "No WiFi device named {} (found: {})",
+            wanted,
+            names.join(", ")
+        ),
+        _ => bail!("No WiFi device found"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(doctor: &Doctor) -> Vec<&'static str> {
+        doctor.checks.iter().map(|c| c.id()).collect()
+    }
+
+    fn strings(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn select_keeps_stack_order() {
+        let doctor = Doctor::select(&strings(&["portal", "dns", "ip"]), &[]).unwrap();
+        assert_eq!(ids(&doctor), ["ip", "dns", "portal"]);
+    }
+
+    #[test]
+    fn select_applies_skip_after_only() {
+        let doctor = Doctor::select(&[], &strings(&["portal", "internet"])).unwrap();
+        assert_eq!(ids(&doctor).len(), Doctor::default().checks.len() - 2);
+        assert!(!ids(&doctor).contains(&"portal"));
+
+        assert!(Doctor::select(&strings(&["dns"]), &strings(&["dns"])).is_err());
+    }
+
+    #[test]
+    fn without_ignores_unknown_ids() {
+        let doctor = Doctor::default().without(&strings(&["portal", "no-such-check"]));
+        assert_eq!(ids(&doctor).len(), Doctor::default().checks.len() - 1);
+    }
+
+    #[test]
+    fn affected_follows_dependents_transitively() {
+        let doctor = Doctor::default().affected(&["ip address"]);
+        let affected = ids(&doctor);
+        for id in ["ip", "dns", "dns6", "gateway", "internet", "portal"] {
+            assert!(affected.contains(&id), "{} missing", id);
+        }
+        assert!(!affected.contains(&"dhcp"));
+        assert!(!affected.contains(&"internet6"));
+    }
+
+    #[test]
+    fn select_rejects_unknown_ids() {
+        let err = Doctor::select(&strings(&["dsn"]), &[]).err().unwrap();
+        assert!(err.to_string().contains("dsn"));
+    }
 }
@@ -6,8 +6,8 @@ use ratatui::{
     widgets::{Block, BorderType, Borders, Cell, Clear, Padding, Paragraph, Row, Table},
 };
 
-use super::check::Status;
-use super::{CheckEntry, DoctorModal};
+use super::check::{Fix, Status};
+use super::{Changes, CheckEntry, DoctorModal};
 
 /// Draws the doctor modal centered on top of the current frame.
 pub fn render_modal(frame: &mut Frame, modal: &DoctorModal) {
@@ -16,8 +16,33 @@ pub fn render_modal(frame: &mut Frame, modal: &DoctorModal) {
     frame.render_widget(Clear, area);
 
     match modal {
-        DoctorModal::Running => render_running(frame, area),
-        DoctorModal::Ready(entries) => render_results(frame, area, entries),
+        DoctorModal::Running(rows) => {
+            let rows: Vec<_> = rows
+                .iter()
+                .map(|(name, entry)| (*name, entry.as_ref()))
+                .collect();
+            render_rows(
+                frame,
+                area,
+                &rows,
+                hint("Running diagnostics...", None, false),
+                None,
+            )
+        }
+        DoctorModal::Ready(entries, changes) => {
+            let rows: Vec<_> = entries.iter().map(|e| (e.name, Some(e))).collect();
+            render_rows(
+                frame,
+                area,
+                &rows,
+                hint("", modal.fix(), modal.login_url().is_some()),
+                changes.as_ref(),
+            )
+        }
+        DoctorModal::ConfirmFix(entries, changes, fix) => {
+            let rows: Vec<_> = entries.iter().map(|e| (e.name, Some(e))).collect();
+            render_rows(frame, area, &rows, confirm_hint(*fix), changes.as_ref())
+        }
     }
 }
 
@@ -58,14 +83,16 @@ fn doctor_block() -> Block<'static> {
         .padding(Padding::uniform(1))
 }
 
-fn render_running(frame: &mut Frame, area: Rect) {
-    let p = Paragraph::new(Line::from("Running diagnostics...").centered())
-        .block(doctor_block())
-        .alignment(Alignment::Center);
-    frame.render_widget(p, area);
-}
-
-fn render_results(frame: &mut Frame, area: Rect, entries: &[CheckEntry]) {
+/// Draws one row per check; `None` rows are still running. Verdicts are
+/// listed below the table as they arrive, followed by `changes` since the last
+/// healthy run, and `hint` goes on the bottom line.
+fn render_rows(
+    frame: &mut Frame,
+    area: Rect,
+    rows: &[(&'static str, Option<&CheckEntry>)],
+    hint: Paragraph<'static>,
+    changes: Option<&Changes>,
+) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .margin(1)
@@ -74,17 +101,27 @@ fn render_results(frame: &mut Frame, area: Rect, entries: &[CheckEntry]) {
 
     frame.render_widget(doctor_block(), area);
 
-    let rows: Vec<Row> = entries
+    let finished: Vec<&CheckEntry> = rows.iter().filter_map(|(_, e)| *e).collect();
+
+    let table_rows: Vec<Row> = rows
         .iter()
-        .map(|(name, outcome)| {
+        .map(|(name, entry)| {
+            let (label, color, summary) = match entry {
+                Some(e) => (
+                    status_label(e.outcome.status),
+                    status_color(e.outcome.status),
+                    e.outcome.summary.clone(),
+                ),
+                None => ("...", Color::DarkGray, String::new()),
+            };
             Row::new(vec![
-                Cell::from(status_label(outcome.status))
-                    .style(Style::default().fg(status_color(outcome.status)).bold()),
+                Cell::from(label).style(Style::default().fg(color).bold()),
                 Cell::from(Span::from(*name).bold()),
-                Cell::from(outcome.summary.clone()),
+                Cell::from(summary),
             ])
         })
-        .chain(verdict_rows(entries))
+        .chain(verdict_rows(&finished))
+        .chain(change_rows(changes))
         .collect();
 
     let widths = [
@@ -93,20 +130,57 @@ fn render_results(frame: &mut Frame, area: Rect, entries: &[CheckEntry]) {
         Constraint::Fill(1),
     ];
 
-    frame.render_widget(Table::new(rows, widths).column_spacing(2), chunks[0]);
+    frame.render_widget(Table::new(table_rows, widths).column_spacing(2), chunks[0]);
 
-    let hint = Paragraph::new(Line::from(vec![
-        Span::from("Esc").bold(),
-        Span::from(" Close"),
-    ]))
-    .alignment(Alignment::Center)
-    .style(Style::default().fg(Color::Blue));
     frame.render_widget(hint, chunks[1]);
 }
 
-fn verdict_rows(entries: &[CheckEntry]) -> Vec<Row<'_>> {
+/// The keys a modal offers: `fix` and `portal` add those for applying a fix
+/// and for the captive portal login.
+fn hint(status: &str, fix: Option<Fix>, portal: bool) -> Paragraph<'static> {
+    let mut hint = Vec::new();
+    if !status.is_empty() {
+        hint.push(Span::from(status.to_string()).fg(Color::DarkGray));
+        hint.push(Span::from("  "));
+    }
+    if status.is_empty() {
+        hint.push(Span::from("b").bold());
+        hint.push(Span::from(" Bundle  "));
+    }
+    if let Some(fix) = fix {
+        hint.push(Span::from("f").bold());
+        hint.push(Span::from(format!(" {}  ", fix.label())));
+    }
+    if portal {
+        hint.push(Span::from("o").bold());
+        hint.push(Span::from(" Log in  "));
+        hint.push(Span::from("r").bold());
+        hint.push(Span::from(" Re-check  "));
+    }
+    hint.push(Span::from("Esc").bold());
+    hint.push(Span::from(" Close"));
+    Paragraph::new(Line::from(hint))
+        .alignment(Alignment::Center)
+        .style(Style::default().fg(Color::Blue))
+}
+
+/// Asks before `fix` changes anything.
+fn confirm_hint(fix: Fix) -> Paragraph<'static> {
+    let line = Line::from(vec![
+        Span::from(format!("{}?  ", fix.label())),
+        Span::from("y").bold(),
+        Span::from(" Yes  "),
+        Span::from("n").bold(),
+        Span::from(" No"),
+    ]);
+    Paragraph::new(line)
+        .alignment(Alignment::Center)
+        .style(Style::default().fg(Color::Red))
+}
+
+fn verdict_rows<'a>(entries: &[&'a CheckEntry]) -> Vec<Row<'a>> {
     let mut rows = Vec::new();
-    let has_verdicts = entries.iter().any(|(_, o)| o.verdict.is_some());
+    let has_verdicts = entries.iter().any(|e| e.outcome.verdict.is_some());
     if !has_verdicts {
         return rows;
     }
@@ -116,8 +190,8 @@ fn verdict_rows(entries: &[CheckEntry]) -> Vec<Row<'_>> {
         Cell::from(""),
         Cell::from(""),
     ]));
-    for (_, outcome) in entries {
-        if let Some(verdict) = &outcome.verdict {
+    for entry in entries {
+        if let Some(verdict) = &entry.outcome.verdict {
             rows.push(Row::new(vec![
                 Cell::from("→").style(Style::default().fg(Color::Yellow).bold()),
                 Cell::from(""),
@@ -128,6 +202,29 @@ fn verdict_rows(entries: &[CheckEntry]) -> Vec<Row<'_>> {
     rows
 }
 
+fn change_rows(changes: Option<&Changes>) -> Vec<Row<'_>> {
+    let Some(changes) = changes else {
+        return Vec::new();
+    };
+
+    let mut rows = vec![
+        Row::new(vec![Cell::from(""), Cell::from(""), Cell::from("")]),
+        Row::new(vec![
+            Cell::from(""),
+            Cell::from(""),
+            Cell::from(format!("Since last healthy run ({}):", changes.since)).bold(),
+        ]),
+    ];
+    rows.extend(changes.lines.iter().map(|line| {
+        Row::new(vec![
+            Cell::from("Δ").style(Style::default().fg(Color::Cyan).bold()),
+            Cell::from(""),
+            Cell::from(line.as_str()).style(Style::default().fg(Color::Cyan)),
+        ])
+    }));
+    rows
+}
+
 fn status_label(status: Status) -> &'static str {
     match status {
         Status::Ok => "ok",
@@ -1,33 +1,208 @@
-use super::check::{Outcome, Status};
+use std::io::{self, Write};
+
+use serde::Serialize;
+
+use super::CheckEntry;
+use super::check::Status;
+
+/// How the CLI prints a finished report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    /// Human-readable rows with `[ok]`/`[fail]` markers and verdicts.
+    #[default]
+    Text,
+    /// One JSON document, for health scripts.
+    Json,
+    /// A JUnit XML test suite, one test case per check, for CI dashboards.
+    Junit,
+}
+
+impl TryFrom<&str> for Format {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "junit" => Ok(Format::Junit),
+            _ => Err(anyhow::anyhow!("Invalid format: {}", value)),
+        }
+    }
+}
 
 pub struct Report<'a> {
-    pub entries: &'a [(&'static str, Outcome)],
+    pub entries: &'a [CheckEntry],
 }
 
 impl Report<'_> {
-    pub fn print(&self) {
-        for (name, outcome) in self.entries {
-            println!(
+    /// The worst status in the report, or `Ok` when it is empty or only skips.
+    pub fn worst(&self) -> Status {
+        self.entries
+            .iter()
+            .map(|e| e.outcome.status)
+            .max_by_key(|s| s.severity())
+            .filter(|s| s.severity() > 0)
+            .unwrap_or(Status::Ok)
+    }
+
+    /// Process exit status for the report: `0` when nothing warned or failed,
+    /// `3` for a warning, `4` for a failure. `1` stays the generic error and
+    /// `2` belongs to clap's usage errors, so monitoring can tell "the network
+    /// is broken" from "doctor itself could not run".
+    pub fn exit_code(&self) -> i32 {
+        match self.worst() {
+            Status::Ok | Status::Skip => 0,
+            Status::Warn => 3,
+            Status::Fail => 4,
+        }
+    }
+
+    pub fn print(&self, format: Format) -> io::Result<()> {
+        let mut out = io::stdout().lock();
+        match format {
+            Format::Text => self.write_text(&mut out),
+            Format::Json => self.write_json(&mut out),
+            Format::Junit => self.write_junit(&mut out),
+        }
+    }
+
+    pub(super) fn write_text(&self, out: &mut impl Write) -> io::Result<()> {
+        for entry in self.entries {
+            writeln!(
+                out,
                 "{} {:<12} {}",
-                symbol(outcome.status),
-                name,
-                outcome.summary
-            );
+                symbol(entry.outcome.status),
+                entry.name,
+                entry.outcome.summary
+            )?;
         }
 
         let verdicts: Vec<&str> = self
             .entries
             .iter()
-            .filter_map(|(_, o)| o.verdict.as_deref())
+            .filter_map(|e| e.outcome.verdict.as_deref())
             .collect();
 
         if !verdicts.is_empty() {
-            println!();
+            writeln!(out)?;
             for v in verdicts {
-                println!("-> {}", v);
+                writeln!(out, "-> {}", v)?;
             }
         }
+        Ok(())
+    }
+
+    pub(super) fn write_json(&self, out: &mut impl Write) -> io::Result<()> {
+        let doc = JsonReport {
+            status: self.worst(),
+            checks: self
+                .entries
+                .iter()
+                .map(|e| JsonCheck {
+                    name: e.name,
+                    status: e.outcome.status,
+                    summary: &e.outcome.summary,
+                    verdict: e.outcome.verdict.as_deref(),
+                    url: e.outcome.link.as_deref(),
+                    duration_ms: e.elapsed.as_millis() as u64,
+                })
+                .collect(),
+        };
+        serde_json::to_writer_pretty(&mut *out, &doc)?;
+        writeln!(out)
+    }
+
+    /// Warnings have no JUnit equivalent, so they pass with the summary noted
+    /// in `system-out`; a CI dashboard should only go red on a real failure.
+    fn write_junit(&self, out: &mut impl Write) -> io::Result<()> {
+        let count = |status: Status| {
+            self.entries
+                .iter()
+                .filter(|e| e.outcome.status == status)
+                .count()
+        };
+        let total: f64 = self.entries.iter().map(|e| e.elapsed.as_secs_f64()).sum();
+
+        writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            out,
+            r#"<testsuite name="wlctl doctor" tests="{}" failures="{}" skipped="{}" time="{:.3}">"#,
+            self.entries.len(),
+            count(Status::Fail),
+            count(Status::Skip),
+            total
+        )?;
+
+        for entry in self.entries {
+            let summary = xml_escape(&entry.outcome.summary);
+            write!(
+                out,
+                r#"  <testcase classname="wlctl.doctor" name="{}" time="{:.3}""#,
+                xml_escape(entry.name),
+                entry.elapsed.as_secs_f64()
+            )?;
+            match entry.outcome.status {
+                Status::Ok => writeln!(out, "/>")?,
+                Status::Warn => {
+                    writeln!(out, ">")?;
+                    writeln!(out, "    <system-out>warn: {summary}</system-out>")?;
+                    writeln!(out, "  </testcase>")?;
+                }
+                Status::Skip => {
+                    writeln!(out, ">")?;
+                    writeln!(out, r#"    <skipped message="{summary}"/>"#)?;
+                    writeln!(out, "  </testcase>")?;
+                }
+                Status::Fail => {
+                    let verdict = xml_escape(entry.outcome.verdict.as_deref().unwrap_or(""));
+                    writeln!(out, ">")?;
+                    writeln!(
+                        out,
+                        r#"    <failure message="{summary}">{verdict}</failure>"#
+                    )?;
+                    writeln!(out, "  </testcase>")?;
+                }
+            }
+        }
+
+        writeln!(out, "</testsuite>")
+    }
+}
+
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    status: Status,
+    checks: Vec<JsonCheck<'a>>,
+}
+
+#[derive(Serialize)]
+struct JsonCheck<'a> {
+    name: &'a str,
+    status: Status,
+    summary: &'a str,
+    verdict: Option<&'a str>,
+    /// Page the user has to visit, e.g. a captive portal's login form.
+    url: Option<&'a str>,
+    duration_ms: u64,
+}
+
+/// Escapes markup in `s`. Characters XML 1.0 does not allow at all, such as
+/// the control bytes an SSID can carry, become U+FFFD.
+fn xml_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\t' | '\n' | '\r' => escaped.push(c),
+            '\u{0}'..='\u{1f}' | '\u{fffe}' | '\u{ffff}' => escaped.push('\u{fffd}'),
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
     }
+    escaped
 }
 
 fn symbol(status: Status) -> &'static str {
@@ -38,3 +213,119 @@ fn symbol(status: Status) -> &'static str {
         Status::Skip => "[skip]",
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::doctor::Outcome;
+
+    fn entry(name: &'static str, outcome: Outcome) -> CheckEntry {
+        CheckEntry {
+            name,
+            outcome,
+            elapsed: Duration::from_millis(12),
+        }
+    }
+
+    fn render(report: &Report, format: Format) -> String {
+        let mut out = Vec::new();
+        match format {
+            Format::Text => report.write_text(&mut out),
+            Format::Json => report.write_json(&mut out),
+            Format::Junit => report.write_junit(&mut out),
+        }
+        .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn exit_code_follows_the_worst_status() {
+        let ok = [
+            entry("a", Outcome::ok("fine")),
+            entry("b", Outcome::skip("n/a")),
+        ];
+        assert_eq!(Report { entries: &ok }.exit_code(), 0);
+
+        let warn = [
+            entry("a", Outcome::ok("fine")),
+            entry("b", Outcome::warn("hm")),
+        ];
+        assert_eq!(Report { entries: &warn }.exit_code(), 3);
+
+        let fail = [
+            entry("a", Outcome::fail("broken", "fix it")),
+            entry("b", Outcome::warn("hm")),
+        ];
+        assert_eq!(Report { entries: &fail }.exit_code(), 4);
+    }
+
+    #[test]
+    fn json_carries_every_field() {
+        let entries = [entry(
+            "dns",
+            Outcome::fail("timed out", "check resolv.conf"),
+        )];
+        let doc: serde_json::Value =
+            serde_json::from_str(&render(&Report { entries: &entries }, Format::Json)).unwrap();
+
+        assert_eq!(doc["status"], "fail");
+        let check = &doc["checks"][0];
+        assert_eq!(check["name"], "dns");
+        assert_eq!(check["status"], "fail");
+        assert_eq!(check["summary"], "timed out");
+        assert_eq!(check["verdict"], "check resolv.conf");
+        assert!(check["url"].is_null());
+        assert_eq!(check["duration_ms"], 12);
+    }
+
+    #[test]
+    fn json_carries_portal_login_url() {
+        let entries = [entry(
+            "connectivity",
+            Outcome::fail("captive portal at 10.0.0.1", "Log in").with_link("http://10.0.0.1/"),
+        )];
+        let doc: serde_json::Value =
+            serde_json::from_str(&render(&Report { entries: &entries }, Format::Json)).unwrap();
+
+        assert_eq!(doc["checks"][0]["url"], "http://10.0.0.1/");
+    }
+
+    #[test]
+    fn junit_escapes_and_counts() {
+        let entries = [
+            entry("gateway", Outcome::fail("<none>", "\"router\" & co")),
+            entry("portal", Outcome::skip("unknown")),
+            entry("rfkill", Outcome::ok("not blocked")),
+        ];
+        let xml = render(&Report { entries: &entries }, Format::Junit);
+
+        assert!(xml.contains(r#"tests="3" failures="1" skipped="1""#));
+        assert!(
+            xml.contains(
+                r#"<failure message="&lt;none&gt;">&quot;router&quot; &amp; co</failure>"#
+            )
+        );
+        assert!(xml.contains(r#"<skipped message="unknown"/>"#));
+    }
+
+    #[test]
+    fn junit_replaces_characters_xml_cannot_hold() {
+        let entries = [entry(
+            "association",
+            Outcome::warn("associated with Cafe\u{1}\u{ffff}\tFree"),
+        )];
+        let xml = render(&Report { entries: &entries }, Format::Junit);
+
+        assert!(xml.contains("Cafe\u{fffd}\u{fffd}\tFree"), "{xml}");
+        assert!(!xml.chars().any(|c| c == '\u{1}' || c == '\u{ffff}'));
+    }
+
+    #[test]
+    fn format_parses_case_insensitively() {
+        assert_eq!(Format::try_from("JSON").unwrap(), Format::Json);
+        assert_eq!(Format::try_from("junit").unwrap(), Format::Junit);
+        assert!(Format::try_from("yaml").is_err());
+    }
+}
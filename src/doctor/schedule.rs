@@ -0,0 +1,255 @@
+//! Runs a check list as a dependency graph: every check whose prerequisites
+//! have reported starts at once, and a check whose prerequisite failed is
+//! skipped instead of adding its own timeout to the wait.
+
+use std::time::{Duration, Instant};
+
+use futures::StreamExt;
+use futures::future::BoxFuture;
+use futures::stream::FuturesUnordered;
+use tokio::time::timeout;
+
+use super::CheckEntry;
+use super::check::{DiagnosticCheck, Outcome, Status};
+
+/// Runs `checks` through `run_one`, calling `on_result` with each entry's index
+/// as it completes. Returns the entries in the order of `checks`. No check is
+/// timed out before `floor`, whatever its own `timeout()` says.
+///
+/// Dependencies on checks that are not in the list (e.g. left out by `--only`)
+/// count as satisfied.
+pub(super) async fn run_all<'a>(
+    checks: &'a [Box<dyn DiagnosticCheck>],
+    floor: Duration,
+    run_one: impl Fn(usize) -> BoxFuture<'a, Outcome>,
+    mut on_result: impl FnMut(usize, &CheckEntry),
+) -> Vec<CheckEntry> {
+    let mut results: Vec<Option<CheckEntry>> = checks.iter().map(|_| None).collect();
+    let mut started = vec![false; checks.len()];
+    // Checks whose dependents must not run: those that failed, and those that
+    // were skipped here because a prerequisite of their own did not pass.
+    let mut blocked = vec![false; checks.len()];
+    let mut running = FuturesUnordered::new();
+
+    loop {
+        // Auto-skips complete synchronously and can unblock further checks,
+        // so keep sweeping until a pass starts nothing new.
+        let mut progressed = true;
+        while progressed {
+            progressed = false;
+            for (i, check) in checks.iter().enumerate() {
+                if started[i] {
+                    continue;
+                }
+                let deps: Vec<usize> = check
+                    .depends_on()
+                    .iter()
+                    .filter_map(|id| checks.iter().position(|c| c.id() == *id))
+                    .collect();
+                if deps.iter().any(|&d| results[d].is_none()) {
+                    continue;
+                }
+                started[i] = true;
+
+                if let Some(&d) = deps.iter().find(|&&d| blocked[d]) {
+                    let entry = CheckEntry {
+                        name: check.name(),
+                        outcome: Outcome::skip(format!(
+                            "not run: {} did not pass",
+                            checks[d].name()
+                        )),
+                        elapsed: Duration::ZERO,
+                    };
+                    blocked[i] = true;
+                    on_result(i, &entry);
+                    results[i] = Some(entry);
+                    progressed = true;
+                    continue;
+                }
+
+                let limit = check.timeout().max(floor);
+                let future = run_one(i);
+                running.push(async move {
+                    let begun = Instant::now();
+                    let outcome = timeout(limit, future).await.unwrap_or_else(|_| {
+                        Outcome::warn(format!("no result within {}s", limit.as_secs()))
+                    });
+                    (i, outcome, begun.elapsed())
+                });
+            }
+        }
+
+        let Some((i, outcome, elapsed)) = running.next().await else {
+            break;
+        };
+        blocked[i] = outcome.status == Status::Fail;
+        let entry = CheckEntry {
+            name: checks[i].name(),
+            outcome,
+            elapsed,
+        };
+        on_result(i, &entry);
+        results[i] = Some(entry);
+    }
+
+    // Anything still unset sits on a dependency cycle; report it rather than
+    // hang or panic.
+    results
+        .into_iter()
+        .zip(checks)
+        .enumerate()
+        .map(|(i, (result, check))| {
+            result.unwrap_or_else(|| {
+                let entry = CheckEntry {
+                    name: check.name(),
+                    outcome: Outcome::skip("not run: dependency cycle"),
+                    elapsed: Duration::ZERO,
+                };
+                on_result(i, &entry);
+                entry
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+    use futures::FutureExt;
+
+    use super::*;
+    use crate::doctor::context::DoctorContext;
+
+    /// Only its graph metadata is used; outcomes come from the `run_one` closure.
+    struct Node {
+        id: &'static str,
+        deps: &'static [&'static str],
+        timeout: Duration,
+    }
+
+    #[async_trait]
+    impl DiagnosticCheck for Node {
+        fn name(&self) -> &'static str {
+            self.id
+        }
+
+        fn depends_on(&self) -> &'static [&'static str] {
+            self.deps
+        }
+
+        fn timeout(&self) -> Duration {
+            self.timeout
+        }
+
+        async fn run(&self, _ctx: &DoctorContext) -> Outcome {
+            unreachable!("tests drive outcomes through run_one")
+        }
+    }
+
+    fn node(id: &'static str, deps: &'static [&'static str]) -> Box<dyn DiagnosticCheck> {
+        Box::new(Node {
+            id,
+            deps,
+            timeout: Duration::from_secs(5),
+        })
+    }
+
+    fn after(ms: u64, outcome: Outcome) -> BoxFuture<'static, Outcome> {
+        async move {
+            tokio::time::sleep(Duration::from_millis(ms)).await;
+            outcome
+        }
+        .boxed()
+    }
+
+    #[tokio::test]
+    async fn independent_checks_overlap() {
+        let checks = vec![node("a", &[]), node("b", &[]), node("c", &[])];
+        let begun = Instant::now();
+        let results = run_all(
+            &checks,
+            Duration::ZERO,
+            |_| after(200, Outcome::ok("up")),
+            |_, _| {},
+        )
+        .await;
+
+        assert!(begun.elapsed() < Duration::from_millis(500));
+        assert!(results.iter().all(|e| e.outcome.status == Status::Ok));
+    }
+
+    #[tokio::test]
+    async fn failure_skips_dependents_transitively() {
+        let checks = vec![
+            node("ip", &[]),
+            node("dns", &["ip"]),
+            node("portal", &["dns"]),
+            node("driver", &[]),
+        ];
+        let results = run_all(
+            &checks,
+            Duration::ZERO,
+            |i| match i {
+                0 => after(0, Outcome::fail("no address", "reconnect")),
+                _ => after(0, Outcome::ok("up")),
+            },
+            |_, _| {},
+        )
+        .await;
+
+        let statuses: Vec<Status> = results.iter().map(|e| e.outcome.status).collect();
+        assert_eq!(
+            statuses,
+            [Status::Fail, Status::Skip, Status::Skip, Status::Ok]
+        );
+        assert!(results[1].outcome.summary.contains("ip"));
+    }
+
+    #[tokio::test]
+    async fn results_stream_in_completion_order() {
+        let checks = vec![node("slow", &[]), node("fast", &[])];
+        let mut order = Vec::new();
+        let results = run_all(
+            &checks,
+            Duration::ZERO,
+            |i| after(if i == 0 { 150 } else { 10 }, Outcome::ok("up")),
+            |i, _| order.push(i),
+        )
+        .await;
+
+        assert_eq!(order, [1, 0]);
+        assert_eq!(results[0].name, "slow");
+    }
+
+    #[tokio::test]
+    async fn timeout_becomes_a_warning() {
+        let checks: Vec<Box<dyn DiagnosticCheck>> = vec![Box::new(Node {
+            id: "hang",
+            deps: &[],
+            timeout: Duration::from_millis(20),
+        })];
+        let results = run_all(
+            &checks,
+            Duration::ZERO,
+            |_| after(5_000, Outcome::ok("up")),
+            |_, _| {},
+        )
+        .await;
+
+        assert_eq!(results[0].outcome.status, Status::Warn);
+    }
+
+    #[tokio::test]
+    async fn cycles_are_reported_not_hung() {
+        let checks = vec![node("a", &["b"]), node("b", &["a"])];
+        let results = run_all(
+            &checks,
+            Duration::ZERO,
+            |_| after(0, Outcome::ok("up")),
+            |_, _| {},
+        )
+        .await;
+
+        assert!(results.iter().all(|e| e.outcome.status == Status::Skip));
+    }
+}
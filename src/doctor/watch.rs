@@ -0,0 +1,119 @@
+//! `wlctl doctor --watch`: rerun the suite on an interval and print only the
+//! checks whose status changed, so it can sit in a terminal while an
+//! intermittent drop is being reproduced.
+
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::Local;
+
+use super::check::Status;
+use super::context::DoctorContext;
+use super::report::{Format, Report};
+use super::{CheckEntry, Doctor, remember};
+
+/// Prints one full report, then a timestamped line per status change until
+/// Ctrl-C. Every run goes into the history, as a one-off run would, unless it
+/// is `partial`. Returns the exit status of the last completed run.
+pub(super) async fn run(
+    doctor: &Doctor,
+    ctx: &DoctorContext,
+    interval: Duration,
+    partial: bool,
+) -> Result<i32> {
+    let mut previous = doctor.run(ctx).await;
+    if let Err(e) = remember(ctx, &previous, partial).await {
+        eprintln!("Could not save this run to the history: {:#}", e);
+    }
+    println!("{}", Local::now().format("%H:%M:%S"));
+    Report { entries: &previous }.print(Format::Text)?;
+    println!();
+
+    loop {
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => break,
+            _ = tokio::time::sleep(interval) => {}
+        }
+
+        let current = tokio::select! {
+            _ = tokio::signal::ctrl_c() => break,
+            results = doctor.run(ctx) => results,
+        };
+        // History is a convenience; the first run already said if it fails.
+        let _ = remember(ctx, &current, partial).await;
+
+        let stamp = Local::now().format("%H:%M:%S");
+        for (entry, was) in transitions(&previous, &current) {
+            println!(
+                "{} {:<12} {} -> {}  {}",
+                stamp,
+                entry.name,
+                label(was),
+                label(entry.outcome.status),
+                entry.outcome.summary
+            );
+            if let Some(verdict) = &entry.outcome.verdict {
+                println!("{:>8} -> {}", "", verdict);
+            }
+        }
+
+        previous = current;
+    }
+
+    Ok(Report { entries: &previous }.exit_code())
+}
+
+/// Entries in `current` whose status differs from the same check in
+/// `previous`, paired with the status they had before.
+fn transitions<'a>(
+    previous: &[CheckEntry],
+    current: &'a [CheckEntry],
+) -> Vec<(&'a CheckEntry, Status)> {
+    current
+        .iter()
+        .filter_map(|entry| {
+            let was = previous.iter().find(|p| p.name == entry.name)?;
+            (was.outcome.status != entry.outcome.status).then_some((entry, was.outcome.status))
+        })
+        .collect()
+}
+
+pub(super) fn label(status: Status) -> &'static str {
+    match status {
+        Status::Ok => "ok",
+        Status::Warn => "warn",
+        Status::Fail => "fail",
+        Status::Skip => "skip",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::doctor::Outcome;
+
+    fn entry(name: &'static str, outcome: Outcome) -> CheckEntry {
+        CheckEntry {
+            name,
+            outcome,
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    #[test]
+    fn only_changed_statuses_are_reported() {
+        let previous = [
+            entry("dns", Outcome::ok("1.1.1.1")),
+            entry("gateway", Outcome::ok("reachable")),
+        ];
+        let current = [
+            entry("dns", Outcome::ok("9.9.9.9")),
+            entry("gateway", Outcome::fail("unreachable", "check the router")),
+        ];
+
+        let changed = transitions(&previous, &current);
+        assert_eq!(changed.len(), 1);
+        assert_eq!(changed[0].0.name, "gateway");
+        assert_eq!(changed[0].1, Status::Ok);
+    }
+}
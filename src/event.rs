@@ -5,13 +5,16 @@ use crossterm::event::{Event as CrosstermEvent, KeyEvent, MouseEvent};
 use futures::{FutureExt, StreamExt};
 use tokio::sync::mpsc;
 
-use crate::doctor::CheckEntry;
+use crate::agent::SecretReply;
+use crate::doctor::{Changes, CheckEntry};
 use crate::mode::station::speed_test::SpeedTest;
 use crate::notification::Notification;
 
 #[derive(Clone, Debug)]
 pub enum Event {
     Tick,
+    /// The backend's state changed; see [`crate::backend::Watch`].
+    NmChanged,
     Key(KeyEvent),
     Mouse(MouseEvent),
     Paste(String),
@@ -21,21 +24,37 @@ pub enum Event {
     Auth(String),
     EapNeworkConfigured(String),
     ConfigureNewEapNetwork(String),
-    AuthRequestPassword((String, Option<String>)),
+    AuthRequestPassword {
+        network_name: String,
+        user_name: Option<String>,
+        message: Option<String>,
+        /// What is asked for, e.g. `Password` or `One-time code`.
+        label: String,
+        reply: SecretReply,
+    },
+    /// The service stopped waiting for the open password prompt.
+    AuthRequestWithdrawn,
     AuthReqKeyPassphrase(String),
     AuthReqUsernameAndPassword(String),
     UsernameAndPasswordSubmit,
     SpeedTestResult(SpeedTest),
+    DoctorProgress {
+        run_id: u64,
+        index: usize,
+        entry: CheckEntry,
+    },
     DoctorCompleted {
         run_id: u64,
         results: Vec<CheckEntry>,
+        changes: Option<Changes>,
     },
 }
 
 /// Forwards terminal input into the main loop.
 ///
-/// Periodic refreshes are not produced here: the main loop drives them from its
-/// own timer so a refresh can never be queued while one is already running.
+/// Refreshes are not produced here: NetworkManager state changes arrive as
+/// [`Event::NmChanged`] from the snapshot watcher, and the main loop drives its
+/// redraw heartbeat from its own timer.
 #[allow(dead_code)]
 #[derive(Debug)]
 pub struct EventHandler {
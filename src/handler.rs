@@ -95,22 +95,126 @@ pub async fn toggle_connect(app: &mut App, sender: UnboundedSender<Event>) -> Re
     Ok(())
 }
 
-async fn start_doctor(app: &mut App, sender: UnboundedSender<Event>) {
+async fn start_doctor(
+    app: &mut App,
+    sender: UnboundedSender<Event>,
+    fix: Option<crate::doctor::Fix>,
+) {
     use crate::doctor::{self, DoctorModal};
 
     app.doctor_run_id = app.doctor_run_id.wrapping_add(1);
     let run_id = app.doctor_run_id;
 
-    app.doctor = Some(DoctorModal::Running);
+    app.doctor = Some(DoctorModal::running(&app.config));
     app.focused_block = FocusedBlock::Doctor;
 
-    let nm = app.client.clone();
+    let backend = app.client.clone();
+    let device_path = app.device.device_path.clone();
+    let interface = app.device.name.clone();
+    let config = app.config.clone();
+
+    tokio::spawn(async move {
+        // Apply the fix first so the fresh run shows its effect.
+        if let Some(fix) = fix {
+            let applied = doctor::apply_fix(
+                backend.clone(),
+                device_path.clone(),
+                interface.clone(),
+                config.clone(),
+                fix,
+            )
+            .await;
+            let _ = match applied {
+                Ok(done) => {
+                    Notification::send(done, notification::NotificationLevel::Info, &sender)
+                }
+                Err(e) => Notification::send(
+                    format!("{}: {e}", fix.label()),
+                    notification::NotificationLevel::Error,
+                    &sender,
+                ),
+            };
+        }
+
+        let progress = sender.clone();
+        let (results, changes) =
+            doctor::check_now(backend, device_path, interface, config, |index, entry| {
+                let _ = progress.send(Event::DoctorProgress {
+                    run_id,
+                    index,
+                    entry,
+                });
+            })
+            .await;
+        let _ = sender.send(Event::DoctorCompleted {
+            run_id,
+            results,
+            changes,
+        });
+    });
+}
+
+/// Re-runs only the captive portal check of a finished doctor run, keeping
+/// every other row as it was.
+fn recheck_portal(app: &mut App, sender: UnboundedSender<Event>) {
+    use crate::doctor::{self, DoctorModal};
+
+    let Some(DoctorModal::Ready(results, changes)) = app.doctor.take() else {
+        return;
+    };
+    app.doctor_run_id = app.doctor_run_id.wrapping_add(1);
+    let run_id = app.doctor_run_id;
+
+    // The portal's row shows as pending again while the rest stay put.
+    let rows = results
+        .iter()
+        .map(|e| (e.name, e.outcome.link.is_none().then(|| e.clone())))
+        .collect();
+    app.doctor = Some(DoctorModal::Running(rows));
+
+    let backend = app.client.clone();
     let device_path = app.device.device_path.clone();
     let interface = app.device.name.clone();
+    let config = app.config.clone();
 
     tokio::spawn(async move {
-        let results = doctor::check_now(nm, device_path, interface).await;
-        let _ = sender.send(Event::DoctorCompleted { run_id, results });
+        let results =
+            doctor::recheck_portal(backend, device_path, interface, config, results).await;
+        let _ = sender.send(Event::DoctorCompleted {
+            run_id,
+            results,
+            changes,
+        });
+    });
+}
+
+/// Writes a diagnostics bundle for the finished doctor run in the background
+/// and reports where it went.
+fn save_doctor_bundle(
+    app: &App,
+    results: Vec<crate::doctor::CheckEntry>,
+    sender: UnboundedSender<Event>,
+) {
+    let backend = app.client.clone();
+    let device_path = app.device.device_path.clone();
+    let interface = app.device.name.clone();
+    let config = app.config.clone();
+
+    tokio::spawn(async move {
+        let saved =
+            crate::doctor::save_bundle(backend, device_path, interface, config, &results).await;
+        let _ = match saved {
+            Ok(path) => Notification::send(
+                format!("Saved diagnostics bundle to {}", path.display()),
+                notification::NotificationLevel::Info,
+                &sender,
+            ),
+            Err(e) => Notification::send(
+                format!("Could not save diagnostics bundle: {e}"),
+                notification::NotificationLevel::Error,
+                &sender,
+            ),
+        };
     });
 }
 
@@ -130,7 +234,7 @@ fn is_navigational(focused: FocusedBlock) -> bool {
 /// Opens the VPN modal, loading the saved profiles up front. On failure the
 /// modal isn't opened and the error surfaces as a notification.
 async fn open_vpn(app: &mut App, sender: &UnboundedSender<Event>) {
-    match crate::vpn::VpnModal::load(&app.client).await {
+    match crate::vpn::VpnModal::load(app.client.as_ref()).await {
         Ok(modal) => {
             app.vpn = Some(modal);
             app.focused_block = FocusedBlock::Vpn;
@@ -151,7 +255,10 @@ pub fn handle_paste(app: &mut App, text: String) {
     if app.focused_block == FocusedBlock::Vpn
         && let Some(modal) = &mut app.vpn
     {
-        modal.import_append(&text);
+        match modal.create_form_mut() {
+            Some(form) => form.paste(&text),
+            None => modal.import_append(&text),
+        }
     }
 }
 
@@ -179,21 +286,21 @@ async fn handle_vpn_keys(
                 if let Some(raw) = modal.take_import() {
                     let input = raw.trim();
                     if !input.is_empty() {
-                        // A pasted config contains an [Interface] section;
-                        // anything else is treated as a path to a `.conf` file.
-                        let result = if input.to_ascii_lowercase().contains("[interface]") {
-                            crate::vpn::import_from_text(&app.client, input).await
-                        } else {
-                            crate::vpn::import_from_file(&app.client, input).await
-                        };
-                        match result {
-                            Ok(id) => {
+                        match crate::vpn::import(app.client.as_ref(), input).await {
+                            Ok(imported) => {
                                 Notification::send(
-                                    format!("Imported VPN {id}"),
+                                    format!("Imported VPN {}", imported.id),
                                     notification::NotificationLevel::Info,
                                     sender,
                                 )?;
-                                modal.refresh(&app.client).await?;
+                                for warning in imported.warnings {
+                                    Notification::send(
+                                        warning,
+                                        notification::NotificationLevel::Warning,
+                                        sender,
+                                    )?;
+                                }
+                                modal.refresh(app.client.as_ref()).await?;
                             }
                             Err(e) => Notification::send(
                                 format!("Import failed: {e}"),
@@ -209,11 +316,90 @@ async fn handle_vpn_keys(
         return Ok(());
     }
 
+    // The new-tunnel form captures all keys: Tab between fields, Enter to
+    // preview and Enter again to save; Esc steps back out of the preview, then
+    // out of the form.
+    if let Some(form) = modal.create_form_mut() {
+        match key_event.code {
+            KeyCode::Esc if form.preview.is_some() => form.preview = None,
+            KeyCode::Esc => modal.cancel_prompt(),
+            KeyCode::Enter if form.preview.is_some() => {
+                match modal.save_created(app.client.as_ref()).await {
+                    Ok(Some(id)) => Notification::send(
+                        format!("Created VPN {id}"),
+                        notification::NotificationLevel::Info,
+                        sender,
+                    )?,
+                    Ok(None) => {}
+                    Err(e) => Notification::send(
+                        format!("Creating the tunnel failed: {e}"),
+                        notification::NotificationLevel::Error,
+                        sender,
+                    )?,
+                }
+            }
+            KeyCode::Enter => {
+                if let Err(e) = form.check() {
+                    Notification::send(
+                        e.to_string(),
+                        notification::NotificationLevel::Warning,
+                        sender,
+                    )?;
+                }
+            }
+            _ if form.preview.is_some() => {}
+            KeyCode::Tab | KeyCode::Down => form.next_field(),
+            KeyCode::BackTab | KeyCode::Up => form.prev_field(),
+            _ => {
+                form.focused_input()
+                    .handle_event(&crossterm::event::Event::Key(key_event));
+            }
+        }
+        return Ok(());
+    }
+
+    // An export on show: `s` saves it to the home directory, Esc closes it.
+    if let Some(export) = modal.export() {
+        match key_event.code {
+            KeyCode::Char('s') => {
+                let saved = std::env::var("HOME")
+                    .map_err(|_| anyhow::anyhow!("HOME is not set"))
+                    .and_then(|home| export.save(std::path::Path::new(&home)));
+                match saved {
+                    Ok(path) => {
+                        Notification::send(
+                            format!("Saved {}", path.display()),
+                            notification::NotificationLevel::Info,
+                            sender,
+                        )?;
+                        modal.cancel_prompt();
+                    }
+                    Err(e) => Notification::send(
+                        format!("Export failed: {e:#}"),
+                        notification::NotificationLevel::Error,
+                        sender,
+                    )?,
+                }
+            }
+            KeyCode::Esc => modal.cancel_prompt(),
+            _ => {}
+        }
+        return Ok(());
+    }
+
     // A pending delete confirmation captures all keys until resolved.
+    // The new tunnel's public key stays up until it is dismissed.
+    if modal.created().is_some() {
+        if matches!(key_event.code, KeyCode::Esc | KeyCode::Enter) {
+            modal.cancel_prompt();
+        }
+        return Ok(());
+    }
+
     if modal.pending_delete().is_some() {
         match key_event.code {
             KeyCode::Char('y') | KeyCode::Char('Y') => {
-                match modal.delete_confirmed(&app.client).await {
+                match modal.delete_confirmed(app.client.as_ref()).await {
                     Ok(Some(id)) => Notification::send(
                         format!("Deleted VPN {id}"),
                         notification::NotificationLevel::Info,
@@ -246,7 +432,7 @@ async fn handle_vpn_keys(
             };
             let bringing_up = !entry.is_active();
 
-            match crate::vpn::toggle(&app.client, &entry).await {
+            match crate::vpn::toggle(app.client.as_ref(), &entry).await {
                 Ok(()) => {
                     let verb = if bringing_up {
                         "Connecting"
@@ -258,7 +444,7 @@ async fn handle_vpn_keys(
                         notification::NotificationLevel::Info,
                         sender,
                     )?;
-                    modal.refresh(&app.client).await?;
+                    modal.refresh(app.client.as_ref()).await?;
                 }
                 Err(e) => {
                     Notification::send(
@@ -269,7 +455,7 @@ async fn handle_vpn_keys(
                 }
             }
         }
-        KeyCode::Char('a') => match modal.toggle_autoconnect(&app.client).await {
+        KeyCode::Char('a') => match modal.toggle_autoconnect(app.client.as_ref()).await {
             Ok(Some((id, on))) => Notification::send(
                 format!("Autoconnect {} for {id}", if on { "on" } else { "off" }),
                 notification::NotificationLevel::Info,
@@ -284,6 +470,24 @@ async fn handle_vpn_keys(
         },
         KeyCode::Char('d') => modal.begin_delete(),
         KeyCode::Char('i') => modal.begin_import(),
+        KeyCode::Char('n') => {
+            if let Err(e) = modal.begin_create() {
+                Notification::send(
+                    format!("Could not generate keys: {e}"),
+                    notification::NotificationLevel::Error,
+                    sender,
+                )?;
+            }
+        }
+        KeyCode::Char('e') => {
+            if let Err(e) = modal.begin_export(app.client.as_ref()).await {
+                Notification::send(
+                    format!("Export failed: {e}"),
+                    notification::NotificationLevel::Error,
+                    sender,
+                )?;
+            }
+        }
         _ => {}
     }
 
@@ -391,11 +595,73 @@ pub async fn handle_key_events(
 
     // Doctor modal captures all keys while open. Esc dismisses it and bumps
     // the run id so an in-flight check's result can't resurrect the modal.
+    // Behind a captive portal, `o` opens the login page and `r` re-checks
+    // just the portal; `b` saves a diagnostics bundle of a finished run, and
+    // `f` asks to apply the first fix it offers, then runs the doctor again.
     if app.focused_block == FocusedBlock::Doctor {
-        if key_event.code == KeyCode::Esc {
-            app.doctor_run_id = app.doctor_run_id.wrapping_add(1);
-            app.doctor = None;
-            app.focused_block = FocusedBlock::Device;
+        // A fix changes the network, so it waits for a yes.
+        if let Some(crate::doctor::DoctorModal::ConfirmFix(_, _, fix)) = &app.doctor {
+            let fix = *fix;
+            match key_event.code {
+                KeyCode::Char('y') | KeyCode::Char('Y') => {
+                    start_doctor(app, sender, Some(fix)).await;
+                }
+                KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                    if let Some(crate::doctor::DoctorModal::ConfirmFix(results, changes, _)) =
+                        app.doctor.take()
+                    {
+                        app.doctor = Some(crate::doctor::DoctorModal::Ready(results, changes));
+                    }
+                }
+                _ => {}
+            }
+            return Ok(());
+        }
+
+        let login_url = app
+            .doctor
+            .as_ref()
+            .and_then(|modal| modal.login_url())
+            .map(str::to_string);
+        match key_event.code {
+            KeyCode::Esc => {
+                app.doctor_run_id = app.doctor_run_id.wrapping_add(1);
+                app.doctor = None;
+                app.focused_block = FocusedBlock::Device;
+            }
+            KeyCode::Char('o') if login_url.is_some() => {
+                let url = login_url.unwrap_or_default();
+                // Without a desktop session there is no browser to hand the
+                // page to; show the URL so it can be opened on another device.
+                let opened =
+                    crate::doctor::has_display() && crate::doctor::open_in_browser(&url).is_ok();
+                if !opened {
+                    Notification::send(
+                        format!("Log in at {url}"),
+                        notification::NotificationLevel::Info,
+                        &sender,
+                    )?;
+                }
+            }
+            KeyCode::Char('r') if login_url.is_some() => {
+                recheck_portal(app, sender);
+            }
+            KeyCode::Char('f') => {
+                if let Some(fix) = app.doctor.as_ref().and_then(|modal| modal.fix())
+                    && let Some(crate::doctor::DoctorModal::Ready(results, changes)) =
+                        app.doctor.take()
+                {
+                    app.doctor = Some(crate::doctor::DoctorModal::ConfirmFix(
+                        results, changes, fix,
+                    ));
+                }
+            }
+            KeyCode::Char('b') => {
+                if let Some(crate::doctor::DoctorModal::Ready(results, _)) = &app.doctor {
+                    save_doctor_bundle(app, results.clone(), sender);
+                }
+            }
+            _ => {}
         }
         return Ok(());
     }
@@ -411,7 +677,7 @@ pub async fn handle_key_events(
         && let KeyCode::Char(c) = key_event.code
         && c == config.device.doctor
     {
-        start_doctor(app, sender).await;
+        start_doctor(app, sender, None).await;
         return Ok(());
     }
 
@@ -511,7 +777,7 @@ pub async fn handle_key_events(
                                         Ok(active_path) => {
                                             crate::mode::station::network::watch_activation(
                                                 station_client,
-                                                active_path.to_string(),
+                                                active_path,
                                                 device_path,
                                                 ssid,
                                                 sender_clone,
@@ -666,13 +932,14 @@ pub async fn handle_key_events(
                             match key_event.code {
                                 KeyCode::Enter => {
                                     req.submit(&app.agent).await?;
-                                    app.focused_block = FocusedBlock::KnownNetworks;
+                                    app.auth.request_password = None;
+                                    app.focused_block = app.focus_after_prompt();
                                 }
 
                                 KeyCode::Esc => {
                                     req.cancel(&app.agent).await?;
                                     app.auth.request_password = None;
-                                    app.focused_block = FocusedBlock::KnownNetworks;
+                                    app.focused_block = app.focus_after_prompt();
                                 }
 
                                 KeyCode::Tab => {
@@ -0,0 +1,95 @@
+// The agent iwd asks for a passphrase while `Network.Connect` runs. Unlike
+// NetworkManager, iwd will not take secrets as call arguments. The TUI has
+// already collected the passphrase by then, so the agent answers from what
+// the connect left in `Secrets` and cancels anything it was not given.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use zbus::interface;
+use zbus::zvariant::OwnedObjectPath;
+
+/// Where the agent is served on the client's own connection.
+pub(super) const AGENT_PATH: &str = "/wlctl/iwd/agent";
+
+/// Passphrases for the connects in flight, by network path.
+#[derive(Debug, Clone, Default)]
+pub(super) struct Secrets(Arc<Mutex<HashMap<String, String>>>);
+
+impl Secrets {
+    pub(super) fn insert(&self, network: &str, secret: &str) {
+        self.lock().insert(network.to_string(), secret.to_string());
+    }
+
+    pub(super) fn remove(&self, network: &str) {
+        self.lock().remove(network);
+    }
+
+    fn get(&self, network: &str) -> Option<String> {
+        self.lock().get(network).cloned()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, String>> {
+        self.0.lock().expect("secrets lock poisoned")
+    }
+}
+
+#[derive(Debug, zbus::DBusError)]
+#[zbus(prefix = "net.connman.iwd.Agent.Error")]
+pub(super) enum AgentError {
+    #[zbus(error)]
+    ZBus(zbus::Error),
+    Canceled(String),
+}
+
+fn canceled(what: &str) -> AgentError {
+    AgentError::Canceled(format!("wlctl has no {what} for this network"))
+}
+
+pub(super) struct Agent {
+    secrets: Secrets,
+}
+
+impl Agent {
+    pub(super) fn new(secrets: Secrets) -> Self {
+        Self { secrets }
+    }
+}
+
+#[interface(name = "net.connman.iwd.Agent")]
+impl Agent {
+    fn release(&self) {}
+
+    fn request_passphrase(&self, network: OwnedObjectPath) -> Result<String, AgentError> {
+        self.secrets
+            .get(network.as_str())
+            .ok_or_else(|| canceled("passphrase"))
+    }
+
+    // 802.1X networks are provisioned in iwd's own files, credentials
+    // included, so these are never needed for one wlctl set up.
+
+    fn request_private_key_passphrase(
+        &self,
+        _network: OwnedObjectPath,
+    ) -> Result<String, AgentError> {
+        Err(canceled("private key passphrase"))
+    }
+
+    fn request_user_name_and_password(
+        &self,
+        _network: OwnedObjectPath,
+    ) -> Result<(String, String), AgentError> {
+        Err(canceled("user name and password"))
+    }
+
+    fn request_user_password(
+        &self,
+        _network: OwnedObjectPath,
+        _user: String,
+    ) -> Result<String, AgentError> {
+        Err(canceled("password"))
+    }
+
+    fn cancel(&self, _reason: String) {}
+}
@@ -0,0 +1,262 @@
+//! iwd as a `Backend`. Reads go through `IwdSnapshot`; the few writes iwd
+//! offers (power, scan, connect, forget, autoconnect, access point mode) are
+//! single method calls or property sets.
+
+use std::sync::Arc;
+
+use anyhow::{Context, Result, anyhow, bail};
+use async_trait::async_trait;
+
+use super::{IwdClient, IwdSnapshot, IwdWatcher, interface, storage_name};
+use crate::backend::{Backend, EnterpriseCredentials, SharedSnapshot, Snapshot, Watch};
+use crate::nm::{
+    AccessPointInfo, ActivationFailureReason, ActivationOutcome, ActiveConnectionInfo,
+    ConnectionInfo, DeviceState, LinkKind, OvpnConfig, SecurityType, VpnConnectionInfo, WgConfig,
+    dump,
+};
+
+#[async_trait]
+impl Backend for IwdClient {
+    async fn get_wifi_devices(&self) -> Result<Vec<String>> {
+        Ok(IwdSnapshot::fetch(self).await?.wifi_devices())
+    }
+
+    async fn get_device_interface(&self, device: &str) -> Result<String> {
+        let proxy = self.proxy(device, interface::DEVICE).await?;
+        Ok(proxy.get_property("Name").await?)
+    }
+
+    async fn get_device_hw_address(&self, device: &str) -> Result<String> {
+        let proxy = self.proxy(device, interface::DEVICE).await?;
+        Ok(proxy.get_property("Address").await?)
+    }
+
+    async fn get_device_state(&self, device: &str) -> Result<DeviceState> {
+        IwdSnapshot::fetch(self)
+            .await?
+            .device_state(device)
+            .with_context(|| format!("No iwd device at {device}"))
+    }
+
+    async fn is_wireless_enabled(&self) -> Result<bool> {
+        Ok(IwdSnapshot::fetch(self)
+            .await?
+            .wireless_enabled()
+            .unwrap_or(false))
+    }
+
+    /// iwd powers each device separately; this switches all of them.
+    async fn set_wireless_enabled(&self, enabled: bool) -> Result<()> {
+        for device in Backend::get_wifi_devices(self).await? {
+            let proxy = self.proxy(&device, interface::DEVICE).await?;
+            proxy.set_property("Powered", enabled).await?;
+        }
+        Ok(())
+    }
+
+    async fn snapshot(&self) -> Result<SharedSnapshot> {
+        Ok(Arc::new(IwdSnapshot::fetch(self).await?))
+    }
+
+    async fn watch(&self, notify: Box<dyn Fn() + Send + Sync>) -> Result<Box<dyn Watch>> {
+        Ok(Box::new(IwdWatcher::spawn(self.clone(), notify).await?))
+    }
+
+    async fn request_scan(&self, device: &str) -> Result<()> {
+        let proxy = self.proxy(device, interface::STATION).await?;
+        proxy.call_method("Scan", &()).await?;
+        Ok(())
+    }
+
+    async fn get_active_access_point(&self, device: &str) -> Result<Option<String>> {
+        let snapshot = IwdSnapshot::fetch(self).await?;
+        Ok(snapshot.active_access_point(device).map(|ap| ap.path))
+    }
+
+    async fn get_access_point_info(&self, access_point: &str) -> Result<AccessPointInfo> {
+        IwdSnapshot::fetch(self)
+            .await?
+            .network(access_point)
+            .with_context(|| format!("No iwd network at {access_point}"))
+    }
+
+    /// Known networks are plain properties in iwd, so one read of the object
+    /// graph has them all; the ones in `snapshot` are not reachable through
+    /// the trait object.
+    async fn wifi_connections(&self, _snapshot: &dyn Snapshot) -> Result<Arc<[ConnectionInfo]>> {
+        let graph = IwdSnapshot::fetch_graph(self).await?;
+        Ok(graph.known_networks().into())
+    }
+
+    async fn get_wifi_psk(&self, profile: &str) -> Result<Option<String>> {
+        let proxy = self.proxy(profile, interface::KNOWN_NETWORK).await?;
+        let kind: String = proxy.get_property("Type").await?;
+        if kind != "psk" {
+            return Ok(None);
+        }
+        let ssid: String = proxy.get_property("Name").await?;
+        Ok(self.stored_psk(&ssid).await)
+    }
+
+    /// iwd does not manage VPNs.
+    async fn get_vpn_connections(&self) -> Result<Vec<VpnConnectionInfo>> {
+        Ok(Vec::new())
+    }
+
+    async fn profile_json(&self, profile: &str) -> Result<serde_json::Value> {
+        let graph = IwdSnapshot::fetch_graph(self).await?;
+        let props = graph
+            .interface_props(profile, interface::KNOWN_NETWORK)
+            .with_context(|| format!("No known network at {profile}"))?;
+        Ok(dump::properties_json(props))
+    }
+
+    async fn wireguard_config(&self, _profile: &str) -> Result<WgConfig> {
+        bail!("iwd does not manage VPNs")
+    }
+
+    async fn add_wireguard_connection(
+        &self,
+        _id: &str,
+        _interface: &str,
+        _cfg: &WgConfig,
+    ) -> Result<String> {
+        bail!("iwd does not manage VPNs; importing a WireGuard tunnel needs NetworkManager")
+    }
+
+    async fn add_openvpn_connection(&self, _id: &str, _cfg: &OvpnConfig) -> Result<String> {
+        bail!("iwd does not manage VPNs; importing an OpenVPN profile needs NetworkManager")
+    }
+
+    async fn add_enterprise_connection(
+        &self,
+        ssid: &str,
+        _credentials: EnterpriseCredentials<'_>,
+    ) -> Result<String> {
+        bail!(
+            "iwd reads 802.1X settings from {}/{}.8021x; provision the network there",
+            super::STORAGE_DIR,
+            storage_name(ssid)
+        )
+    }
+
+    async fn set_connection_autoconnect(&self, profile: &str, autoconnect: bool) -> Result<()> {
+        let proxy = self.proxy(profile, interface::KNOWN_NETWORK).await?;
+        proxy.set_property("AutoConnect", autoconnect).await?;
+        Ok(())
+    }
+
+    async fn delete_connection(&self, profile: &str) -> Result<()> {
+        let proxy = self.proxy(profile, interface::KNOWN_NETWORK).await?;
+        proxy.call_method("Forget", &()).await?;
+        Ok(())
+    }
+
+    /// Connects `device` to the in-range network iwd remembers as `profile`.
+    /// Without a device (`/`) the profile would be a VPN, which iwd has none
+    /// of.
+    async fn activate_connection(&self, profile: &str, device: &str) -> Result<String> {
+        if device == "/" {
+            bail!("iwd does not manage VPNs; activating one needs NetworkManager");
+        }
+        let network = IwdSnapshot::fetch(self)
+            .await?
+            .network_for_known(device, profile)
+            .context("The network is not in range")?;
+        self.connect(&network, None).await
+    }
+
+    async fn add_and_activate_connection(
+        &self,
+        _device: &str,
+        access_point: &str,
+        password: Option<&str>,
+    ) -> Result<String> {
+        self.connect(access_point, password).await
+    }
+
+    async fn add_and_activate_hidden_connection(
+        &self,
+        device: &str,
+        ssid: &str,
+        security: SecurityType,
+        password: Option<&str>,
+    ) -> Result<String> {
+        self.connect_hidden(device, ssid, security, password).await
+    }
+
+    /// `Connect` itself blocks until iwd knows the outcome, so this waits on
+    /// that call. Without one in flight it falls back to whether the device
+    /// is now connected to `active`.
+    async fn await_activation(&self, active: &str, device: &str) -> Result<ActivationOutcome> {
+        if let Some(outcome) = self.take_activation(active) {
+            return outcome
+                .await
+                .map_err(|_| anyhow!("The connect call was dropped"));
+        }
+
+        let snapshot = IwdSnapshot::fetch(self).await?;
+        let connected = snapshot
+            .active_access_point(device)
+            .is_some_and(|ap| ap.path == active)
+            && snapshot.device_state(device) == Some(DeviceState::Activated);
+        Ok(if connected {
+            ActivationOutcome::Activated
+        } else {
+            ActivationOutcome::Failed(ActivationFailureReason::Other(0))
+        })
+    }
+
+    async fn disconnect_device(&self, device: &str) -> Result<()> {
+        let proxy = self.proxy(device, interface::STATION).await?;
+        proxy.call_method("Disconnect", &()).await?;
+        Ok(())
+    }
+
+    /// `active` is either a hotspot (named by its device) or a network a
+    /// station is connected to.
+    async fn deactivate_connection(&self, active: &str) -> Result<()> {
+        let snapshot = IwdSnapshot::fetch(self).await?;
+        if snapshot.hotspot(active).is_some() {
+            let proxy = self.proxy(active, interface::ACCESS_POINT).await?;
+            proxy.call_method("Stop", &()).await?;
+            let device = self.proxy(active, interface::DEVICE).await?;
+            device.set_property("Mode", "station").await?;
+            return Ok(());
+        }
+
+        let device = snapshot
+            .network_device(active)
+            .with_context(|| format!("Nothing is active at {active}"))?;
+        self.disconnect_device(&device).await
+    }
+
+    async fn get_active_connections(&self) -> Result<Vec<ActiveConnectionInfo>> {
+        Ok(IwdSnapshot::fetch(self).await?.active_connections())
+    }
+
+    /// iwd only configures addresses when its own DHCP client is enabled, and
+    /// does not report them either way.
+    async fn active_connection_ipv4(&self, _active: &str) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    /// Switches the device into access point mode and starts it. The hotspot
+    /// is named by the device's path.
+    async fn create_hotspot(&self, device: &str, ssid: &str, password: &str) -> Result<String> {
+        let proxy = self.proxy(device, interface::DEVICE).await?;
+        proxy.set_property("Mode", "ap").await?;
+
+        let ap = self.proxy(device, interface::ACCESS_POINT).await?;
+        ap.call_method("Start", &(ssid, password)).await?;
+        Ok(device.to_string())
+    }
+
+    async fn active_hotspot(&self, device: &str) -> Result<Option<ActiveConnectionInfo>> {
+        Ok(IwdSnapshot::fetch(self).await?.hotspot(device))
+    }
+
+    async fn prefer_internet(&self, _kind: LinkKind) -> Result<()> {
+        bail!("iwd does not manage routes; choosing the internet link needs NetworkManager")
+    }
+}
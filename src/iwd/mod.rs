@@ -0,0 +1,420 @@
+//! iwd client, for machines that run iwd on its own rather than underneath
+//! NetworkManager.
+//!
+//! iwd's model is smaller than NetworkManager's: a `Station` on each device
+//! sees `Network`s (one per SSID and security type), and a `KnownNetwork` is
+//! what iwd remembers after a successful connection. Those map onto the same
+//! `AccessPointInfo`, `ConnectionInfo` and `ActiveConnectionInfo` the TUI
+//! already draws; see [`IwdSnapshot`]. What iwd does not do at all (VPNs,
+//! routing, IP configuration unless it runs its own DHCP client) is reported
+//! as unsupported.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, Result};
+use tokio::sync::oneshot;
+use zbus::zvariant::{ObjectPath, OwnedObjectPath, OwnedValue};
+use zbus::{Connection, Proxy};
+
+use crate::nm::{ActivationFailureReason, ActivationOutcome, DeviceState, SecurityType};
+
+mod agent;
+mod backend;
+pub mod snapshot;
+
+pub use snapshot::{IwdSnapshot, IwdWatcher};
+
+pub(crate) const IWD_BUS_NAME: &str = "net.connman.iwd";
+
+/// Where iwd serves its `AgentManager`.
+const AGENT_MANAGER_PATH: &str = "/net/connman/iwd";
+
+/// Where iwd keeps known networks' settings, passphrases included.
+const STORAGE_DIR: &str = "/var/lib/iwd";
+
+/// D-Bus interface names iwd exports.
+pub(crate) mod interface {
+    pub const ACCESS_POINT: &str = "net.connman.iwd.AccessPoint";
+    pub const AGENT_MANAGER: &str = "net.connman.iwd.AgentManager";
+    pub const BASIC_SERVICE_SET: &str = "net.connman.iwd.BasicServiceSet";
+    pub const DEVICE: &str = "net.connman.iwd.Device";
+    pub const KNOWN_NETWORK: &str = "net.connman.iwd.KnownNetwork";
+    pub const NETWORK: &str = "net.connman.iwd.Network";
+    pub const STATION: &str = "net.connman.iwd.Station";
+    pub const STATION_DIAGNOSTIC: &str = "net.connman.iwd.StationDiagnostic";
+}
+
+/// Maps iwd's signal strength (100 * dBm) onto NetworkManager's 0-100 scale:
+/// -100 dBm and weaker read 0, -50 dBm and stronger read 100.
+pub(crate) fn signal_strength(signal: i16) -> u8 {
+    let dbm = i32::from(signal) / 100;
+    (2 * (dbm + 100)).clamp(0, 100) as u8
+}
+
+/// Maps a `Network`/`KnownNetwork` `Type`. iwd folds WPA, WPA2 and WPA3
+/// personal into `psk` and negotiates the best one itself.
+pub(crate) fn security_type(kind: &str) -> SecurityType {
+    match kind {
+        "wep" => SecurityType::WEP,
+        "psk" => SecurityType::WPA2,
+        "8021x" => SecurityType::Enterprise,
+        _ => SecurityType::Open,
+    }
+}
+
+/// The `Type` iwd gives a network of `security`; the inverse of
+/// [`security_type`].
+fn network_type(security: SecurityType) -> &'static str {
+    match security {
+        SecurityType::Open => "open",
+        SecurityType::WEP => "wep",
+        SecurityType::WPA | SecurityType::WPA2 | SecurityType::WPA3 => "psk",
+        SecurityType::Enterprise => "8021x",
+    }
+}
+
+/// Maps a `Station.State` onto the NetworkManager device state the UI keys
+/// off. Roaming keeps the link up, so it reads as connected.
+pub(crate) fn device_state(station_state: &str) -> DeviceState {
+    match station_state {
+        "connected" | "roaming" => DeviceState::Activated,
+        "connecting" => DeviceState::Prepare,
+        "disconnecting" => DeviceState::Deactivating,
+        _ => DeviceState::Disconnected,
+    }
+}
+
+/// Explains a failed `Connect` from the D-Bus error iwd returned. iwd answers
+/// a rejected passphrase with a bare `Failed`, so on a secured network that is
+/// read as bad secrets.
+pub(crate) fn failure_reason(error: &str, secured: bool) -> ActivationFailureReason {
+    match error {
+        "net.connman.iwd.Failed" if secured => ActivationFailureReason::BadSecrets,
+        "net.connman.iwd.Agent.Error.Canceled" => ActivationFailureReason::BadSecrets,
+        "net.connman.iwd.NoAgent" => ActivationFailureReason::NoAgent,
+        "net.connman.iwd.NotFound" => ActivationFailureReason::SsidNotFound,
+        "net.connman.iwd.Timeout" | "org.freedesktop.DBus.Error.NoReply" => {
+            ActivationFailureReason::Timeout
+        }
+        _ => ActivationFailureReason::Other(0),
+    }
+}
+
+/// The name iwd stores a network under in [`STORAGE_DIR`]: the SSID itself
+/// when it is plain alphanumerics, `-`, `_` and spaces, otherwise `=` followed
+/// by the SSID's bytes in hex.
+pub(crate) fn storage_name(ssid: &str) -> String {
+    let plain = ssid
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | ' '));
+    if plain {
+        ssid.to_string()
+    } else {
+        format!("={}", hex::encode(ssid))
+    }
+}
+
+/// The `Passphrase=` entry of a stored `.psk` file.
+fn stored_passphrase(contents: &str) -> Option<String> {
+    contents
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("Passphrase="))
+        .map(str::to_string)
+}
+
+/// Seconds since the epoch of a `KnownNetwork.LastConnectedTime`, `0` when it
+/// is absent or unreadable.
+pub(crate) fn last_connected(time: &str) -> u64 {
+    chrono::DateTime::parse_from_rfc3339(time)
+        .map(|t| t.timestamp().max(0) as u64)
+        .unwrap_or(0)
+}
+
+/// The object path iwd gives a network on `device`. Needed for a hidden
+/// network, which has no `Network` object until iwd has connected to it.
+fn network_path(device: &str, ssid: &str, security: SecurityType) -> String {
+    format!("{device}/{}_{}", hex::encode(ssid), network_type(security))
+}
+
+/// Reads `Connect`'s reply into an activation outcome.
+fn outcome(reply: zbus::Result<zbus::Message>, secured: bool) -> ActivationOutcome {
+    match reply {
+        Ok(_) => ActivationOutcome::Activated,
+        Err(zbus::Error::MethodError(name, _, _)) => {
+            ActivationOutcome::Failed(failure_reason(name.as_str(), secured))
+        }
+        Err(_) => ActivationOutcome::Failed(ActivationFailureReason::Other(0)),
+    }
+}
+
+/// How long a finished connect's outcome waits to be collected.
+const UNCLAIMED_OUTCOME: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// A connect in flight: its number and where its outcome arrives.
+type Activation = (u64, oneshot::Receiver<ActivationOutcome>);
+
+/// Main iwd client
+#[derive(Clone, Debug)]
+pub struct IwdClient {
+    connection: Connection,
+    // Passphrases the agent hands iwd during a connect; see `agent`.
+    secrets: agent::Secrets,
+    // `Connect` blocks until iwd has an answer, so it runs on its own task
+    // and `await_activation` collects the outcome from here. Each entry is
+    // numbered so its task removes its own, and not a newer connect's.
+    activations: Arc<Mutex<HashMap<String, Activation>>>,
+    next_activation: Arc<AtomicU64>,
+}
+
+impl IwdClient {
+    /// Create a new iwd client and register its passphrase agent
+    pub async fn new() -> Result<Self> {
+        let connection = Connection::system()
+            .await
+            .context("Failed to connect to system D-Bus")?;
+
+        let secrets = agent::Secrets::default();
+        connection
+            .object_server()
+            .at(agent::AGENT_PATH, agent::Agent::new(secrets.clone()))
+            .await?;
+
+        // Registering doubles as the check that iwd is there at all.
+        let manager = Proxy::new(
+            &connection,
+            IWD_BUS_NAME,
+            AGENT_MANAGER_PATH,
+            interface::AGENT_MANAGER,
+        )
+        .await?;
+        manager
+            .call_method(
+                "RegisterAgent",
+                &(ObjectPath::try_from(agent::AGENT_PATH)?,),
+            )
+            .await
+            .context(
+                "iwd is not running or not accessible. Please ensure the iwd service is active.",
+            )?;
+
+        Ok(Self {
+            connection,
+            secrets,
+            activations: Arc::new(Mutex::new(HashMap::new())),
+            next_activation: Arc::new(AtomicU64::new(0)),
+        })
+    }
+
+    /// Get the D-Bus connection
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    async fn proxy<'a>(&self, path: &'a str, interface: &'a str) -> Result<Proxy<'a>> {
+        Ok(Proxy::new(&self.connection, IWD_BUS_NAME, path, interface).await?)
+    }
+
+    /// Networks a station can see, strongest first, with their signal in
+    /// 100 * dBm.
+    pub async fn ordered_networks(&self, device: &str) -> Result<Vec<(String, i16)>> {
+        let proxy = self.proxy(device, interface::STATION).await?;
+        let networks: Vec<(OwnedObjectPath, i16)> = proxy.call("GetOrderedNetworks", &()).await?;
+        Ok(networks
+            .into_iter()
+            .map(|(path, signal)| (path.to_string(), signal))
+            .collect())
+    }
+
+    /// Details of a connected station's link: `Frequency`, `RxBitrate`,
+    /// `TxBitrate` (in 100 kbit/s), `RSSI` and more.
+    pub async fn diagnostics(&self, device: &str) -> Result<HashMap<String, OwnedValue>> {
+        let proxy = self.proxy(device, interface::STATION_DIAGNOSTIC).await?;
+        Ok(proxy.call("GetDiagnostics", &()).await?)
+    }
+
+    /// Starts `Connect` on `network` and returns straight away; the outcome is
+    /// collected by `await_activation` under `network`.
+    async fn connect(&self, network: &str, secret: Option<&str>) -> Result<String> {
+        let proxy = Proxy::new(
+            &self.connection,
+            IWD_BUS_NAME,
+            network.to_string(),
+            interface::NETWORK,
+        )
+        .await?;
+        let kind: String = proxy.get_property("Type").await?;
+
+        self.start(network, secret, security_type(&kind), async move {
+            proxy.call_method("Connect", &()).await
+        })
+    }
+
+    /// Starts `Station.ConnectHiddenNetwork`; the outcome is collected under
+    /// the path the network will have once iwd finds it.
+    async fn connect_hidden(
+        &self,
+        device: &str,
+        ssid: &str,
+        security: SecurityType,
+        secret: Option<&str>,
+    ) -> Result<String> {
+        let proxy = Proxy::new(
+            &self.connection,
+            IWD_BUS_NAME,
+            device.to_string(),
+            interface::STATION,
+        )
+        .await?;
+        let network = network_path(device, ssid, security);
+        let ssid = ssid.to_string();
+
+        self.start(&network, secret, security, async move {
+            proxy.call_method("ConnectHiddenNetwork", &(ssid,)).await
+        })
+    }
+
+    fn start<F>(
+        &self,
+        network: &str,
+        secret: Option<&str>,
+        security: SecurityType,
+        call: F,
+    ) -> Result<String>
+    where
+        F: Future<Output = zbus::Result<zbus::Message>> + Send + 'static,
+    {
+        if let Some(secret) = secret {
+            self.secrets.insert(network, secret);
+        }
+
+        let (tx, rx) = oneshot::channel();
+        let number = self.next_activation.fetch_add(1, Ordering::Relaxed);
+        self.activations
+            .lock()
+            .expect("activations lock poisoned")
+            .insert(network.to_string(), (number, rx));
+
+        let secrets = self.secrets.clone();
+        let activations = self.activations.clone();
+        let path = network.to_string();
+        let secured = security.requires_password();
+        tokio::spawn(async move {
+            let reply = call.await;
+            secrets.remove(&path);
+            let _ = tx.send(outcome(reply, secured));
+            // A caller that connected and went straight to waiting has taken
+            // the outcome well before this; an entry still here by then has
+            // no one waiting, and would otherwise stay forever.
+            tokio::time::sleep(UNCLAIMED_OUTCOME).await;
+            let mut pending = activations.lock().expect("activations lock poisoned");
+            if pending.get(&path).is_some_and(|(n, _)| *n == number) {
+                pending.remove(&path);
+            }
+        });
+
+        Ok(network.to_string())
+    }
+
+    /// Takes the pending outcome of a connect started on `network`.
+    fn take_activation(&self, network: &str) -> Option<oneshot::Receiver<ActivationOutcome>> {
+        self.activations
+            .lock()
+            .expect("activations lock poisoned")
+            .remove(network)
+            .map(|(_, rx)| rx)
+    }
+
+    /// The stored passphrase of a known PSK network. iwd keeps it in a file
+    /// only root can read, so without that access there is none to give.
+    async fn stored_psk(&self, ssid: &str) -> Option<String> {
+        let file = format!("{STORAGE_DIR}/{}.psk", storage_name(ssid));
+        let contents = tokio::fs::read_to_string(file).await.ok()?;
+        stored_passphrase(&contents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signal_strength_spans_minus_100_to_minus_50_dbm() {
+        assert_eq!(signal_strength(-10000), 0);
+        assert_eq!(signal_strength(-12000), 0);
+        assert_eq!(signal_strength(-7500), 50);
+        assert_eq!(signal_strength(-5000), 100);
+        assert_eq!(signal_strength(-3000), 100);
+    }
+
+    #[test]
+    fn security_types_round_trip() {
+        for kind in ["open", "wep", "psk", "8021x"] {
+            assert_eq!(network_type(security_type(kind)), kind);
+        }
+        assert_eq!(network_type(SecurityType::WPA3), "psk");
+    }
+
+    #[test]
+    fn station_states_map_onto_device_states() {
+        assert_eq!(device_state("connected"), DeviceState::Activated);
+        assert_eq!(device_state("roaming"), DeviceState::Activated);
+        assert_eq!(device_state("connecting"), DeviceState::Prepare);
+        assert_eq!(device_state("disconnecting"), DeviceState::Deactivating);
+        assert_eq!(device_state("disconnected"), DeviceState::Disconnected);
+    }
+
+    /// `Failed` only means a wrong passphrase when there was one to get wrong.
+    #[test]
+    fn failed_connect_is_bad_secrets_only_on_a_secured_network() {
+        assert_eq!(
+            failure_reason("net.connman.iwd.Failed", true),
+            ActivationFailureReason::BadSecrets
+        );
+        assert_eq!(
+            failure_reason("net.connman.iwd.Failed", false),
+            ActivationFailureReason::Other(0)
+        );
+        assert_eq!(
+            failure_reason("net.connman.iwd.Agent.Error.Canceled", false),
+            ActivationFailureReason::BadSecrets
+        );
+        assert_eq!(
+            failure_reason("net.connman.iwd.NoAgent", true),
+            ActivationFailureReason::NoAgent
+        );
+        assert_eq!(
+            failure_reason("net.connman.iwd.NotFound", true),
+            ActivationFailureReason::SsidNotFound
+        );
+    }
+
+    #[test]
+    fn storage_name_hex_encodes_unusual_ssids() {
+        assert_eq!(storage_name("Home Net_5G-2"), "Home Net_5G-2");
+        assert_eq!(storage_name("café"), "=636166c3a9");
+        assert_eq!(storage_name("a.b"), "=612e62");
+    }
+
+    #[test]
+    fn stored_passphrase_reads_the_security_section() {
+        let file = "[Security]\nPreSharedKey=abcdef\nPassphrase=hunter22\n\n[Settings]\nAutoConnect=true\n";
+        assert_eq!(stored_passphrase(file).as_deref(), Some("hunter22"));
+        assert_eq!(stored_passphrase("[Security]\nPreSharedKey=abcdef\n"), None);
+    }
+
+    #[test]
+    fn last_connected_parses_iso_8601() {
+        assert_eq!(last_connected("2026-01-02T03:04:05Z"), 1_767_323_045);
+        assert_eq!(last_connected(""), 0);
+    }
+
+    #[test]
+    fn hidden_network_path_matches_iwd_naming() {
+        assert_eq!(
+            network_path("/net/connman/iwd/0/4", "home", SecurityType::WPA2),
+            "/net/connman/iwd/0/4/686f6d65_psk"
+        );
+    }
+}
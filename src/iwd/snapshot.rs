@@ -0,0 +1,628 @@
+// Single-read view of iwd's exported object graph.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::StreamExt;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use zbus::message::Type as MessageType;
+use zbus::zvariant::{OwnedObjectPath, OwnedValue};
+use zbus::{MatchRule, MessageStream, Proxy};
+
+use crate::backend::{SharedSnapshot, Snapshot, Watch};
+use crate::nm::snapshot::{ManagedObjects, prop};
+use crate::nm::{
+    AccessPointInfo, ActiveConnectionInfo, ActiveConnectionState, ConnectionInfo, DeviceState,
+    WifiMode, dump,
+};
+
+use super::{
+    IWD_BUS_NAME, IwdClient, device_state, interface, last_connected, security_type,
+    signal_strength,
+};
+
+/// iwd exports its ObjectManager at the root path.
+const OBJECT_MANAGER_PATH: &str = "/";
+
+/// The `connection_type` a WiFi link gets, as NetworkManager names it.
+const WIFI_CONNECTION_TYPE: &str = "802-11-wireless";
+
+/// A point-in-time view of iwd's objects, plus what iwd only answers through
+/// method calls: each station's signal readings and the connected link's
+/// diagnostics.
+///
+/// Mapping onto the NetworkManager-shaped types: a `Network` is an
+/// `AccessPointInfo` (iwd groups BSSes by SSID itself), a `KnownNetwork` is a
+/// saved `ConnectionInfo`, and a station's connected network, or a started
+/// access point, is an `ActiveConnectionInfo` named by the network's (or the
+/// device's) path.
+pub struct IwdSnapshot {
+    objects: ManagedObjects,
+    /// Per station, `GetOrderedNetworks`: network paths strongest first, with
+    /// their signal in 100 * dBm.
+    ordered: HashMap<String, Vec<(String, i16)>>,
+    /// Per connected station, `StationDiagnostic.GetDiagnostics`.
+    diagnostics: HashMap<String, HashMap<String, OwnedValue>>,
+}
+
+impl IwdSnapshot {
+    pub async fn fetch(client: &IwdClient) -> Result<Self> {
+        let objects = fetch_objects(client).await?;
+
+        let mut ordered = HashMap::new();
+        let mut diagnostics = HashMap::new();
+        for (path, interfaces) in &objects {
+            let Some(station) = interfaces.get(interface::STATION) else {
+                continue;
+            };
+            // A station can go away between the read and these calls; it
+            // then reads as seeing nothing rather than failing the refresh.
+            if let Ok(networks) = client.ordered_networks(path).await {
+                ordered.insert(path.to_string(), networks);
+            }
+            if prop::<String>(station, "State").as_deref() == Some("connected")
+                && interfaces.contains_key(interface::STATION_DIAGNOSTIC)
+                && let Ok(diagnostic) = client.diagnostics(path).await
+            {
+                diagnostics.insert(path.to_string(), diagnostic);
+            }
+        }
+
+        Ok(Self {
+            objects,
+            ordered,
+            diagnostics,
+        })
+    }
+
+    /// The object graph alone, without asking each station for its signal
+    /// readings: enough for anything but the scan list.
+    pub async fn fetch_graph(client: &IwdClient) -> Result<Self> {
+        Ok(Self {
+            objects: fetch_objects(client).await?,
+            ordered: HashMap::new(),
+            diagnostics: HashMap::new(),
+        })
+    }
+
+    /// Every known network as a saved profile, most recently used first.
+    pub fn known_networks(&self) -> Vec<ConnectionInfo> {
+        let mut known: Vec<ConnectionInfo> = self
+            .objects
+            .iter()
+            .filter_map(|(path, interfaces)| {
+                let props = interfaces.get(interface::KNOWN_NETWORK)?;
+                Some(known_network_info(path.as_str(), props))
+            })
+            .collect();
+
+        known.sort_by(|a, b| b.timestamp.cmp(&a.timestamp).then(a.id.cmp(&b.id)));
+        known
+    }
+
+    /// A network by path, with its signal as `device`'s station last read it.
+    pub fn network(&self, path: &str) -> Option<AccessPointInfo> {
+        let props = self.interface_props(path, interface::NETWORK)?;
+        let device = prop::<OwnedObjectPath>(props, "Device").map(|d| d.to_string());
+
+        let signal = device
+            .as_ref()
+            .and_then(|device| self.ordered.get(device))
+            .and_then(|networks| networks.iter().find(|(p, _)| p == path))
+            .map(|(_, signal)| *signal);
+
+        // iwd only reports a frequency for the link in use.
+        let connected = prop::<bool>(props, "Connected").unwrap_or(false);
+        let frequency = match device {
+            Some(device) if connected => self
+                .diagnostics
+                .get(&device)
+                .and_then(|d| prop::<u32>(d, "Frequency")),
+            _ => None,
+        };
+
+        let services: Vec<OwnedObjectPath> = prop(props, "ExtendedServiceSet").unwrap_or_default();
+        let hw_address = services
+            .first()
+            .and_then(|bss| self.interface_props(bss, interface::BASIC_SERVICE_SET))
+            .and_then(|bss| prop(bss, "Address"))
+            .unwrap_or_default();
+
+        Some(AccessPointInfo {
+            path: path.to_string(),
+            ssid: prop(props, "Name").unwrap_or_default(),
+            strength: signal.map(signal_strength).unwrap_or(0),
+            frequency: frequency.unwrap_or(0),
+            hw_address,
+            security: security_type(&prop::<String>(props, "Type").unwrap_or_default()),
+            mode: WifiMode::Infrastructure,
+        })
+    }
+
+    /// The in-range network `device` would use for `known`, a `KnownNetwork`
+    /// path.
+    pub fn network_for_known(&self, device: &str, known: &str) -> Option<String> {
+        self.networks_on(device).into_iter().find(|network| {
+            self.interface_props(network, interface::NETWORK)
+                .and_then(|props| prop::<OwnedObjectPath>(props, "KnownNetwork"))
+                .is_some_and(|path| path.as_str() == known)
+        })
+    }
+
+    /// The hotspot `device` is running, if it is in access point mode and
+    /// started. It is named by the device's path.
+    pub fn hotspot(&self, device: &str) -> Option<ActiveConnectionInfo> {
+        let ap = self.interface_props(device, interface::ACCESS_POINT)?;
+        if !prop::<bool>(ap, "Started").unwrap_or(false) {
+            return None;
+        }
+
+        Some(ActiveConnectionInfo {
+            path: device.to_string(),
+            id: prop(ap, "Name").unwrap_or_default(),
+            uuid: String::new(),
+            connection_type: WIFI_CONNECTION_TYPE.to_string(),
+            state: ActiveConnectionState::Activated,
+            connection_path: device.to_string(),
+            devices: vec![device.to_string()],
+        })
+    }
+
+    /// The device a network belongs to.
+    pub fn network_device(&self, network: &str) -> Option<String> {
+        let props = self.interface_props(network, interface::NETWORK)?;
+        prop::<OwnedObjectPath>(props, "Device").map(|path| path.to_string())
+    }
+
+    /// Network paths `device` can see, strongest first.
+    fn networks_on(&self, device: &str) -> Vec<String> {
+        self.ordered
+            .get(device)
+            .map(|networks| networks.iter().map(|(path, _)| path.clone()).collect())
+            .unwrap_or_default()
+    }
+
+    /// The network `device`'s station is connected (or connecting) to.
+    fn connected_network(&self, device: &str) -> Option<String> {
+        let station = self.interface_props(device, interface::STATION)?;
+        let path: OwnedObjectPath = prop(station, "ConnectedNetwork")?;
+        (path.as_str() != "/").then(|| path.to_string())
+    }
+
+    /// The station's connection as an active connection.
+    fn station_connection(&self, device: &str) -> Option<ActiveConnectionInfo> {
+        let station = self.interface_props(device, interface::STATION)?;
+        let state = match prop::<String>(station, "State")?.as_str() {
+            "connected" | "roaming" => ActiveConnectionState::Activated,
+            "connecting" => ActiveConnectionState::Activating,
+            "disconnecting" => ActiveConnectionState::Deactivating,
+            _ => return None,
+        };
+        let network = self.connected_network(device)?;
+        let props = self.interface_props(&network, interface::NETWORK)?;
+        let known = prop::<OwnedObjectPath>(props, "KnownNetwork")
+            .filter(|path| path.as_str() != "/")
+            .map(|path| path.to_string());
+
+        Some(ActiveConnectionInfo {
+            path: network.clone(),
+            id: prop(props, "Name").unwrap_or_default(),
+            uuid: known.clone().unwrap_or_default(),
+            connection_type: WIFI_CONNECTION_TYPE.to_string(),
+            state,
+            connection_path: known.unwrap_or(network),
+            devices: vec![device.to_string()],
+        })
+    }
+
+    pub(super) fn interface_props(
+        &self,
+        path: &str,
+        interface: &str,
+    ) -> Option<&HashMap<String, OwnedValue>> {
+        let object_path = OwnedObjectPath::try_from(path.to_string()).ok()?;
+        self.objects.get(&object_path)?.get(interface)
+    }
+}
+
+impl Snapshot for IwdSnapshot {
+    /// Every exported object as JSON (path -> interface -> property), for
+    /// diagnostics bundles.
+    fn to_json(&self) -> serde_json::Value {
+        let objects: std::collections::BTreeMap<&str, serde_json::Value> = self
+            .objects
+            .iter()
+            .map(|(path, interfaces)| {
+                let interfaces: std::collections::BTreeMap<&String, serde_json::Value> = interfaces
+                    .iter()
+                    .map(|(name, props)| (name, dump::properties_json(props)))
+                    .collect();
+                (path.as_str(), serde_json::json!(interfaces))
+            })
+            .collect();
+        serde_json::json!(objects)
+    }
+
+    /// Whether any device's radio is powered.
+    fn wireless_enabled(&self) -> Option<bool> {
+        let powered: Vec<bool> = self
+            .wifi_devices()
+            .iter()
+            .filter_map(|device| prop(self.interface_props(device, interface::DEVICE)?, "Powered"))
+            .collect();
+        (!powered.is_empty()).then(|| powered.contains(&true))
+    }
+
+    /// iwd's devices, sorted by path so the adapter table keeps its rows.
+    fn wifi_devices(&self) -> Vec<String> {
+        let mut devices: Vec<String> = self
+            .objects
+            .iter()
+            .filter(|(_, interfaces)| interfaces.contains_key(interface::DEVICE))
+            .map(|(path, _)| path.to_string())
+            .collect();
+        devices.sort();
+        devices
+    }
+
+    fn device_interface(&self, device: &str) -> Option<String> {
+        prop(self.interface_props(device, interface::DEVICE)?, "Name")
+    }
+
+    /// A powered-off device is unavailable; one in access point mode has no
+    /// station, and reads as connected while its hotspot runs.
+    fn device_state(&self, device: &str) -> Option<DeviceState> {
+        let props = self.interface_props(device, interface::DEVICE)?;
+        if !prop::<bool>(props, "Powered").unwrap_or(false) {
+            return Some(DeviceState::Unavailable);
+        }
+        if let Some(station) = self.interface_props(device, interface::STATION) {
+            return Some(device_state(
+                &prop::<String>(station, "State").unwrap_or_default(),
+            ));
+        }
+        Some(match self.hotspot(device) {
+            Some(_) => DeviceState::Activated,
+            None => DeviceState::Disconnected,
+        })
+    }
+
+    /// One entry per network (SSID and security type), strongest first. iwd
+    /// does not list hidden networks here.
+    fn access_points(&self, device: &str) -> Vec<AccessPointInfo> {
+        self.networks_on(device)
+            .iter()
+            .filter_map(|path| self.network(path))
+            .collect()
+    }
+
+    /// The transmit rate of the current link in kbit/s.
+    fn bitrate(&self, device: &str) -> Option<u32> {
+        let diagnostics = self.diagnostics.get(device)?;
+        // iwd reports bitrates in units of 100 kbit/s.
+        prop::<u32>(diagnostics, "TxBitrate")
+            .map(|rate| rate * 100)
+            .filter(|&rate| rate > 0)
+    }
+
+    fn active_access_point(&self, device: &str) -> Option<AccessPointInfo> {
+        self.network(&self.connected_network(device)?)
+    }
+
+    fn active_connections(&self) -> Vec<ActiveConnectionInfo> {
+        self.wifi_devices()
+            .iter()
+            .flat_map(|device| [self.station_connection(device), self.hotspot(device)])
+            .flatten()
+            .collect()
+    }
+
+    /// iwd does not manage routes, so it cannot say which link carries
+    /// internet traffic.
+    fn primary_connection(&self) -> Option<ActiveConnectionInfo> {
+        None
+    }
+
+    /// Known networks have no revision; the list only changes when one is
+    /// added or forgotten.
+    fn profile_versions(&self) -> Vec<(String, Option<u64>)> {
+        let mut versions: Vec<(String, Option<u64>)> = self
+            .objects
+            .iter()
+            .filter(|(_, interfaces)| interfaces.contains_key(interface::KNOWN_NETWORK))
+            .map(|(path, _)| (path.to_string(), None))
+            .collect();
+        versions.sort();
+        versions
+    }
+}
+
+/// A `KnownNetwork` as a saved profile. iwd has no separate profile id or
+/// UUID, so both are the SSID and the path.
+fn known_network_info(path: &str, props: &HashMap<String, OwnedValue>) -> ConnectionInfo {
+    let name: String = prop(props, "Name").unwrap_or_default();
+    ConnectionInfo {
+        path: path.to_string(),
+        id: name.clone(),
+        uuid: path.to_string(),
+        ssid: name,
+        autoconnect: prop(props, "AutoConnect").unwrap_or(true),
+        timestamp: prop::<String>(props, "LastConnectedTime")
+            .map(|time| last_connected(&time))
+            .unwrap_or(0),
+        hidden: prop(props, "Hidden").unwrap_or(false),
+        security: security_type(&prop::<String>(props, "Type").unwrap_or_default()),
+    }
+}
+
+async fn fetch_objects(client: &IwdClient) -> Result<ManagedObjects> {
+    let proxy = Proxy::new(
+        client.connection(),
+        IWD_BUS_NAME,
+        OBJECT_MANAGER_PATH,
+        "org.freedesktop.DBus.ObjectManager",
+    )
+    .await?;
+    Ok(proxy.call("GetManagedObjects", &()).await?)
+}
+
+/// A snapshot refreshed when iwd announces a change.
+///
+/// Unlike `NmWatcher` this does not patch the graph from the signals: signal
+/// strengths and link diagnostics are not properties and never arrive in one,
+/// and iwd's graph is small enough to re-read whole. A signal only marks the
+/// snapshot stale, and the next [`Watch::snapshot`] reads it again; an idle
+/// machine still generates no traffic.
+pub struct IwdWatcher {
+    client: IwdClient,
+    current: Mutex<Arc<IwdSnapshot>>,
+    stale: Arc<AtomicBool>,
+    pending: Arc<AtomicBool>,
+    task: JoinHandle<()>,
+}
+
+impl IwdWatcher {
+    pub async fn spawn<F>(client: IwdClient, notify: F) -> Result<Self>
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        let rule = MatchRule::builder()
+            .msg_type(MessageType::Signal)
+            .sender(IWD_BUS_NAME)?
+            .build();
+        let mut signals = MessageStream::for_match_rule(rule, client.connection(), None).await?;
+
+        // Subscribe before the initial read so a change racing it still marks
+        // the snapshot stale.
+        let current = Mutex::new(Arc::new(IwdSnapshot::fetch(&client).await?));
+        let stale = Arc::new(AtomicBool::new(false));
+        let pending = Arc::new(AtomicBool::new(false));
+
+        let task = tokio::spawn({
+            let stale = Arc::clone(&stale);
+            let pending = Arc::clone(&pending);
+            async move {
+                while let Some(msg) = signals.next().await {
+                    if msg.is_err() {
+                        continue;
+                    }
+                    stale.store(true, Ordering::Release);
+                    if !pending.swap(true, Ordering::AcqRel) {
+                        notify();
+                    }
+                }
+            }
+        });
+
+        Ok(Self {
+            client,
+            current,
+            stale,
+            pending,
+            task,
+        })
+    }
+}
+
+#[async_trait]
+impl Watch for IwdWatcher {
+    /// Re-reads iwd if anything changed since the last call. A failed read
+    /// keeps serving the previous snapshot and tries again next time.
+    async fn snapshot(&self) -> SharedSnapshot {
+        self.pending.store(false, Ordering::Release);
+
+        let mut current = self.current.lock().await;
+        if self.stale.swap(false, Ordering::AcqRel) {
+            match IwdSnapshot::fetch(&self.client).await {
+                Ok(fresh) => *current = Arc::new(fresh),
+                Err(_) => self.stale.store(true, Ordering::Release),
+            }
+        }
+        current.clone()
+    }
+}
+
+impl Drop for IwdWatcher {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zbus::zvariant::Value;
+
+    const DEVICE: &str = "/net/connman/iwd/0/4";
+    const HOME: &str = "/net/connman/iwd/0/4/686f6d65_psk";
+    const CAFE: &str = "/net/connman/iwd/0/4/63616665_open";
+    const HOME_KNOWN: &str = "/net/connman/iwd/686f6d65_psk";
+
+    fn value<'a>(v: impl Into<Value<'a>>) -> OwnedValue {
+        OwnedValue::try_from(v.into()).expect("value is convertible")
+    }
+
+    fn object_path(path: &str) -> OwnedObjectPath {
+        OwnedObjectPath::try_from(path.to_string()).expect("valid object path")
+    }
+
+    fn props(entries: Vec<(&str, OwnedValue)>) -> HashMap<String, OwnedValue> {
+        entries
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect()
+    }
+
+    /// A powered station connected to `home` (known), also seeing `cafe`.
+    fn connected() -> IwdSnapshot {
+        let objects: Vec<(&str, &str, HashMap<String, OwnedValue>)> = vec![
+            (
+                DEVICE,
+                interface::DEVICE,
+                props(vec![
+                    ("Name", value("wlan0")),
+                    ("Powered", value(true)),
+                    ("Mode", value("station")),
+                ]),
+            ),
+            (
+                DEVICE,
+                interface::STATION,
+                props(vec![
+                    ("State", value("connected")),
+                    ("ConnectedNetwork", value(object_path(HOME))),
+                ]),
+            ),
+            (
+                HOME,
+                interface::NETWORK,
+                props(vec![
+                    ("Name", value("home")),
+                    ("Type", value("psk")),
+                    ("Connected", value(true)),
+                    ("Device", value(object_path(DEVICE))),
+                    ("KnownNetwork", value(object_path(HOME_KNOWN))),
+                ]),
+            ),
+            (
+                CAFE,
+                interface::NETWORK,
+                props(vec![
+                    ("Name", value("cafe")),
+                    ("Type", value("open")),
+                    ("Connected", value(false)),
+                    ("Device", value(object_path(DEVICE))),
+                ]),
+            ),
+            (
+                HOME_KNOWN,
+                interface::KNOWN_NETWORK,
+                props(vec![
+                    ("Name", value("home")),
+                    ("Type", value("psk")),
+                    ("AutoConnect", value(false)),
+                    ("LastConnectedTime", value("2026-01-02T03:04:05Z")),
+                ]),
+            ),
+        ];
+
+        let mut map: ManagedObjects = HashMap::new();
+        for (path, interface, props) in objects {
+            map.entry(object_path(path))
+                .or_default()
+                .insert(interface.to_string(), props);
+        }
+
+        IwdSnapshot {
+            objects: map,
+            ordered: HashMap::from([(
+                DEVICE.to_string(),
+                vec![(HOME.to_string(), -5500), (CAFE.to_string(), -8000)],
+            )]),
+            diagnostics: HashMap::from([(
+                DEVICE.to_string(),
+                props(vec![
+                    ("Frequency", value(5180u32)),
+                    ("TxBitrate", value(8667u32)),
+                ]),
+            )]),
+        }
+    }
+
+    #[test]
+    fn networks_read_as_access_points_in_signal_order() {
+        let snapshot = connected();
+        let networks = snapshot.access_points(DEVICE);
+
+        let names: Vec<&str> = networks.iter().map(|n| n.ssid.as_str()).collect();
+        assert_eq!(names, ["home", "cafe"]);
+        assert_eq!(networks[0].strength, 90);
+        assert_eq!(networks[0].security, crate::nm::SecurityType::WPA2);
+        assert_eq!(networks[1].strength, 40);
+        assert_eq!(networks[1].security, crate::nm::SecurityType::Open);
+    }
+
+    /// Only the connected network has a frequency; the others must not
+    /// borrow it.
+    #[test]
+    fn only_the_connected_network_has_link_details() {
+        let snapshot = connected();
+
+        let active = snapshot.active_access_point(DEVICE).expect("connected");
+        assert_eq!(active.ssid, "home");
+        assert_eq!(active.frequency, 5180);
+        assert_eq!(snapshot.bitrate(DEVICE), Some(866_700));
+        assert_eq!(snapshot.network(CAFE).expect("visible").frequency, 0);
+    }
+
+    #[test]
+    fn station_connection_reads_as_an_active_wifi_connection() {
+        let snapshot = connected();
+
+        assert_eq!(snapshot.device_state(DEVICE), Some(DeviceState::Activated));
+        let actives = snapshot.active_connections();
+        assert_eq!(actives.len(), 1);
+        assert_eq!(actives[0].path, HOME);
+        assert_eq!(actives[0].id, "home");
+        assert_eq!(actives[0].connection_path, HOME_KNOWN);
+        assert_eq!(actives[0].state, ActiveConnectionState::Activated);
+        assert_eq!(actives[0].devices, [DEVICE]);
+    }
+
+    #[test]
+    fn known_networks_read_as_saved_profiles() {
+        let snapshot = connected();
+
+        let known = snapshot.known_networks();
+        assert_eq!(known.len(), 1);
+        assert_eq!(known[0].path, HOME_KNOWN);
+        assert_eq!(known[0].ssid, "home");
+        assert!(!known[0].autoconnect);
+        assert_eq!(known[0].timestamp, 1_767_323_045);
+        assert_eq!(
+            snapshot.network_for_known(DEVICE, HOME_KNOWN).as_deref(),
+            Some(HOME)
+        );
+    }
+
+    #[test]
+    fn powered_off_device_is_unavailable() {
+        let mut snapshot = connected();
+        snapshot
+            .objects
+            .get_mut(&object_path(DEVICE))
+            .and_then(|object| object.get_mut(interface::DEVICE))
+            .expect("device")
+            .insert("Powered".to_string(), value(false));
+
+        assert_eq!(snapshot.wireless_enabled(), Some(false));
+        assert_eq!(
+            snapshot.device_state(DEVICE),
+            Some(DeviceState::Unavailable)
+        );
+    }
+}
@@ -18,6 +18,8 @@ pub mod adapter;
 
 pub mod cli;
 
+pub mod commands;
+
 pub mod rfkill;
 
 pub mod mode;
@@ -28,10 +30,16 @@ pub mod agent;
 
 pub mod nm;
 
+pub mod iwd;
+
+pub mod backend;
+
 pub mod doctor;
 
 pub mod vpn;
 
+pub mod replay;
+
 pub fn nm_network_name(name: &str) -> String {
     // NetworkManager handles SSID encoding internally, so we just return as-is
     name.to_string()
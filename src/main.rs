@@ -2,18 +2,22 @@ use anyhow::Result;
 use anyhow::anyhow;
 use env_logger::Target;
 use ratatui::{Terminal, backend::CrosstermBackend};
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 use std::{io, process::exit};
 use tokio::time::{Instant, MissedTickBehavior, interval_at};
 use wlctl::{
     app::App,
-    cli,
+    backend::{Backend, BackendKind},
+    cli, commands,
     config::Config,
     doctor,
     event::{Event, EventHandler},
     handler::{handle_key_events, handle_paste, toggle_connect},
     nm::Mode,
     notification::{Notification, NotificationLevel},
+    replay::{self, Recording, ReplayBackend},
     rfkill,
     tui::Tui,
 };
@@ -27,13 +31,63 @@ async fn main() -> Result<()> {
 
     let args = cli::cli().get_matches();
 
-    if let Some(("doctor", _)) = args.subcommand() {
-        return doctor::run().await;
+    let mut config = Config::new()?;
+    if let Some(backend) = args.get_one::<String>("backend") {
+        config.backend = backend.clone();
+    }
+    let backend_kind = BackendKind::try_from(config.backend.as_str())?;
+
+    match args.subcommand() {
+        Some(("doctor", sub)) => {
+            let format = sub
+                .get_one::<String>("format")
+                .map(|f| doctor::Format::try_from(f.as_str()))
+                .transpose()?
+                .unwrap_or_default();
+            let ids = |name: &str| -> Vec<String> {
+                sub.get_many::<String>(name)
+                    .map(|v| v.cloned().collect())
+                    .unwrap_or_default()
+            };
+            let options = doctor::Options {
+                interface: sub.get_one::<String>("interface").cloned(),
+                only: ids("only"),
+                skip: ids("skip"),
+                watch: sub.get_one::<u64>("watch").map(|s| Duration::from_secs(*s)),
+                format,
+                bundle: sub.get_one::<std::path::PathBuf>("bundle").cloned(),
+                fix: sub.get_flag("fix"),
+                history: sub.get_flag("history"),
+                backend: backend_kind,
+            };
+            exit(doctor::run(options, config).await?);
+        }
+        Some(("connect", sub)) => {
+            let ssid = sub.get_one::<String>("ssid").expect("ssid is required");
+            exit(commands::connect(backend_kind, ssid, sub.get_flag("password-stdin")).await?);
+        }
+        Some(("record", sub)) => {
+            let path = sub.get_one::<PathBuf>("file").expect("file is required");
+            let duration = sub
+                .get_one::<u64>("duration")
+                .map(|s| Duration::from_secs(*s));
+            exit(replay::run(path, duration, backend_kind).await?);
+        }
+        Some(("disconnect", _)) => exit(commands::disconnect(backend_kind).await?),
+        Some(("list", _)) => exit(commands::list(backend_kind).await?),
+        _ => {}
     }
 
-    rfkill::check()?;
+    // A replay stands in for the network service, hardware included.
+    let replay: Option<Arc<dyn Backend>> = match args.get_one::<PathBuf>("replay") {
+        Some(path) => Some(Arc::new(ReplayBackend::new(Recording::load(path)?))),
+        None => {
+            rfkill::check()?;
+            None
+        }
+    };
 
-    let config = Arc::new(Config::new());
+    let config = Arc::new(config);
 
     let backend = CrosstermBackend::new(io::stdout());
     let terminal = Terminal::new(backend)?;
@@ -46,7 +100,19 @@ async fn main() -> Result<()> {
 
     let mode = Mode::try_from(mode.as_str())?;
 
-    let mut app = match App::new(tui.events.sender.clone(), config.clone(), mode).await {
+    let app = match &replay {
+        Some(backend) => {
+            App::with_backend(
+                tui.events.sender.clone(),
+                backend.clone(),
+                config.clone(),
+                mode,
+            )
+            .await
+        }
+        None => App::new(tui.events.sender.clone(), config.clone(), mode).await,
+    };
+    let mut app = match app {
         Ok(app) => app,
         Err(e) => {
             tui.exit()?;
@@ -71,10 +137,11 @@ async fn main() -> Result<()> {
     // to the VPN import field so pastes elsewhere keep their default behavior.
     let mut bracketed_paste = false;
 
-    // Driving the refresh timer from this loop — rather than from a task
-    // feeding the event channel — means a refresh cannot be queued while one is
-    // already running, so a slow refresh simply delays the next one instead of
-    // building a backlog the UI then has to work through.
+    // NetworkManager state arrives as `Event::NmChanged`; this timer only keeps
+    // time-based output (tunnel uptimes, notification expiry) moving while
+    // nothing changes. Driving it from this loop rather than from a task
+    // feeding the event channel means ticks cannot pile up behind a slow
+    // refresh.
     let refresh_interval = config.refresh_interval();
     let mut refresh = interval_at(Instant::now() + refresh_interval, refresh_interval);
     refresh.set_missed_tick_behavior(MissedTickBehavior::Delay);
@@ -86,7 +153,7 @@ async fn main() -> Result<()> {
         let want_paste = app
             .vpn
             .as_ref()
-            .is_some_and(|m| m.import_buffer().is_some());
+            .is_some_and(|m| m.import_buffer().is_some() || m.create_form().is_some());
         if want_paste != bracketed_paste {
             let _ = tui.set_bracketed_paste(want_paste);
             bracketed_paste = want_paste;
@@ -98,7 +165,10 @@ async fn main() -> Result<()> {
         };
 
         match event {
-            Event::Tick => {
+            // Nothing to do beyond the redraw at the top of the loop.
+            Event::Tick => {}
+
+            Event::NmChanged => {
                 if let Err(e) = app.tick().await {
                     exit_error_message = Some(e);
                     break;
@@ -123,8 +193,16 @@ async fn main() -> Result<()> {
                 app.notifications.push(notification);
             }
 
+            Event::Reset(_) if replay.is_some() => {
+                Notification::send(
+                    "A replay cannot switch modes".to_string(),
+                    NotificationLevel::Error,
+                    &tui.events.sender,
+                )?;
+            }
+
             Event::Reset(mode) => {
-                if let Err(e) = App::reset(mode).await {
+                if let Err(e) = App::reset(mode, backend_kind).await {
                     exit_error_message = Some(e);
                     break;
                 };
@@ -195,11 +273,23 @@ You do not have the required permissions. Ensure you are part of the appropriate
                 app.focused_block = wlctl::app::FocusedBlock::RequestKeyPasshphrase;
             }
 
-            Event::AuthRequestPassword((network_name, user_name)) => {
-                app.auth.init_request_password(network_name, user_name);
+            Event::AuthRequestPassword {
+                network_name,
+                user_name,
+                message,
+                label,
+                reply,
+            } => {
+                app.auth
+                    .init_request_password(network_name, user_name, message, label, reply);
                 app.focused_block = wlctl::app::FocusedBlock::RequestPassword
             }
 
+            Event::AuthRequestWithdrawn if app.auth.request_password.is_some() => {
+                app.auth.request_password = None;
+                app.focused_block = app.focus_after_prompt();
+            }
+
             Event::AuthReqUsernameAndPassword(network_name) => {
                 app.auth.init_request_username_and_password(network_name);
                 app.focused_block = wlctl::app::FocusedBlock::RequestUsernameAndPassword
@@ -214,11 +304,25 @@ You do not have the required permissions. Ensure you are part of the appropriate
             // Only accept results for the current run that is still running.
             // Stale results (from a dismissed or superseded run) fall through
             // to the `_` arm and are dropped.
-            Event::DoctorCompleted { run_id, results }
-                if run_id == app.doctor_run_id
-                    && matches!(app.doctor, Some(wlctl::doctor::DoctorModal::Running)) =>
+            Event::DoctorProgress {
+                run_id,
+                index,
+                entry,
+            } if run_id == app.doctor_run_id => {
+                if let Some(wlctl::doctor::DoctorModal::Running(rows)) = &mut app.doctor
+                    && let Some(row) = rows.get_mut(index)
+                {
+                    row.1 = Some(entry);
+                }
+            }
+            Event::DoctorCompleted {
+                run_id,
+                results,
+                changes,
+            } if run_id == app.doctor_run_id
+                && matches!(app.doctor, Some(wlctl::doctor::DoctorModal::Running(_))) =>
             {
-                app.doctor = Some(wlctl::doctor::DoctorModal::Ready(results));
+                app.doctor = Some(wlctl::doctor::DoctorModal::Ready(results, changes));
             }
 
             _ => {}
@@ -1,7 +1,8 @@
 use anyhow::Result;
 use std::sync::{Arc, atomic::AtomicBool};
 
-use crate::nm::{NMClient, SecurityType};
+use crate::backend::Backend;
+use crate::nm::SecurityType;
 use tokio::sync::mpsc::UnboundedSender;
 
 use ratatui::{
@@ -55,7 +56,7 @@ pub enum APFocusedSection {
 
 #[derive(Debug, Clone)]
 pub struct AccessPoint {
-    client: Arc<NMClient>,
+    client: Arc<dyn Backend>,
     device_path: String,
     pub has_started: bool,
     pub name: Option<String>,
@@ -72,10 +73,10 @@ pub struct AccessPoint {
 }
 
 impl AccessPoint {
-    pub async fn new(client: Arc<NMClient>, device_path: String) -> Result<Self> {
+    pub async fn new(client: Arc<dyn Backend>, device_path: String) -> Result<Self> {
         // Check if there's an active hotspot connection
         let (has_started, name, active_hotspot_path) =
-            Self::check_active_hotspot(&client, &device_path).await;
+            Self::check_active_hotspot(client.as_ref(), &device_path).await;
 
         // NetworkManager doesn't expose frequency/cipher info the same way iwd does
         // We'll populate these when the hotspot is active
@@ -111,43 +112,14 @@ impl AccessPoint {
         })
     }
 
-    #[allow(clippy::collapsible_if)]
     async fn check_active_hotspot(
-        client: &NMClient,
+        client: &dyn Backend,
         device_path: &str,
     ) -> (bool, Option<String>, Option<String>) {
-        // Check active connections for a hotspot on this device
-        if let Ok(active_connections) = client.get_active_connections().await {
-            for conn_path in active_connections {
-                if let Ok(info) = client.get_active_connection_info(conn_path.as_str()).await {
-                    // Check if this connection is on our device and is a hotspot
-                    if info.devices.contains(&device_path.to_string()) {
-                        // Check if it's a hotspot by looking at the connection settings
-                        if let Ok(settings) =
-                            client.get_connection_settings(&info.connection_path).await
-                        {
-                            if let Some(wireless) = settings.get("802-11-wireless") {
-                                if let Some(mode) = wireless.get("mode") {
-                                    if let Ok(mode_str) =
-                                        mode.try_clone().and_then(|v| v.try_into())
-                                    {
-                                        let mode_string: String = mode_str;
-                                        if mode_string == "ap" {
-                                            return (
-                                                true,
-                                                Some(info.id),
-                                                Some(conn_path.to_string()),
-                                            );
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            }
+        match client.active_hotspot(device_path).await {
+            Ok(Some(info)) => (true, Some(info.id), Some(info.path)),
+            _ => (false, None, None),
         }
-        (false, None, None)
     }
 
     pub fn render_input(&self, frame: &mut Frame) {
@@ -271,7 +243,7 @@ impl AccessPoint {
 
     pub async fn refresh(&mut self) -> Result<()> {
         let (has_started, name, active_hotspot_path) =
-            Self::check_active_hotspot(&self.client, &self.device_path).await;
+            Self::check_active_hotspot(self.client.as_ref(), &self.device_path).await;
 
         self.has_started = has_started;
         self.name = name;
@@ -322,7 +294,7 @@ impl AccessPoint {
             Ok(active_path) => {
                 self.has_started = true;
                 self.name = Some(ssid.clone());
-                self.active_hotspot_path = Some(active_path.to_string());
+                self.active_hotspot_path = Some(active_path);
                 Notification::send(
                     format!("AP Started\nSSID: {}", ssid),
                     NotificationLevel::Info,
@@ -7,9 +7,9 @@ pub mod speed_test;
 
 use std::sync::Arc;
 
+use crate::backend::{Backend, Snapshot};
 use crate::nm::{
-    AccessPointInfo, ConnectionInfo, DiagnosticInfo, LinkKind, NMClient, NmSnapshot, PrimaryLink,
-    StationState,
+    AccessPointInfo, ConnectionInfo, DiagnosticInfo, LinkKind, PrimaryLink, StationState,
 };
 use ratatui::{
     Frame,
@@ -105,7 +105,7 @@ pub struct HiddenNetwork {
 
 #[derive(Clone)]
 pub struct Station {
-    pub client: Arc<NMClient>,
+    pub client: Arc<dyn Backend>,
     pub device_path: String,
     pub state: StationState,
     pub is_scanning: bool,
@@ -140,7 +140,7 @@ pub struct Station {
 
 impl Station {
     pub async fn new(
-        client: Arc<NMClient>,
+        client: Arc<dyn Backend>,
         device_path: String,
         is_ethernet_connected: bool,
     ) -> Result<Self> {
@@ -152,9 +152,9 @@ impl Station {
         // the periodic refresh() tick will pick them up.
         let _ = client.request_scan(&device_path).await;
 
-        let snapshot = NmSnapshot::fetch(&client).await?;
+        let snapshot = client.snapshot().await?;
         let visible_networks = snapshot.visible_networks(&device_path);
-        let saved_connections = client.wifi_connections(&snapshot).await?;
+        let saved_connections = client.wifi_connections(&*snapshot).await?;
         let active_ap = snapshot.active_access_point(&device_path);
         let connected_ssid = active_ap.as_ref().map(|ap| ap.ssid.clone());
 
@@ -217,7 +217,7 @@ impl Station {
 
     /// Reads the active adapter's primary IPv4, if any. Best-effort: a missing
     /// or unreadable address just yields `None`.
-    async fn fetch_device_ipv4(client: &Arc<NMClient>, device_path: &str) -> Option<String> {
+    async fn fetch_device_ipv4(client: &Arc<dyn Backend>, device_path: &str) -> Option<String> {
         client
             .get_ip4_info(device_path)
             .await
@@ -226,7 +226,7 @@ impl Station {
             .and_then(|ip| ip.addresses.into_iter().next().map(|(addr, _)| addr))
     }
 
-    pub async fn refresh(&mut self, snapshot: &NmSnapshot) -> Result<()> {
+    pub async fn refresh(&mut self, snapshot: &dyn Snapshot) -> Result<()> {
         if let Some(device_state) = snapshot.device_state(&self.device_path) {
             self.state = StationState::from(device_state);
         }
@@ -271,7 +271,7 @@ impl Station {
     /// Categorize visible APs into known networks, new networks, and the connected network.
     #[allow(clippy::type_complexity)]
     fn categorize_networks(
-        client: &Arc<NMClient>,
+        client: &Arc<dyn Backend>,
         device_path: &str,
         visible_networks: &[AccessPointInfo],
         saved_connections: &[ConnectionInfo],
@@ -314,7 +314,7 @@ impl Station {
 
     /// Find saved connections that are not currently visible.
     fn find_unavailable_networks(
-        client: &Arc<NMClient>,
+        client: &Arc<dyn Backend>,
         known_networks: &[(Network, i16)],
         saved_connections: &[ConnectionInfo],
     ) -> Vec<KnownNetwork> {
@@ -1240,3 +1240,81 @@ impl Station {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::fake::FakeBackend;
+    use crate::nm::SecurityType;
+    use tokio::sync::mpsc::unbounded_channel;
+
+    fn names(networks: &[(Network, i16)]) -> Vec<&str> {
+        networks.iter().map(|(n, _)| n.name.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn new_sorts_networks_into_connected_known_new_and_unavailable() {
+        let fake = FakeBackend::default();
+        let device = fake.add_wifi_device("wlan0");
+        fake.add_access_point(&device, "cafe", 50, SecurityType::Open);
+        fake.add_access_point(&device, "home", 80, SecurityType::WPA2);
+        let home = fake.add_wifi_profile("home", Some("hunter22"));
+        fake.add_wifi_profile("faraway", None);
+        fake.activate_now(&home, &device);
+
+        let station = Station::new(Arc::new(fake.clone()), device, false)
+            .await
+            .unwrap();
+
+        assert_eq!(station.state, StationState::Connected);
+        assert_eq!(
+            station.connected_network.as_ref().map(|n| n.name.as_str()),
+            Some("home")
+        );
+        assert_eq!(names(&station.known_networks), ["home"]);
+        assert_eq!(names(&station.new_networks), ["cafe"]);
+        assert_eq!(station.unavailable_known_networks.len(), 1);
+        assert_eq!(station.unavailable_known_networks[0].name, "faraway");
+        assert_eq!(fake.scans(), 1);
+    }
+
+    #[tokio::test]
+    async fn refresh_follows_the_backend_state() {
+        let fake = FakeBackend::default();
+        let device = fake.add_wifi_device("wlan0");
+        let backend: Arc<dyn Backend> = Arc::new(fake.clone());
+        let mut station = Station::new(backend.clone(), device.clone(), false)
+            .await
+            .unwrap();
+        assert!(station.new_networks.is_empty());
+        let (tx, _rx) = unbounded_channel();
+
+        fake.add_access_point(&device, "cafe", 50, SecurityType::Open);
+        station
+            .refresh(&*backend.snapshot().await.unwrap())
+            .await
+            .unwrap();
+        assert_eq!(names(&station.new_networks), ["cafe"]);
+
+        station.new_networks[0]
+            .0
+            .connect(tx.clone(), None)
+            .await
+            .unwrap();
+        station
+            .refresh(&*backend.snapshot().await.unwrap())
+            .await
+            .unwrap();
+        assert_eq!(names(&station.known_networks), ["cafe"]);
+        assert!(station.new_networks.is_empty());
+        assert!(station.connected_network.is_some());
+
+        station.disconnect(tx).await.unwrap();
+        station
+            .refresh(&*backend.snapshot().await.unwrap())
+            .await
+            .unwrap();
+        assert!(station.connected_network.is_none());
+        assert_eq!(station.state, StationState::Disconnected);
+    }
+}
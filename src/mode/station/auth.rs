@@ -4,6 +4,8 @@ pub mod psk;
 
 use std::sync::Arc;
 
+use crate::agent::SecretReply;
+use crate::backend::Backend;
 use crate::mode::station::auth::{
     entreprise::{
         WPAEntreprise,
@@ -15,7 +17,6 @@ use crate::mode::station::auth::{
     hidden::HiddenSsidDialog,
     psk::Psk,
 };
-use crate::nm::NMClient;
 
 #[derive(Debug, Default)]
 pub struct Auth {
@@ -28,7 +29,7 @@ pub struct Auth {
 }
 
 impl Auth {
-    pub fn init_eap(&mut self, network_name: String, client: Option<Arc<NMClient>>) {
+    pub fn init_eap(&mut self, network_name: String, client: Option<Arc<dyn Backend>>) {
         self.eap = Some(WPAEntreprise::new(network_name, client));
     }
 
@@ -42,8 +43,21 @@ impl Auth {
         self.request_key_passphrase = Some(RequestKeyPassphrase::new(network_name));
     }
 
-    pub fn init_request_password(&mut self, network_name: String, user_name: Option<String>) {
-        self.request_password = Some(RequestPassword::new(network_name, user_name));
+    pub fn init_request_password(
+        &mut self,
+        network_name: String,
+        user_name: Option<String>,
+        message: Option<String>,
+        label: String,
+        reply: SecretReply,
+    ) {
+        self.request_password = Some(RequestPassword::new(
+            network_name,
+            user_name,
+            message,
+            label,
+            reply,
+        ));
     }
 
     pub fn init_request_username_and_password(&mut self, network_name: String) {
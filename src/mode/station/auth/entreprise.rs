@@ -12,8 +12,8 @@ use ratatui::{
 };
 use tui_input::Input;
 
+use crate::backend::{Backend, EnterpriseCredentials};
 use crate::event::Event;
-use crate::nm::NMClient;
 
 pub mod eduroam;
 pub mod peap;
@@ -61,7 +61,7 @@ pub struct WPAEntreprise {
     pub eap: Eap,
     pub network_name: String,
     focused_section: FocusedSection,
-    client: Option<Arc<NMClient>>,
+    client: Option<Arc<dyn Backend>>,
 }
 
 #[derive(Debug)]
@@ -85,7 +85,7 @@ impl Eap {
 }
 
 impl WPAEntreprise {
-    pub fn new(network_name: String, client: Option<Arc<NMClient>>) -> Self {
+    pub fn new(network_name: String, client: Option<Arc<dyn Backend>>) -> Self {
         Self {
             eap: Eap::new(),
             network_name,
@@ -532,14 +532,16 @@ impl WPAEntreprise {
                                 let result = client
                                     .add_enterprise_connection(
                                         &network_name,
-                                        eap_method,
-                                        &identity,
-                                        password.as_deref(),
-                                        phase2_auth.as_deref(),
-                                        ca_cert.as_deref(),
-                                        client_cert.as_deref(),
-                                        private_key.as_deref(),
-                                        key_passphrase.as_deref(),
+                                        EnterpriseCredentials {
+                                            eap_method,
+                                            identity: &identity,
+                                            password: password.as_deref(),
+                                            phase2_auth: phase2_auth.as_deref(),
+                                            ca_cert: ca_cert.as_deref(),
+                                            client_cert: client_cert.as_deref(),
+                                            private_key: private_key.as_deref(),
+                                            private_key_password: key_passphrase.as_deref(),
+                                        },
                                     )
                                     .await;
 
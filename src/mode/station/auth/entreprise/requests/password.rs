@@ -1,4 +1,4 @@
-use crate::agent::AuthAgent;
+use crate::agent::{AuthAgent, SecretReply};
 use anyhow::Result;
 
 use ratatui::{
@@ -16,20 +16,34 @@ pub struct RequestPassword {
     pub show_password: bool,
     network_name: String,
     user_name: Option<String>,
+    /// Shown above the password, e.g. a server's one-time code challenge.
+    message: Option<String>,
+    /// Names the field, e.g. `Password` or `One-time code`.
+    label: String,
+    reply: SecretReply,
 }
 
 impl RequestPassword {
-    pub fn new(network_name: String, user_name: Option<String>) -> Self {
+    pub fn new(
+        network_name: String,
+        user_name: Option<String>,
+        message: Option<String>,
+        label: String,
+        reply: SecretReply,
+    ) -> Self {
         Self {
             password: Input::default(),
             show_password: true,
             network_name,
             user_name,
+            message,
+            label,
+            reply,
         }
     }
     pub async fn submit(&mut self, agent: &AuthAgent) -> Result<()> {
         let passkey: String = self.password.value().into();
-        agent.tx_passphrase.send(passkey).await?;
+        self.reply.send(passkey);
         agent
             .password_required
             .store(false, std::sync::atomic::Ordering::Relaxed);
@@ -38,7 +52,7 @@ impl RequestPassword {
     }
 
     pub async fn cancel(&mut self, agent: &AuthAgent) -> Result<()> {
-        agent.tx_cancel.send(()).await?;
+        self.reply.cancel();
         agent
             .password_required
             .store(false, std::sync::atomic::Ordering::Relaxed);
@@ -104,9 +118,14 @@ impl RequestPassword {
                     Line::from("")
                 }
             },
-            Line::from(""),
+            match &self.message {
+                Some(message) => Line::from(message.as_str()).fg(Color::Yellow),
+                None => Line::from(""),
+            },
             Line::from(vec![
-                Span::raw(" Password ").bold().bg(Color::DarkGray),
+                Span::raw(format!(" {} ", self.label))
+                    .bold()
+                    .bg(Color::DarkGray),
                 Span::from("  "),
                 Span::from({
                     if self.show_password {
@@ -2,7 +2,8 @@ use anyhow::Result;
 use chrono::{DateTime, FixedOffset, TimeZone};
 use std::sync::Arc;
 
-use crate::nm::{ConnectionInfo, NMClient, SecurityType};
+use crate::backend::Backend;
+use crate::nm::{ConnectionInfo, SecurityType};
 
 use tokio::sync::mpsc::UnboundedSender;
 
@@ -13,7 +14,7 @@ use crate::{
 
 #[derive(Debug, Clone)]
 pub struct KnownNetwork {
-    pub client: Arc<NMClient>,
+    pub client: Arc<dyn Backend>,
     pub connection_path: String,
     pub name: String,
     pub network_type: SecurityType,
@@ -23,7 +24,7 @@ pub struct KnownNetwork {
 }
 
 impl KnownNetwork {
-    pub fn from_connection_info(client: Arc<NMClient>, info: ConnectionInfo) -> Self {
+    pub fn from_connection_info(client: Arc<dyn Backend>, info: ConnectionInfo) -> Self {
         // Convert unix timestamp to DateTime
         let last_connected = if info.timestamp > 0 {
             FixedOffset::east_opt(0)
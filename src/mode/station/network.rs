@@ -2,9 +2,8 @@ use anyhow::Result;
 use std::sync::Arc;
 use tokio::sync::mpsc::UnboundedSender;
 
-use crate::nm::{
-    AccessPointInfo, ActivationFailureReason, ActivationOutcome, NMClient, SecurityType,
-};
+use crate::backend::Backend;
+use crate::nm::{AccessPointInfo, ActivationFailureReason, ActivationOutcome, SecurityType};
 
 use crate::{
     event::Event,
@@ -28,7 +27,7 @@ use crate::{
 /// an activation outcome the caller has typically been spawned onto a
 /// background task with no error path back to the UI thread.
 pub async fn watch_activation(
-    client: Arc<NMClient>,
+    client: Arc<dyn Backend>,
     active_path: String,
     device_path: String,
     ssid: String,
@@ -69,6 +68,10 @@ pub async fn watch_activation(
             format!("Connection to {} timed out", ssid),
             NotificationLevel::Error,
         ),
+        ActivationOutcome::Failed(ActivationFailureReason::NoAgent) => (
+            format!("Nothing could ask for the password of {}", ssid),
+            NotificationLevel::Error,
+        ),
         ActivationOutcome::Failed(ActivationFailureReason::Other(code)) => (
             format!("Failed to connect to {} (reason {})", ssid, code),
             NotificationLevel::Error,
@@ -80,7 +83,7 @@ pub async fn watch_activation(
 
 #[derive(Debug, Clone)]
 pub struct Network {
-    pub client: Arc<NMClient>,
+    pub client: Arc<dyn Backend>,
     pub device_path: String,
     pub ap_path: String,
     pub name: String,
@@ -92,7 +95,7 @@ pub struct Network {
 
 impl Network {
     pub fn from_access_point(
-        client: Arc<NMClient>,
+        client: Arc<dyn Backend>,
         device_path: String,
         ap_info: AccessPointInfo,
         known_network: Option<KnownNetwork>,
@@ -131,7 +134,7 @@ impl Network {
             Ok(active_path) => {
                 tokio::spawn(watch_activation(
                     self.client.clone(),
-                    active_path.to_string(),
+                    active_path,
                     self.device_path.clone(),
                     self.name.clone(),
                     sender,
@@ -12,7 +12,7 @@ use ratatui::{
     widgets::{Block, BorderType, Borders, Clear},
 };
 
-use crate::nm::NMClient;
+use crate::backend::Backend;
 
 #[derive(Clone)]
 pub struct Share {
@@ -23,7 +23,7 @@ pub struct Share {
 
 impl Share {
     pub async fn new(
-        client: Arc<NMClient>,
+        client: Arc<dyn Backend>,
         connection_path: &str,
         network_name: String,
     ) -> Result<Self> {
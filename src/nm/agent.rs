@@ -0,0 +1,382 @@
+// The secret agent NetworkManager asks when activating a VPN profile needs a
+// password or one-time code it has not stored. Each secret a request names
+// gets its own password prompt through `AuthAgent`, answered on a channel of
+// its own; WiFi secrets never come here, as the TUI collects those before it
+// connects.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use tokio::sync::oneshot;
+use zbus::interface;
+use zbus::zvariant::{OwnedObjectPath, OwnedValue, Value};
+
+use super::{setting, setting_str};
+use crate::agent::{AuthAgent, SecretReply};
+use crate::notification::{Notification, NotificationLevel};
+
+/// Where NetworkManager calls every secret agent.
+pub(super) const AGENT_PATH: &str = "/org/freedesktop/NetworkManager/SecretAgent";
+
+/// The name the agent registers under.
+pub(super) const AGENT_IDENTIFIER: &str = "wlctl";
+
+const OPENCONNECT_SERVICE: &str = "org.freedesktop.NetworkManager.openconnect";
+
+/// `GetSecrets` flags.
+const ALLOW_INTERACTION: u32 = 0x1;
+const REQUEST_NEW: u32 = 0x2;
+
+/// Hints carrying text for the user rather than a secret's name.
+const MESSAGE_HINT: &str = "x-vpn-message:";
+
+type Settings = HashMap<String, HashMap<String, OwnedValue>>;
+
+/// A request is known by its profile and the setting it wants secrets for.
+type RequestKey = (String, String);
+
+#[derive(Debug, zbus::DBusError)]
+#[zbus(prefix = "org.freedesktop.NetworkManager.SecretAgent")]
+pub(super) enum AgentError {
+    #[zbus(error)]
+    ZBus(zbus::Error),
+    /// Another agent, or none, has to answer this one.
+    NoSecrets(String),
+    UserCanceled(String),
+}
+
+/// What a `GetSecrets` call asks of the user.
+#[derive(Debug, PartialEq, Eq)]
+struct SecretRequest {
+    /// The profile's name, for the prompt's title.
+    id: String,
+    /// The secrets to ask for, in order, e.g. `password` then
+    /// `challenge-response`.
+    keys: Vec<String>,
+    user_name: Option<String>,
+    message: Option<String>,
+}
+
+impl SecretRequest {
+    /// Reads a request, or says why this agent cannot answer it.
+    fn read(
+        connection: &Settings,
+        setting_name: &str,
+        hints: &[String],
+        flags: u32,
+    ) -> Result<Self, AgentError> {
+        if setting_name != "vpn" {
+            return Err(no_secrets("wlctl only asks for VPN secrets"));
+        }
+        if flags & ALLOW_INTERACTION == 0 {
+            return Err(no_secrets("wlctl needs to ask for these secrets"));
+        }
+        let vpn = connection.get("vpn");
+        if is_openconnect(connection) {
+            return Err(no_secrets(
+                "OpenConnect logins need the gateway's own login dialog",
+            ));
+        }
+
+        let data: HashMap<String, String> =
+            vpn.and_then(|vpn| setting(vpn, "data")).unwrap_or_default();
+
+        let mut message = hints
+            .iter()
+            .find_map(|hint| hint.strip_prefix(MESSAGE_HINT))
+            .map(str::to_string);
+        if message.is_none() && flags & REQUEST_NEW != 0 {
+            message = Some("The last password was not accepted".to_string());
+        }
+        // Plugins name every secret they want, e.g. `password` and then
+        // `challenge-response` for a one-time code; without names, the
+        // profile's own password is asked for.
+        let mut keys: Vec<String> = Vec::new();
+        for hint in hints {
+            if !hint.is_empty() && !hint.starts_with(MESSAGE_HINT) && !keys.contains(hint) {
+                keys.push(hint.clone());
+            }
+        }
+        if keys.is_empty() {
+            keys.push(match data.get("connection-type").map(String::as_str) {
+                Some("tls") => "cert-pass".to_string(),
+                _ => "password".to_string(),
+            });
+        }
+
+        Ok(Self {
+            id: profile_id(connection),
+            keys,
+            user_name: data.get("username").cloned(),
+            message,
+        })
+    }
+
+    /// The `GetSecrets` reply holding `secrets`, by key.
+    fn reply(secrets: HashMap<String, String>) -> Result<Settings, AgentError> {
+        let value = OwnedValue::try_from(Value::from(secrets)).map_err(zbus::Error::from)?;
+        Ok(HashMap::from([(
+            "vpn".to_string(),
+            HashMap::from([("secrets".to_string(), value)]),
+        )]))
+    }
+}
+
+/// How the prompt names a secret.
+fn label(key: &str) -> String {
+    match key {
+        "password" => "Password".to_string(),
+        "cert-pass" => "Key password".to_string(),
+        "http-proxy-password" => "Proxy password".to_string(),
+        "challenge-response" => "One-time code".to_string(),
+        other => other.to_string(),
+    }
+}
+
+fn profile_id(connection: &Settings) -> String {
+    connection
+        .get("connection")
+        .and_then(|c| setting_str(c, "id"))
+        .unwrap_or_else(|| "VPN".to_string())
+}
+
+fn is_openconnect(connection: &Settings) -> bool {
+    let service = connection
+        .get("vpn")
+        .and_then(|vpn| setting_str(vpn, "service-type"));
+    service.as_deref() == Some(OPENCONNECT_SERVICE)
+}
+
+fn no_secrets(why: &str) -> AgentError {
+    AgentError::NoSecrets(why.to_string())
+}
+
+/// Closes the prompt it was made for if dropped before an answer came, as
+/// when NetworkManager withdraws the request.
+struct OpenPrompt<'a>(Option<&'a AuthAgent>);
+
+impl OpenPrompt<'_> {
+    fn answered(mut self) {
+        self.0 = None;
+    }
+}
+
+impl Drop for OpenPrompt<'_> {
+    fn drop(&mut self) {
+        if let Some(auth) = self.0 {
+            auth.close_password_prompt();
+        }
+    }
+}
+
+pub(super) struct SecretAgent {
+    auth: AuthAgent,
+    /// Held while a prompt is open. NetworkManager may ask for a second
+    /// profile before the first is answered; the TUI shows one at a time.
+    prompt: tokio::sync::Mutex<()>,
+    /// Every request not yet answered, shown or still queued, with what
+    /// cancels it. The number tells a request from a newer one for the same
+    /// profile.
+    pending: Mutex<HashMap<RequestKey, (u64, oneshot::Sender<()>)>>,
+    next: AtomicU64,
+}
+
+impl SecretAgent {
+    pub(super) fn new(auth: AuthAgent) -> Self {
+        Self {
+            auth,
+            prompt: tokio::sync::Mutex::new(()),
+            pending: Mutex::new(HashMap::new()),
+            next: AtomicU64::new(0),
+        }
+    }
+
+    fn pending(
+        &self,
+    ) -> std::sync::MutexGuard<'_, HashMap<RequestKey, (u64, oneshot::Sender<()>)>> {
+        self.pending.lock().expect("pending lock poisoned")
+    }
+
+    /// Asks for each of the request's secrets in turn, one prompt at a time.
+    async fn ask(&self, request: &SecretRequest) -> Option<HashMap<String, String>> {
+        let _prompt = self.prompt.lock().await;
+        let mut secrets = HashMap::new();
+        for key in &request.keys {
+            let (reply, answer) = SecretReply::new();
+            self.auth
+                .request_password(
+                    request.id.clone(),
+                    request.user_name.clone(),
+                    request.message.clone(),
+                    label(key),
+                    reply,
+                )
+                .ok()?;
+            let open = OpenPrompt(Some(&self.auth));
+            let secret = answer.await.ok();
+            open.answered();
+            secrets.insert(key.clone(), secret?);
+        }
+        Some(secrets)
+    }
+}
+
+#[interface(name = "org.freedesktop.NetworkManager.SecretAgent")]
+impl SecretAgent {
+    async fn get_secrets(
+        &self,
+        connection: Settings,
+        connection_path: OwnedObjectPath,
+        setting_name: String,
+        hints: Vec<String>,
+        flags: u32,
+    ) -> Result<Settings, AgentError> {
+        // Turned away, but not silently: the user is waiting on the connect.
+        if flags & ALLOW_INTERACTION != 0 && is_openconnect(&connection) {
+            let _ = Notification::send(
+                format!(
+                    "{} is an OpenConnect VPN; wlctl cannot show its web login, so connect it from your desktop's VPN dialog",
+                    profile_id(&connection)
+                ),
+                NotificationLevel::Warning,
+                &self.auth.event_sender,
+            );
+        }
+        let request = SecretRequest::read(&connection, &setting_name, &hints, flags)?;
+
+        let key = (connection_path.to_string(), setting_name);
+        let (cancel, canceled) = oneshot::channel();
+        let number = self.next.fetch_add(1, Ordering::Relaxed);
+        // A newer request for the same profile replaces this one's entry;
+        // dropping the old sender cancels the old request.
+        self.pending().insert(key.clone(), (number, cancel));
+
+        let answer = tokio::select! {
+            answer = self.ask(&request) => answer,
+            _ = canceled => None,
+        };
+
+        let mut pending = self.pending();
+        if pending.get(&key).is_some_and(|(n, _)| *n == number) {
+            pending.remove(&key);
+        }
+        drop(pending);
+
+        match answer {
+            Some(secrets) => SecretRequest::reply(secrets),
+            None => Err(AgentError::UserCanceled(format!(
+                "No secrets given for {}",
+                request.id
+            ))),
+        }
+    }
+
+    /// NetworkManager gave up waiting, or the activation was stopped.
+    async fn cancel_get_secrets(&self, connection_path: OwnedObjectPath, setting_name: String) {
+        let key = (connection_path.to_string(), setting_name);
+        if let Some((_, cancel)) = self.pending().remove(&key) {
+            let _ = cancel.send(());
+        }
+    }
+
+    // Secrets stay with NetworkManager, which keeps the ones the profile
+    // says to keep; there is nothing to save or delete here.
+
+    fn save_secrets(&self, _connection: Settings, _connection_path: OwnedObjectPath) {}
+
+    fn delete_secrets(&self, _connection: Settings, _connection_path: OwnedObjectPath) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn openvpn(connection_type: &str, username: Option<&str>) -> Settings {
+        let mut data =
+            HashMap::from([("connection-type".to_string(), connection_type.to_string())]);
+        if let Some(username) = username {
+            data.insert("username".to_string(), username.to_string());
+        }
+        let owned = |v: Value<'static>| OwnedValue::try_from(v).unwrap();
+        HashMap::from([
+            (
+                "connection".to_string(),
+                HashMap::from([("id".to_string(), owned(Value::from("work")))]),
+            ),
+            (
+                "vpn".to_string(),
+                HashMap::from([
+                    (
+                        "service-type".to_string(),
+                        owned(Value::from("org.freedesktop.NetworkManager.openvpn")),
+                    ),
+                    ("data".to_string(), owned(Value::from(data))),
+                ]),
+            ),
+        ])
+    }
+
+    #[test]
+    fn a_vpn_request_asks_for_the_password_by_default() {
+        let request = SecretRequest::read(
+            &openvpn("password", Some("alice")),
+            "vpn",
+            &[],
+            ALLOW_INTERACTION,
+        )
+        .unwrap();
+        assert_eq!(
+            request,
+            SecretRequest {
+                id: "work".into(),
+                keys: vec!["password".into()],
+                user_name: Some("alice".into()),
+                message: None,
+            }
+        );
+
+        let request =
+            SecretRequest::read(&openvpn("tls", None), "vpn", &[], ALLOW_INTERACTION).unwrap();
+        assert_eq!(request.keys, ["cert-pass"]);
+    }
+
+    #[test]
+    fn hints_name_the_secret_and_carry_the_message() {
+        let hints = [
+            "password".to_string(),
+            "x-vpn-message:Enter the code from your token".to_string(),
+            "challenge-response".to_string(),
+        ];
+        let request = SecretRequest::read(
+            &openvpn("password", Some("alice")),
+            "vpn",
+            &hints,
+            ALLOW_INTERACTION | REQUEST_NEW,
+        )
+        .unwrap();
+        assert_eq!(request.keys, ["password", "challenge-response"]);
+        assert_eq!(
+            request.message.as_deref(),
+            Some("Enter the code from your token")
+        );
+    }
+
+    #[test]
+    fn only_interactive_vpn_requests_are_answered() {
+        let settings = openvpn("password", None);
+        assert!(matches!(
+            SecretRequest::read(
+                &settings,
+                "802-11-wireless-security",
+                &[],
+                ALLOW_INTERACTION
+            ),
+            Err(AgentError::NoSecrets(_))
+        ));
+        assert!(matches!(
+            SecretRequest::read(&settings, "vpn", &[], 0),
+            Err(AgentError::NoSecrets(_))
+        ));
+    }
+}
@@ -0,0 +1,227 @@
+//! NetworkManager as a `Backend`: forwards to the `NMClient` D-Bus calls,
+//! handing object paths out as plain strings.
+
+use std::net::Ipv4Addr;
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+
+use super::{
+    AccessPointInfo, ActivationOutcome, ActiveConnectionInfo, ConnectionInfo, Connectivity,
+    DeviceState, Dhcp4Lease, Ip4Info, Ip6Info, LinkKind, NMClient, NmSnapshot, NmWatcher,
+    OvpnConfig, SecurityType, VpnConnectionInfo, WgConfig, dump,
+};
+use crate::agent::AuthAgent;
+use crate::backend::{Backend, EnterpriseCredentials, SharedSnapshot, Snapshot, Watch};
+
+#[async_trait]
+impl Backend for NMClient {
+    async fn get_wifi_devices(&self) -> Result<Vec<String>> {
+        let devices = NMClient::get_wifi_devices(self).await?;
+        Ok(devices.into_iter().map(|path| path.to_string()).collect())
+    }
+
+    async fn get_device_interface(&self, device: &str) -> Result<String> {
+        NMClient::get_device_interface(self, device).await
+    }
+
+    async fn get_device_hw_address(&self, device: &str) -> Result<String> {
+        NMClient::get_device_hw_address(self, device).await
+    }
+
+    async fn get_device_state(&self, device: &str) -> Result<DeviceState> {
+        NMClient::get_device_state(self, device).await
+    }
+
+    async fn is_wireless_enabled(&self) -> Result<bool> {
+        NMClient::is_wireless_enabled(self).await
+    }
+
+    async fn set_wireless_enabled(&self, enabled: bool) -> Result<()> {
+        NMClient::set_wireless_enabled(self, enabled).await
+    }
+
+    async fn snapshot(&self) -> Result<SharedSnapshot> {
+        Ok(Arc::new(NmSnapshot::fetch(self).await?))
+    }
+
+    async fn watch(&self, notify: Box<dyn Fn() + Send + Sync>) -> Result<Box<dyn Watch>> {
+        Ok(Box::new(NmWatcher::spawn(self, notify).await?))
+    }
+
+    async fn request_scan(&self, device: &str) -> Result<()> {
+        NMClient::request_scan(self, device).await
+    }
+
+    async fn get_active_access_point(&self, device: &str) -> Result<Option<String>> {
+        let ap = NMClient::get_active_access_point(self, device).await?;
+        Ok(ap.map(|path| path.to_string()))
+    }
+
+    async fn get_access_point_info(&self, access_point: &str) -> Result<AccessPointInfo> {
+        NMClient::get_access_point_info(self, access_point).await
+    }
+
+    async fn wifi_connections(&self, snapshot: &dyn Snapshot) -> Result<Arc<[ConnectionInfo]>> {
+        NMClient::wifi_connections(self, snapshot).await
+    }
+
+    async fn get_wifi_psk(&self, profile: &str) -> Result<Option<String>> {
+        NMClient::get_wifi_psk(self, profile).await
+    }
+
+    async fn get_vpn_connections(&self) -> Result<Vec<VpnConnectionInfo>> {
+        NMClient::get_vpn_connections(self).await
+    }
+
+    async fn profile_json(&self, profile: &str) -> Result<serde_json::Value> {
+        let settings = self.get_connection_settings(profile).await?;
+        Ok(dump::settings_json(&settings))
+    }
+
+    async fn wireguard_config(&self, profile: &str) -> Result<WgConfig> {
+        self.get_wireguard_config(profile).await
+    }
+
+    async fn add_wireguard_connection(
+        &self,
+        id: &str,
+        interface: &str,
+        cfg: &WgConfig,
+    ) -> Result<String> {
+        let path = NMClient::add_wireguard_connection(self, id, interface, cfg).await?;
+        Ok(path.to_string())
+    }
+
+    async fn register_secret_agent(&self, agent: AuthAgent) -> Result<()> {
+        NMClient::register_secret_agent(self, agent).await
+    }
+
+    async fn add_openvpn_connection(&self, id: &str, cfg: &OvpnConfig) -> Result<String> {
+        let path = NMClient::add_openvpn_connection(self, id, cfg).await?;
+        Ok(path.to_string())
+    }
+
+    async fn add_enterprise_connection(
+        &self,
+        ssid: &str,
+        credentials: EnterpriseCredentials<'_>,
+    ) -> Result<String> {
+        let path = NMClient::add_enterprise_connection(
+            self,
+            ssid,
+            credentials.eap_method,
+            credentials.identity,
+            credentials.password,
+            credentials.phase2_auth,
+            credentials.ca_cert,
+            credentials.client_cert,
+            credentials.private_key,
+            credentials.private_key_password,
+        )
+        .await?;
+        Ok(path.to_string())
+    }
+
+    async fn set_connection_autoconnect(&self, profile: &str, autoconnect: bool) -> Result<()> {
+        NMClient::set_connection_autoconnect(self, profile, autoconnect).await
+    }
+
+    async fn delete_connection(&self, profile: &str) -> Result<()> {
+        NMClient::delete_connection(self, profile).await
+    }
+
+    async fn activate_connection(&self, profile: &str, device: &str) -> Result<String> {
+        let path = NMClient::activate_connection(self, profile, device).await?;
+        Ok(path.to_string())
+    }
+
+    async fn add_and_activate_connection(
+        &self,
+        device: &str,
+        access_point: &str,
+        password: Option<&str>,
+    ) -> Result<String> {
+        let path =
+            NMClient::add_and_activate_connection(self, device, access_point, password).await?;
+        Ok(path.to_string())
+    }
+
+    async fn add_and_activate_hidden_connection(
+        &self,
+        device: &str,
+        ssid: &str,
+        security: SecurityType,
+        password: Option<&str>,
+    ) -> Result<String> {
+        let path =
+            NMClient::add_and_activate_hidden_connection(self, device, ssid, security, password)
+                .await?;
+        Ok(path.to_string())
+    }
+
+    async fn await_activation(&self, active: &str, device: &str) -> Result<ActivationOutcome> {
+        NMClient::await_activation(self, active, device).await
+    }
+
+    async fn disconnect_device(&self, device: &str) -> Result<()> {
+        NMClient::disconnect_device(self, device).await
+    }
+
+    async fn deactivate_connection(&self, active: &str) -> Result<()> {
+        NMClient::deactivate_connection(self, active).await
+    }
+
+    async fn get_active_connections(&self) -> Result<Vec<ActiveConnectionInfo>> {
+        let mut infos = Vec::new();
+        for path in NMClient::get_active_connections(self).await? {
+            // An activation can vanish between the list and the read.
+            if let Ok(info) = self.get_active_connection_info(path.as_str()).await {
+                infos.push(info);
+            }
+        }
+        Ok(infos)
+    }
+
+    async fn active_connection_ipv4(&self, active: &str) -> Result<Option<String>> {
+        NMClient::active_connection_ipv4(self, active).await
+    }
+
+    async fn create_hotspot(&self, device: &str, ssid: &str, password: &str) -> Result<String> {
+        let path = NMClient::create_hotspot(self, device, ssid, password).await?;
+        Ok(path.to_string())
+    }
+
+    async fn active_hotspot(&self, device: &str) -> Result<Option<ActiveConnectionInfo>> {
+        NMClient::active_hotspot(self, device).await
+    }
+
+    async fn prefer_internet(&self, kind: LinkKind) -> Result<()> {
+        NMClient::prefer_internet(self, kind).await
+    }
+
+    async fn get_ip4_info(&self, device: &str) -> Result<Option<Ip4Info>> {
+        NMClient::get_ip4_info(self, device).await
+    }
+
+    async fn get_ip6_info(&self, device: &str) -> Result<Option<Ip6Info>> {
+        NMClient::get_ip6_info(self, device).await
+    }
+
+    async fn get_dhcp4_lease(&self, device: &str) -> Result<Option<Dhcp4Lease>> {
+        NMClient::get_dhcp4_lease(self, device).await
+    }
+
+    async fn check_connectivity(&self) -> Result<Connectivity> {
+        NMClient::check_connectivity(self).await
+    }
+
+    async fn connectivity_check_uri(&self) -> Result<Option<String>> {
+        NMClient::connectivity_check_uri(self).await
+    }
+
+    async fn set_runtime_dns(&self, device: &str, servers: &[Ipv4Addr]) -> Result<()> {
+        NMClient::set_runtime_dns(self, device, servers).await
+    }
+}
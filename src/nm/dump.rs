@@ -0,0 +1,146 @@
+// JSON renderings of NetworkManager state for diagnostics bundles, with
+// secrets blanked out so the result can be attached to a ticket.
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde_json::{Value as Json, json};
+use zbus::zvariant::{OwnedValue, Value};
+
+pub(crate) const REDACTED: &str = "<redacted>";
+
+/// Whether a settings or property key holds a secret. The matching `*-flags`
+/// keys only say where a secret is stored and are kept.
+pub(crate) fn is_secret(key: &str) -> bool {
+    let key = key.to_ascii_lowercase();
+    if key.ends_with("-flags") {
+        return false;
+    }
+    key.contains("psk")
+        || key.contains("password")
+        || key.contains("secret")
+        || key.ends_with("private-key")
+        || key == "preshared-key"
+        || key.starts_with("wep-key")
+        || key == "pin"
+}
+
+/// One D-Bus value as JSON. Byte arrays (SSIDs, mostly) become a string when
+/// they are readable text and hex otherwise; dictionary entries whose key
+/// names a secret are replaced with `"<redacted>"`.
+pub fn value_json(value: &Value) -> Json {
+    match value {
+        Value::U8(v) => json!(v),
+        Value::Bool(v) => json!(v),
+        Value::I16(v) => json!(v),
+        Value::U16(v) => json!(v),
+        Value::I32(v) => json!(v),
+        Value::U32(v) => json!(v),
+        Value::I64(v) => json!(v),
+        Value::U64(v) => json!(v),
+        Value::F64(v) => json!(v),
+        Value::Str(v) => json!(v.as_str()),
+        Value::Signature(v) => json!(v.to_string()),
+        Value::ObjectPath(v) => json!(v.as_str()),
+        Value::Value(v) => value_json(v),
+        Value::Array(array) => {
+            let items = array.inner();
+            let bytes: Option<Vec<u8>> = items
+                .iter()
+                .map(|v| match v {
+                    Value::U8(b) => Some(*b),
+                    _ => None,
+                })
+                .collect();
+            match bytes {
+                Some(bytes) if !bytes.is_empty() => match std::str::from_utf8(&bytes) {
+                    Ok(text) if !text.chars().any(char::is_control) => json!(text),
+                    _ => json!(hex::encode(&bytes)),
+                },
+                _ => Json::Array(items.iter().map(value_json).collect()),
+            }
+        }
+        Value::Dict(dict) => {
+            let entries: serde_json::Map<String, Json> = dict
+                .iter()
+                .map(|(k, v)| {
+                    let key = match k {
+                        Value::Str(s) => s.to_string(),
+                        other => value_json(other).to_string(),
+                    };
+                    let value = if is_secret(&key) {
+                        json!(REDACTED)
+                    } else {
+                        value_json(v)
+                    };
+                    (key, value)
+                })
+                .collect();
+            Json::Object(entries)
+        }
+        Value::Structure(structure) => {
+            Json::Array(structure.fields().iter().map(value_json).collect())
+        }
+        Value::Fd(_) => json!("<fd>"),
+    }
+}
+
+/// A name -> value property map as a sorted JSON object, secrets redacted.
+pub fn properties_json(props: &HashMap<String, OwnedValue>) -> Json {
+    let sorted: BTreeMap<&String, Json> = props
+        .iter()
+        .map(|(key, value)| {
+            let value = if is_secret(key) {
+                json!(REDACTED)
+            } else {
+                value_json(value)
+            };
+            (key, value)
+        })
+        .collect();
+    json!(sorted)
+}
+
+/// Connection settings as returned by `GetSettings`, secrets redacted.
+/// NetworkManager leaves secrets out of `GetSettings` already; this also
+/// catches any an agent or plugin left in.
+pub fn settings_json(settings: &HashMap<String, HashMap<String, OwnedValue>>) -> Json {
+    let sorted: BTreeMap<&String, Json> = settings
+        .iter()
+        .map(|(section, props)| (section, properties_json(props)))
+        .collect();
+    json!(sorted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn owned(value: Value) -> OwnedValue {
+        value.try_to_owned().unwrap()
+    }
+
+    #[test]
+    fn secrets_are_redacted_and_flags_kept() {
+        let mut security = HashMap::new();
+        security.insert("psk".to_string(), owned(Value::from("hunter22")));
+        security.insert("psk-flags".to_string(), owned(Value::from(0u32)));
+        security.insert("key-mgmt".to_string(), owned(Value::from("wpa-psk")));
+        let mut settings = HashMap::new();
+        settings.insert("802-11-wireless-security".to_string(), security);
+
+        let doc = settings_json(&settings);
+        let section = &doc["802-11-wireless-security"];
+        assert_eq!(section["psk"], REDACTED);
+        assert_eq!(section["psk-flags"], 0);
+        assert_eq!(section["key-mgmt"], "wpa-psk");
+    }
+
+    #[test]
+    fn ssid_bytes_read_as_text() {
+        let ssid = owned(Value::from(b"home".to_vec()));
+        assert_eq!(value_json(&ssid), "home");
+
+        let binary = owned(Value::from(vec![0u8, 1, 255]));
+        assert_eq!(value_json(&binary), "0001ff");
+    }
+}
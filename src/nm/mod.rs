@@ -1,23 +1,31 @@
 // NetworkManager D-Bus abstraction layer
 // Replaces iwdrs with direct NetworkManager D-Bus calls
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 use std::collections::HashMap;
-use std::net::IpAddr;
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::Path;
 use std::sync::{Arc, Mutex, MutexGuard};
 use zbus::zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, Value};
 use zbus::{Connection, Proxy};
 
+use crate::agent::AuthAgent;
+use crate::backend::Snapshot;
+
+mod agent;
+mod backend;
 pub mod dbus_interfaces;
+pub mod dump;
 pub mod snapshot;
 pub mod types;
 pub mod wifi;
 
-pub use snapshot::NmSnapshot;
+pub use snapshot::{NmSnapshot, NmWatcher};
 pub use types::*;
 
 const NM_BUS_NAME: &str = "org.freedesktop.NetworkManager";
 const NM_PATH: &str = "/org/freedesktop/NetworkManager";
+const AGENT_MANAGER_PATH: &str = "/org/freedesktop/NetworkManager/AgentManager";
 
 /// An activated physical (WiFi/Ethernet) connection paired with its device,
 /// used when reshuffling the default route between links.
@@ -48,6 +56,295 @@ fn setting_u64(section: &HashMap<String, OwnedValue>, key: &str) -> Option<u64>
     u64::try_from(value).ok()
 }
 
+/// Reads a field of any type out of one NetworkManager settings section,
+/// returning `None` if the key is absent or of another type.
+fn setting<T: TryFrom<OwnedValue>>(section: &HashMap<String, OwnedValue>, key: &str) -> Option<T> {
+    let value = section.get(key)?.try_clone().ok()?;
+    T::try_from(value).ok()
+}
+
+/// A saved WiFi profile's `ConnectionInfo`, from its `GetSettings` reply.
+/// `None` for any other kind of profile.
+pub(crate) fn wifi_connection_info(
+    path: &str,
+    settings: &HashMap<String, HashMap<String, OwnedValue>>,
+) -> Option<ConnectionInfo> {
+    let connection = settings.get("connection")?;
+    if setting_str(connection, "type").as_deref() != Some("802-11-wireless") {
+        return None;
+    }
+
+    let id = setting_str(connection, "id").unwrap_or_default();
+    let wireless = settings.get("802-11-wireless");
+    // NetworkManager stores the SSID as raw bytes; the profile name is
+    // the best stand-in when it is absent.
+    let ssid = wireless
+        .and_then(|w| w.get("ssid"))
+        .and_then(|v| v.try_clone().ok())
+        .and_then(|v| Vec::<u8>::try_from(v).ok())
+        .map(|bytes| String::from_utf8_lossy(&bytes).to_string())
+        .unwrap_or_else(|| id.clone());
+    let security = if settings.contains_key("802-11-wireless-security") {
+        if settings.contains_key("802-1x") {
+            SecurityType::Enterprise
+        } else {
+            SecurityType::WPA
+        }
+    } else {
+        SecurityType::Open
+    };
+
+    Some(ConnectionInfo {
+        path: path.to_string(),
+        id,
+        uuid: setting_str(connection, "uuid").unwrap_or_default(),
+        ssid,
+        // NetworkManager omits `autoconnect` when it is at its default of true.
+        autoconnect: setting_bool(connection, "autoconnect").unwrap_or(true),
+        timestamp: setting_u64(connection, "timestamp").unwrap_or(0),
+        hidden: wireless
+            .and_then(|w| setting_bool(w, "hidden"))
+            .unwrap_or(false),
+        security,
+    })
+}
+
+/// A saved VPN or WireGuard profile's `VpnConnectionInfo`, from its
+/// `GetSettings` reply. `None` for any other kind of profile.
+pub(crate) fn vpn_connection_info(
+    path: &str,
+    settings: &HashMap<String, HashMap<String, OwnedValue>>,
+) -> Option<VpnConnectionInfo> {
+    let connection = settings.get("connection")?;
+    let kind = match setting_str(connection, "type").as_deref() {
+        Some("vpn") => VpnKind::Vpn,
+        Some("wireguard") => VpnKind::WireGuard,
+        _ => return None,
+    };
+
+    Some(VpnConnectionInfo {
+        path: path.to_string(),
+        id: setting_str(connection, "id").unwrap_or_default(),
+        uuid: setting_str(connection, "uuid").unwrap_or_default(),
+        kind,
+        interface_name: setting_str(connection, "interface-name").unwrap_or_default(),
+        // NetworkManager omits `autoconnect` when it's at its default of true.
+        autoconnect: setting_bool(connection, "autoconnect").unwrap_or(true),
+        timestamp: setting_u64(connection, "timestamp").unwrap_or(0),
+    })
+}
+
+/// Whether a profile's `GetSettings` reply describes a hotspot.
+pub(crate) fn is_hotspot_profile(settings: &HashMap<String, HashMap<String, OwnedValue>>) -> bool {
+    settings
+        .get("802-11-wireless")
+        .and_then(|wireless| setting_str(wireless, "mode"))
+        .as_deref()
+        == Some("ap")
+}
+
+/// A saved WireGuard profile as a [`WgConfig`], from its `GetSettings` reply
+/// and its `wireguard` secrets: the inverse of `add_wireguard_connection`.
+pub(crate) fn wireguard_config(
+    settings: &HashMap<String, HashMap<String, OwnedValue>>,
+    secrets: &HashMap<String, HashMap<String, OwnedValue>>,
+) -> Result<WgConfig> {
+    type Dicts = Vec<HashMap<String, OwnedValue>>;
+
+    let wireguard = settings
+        .get("wireguard")
+        .context("Not a WireGuard profile")?;
+    let secret = secrets.get("wireguard");
+    let private_key = secret
+        .and_then(|s| setting_str(s, "private-key"))
+        .or_else(|| setting_str(wireguard, "private-key"))
+        .context("NetworkManager did not hand out the private key")?;
+
+    // Preshared keys come back with the secrets, keyed by the peer's public key.
+    let preshared: HashMap<String, String> = secret
+        .and_then(|s| setting::<Dicts>(s, "peers"))
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|p| {
+            Some((
+                setting_str(p, "public-key")?,
+                setting_str(p, "preshared-key")?,
+            ))
+        })
+        .collect();
+    let peers = setting::<Dicts>(wireguard, "peers")
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|p| {
+            let public_key = setting_str(p, "public-key")?;
+            Some(WgPeerConfig {
+                endpoint: setting_str(p, "endpoint"),
+                allowed_ips: setting(p, "allowed-ips").unwrap_or_default(),
+                preshared_key: preshared
+                    .get(&public_key)
+                    .cloned()
+                    .or_else(|| setting_str(p, "preshared-key")),
+                persistent_keepalive: setting::<u32>(p, "persistent-keepalive")
+                    .filter(|&secs| secs != 0),
+                public_key,
+            })
+        })
+        .collect();
+
+    let mut addresses = Vec::new();
+    let mut dns = Vec::new();
+    let mut dns_search = Vec::new();
+    let mut route_table = None;
+    for family in ["ipv4", "ipv6"] {
+        let Some(section) = settings.get(family) else {
+            continue;
+        };
+        for entry in setting::<Dicts>(section, "address-data").unwrap_or_default() {
+            let ip = setting_str(&entry, "address").and_then(|a| a.parse::<IpAddr>().ok());
+            let prefix = setting::<u32>(&entry, "prefix").and_then(|p| u8::try_from(p).ok());
+            if let (Some(ip), Some(prefix)) = (ip, prefix) {
+                addresses.push((ip, prefix));
+            }
+        }
+        if family == "ipv4" {
+            let words = setting::<Vec<u32>>(section, "dns").unwrap_or_default();
+            dns.extend(words.into_iter().map(|w| IpAddr::from(w.to_ne_bytes())));
+        } else {
+            let raw = setting::<Vec<Vec<u8>>>(section, "dns").unwrap_or_default();
+            dns.extend(
+                raw.into_iter()
+                    .filter_map(|bytes| <[u8; 16]>::try_from(bytes).ok())
+                    .map(IpAddr::from),
+            );
+        }
+        for domain in setting::<Vec<String>>(section, "dns-search").unwrap_or_default() {
+            if !dns_search.contains(&domain) {
+                dns_search.push(domain);
+            }
+        }
+        route_table = route_table.or(setting::<u32>(section, "route-table").filter(|&t| t != 0));
+    }
+
+    let table = if setting_bool(wireguard, "peer-routes") == Some(false) {
+        WgRouteTable::Off
+    } else {
+        route_table.map_or(WgRouteTable::Auto, WgRouteTable::Id)
+    };
+
+    Ok(WgConfig {
+        private_key: private_key.into(),
+        addresses,
+        dns,
+        dns_search,
+        listen_port: setting::<u32>(wireguard, "listen-port")
+            .and_then(|port| u16::try_from(port).ok())
+            .filter(|&port| port != 0),
+        mtu: setting::<u32>(wireguard, "mtu").filter(|&mtu| mtu != 0),
+        fwmark: setting::<u32>(wireguard, "fwmark").filter(|&mark| mark != 0),
+        table,
+        peers,
+    })
+}
+
+/// The D-Bus service of NetworkManager's OpenVPN plugin.
+const OPENVPN_SERVICE: &str = "org.freedesktop.NetworkManager.openvpn";
+
+/// Where NetworkManager looks for the `.name` files VPN plugins install.
+const VPN_PLUGIN_DIRS: [&str; 2] = ["/usr/lib/NetworkManager/VPN", "/etc/NetworkManager/VPN"];
+
+/// Whether a VPN plugin for `service` is installed: one of the `.name` files
+/// in `dirs` declares it.
+fn vpn_plugin_installed(dirs: &[&Path], service: &str) -> bool {
+    dirs.iter()
+        .filter_map(|dir| std::fs::read_dir(dir).ok())
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "name"))
+        .filter_map(|entry| std::fs::read_to_string(entry.path()).ok())
+        .any(|text| {
+            text.lines().any(|line| {
+                line.split_once('=')
+                    .is_some_and(|(key, value)| key.trim() == "service" && value.trim() == service)
+            })
+        })
+}
+
+/// The settings of an OpenVPN profile, as the plugin's `vpn.data` keys.
+fn openvpn_settings(
+    id: &str,
+    cfg: &OvpnConfig,
+) -> HashMap<&'static str, HashMap<&'static str, Value<'static>>> {
+    let path = |p: &Path| p.display().to_string();
+    let mut data: HashMap<String, String> = HashMap::new();
+    let mut set = |key: &str, value: String| {
+        data.insert(key.to_string(), value);
+    };
+
+    set("remote", cfg.remotes.join(", "));
+    let connection_type = match (cfg.cert.is_some(), cfg.user_pass) {
+        (true, true) => "password-tls",
+        (true, false) => "tls",
+        (false, _) => "password",
+    };
+    set("connection-type", connection_type.to_string());
+    set("ca", path(&cfg.ca));
+    if let (Some(cert), Some(key)) = (&cfg.cert, &cfg.key) {
+        set("cert", path(cert));
+        set("key", path(key));
+    }
+    if let Some(ta) = &cfg.tls_auth {
+        set("ta", path(ta));
+        if let Some(dir) = cfg.key_direction {
+            set("ta-dir", dir.to_string());
+        }
+    }
+    if let Some(tls_crypt) = &cfg.tls_crypt {
+        set("tls-crypt", path(tls_crypt));
+    }
+    if let Some(dev_type) = &cfg.dev_type {
+        set("dev-type", dev_type.clone());
+    }
+    for (key, value) in [
+        ("cipher", &cfg.cipher),
+        ("data-ciphers", &cfg.data_ciphers),
+        ("auth", &cfg.auth),
+        ("remote-cert-tls", &cfg.remote_cert_tls),
+        ("username", &cfg.username),
+    ] {
+        if let Some(value) = value {
+            set(key, value.clone());
+        }
+    }
+
+    let mut secrets: HashMap<String, String> = HashMap::new();
+    if cfg.user_pass {
+        // 0 keeps a supplied password with the profile; 2 asks every time.
+        match &cfg.password {
+            Some(password) => {
+                set("password-flags", "0".to_string());
+                secrets.insert("password".to_string(), password.clone());
+            }
+            None => set("password-flags", "2".to_string()),
+        }
+    }
+
+    let mut connection: HashMap<&str, Value> = HashMap::new();
+    connection.insert("type", Value::from("vpn"));
+    connection.insert("id", Value::from(id.to_string()));
+    connection.insert("uuid", Value::from(cfg.uuid.clone()));
+    // Don't surprise-connect on import; the user toggles it in the modal.
+    connection.insert("autoconnect", Value::from(false));
+
+    let mut vpn: HashMap<&str, Value> = HashMap::new();
+    vpn.insert("service-type", Value::from(OPENVPN_SERVICE));
+    vpn.insert("data", Value::from(data));
+    if !secrets.is_empty() {
+        vpn.insert("secrets", Value::from(secrets));
+    }
+
+    HashMap::from([("connection", connection), ("vpn", vpn)])
+}
+
 /// Encodes the IPv4 entries of `dns` for NM's `ipv4.dns` property (`au`). NM
 /// stores each value straight into an `in_addr_t`, so the integer's in-memory
 /// bytes must equal the address in network order — that's the native-endian
@@ -73,6 +370,24 @@ fn ipv6_dns_bytes(dns: &[IpAddr]) -> Vec<Vec<u8>> {
         .collect()
 }
 
+/// One entry of NM's `wireguard.peers` (`aa{sv}`).
+fn wireguard_peer(peer: &WgPeerConfig) -> HashMap<&'static str, Value<'static>> {
+    let mut entry: HashMap<&str, Value> = HashMap::new();
+    entry.insert("public-key", Value::from(peer.public_key.clone()));
+    if let Some(endpoint) = &peer.endpoint {
+        entry.insert("endpoint", Value::from(endpoint.clone()));
+    }
+    entry.insert("allowed-ips", Value::from(peer.allowed_ips.clone()));
+    if let Some(psk) = &peer.preshared_key {
+        entry.insert("preshared-key", Value::from(psk.clone()));
+        entry.insert("preshared-key-flags", Value::from(0u32));
+    }
+    if let Some(keepalive) = peer.persistent_keepalive {
+        entry.insert("persistent-keepalive", Value::from(keepalive));
+    }
+    entry
+}
+
 /// Saved WiFi profiles together with the `(path, VersionId)` list they were
 /// built from, so an unchanged list can be served without re-reading every
 /// profile's settings.
@@ -117,6 +432,27 @@ impl NMClient {
         })
     }
 
+    /// Serves the VPN secret agent on this client's connection and registers
+    /// it with NetworkManager, which drops it when the connection closes.
+    pub async fn register_secret_agent(&self, auth: AuthAgent) -> Result<()> {
+        self.connection
+            .object_server()
+            .at(agent::AGENT_PATH, agent::SecretAgent::new(auth))
+            .await?;
+        let manager = Proxy::new(
+            &self.connection,
+            NM_BUS_NAME,
+            AGENT_MANAGER_PATH,
+            "org.freedesktop.NetworkManager.AgentManager",
+        )
+        .await?;
+        manager
+            .call_method("Register", &(agent::AGENT_IDENTIFIER,))
+            .await
+            .context("Could not register as NetworkManager's secret agent")?;
+        Ok(())
+    }
+
     /// Get the D-Bus connection
     pub fn connection(&self) -> &Connection {
         &self.connection
@@ -276,6 +612,102 @@ impl NMClient {
         }))
     }
 
+    /// Get the IPv6 configuration in use on the given device. Returns `None`
+    /// when the device has no active IPv6 config (e.g. `ipv6.method disabled`).
+    pub async fn get_ip6_info(&self, device_path: &str) -> Result<Option<Ip6Info>> {
+        let device_proxy = Proxy::new(
+            &self.connection,
+            NM_BUS_NAME,
+            device_path,
+            "org.freedesktop.NetworkManager.Device",
+        )
+        .await?;
+
+        let ip6_path: OwnedObjectPath = device_proxy.get_property("Ip6Config").await?;
+        if ip6_path.as_str() == "/" {
+            return Ok(None);
+        }
+
+        let ip6_proxy = Proxy::new(
+            &self.connection,
+            NM_BUS_NAME,
+            ip6_path.as_str(),
+            "org.freedesktop.NetworkManager.IP6Config",
+        )
+        .await?;
+
+        let address_data: Vec<HashMap<String, OwnedValue>> =
+            ip6_proxy.get_property("AddressData").await?;
+        let gateway: String = ip6_proxy.get_property("Gateway").await?;
+        // IP6Config has no NameserverData; servers come as raw 16-byte arrays.
+        let nameservers: Vec<Vec<u8>> = ip6_proxy.get_property("Nameservers").await?;
+
+        let addresses = address_data
+            .into_iter()
+            .filter_map(|m| {
+                let addr: String = m.get("address")?.try_clone().ok()?.try_into().ok()?;
+                let prefix: u32 = m
+                    .get("prefix")
+                    .and_then(|v| v.try_clone().ok())
+                    .and_then(|v| v.try_into().ok())
+                    .unwrap_or(0);
+                Some((addr, prefix))
+            })
+            .collect();
+
+        let nameservers = nameservers
+            .into_iter()
+            .filter_map(|bytes| <[u8; 16]>::try_from(bytes).ok())
+            .map(|octets| std::net::Ipv6Addr::from(octets).to_string())
+            .collect();
+
+        Ok(Some(Ip6Info {
+            addresses,
+            gateway: if gateway.is_empty() {
+                None
+            } else {
+                Some(gateway)
+            },
+            nameservers,
+        }))
+    }
+
+    /// Get the DHCPv4 lease behind the device's address. Returns `None` when
+    /// the address did not come from DHCP (static config, or no lease yet).
+    pub async fn get_dhcp4_lease(&self, device_path: &str) -> Result<Option<Dhcp4Lease>> {
+        let device_proxy = Proxy::new(
+            &self.connection,
+            NM_BUS_NAME,
+            device_path,
+            "org.freedesktop.NetworkManager.Device",
+        )
+        .await?;
+
+        let dhcp_path: OwnedObjectPath = device_proxy.get_property("Dhcp4Config").await?;
+        if dhcp_path.as_str() == "/" {
+            return Ok(None);
+        }
+
+        let dhcp_proxy = Proxy::new(
+            &self.connection,
+            NM_BUS_NAME,
+            dhcp_path.as_str(),
+            "org.freedesktop.NetworkManager.DHCP4Config",
+        )
+        .await?;
+
+        let options: HashMap<String, OwnedValue> = dhcp_proxy.get_property("Options").await?;
+        let options = options
+            .into_iter()
+            .filter_map(|(k, v)| {
+                let v: String = v.try_into().ok()?;
+                Some((k, v))
+            })
+            .collect();
+
+        Ok(Some(Dhcp4Lease::from_options(options)))
+    }
+
     /// NetworkManager's global connectivity check. Triggers a fresh probe and
     /// returns the result: full internet, captive portal, limited, or none.
     pub async fn check_connectivity(&self) -> Result<Connectivity> {
@@ -291,6 +723,21 @@ impl NMClient {
         Ok(Connectivity::from(state))
     }
 
+    /// The URL NetworkManager fetches for its connectivity check, or `None`
+    /// when checking is disabled.
+    pub async fn connectivity_check_uri(&self) -> Result<Option<String>> {
+        let proxy = Proxy::new(
+            &self.connection,
+            NM_BUS_NAME,
+            NM_PATH,
+            "org.freedesktop.NetworkManager",
+        )
+        .await?;
+
+        let uri: String = proxy.get_property("ConnectivityCheckUri").await?;
+        Ok((!uri.is_empty()).then_some(uri))
+    }
+
     /// Get device state
     pub async fn get_device_state(&self, device_path: &str) -> Result<DeviceState> {
         let proxy = Proxy::new(
@@ -437,6 +884,15 @@ impl NMClient {
         Ok(None)
     }
 
+    /// Reads a saved WireGuard profile back into a config, keys included.
+    pub async fn get_wireguard_config(&self, connection_path: &str) -> Result<WgConfig> {
+        let settings = self.get_connection_settings(connection_path).await?;
+        let secrets = self
+            .get_connection_secrets(connection_path, "wireguard")
+            .await?;
+        wireguard_config(&settings, &secrets)
+    }
+
     /// Get WiFi connection profiles, reading the object graph once.
     pub async fn get_wifi_connections(&self) -> Result<Arc<[ConnectionInfo]>> {
         let snapshot = NmSnapshot::fetch(self).await?;
@@ -449,8 +905,8 @@ impl NMClient {
     /// refresh once a machine has accumulated a few hundred of them. Profiles
     /// change rarely, so the previous result is reused until `snapshot` reports
     /// one added, removed, or edited.
-    pub async fn wifi_connections(&self, snapshot: &NmSnapshot) -> Result<Arc<[ConnectionInfo]>> {
-        let versions = snapshot.connection_versions();
+    pub async fn wifi_connections(&self, snapshot: &dyn Snapshot) -> Result<Arc<[ConnectionInfo]>> {
+        let versions = snapshot.profile_versions();
         if let Some(cached) = self.cached_wifi_connections(&versions) {
             return Ok(cached);
         }
@@ -460,46 +916,7 @@ impl NMClient {
             let Ok(settings) = self.get_connection_settings(path).await else {
                 continue;
             };
-            let Some(connection) = settings.get("connection") else {
-                continue;
-            };
-            if setting_str(connection, "type").as_deref() != Some("802-11-wireless") {
-                continue;
-            }
-
-            let id = setting_str(connection, "id").unwrap_or_default();
-            let wireless = settings.get("802-11-wireless");
-            // NetworkManager stores the SSID as raw bytes; the profile name is
-            // the best stand-in when it is absent.
-            let ssid = wireless
-                .and_then(|w| w.get("ssid"))
-                .and_then(|v| v.try_clone().ok())
-                .and_then(|v| Vec::<u8>::try_from(v).ok())
-                .map(|bytes| String::from_utf8_lossy(&bytes).to_string())
-                .unwrap_or_else(|| id.clone());
-            let security = if settings.contains_key("802-11-wireless-security") {
-                if settings.contains_key("802-1x") {
-                    SecurityType::Enterprise
-                } else {
-                    SecurityType::WPA
-                }
-            } else {
-                SecurityType::Open
-            };
-
-            wifi_connections.push(ConnectionInfo {
-                path: path.clone(),
-                id,
-                uuid: setting_str(connection, "uuid").unwrap_or_default(),
-                ssid,
-                // NetworkManager omits `autoconnect` when it is at its default of true.
-                autoconnect: setting_bool(connection, "autoconnect").unwrap_or(true),
-                timestamp: setting_u64(connection, "timestamp").unwrap_or(0),
-                hidden: wireless
-                    .and_then(|w| setting_bool(w, "hidden"))
-                    .unwrap_or(false),
-                security,
-            });
+            wifi_connections.extend(wifi_connection_info(path, &settings));
         }
 
         // Sort by timestamp (most recent first)
@@ -556,47 +973,13 @@ impl NMClient {
             let Ok(settings) = self.get_connection_settings(conn_path.as_str()).await else {
                 continue;
             };
-            let Some(connection) = settings.get("connection") else {
-                continue;
-            };
-
-            let kind = match setting_str(connection, "type").as_deref() {
-                Some("vpn") => VpnKind::Vpn,
-                Some("wireguard") => VpnKind::WireGuard,
-                _ => continue,
-            };
-
-            vpns.push(VpnConnectionInfo {
-                path: conn_path.to_string(),
-                id: setting_str(connection, "id").unwrap_or_default(),
-                uuid: setting_str(connection, "uuid").unwrap_or_default(),
-                kind,
-                interface_name: setting_str(connection, "interface-name").unwrap_or_default(),
-                // NetworkManager omits `autoconnect` when it's at its default of true.
-                autoconnect: setting_bool(connection, "autoconnect").unwrap_or(true),
-                timestamp: setting_u64(connection, "timestamp").unwrap_or(0),
-            });
+            vpns.extend(vpn_connection_info(conn_path.as_str(), &settings));
         }
 
         vpns.sort_by_key(|v| v.id.to_lowercase());
         Ok(vpns)
     }
 
-    /// Names of the VPN / WireGuard profiles that are currently activated.
-    /// Drives the always-on VPN indicator without opening the modal.
-    pub fn active_vpn_names(&self, snapshot: &NmSnapshot) -> Vec<String> {
-        let mut names: Vec<String> = snapshot
-            .active_connections()
-            .into_iter()
-            .filter(|info| info.state == ActiveConnectionState::Activated)
-            .filter(|info| info.connection_type == "vpn" || info.connection_type == "wireguard")
-            .map(|info| info.id)
-            .collect();
-
-        names.sort_by_key(|n| n.to_lowercase());
-        names
-    }
-
     /// First IPv4 address (as `addr/prefix`) assigned to an active connection,
     /// read from its `Ip4Config`. `None` when the tunnel carries no IPv4 lease.
     pub async fn active_connection_ipv4(&self, active_conn_path: &str) -> Result<Option<String>> {
@@ -666,25 +1049,24 @@ impl NMClient {
         conn.insert("autoconnect", Value::from(false));
         settings.insert("connection", conn);
 
-        // wireguard (single peer)
-        let mut peer: HashMap<&str, Value> = HashMap::new();
-        peer.insert("public-key", Value::from(cfg.peer.public_key.clone()));
-        if let Some(endpoint) = &cfg.peer.endpoint {
-            peer.insert("endpoint", Value::from(endpoint.clone()));
+        // wireguard
+        let peers: Vec<HashMap<&str, Value>> = cfg.peers.iter().map(wireguard_peer).collect();
+        let mut wireguard: HashMap<&str, Value> = HashMap::new();
+        wireguard.insert("private-key", Value::from(cfg.private_key.as_str()));
+        wireguard.insert("private-key-flags", Value::from(0u32));
+        wireguard.insert("peers", Value::from(peers));
+        if let Some(port) = cfg.listen_port {
+            wireguard.insert("listen-port", Value::from(u32::from(port)));
         }
-        peer.insert("allowed-ips", Value::from(cfg.peer.allowed_ips.clone()));
-        if let Some(psk) = &cfg.peer.preshared_key {
-            peer.insert("preshared-key", Value::from(psk.clone()));
-            peer.insert("preshared-key-flags", Value::from(0u32));
+        if let Some(mtu) = cfg.mtu {
+            wireguard.insert("mtu", Value::from(mtu));
         }
-        if let Some(keepalive) = cfg.peer.persistent_keepalive {
-            peer.insert("persistent-keepalive", Value::from(keepalive));
+        if let Some(fwmark) = cfg.fwmark {
+            wireguard.insert("fwmark", Value::from(fwmark));
+        }
+        if cfg.table == WgRouteTable::Off {
+            wireguard.insert("peer-routes", Value::from(false));
         }
-
-        let mut wireguard: HashMap<&str, Value> = HashMap::new();
-        wireguard.insert("private-key", Value::from(cfg.private_key.clone()));
-        wireguard.insert("private-key-flags", Value::from(0u32));
-        wireguard.insert("peers", Value::from(vec![peer]));
         settings.insert("wireguard", wireguard);
 
         // Split interface addresses by family for the ipv4/ipv6 sections.
@@ -702,6 +1084,7 @@ impl NMClient {
         }
 
         // ipv4
+        let has_v4 = !v4.is_empty();
         let mut ipv4: HashMap<&str, Value> = HashMap::new();
         if v4.is_empty() {
             ipv4.insert("method", Value::from("disabled"));
@@ -712,6 +1095,12 @@ impl NMClient {
             if !dns4.is_empty() {
                 ipv4.insert("dns", Value::from(dns4));
             }
+            if !cfg.dns_search.is_empty() {
+                ipv4.insert("dns-search", Value::from(cfg.dns_search.clone()));
+            }
+        }
+        if let WgRouteTable::Id(table) = cfg.table {
+            ipv4.insert("route-table", Value::from(table));
         }
         settings.insert("ipv4", ipv4);
 
@@ -728,6 +1117,14 @@ impl NMClient {
             if !dns6.is_empty() {
                 ipv6.insert("dns", Value::from(dns6));
             }
+            // Only reached by an IPv6-only tunnel's domains; the IPv4 section
+            // holds them otherwise.
+            if !has_v4 && !cfg.dns_search.is_empty() {
+                ipv6.insert("dns-search", Value::from(cfg.dns_search.clone()));
+            }
+        }
+        if let WgRouteTable::Id(table) = cfg.table {
+            ipv6.insert("route-table", Value::from(table));
         }
         settings.insert("ipv6", ipv6);
 
@@ -735,6 +1132,33 @@ impl NMClient {
         Ok(path)
     }
 
+    /// Saves an OpenVPN profile for NetworkManager's OpenVPN plugin (without
+    /// activating it). Fails up front when the plugin is not installed, as
+    /// the profile could never connect. Returns the new connection's path.
+    pub async fn add_openvpn_connection(
+        &self,
+        id: &str,
+        cfg: &OvpnConfig,
+    ) -> Result<OwnedObjectPath> {
+        if !vpn_plugin_installed(&VPN_PLUGIN_DIRS.map(Path::new), OPENVPN_SERVICE) {
+            bail!(
+                "NetworkManager's OpenVPN plugin is not installed; install NetworkManager-openvpn (network-manager-openvpn on Debian/Ubuntu)"
+            );
+        }
+
+        let proxy = Proxy::new(
+            &self.connection,
+            NM_BUS_NAME,
+            "/org/freedesktop/NetworkManager/Settings",
+            "org.freedesktop.NetworkManager.Settings",
+        )
+        .await?;
+        let path: OwnedObjectPath = proxy
+            .call("AddConnection", &(openvpn_settings(id, cfg),))
+            .await?;
+        Ok(path)
+    }
+
     /// Connect to a network using an existing connection profile
     pub async fn activate_connection(
         &self,
@@ -764,6 +1188,50 @@ impl NMClient {
         Ok(active_connection)
     }
 
+    /// Overrides the device's IPv4 nameservers until it next reconnects. Only
+    /// the applied (runtime) settings change; the saved profile is untouched.
+    pub async fn set_runtime_dns(&self, device_path: &str, servers: &[Ipv4Addr]) -> Result<()> {
+        self.reapply_with(device_path, |settings| {
+            let ipv4 = settings.entry("ipv4".to_string()).or_default();
+            // NM wants each address as a u32 in network byte order.
+            let dns: Vec<u32> = servers
+                .iter()
+                .map(|ip| u32::from_ne_bytes(ip.octets()))
+                .collect();
+            ipv4.insert("dns".to_string(), Value::from(dns).try_to_owned()?);
+            ipv4.insert(
+                "ignore-auto-dns".to_string(),
+                Value::from(true).try_to_owned()?,
+            );
+            Ok(())
+        })
+        .await
+    }
+
+    /// Reads the device's applied connection, lets `edit` change it, and
+    /// hands it back through `Reapply`.
+    async fn reapply_with(
+        &self,
+        device_path: &str,
+        edit: impl FnOnce(&mut HashMap<String, HashMap<String, OwnedValue>>) -> Result<()>,
+    ) -> Result<()> {
+        let proxy = Proxy::new(
+            &self.connection,
+            NM_BUS_NAME,
+            device_path,
+            "org.freedesktop.NetworkManager.Device",
+        )
+        .await?;
+
+        let (mut settings, version_id): (HashMap<String, HashMap<String, OwnedValue>>, u64) =
+            proxy.call("GetAppliedConnection", &(0u32,)).await?;
+        edit(&mut settings)?;
+        proxy
+            .call::<_, _, ()>("Reapply", &(settings, version_id, 0u32))
+            .await?;
+        Ok(())
+    }
+
     /// Connect to a new network (creates connection profile)
     pub async fn add_and_activate_connection(
         &self,
@@ -1002,55 +1470,6 @@ impl NMClient {
         Ok(proxy.get_property("ActiveConnections").await?)
     }
 
-    /// Details of the first activated wired (`802-3-ethernet`) connection, if
-    /// any. Resolved independently of the WiFi device so link status can be
-    /// shown even while the WiFi radio is off. Includes the device interface
-    /// and primary IPv4 address when they can be read; those are best-effort
-    /// and left `None` rather than failing the whole lookup.
-    pub async fn active_ethernet(&self, snapshot: &NmSnapshot) -> Result<Option<EthernetInfo>> {
-        for info in snapshot.active_connections() {
-            if info.state != ActiveConnectionState::Activated {
-                continue;
-            }
-
-            if info.connection_type != "802-3-ethernet" {
-                continue;
-            }
-
-            let device_path = info.devices.first().cloned();
-            let interface = device_path
-                .as_deref()
-                .and_then(|path| snapshot.device_interface(path));
-            let ipv4 = match &device_path {
-                Some(path) => self
-                    .get_ip4_info(path)
-                    .await
-                    .ok()
-                    .flatten()
-                    .and_then(|ip| ip.addresses.into_iter().next().map(|(addr, _)| addr)),
-                None => None,
-            };
-
-            return Ok(Some(EthernetInfo {
-                id: info.id,
-                interface,
-                ipv4,
-            }));
-        }
-
-        Ok(None)
-    }
-
-    /// The connection NetworkManager is currently using as the default route —
-    /// the link that actually carries internet traffic. `None` when NM reports
-    /// no primary connection (e.g. nothing is online).
-    pub fn primary_link(&self, snapshot: &NmSnapshot) -> Option<PrimaryLink> {
-        snapshot.primary_connection().map(|info| PrimaryLink {
-            id: info.id,
-            kind: LinkKind::from_nm_type(&info.connection_type),
-        })
-    }
-
     /// Activated WiFi / Ethernet connections paired with their device, used to
     /// reason about and reshuffle the default route. VPNs, bridges and other
     /// virtual links are skipped.
@@ -1378,6 +1797,26 @@ impl NMClient {
         Ok(result.1)
     }
 
+    /// The hotspot active on `device_path`: an active connection on that
+    /// device whose profile is in `ap` mode.
+    pub async fn active_hotspot(&self, device_path: &str) -> Result<Option<ActiveConnectionInfo>> {
+        for conn_path in self.get_active_connections().await? {
+            let Ok(info) = self.get_active_connection_info(conn_path.as_str()).await else {
+                continue;
+            };
+            if !info.devices.iter().any(|d| d == device_path) {
+                continue;
+            }
+            let Ok(settings) = self.get_connection_settings(&info.connection_path).await else {
+                continue;
+            };
+            if is_hotspot_profile(&settings) {
+                return Ok(Some(info));
+            }
+        }
+        Ok(None)
+    }
+
     /// Stop hotspot (deactivate connection)
     pub async fn deactivate_connection(&self, active_connection_path: &str) -> Result<()> {
         let proxy = Proxy::new(
@@ -1650,3 +2089,101 @@ mod dns_encoding_tests {
         assert_eq!(out[0][15], 0x01);
     }
 }
+
+#[cfg(test)]
+mod openvpn_tests {
+    use super::*;
+
+    #[test]
+    fn openvpn_settings_pick_the_connection_type_and_keep_secrets_apart() {
+        let cfg = OvpnConfig {
+            uuid: "5f3b2f0c-2b7e-4f43-9d0e-8f1a8c1b7a11".into(),
+            remotes: vec![
+                "a.example.com:1194:udp".into(),
+                "b.example.com:443:tcp".into(),
+            ],
+            dev_type: None,
+            ca: "/ca.crt".into(),
+            cert: Some("/me.crt".into()),
+            key: Some("/me.key".into()),
+            tls_auth: Some("/ta.key".into()),
+            key_direction: Some(1),
+            tls_crypt: None,
+            user_pass: true,
+            username: Some("alice".into()),
+            password: Some("hunter2".into()),
+            cipher: Some("AES-256-GCM".into()),
+            data_ciphers: None,
+            auth: None,
+            remote_cert_tls: Some("server".into()),
+        };
+        let settings = openvpn_settings("work", &cfg);
+        let vpn = &settings["vpn"];
+        assert_eq!(
+            vpn["service-type"],
+            Value::from("org.freedesktop.NetworkManager.openvpn")
+        );
+        let data = HashMap::<String, String>::try_from(vpn["data"].try_clone().unwrap()).unwrap();
+        assert_eq!(
+            data["remote"],
+            "a.example.com:1194:udp, b.example.com:443:tcp"
+        );
+        assert_eq!(data["connection-type"], "password-tls");
+        assert_eq!(
+            settings["connection"]["uuid"],
+            Value::from("5f3b2f0c-2b7e-4f43-9d0e-8f1a8c1b7a11")
+        );
+        assert_eq!(data["ta"], "/ta.key");
+        assert_eq!(data["ta-dir"], "1");
+        assert_eq!(data["username"], "alice");
+        assert_eq!(data["password-flags"], "0");
+        assert!(
+            !data.contains_key("password"),
+            "secrets stay out of vpn.data"
+        );
+        let secrets =
+            HashMap::<String, String>::try_from(vpn["secrets"].try_clone().unwrap()).unwrap();
+        assert_eq!(secrets["password"], "hunter2");
+
+        // Without a certificate or a stored password.
+        let settings = openvpn_settings(
+            "work",
+            &OvpnConfig {
+                cert: None,
+                key: None,
+                password: None,
+                ..cfg
+            },
+        );
+        let data =
+            HashMap::<String, String>::try_from(settings["vpn"]["data"].try_clone().unwrap())
+                .unwrap();
+        assert_eq!(data["connection-type"], "password");
+        assert_eq!(data["password-flags"], "2");
+        assert!(!settings["vpn"].contains_key("secrets"));
+    }
+
+    #[test]
+    fn vpn_plugins_are_found_by_their_name_files() {
+        let dir = std::env::temp_dir().join(format!("wlctl-vpn-plugins-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("nm-openconnect-service.name"),
+            "[VPN Connection]\nname=openconnect\nservice=org.freedesktop.NetworkManager.openconnect\n",
+        )
+        .unwrap();
+        let dirs = [dir.as_path(), Path::new("/nonexistent")];
+        let before = vpn_plugin_installed(&dirs, OPENVPN_SERVICE);
+
+        std::fs::write(
+            dir.join("nm-openvpn-service.name"),
+            "[VPN Connection]\nname=openvpn\nservice = org.freedesktop.NetworkManager.openvpn\n",
+        )
+        .unwrap();
+        let after = vpn_plugin_installed(&dirs, OPENVPN_SERVICE);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(!before);
+        assert!(after);
+    }
+}
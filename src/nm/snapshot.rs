@@ -1,10 +1,18 @@
 // Single-read view of NetworkManager's exported object graph.
 
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
 
 use anyhow::Result;
-use zbus::Proxy;
+use async_trait::async_trait;
+use futures::{Stream, StreamExt};
+use tokio::task::JoinHandle;
+use zbus::message::Type as MessageType;
 use zbus::zvariant::{OwnedObjectPath, OwnedValue};
+use zbus::{MatchRule, Message, MessageStream, Proxy};
+
+use crate::backend::{SharedSnapshot, Snapshot, Watch};
 
 use super::{
     AccessPointInfo, ActiveConnectionInfo, ActiveConnectionState, DeviceState, NM_BUS_NAME,
@@ -15,6 +23,10 @@ use super::{
 /// path rather than beneath its own `/org/freedesktop/NetworkManager` tree.
 const OBJECT_MANAGER_PATH: &str = "/org/freedesktop";
 
+/// Standard D-Bus interfaces whose signals keep a watched snapshot current.
+const OBJECT_MANAGER_INTERFACE: &str = "org.freedesktop.DBus.ObjectManager";
+const PROPERTIES_INTERFACE: &str = "org.freedesktop.DBus.Properties";
+
 /// D-Bus interface names read out of a snapshot.
 pub(crate) mod interface {
     pub const ACCESS_POINT: &str = "org.freedesktop.NetworkManager.AccessPoint";
@@ -30,11 +42,12 @@ const WIFI_DEVICE_TYPE: u32 = 2;
 
 /// Object path -> interface name -> property name -> value, as returned by a
 /// single `GetManagedObjects` call.
-type ManagedObjects = HashMap<OwnedObjectPath, HashMap<String, HashMap<String, OwnedValue>>>;
+pub(crate) type ManagedObjects =
+    HashMap<OwnedObjectPath, HashMap<String, HashMap<String, OwnedValue>>>;
 
 /// Reads one property out of a D-Bus property map, yielding `None` when it is
 /// absent or not convertible to `T`.
-fn prop<T>(props: &HashMap<String, OwnedValue>, key: &str) -> Option<T>
+pub(crate) fn prop<T>(props: &HashMap<String, OwnedValue>, key: &str) -> Option<T>
 where
     T: TryFrom<OwnedValue>,
 {
@@ -97,15 +110,16 @@ pub(crate) fn active_connection_info(
 
 /// A point-in-time view of every object NetworkManager exports.
 ///
-/// The UI re-reads every access point and saved profile on a timer, and reading
-/// them one object at a time costs a D-Bus round trip each — and a zbus proxy
+/// The UI re-reads every access point and saved profile on each refresh, and
+/// reading them one object at a time costs a D-Bus round trip each — and a zbus proxy
 /// property read costs more than that, since it also registers a match rule and
 /// spawns a cache task it immediately drops. One `GetManagedObjects` replaces
 /// all of them, so a single snapshot can serve an entire refresh, and every
 /// value read from it is consistent with every other.
 ///
 /// Deliberately neither `Clone` nor `Debug`: copying the object graph is the
-/// cost this type exists to avoid, and printing it would dump the whole bus.
+/// cost this type exists to avoid, so [`NmSnapshot::copy`] is spelled out
+/// where it is needed, and printing it would dump the whole bus.
 pub struct NmSnapshot {
     objects: ManagedObjects,
 }
@@ -125,15 +139,129 @@ impl NmSnapshot {
         })
     }
 
+    /// A snapshot of an object graph read some other way, e.g. from a
+    /// recording.
+    pub(crate) fn from_objects(objects: ManagedObjects) -> Self {
+        Self { objects }
+    }
+
+    pub(crate) fn objects(&self) -> &ManagedObjects {
+        &self.objects
+    }
+
+    /// Copies the graph value by value. `OwnedValue` only fails to clone file
+    /// descriptors, which NetworkManager never exports as properties.
+    pub(crate) fn copy(&self) -> Self {
+        let objects = self
+            .objects
+            .iter()
+            .map(|(path, interfaces)| {
+                let interfaces = interfaces
+                    .iter()
+                    .map(|(name, props)| {
+                        let props = props
+                            .iter()
+                            .filter_map(|(key, value)| Some((key.clone(), value.try_clone().ok()?)))
+                            .collect();
+                        (name.clone(), props)
+                    })
+                    .collect();
+                (path.clone(), interfaces)
+            })
+            .collect();
+        Self { objects }
+    }
+
+    /// Patches the object graph with one incremental change, returning whether
+    /// anything a reader could observe actually differs afterwards.
+    ///
+    /// Property updates for an object or interface the snapshot has never seen
+    /// are dropped: its `InterfacesAdded` carries the full property set, and a
+    /// half-described object would read worse than a missing one.
+    pub(crate) fn apply(&mut self, change: SnapshotChange) -> bool {
+        match change {
+            SnapshotChange::InterfacesAdded { path, interfaces } => {
+                let object = self.objects.entry(path).or_default();
+                let mut changed = false;
+                for (name, props) in interfaces {
+                    if object.get(&name) != Some(&props) {
+                        object.insert(name, props);
+                        changed = true;
+                    }
+                }
+                changed
+            }
+            SnapshotChange::InterfacesRemoved { path, interfaces } => {
+                let Some(object) = self.objects.get_mut(&path) else {
+                    return false;
+                };
+                let mut changed = false;
+                for name in &interfaces {
+                    changed |= object.remove(name).is_some();
+                }
+                if object.is_empty() {
+                    self.objects.remove(&path);
+                }
+                changed
+            }
+            SnapshotChange::PropertiesChanged {
+                path,
+                interface,
+                changed: updates,
+                invalidated,
+            } => {
+                let Some(props) = self
+                    .objects
+                    .get_mut(&path)
+                    .and_then(|object| object.get_mut(&interface))
+                else {
+                    return false;
+                };
+                let mut changed = false;
+                for (key, value) in updates {
+                    if props.get(&key) != Some(&value) {
+                        props.insert(key, value);
+                        changed = true;
+                    }
+                }
+                // NetworkManager always sends values, but an invalidated
+                // property can no longer be trusted, so it reads as absent.
+                for key in &invalidated {
+                    changed |= props.remove(key).is_some();
+                }
+                changed
+            }
+        }
+    }
+}
+
+impl Snapshot for NmSnapshot {
+    /// Every exported object as JSON (path -> interface -> property), for
+    /// diagnostics bundles. Secret-looking properties are redacted.
+    fn to_json(&self) -> serde_json::Value {
+        let objects: std::collections::BTreeMap<&str, serde_json::Value> = self
+            .objects
+            .iter()
+            .map(|(path, interfaces)| {
+                let interfaces: std::collections::BTreeMap<&String, serde_json::Value> = interfaces
+                    .iter()
+                    .map(|(name, props)| (name, super::dump::properties_json(props)))
+                    .collect();
+                (path.as_str(), serde_json::json!(interfaces))
+            })
+            .collect();
+        serde_json::json!(objects)
+    }
+
     /// Whether the WiFi radio is enabled.
-    pub fn wireless_enabled(&self) -> Option<bool> {
+    fn wireless_enabled(&self) -> Option<bool> {
         let manager = self.interface_props(super::NM_PATH, interface::NETWORK_MANAGER)?;
         prop(manager, "WirelessEnabled")
     }
 
     /// WiFi devices, in the order NetworkManager lists them so the adapter
     /// table keeps a stable row order between refreshes.
-    pub fn wifi_devices(&self) -> Vec<OwnedObjectPath> {
+    fn wifi_devices(&self) -> Vec<String> {
         let Some(manager) = self.interface_props(super::NM_PATH, interface::NETWORK_MANAGER) else {
             return Vec::new();
         };
@@ -146,11 +274,12 @@ impl NmSnapshot {
                     .and_then(|props| prop::<u32>(props, "DeviceType"))
                     == Some(WIFI_DEVICE_TYPE)
             })
+            .map(|path| path.to_string())
             .collect()
     }
 
     /// A device's kernel interface name, e.g. `wlan0`.
-    pub fn device_interface(&self, device_path: &str) -> Option<String> {
+    fn device_interface(&self, device_path: &str) -> Option<String> {
         prop(
             self.interface_props(device_path, interface::DEVICE)?,
             "Interface",
@@ -158,47 +287,33 @@ impl NmSnapshot {
     }
 
     /// A device's current NetworkManager state.
-    pub fn device_state(&self, device_path: &str) -> Option<DeviceState> {
+    fn device_state(&self, device_path: &str) -> Option<DeviceState> {
         let props = self.interface_props(device_path, interface::DEVICE)?;
         prop::<u32>(props, "State").map(DeviceState::from)
     }
 
-    fn device_props(&self, path: &OwnedObjectPath) -> Option<&HashMap<String, OwnedValue>> {
-        self.objects.get(path)?.get(interface::DEVICE)
-    }
-
-    /// Access points the device can currently see: deduplicated by SSID keeping
-    /// the strongest signal, then sorted strongest first.
-    pub fn visible_networks(&self, device_path: &str) -> Vec<AccessPointInfo> {
+    /// Every access point (BSSID) the device can currently see, hidden ones
+    /// included, in the order NetworkManager lists them.
+    fn access_points(&self, device_path: &str) -> Vec<AccessPointInfo> {
         let Some(device) = self.interface_props(device_path, interface::DEVICE_WIRELESS) else {
             return Vec::new();
         };
         let ap_paths: Vec<OwnedObjectPath> = prop(device, "AccessPoints").unwrap_or_default();
 
-        let mut networks: Vec<AccessPointInfo> = Vec::new();
-        for ap_path in ap_paths {
-            let Some(ap) = self.access_point(&ap_path) else {
-                continue;
-            };
-            // A hidden network is exported with an empty SSID until one is
-            // observed, and has nothing to show in a network list.
-            if ap.ssid.is_empty() {
-                continue;
-            }
-
-            match networks.iter_mut().find(|known| known.ssid == ap.ssid) {
-                Some(weaker) if ap.strength > weaker.strength => *weaker = ap,
-                Some(_) => {}
-                None => networks.push(ap),
-            }
-        }
+        ap_paths
+            .iter()
+            .filter_map(|ap_path| self.access_point(ap_path))
+            .collect()
+    }
 
-        networks.sort_by_key(|n| std::cmp::Reverse(n.strength));
-        networks
+    /// The device's current link rate in kbit/s, `None` while not associated.
+    fn bitrate(&self, device_path: &str) -> Option<u32> {
+        let device = self.interface_props(device_path, interface::DEVICE_WIRELESS)?;
+        prop::<u32>(device, "Bitrate").filter(|&rate| rate > 0)
     }
 
     /// The access point the device is currently associated with.
-    pub fn active_access_point(&self, device_path: &str) -> Option<AccessPointInfo> {
+    fn active_access_point(&self, device_path: &str) -> Option<AccessPointInfo> {
         let device = self.interface_props(device_path, interface::DEVICE_WIRELESS)?;
         let ap_path: OwnedObjectPath = prop(device, "ActiveAccessPoint")?;
 
@@ -211,7 +326,7 @@ impl NmSnapshot {
 
     /// Every connection NetworkManager currently reports as active, in no
     /// particular order.
-    pub fn active_connections(&self) -> Vec<ActiveConnectionInfo> {
+    fn active_connections(&self) -> Vec<ActiveConnectionInfo> {
         self.objects
             .iter()
             .filter_map(|(path, interfaces)| {
@@ -223,7 +338,7 @@ impl NmSnapshot {
 
     /// The connection carrying the default route — the link actually reaching
     /// the internet — when NetworkManager reports one.
-    pub fn primary_connection(&self) -> Option<ActiveConnectionInfo> {
+    fn primary_connection(&self) -> Option<ActiveConnectionInfo> {
         let manager = self.interface_props(super::NM_PATH, interface::NETWORK_MANAGER)?;
         let path: OwnedObjectPath = prop(manager, "PrimaryConnection")?;
         if path.as_str() == "/" {
@@ -241,7 +356,7 @@ impl NmSnapshot {
     /// An unreadable version stays `None` rather than defaulting: this is a
     /// cache key, and a version that silently reads as a real one would pin the
     /// profile to a stale entry for the rest of the process.
-    pub(crate) fn connection_versions(&self) -> Vec<(String, Option<u64>)> {
+    fn profile_versions(&self) -> Vec<(String, Option<u64>)> {
         let mut versions: Vec<(String, Option<u64>)> = self
             .objects
             .iter()
@@ -254,18 +369,198 @@ impl NmSnapshot {
         versions.sort();
         versions
     }
+}
+
+impl NmSnapshot {
+    fn device_props(&self, path: &OwnedObjectPath) -> Option<&HashMap<String, OwnedValue>> {
+        self.objects.get(path)?.get(interface::DEVICE)
+    }
 
     fn access_point(&self, ap_path: &OwnedObjectPath) -> Option<AccessPointInfo> {
         let props = self.objects.get(ap_path)?.get(interface::ACCESS_POINT)?;
         Some(access_point_info(ap_path.as_str(), props))
     }
 
-    fn interface_props(&self, path: &str, interface: &str) -> Option<&HashMap<String, OwnedValue>> {
+    pub(crate) fn interface_props(
+        &self,
+        path: &str,
+        interface: &str,
+    ) -> Option<&HashMap<String, OwnedValue>> {
         let object_path = OwnedObjectPath::try_from(path.to_string()).ok()?;
         self.objects.get(&object_path)?.get(interface)
     }
 }
 
+/// One incremental edit to NetworkManager's object graph, decoded from the
+/// signal that announced it.
+#[derive(Debug)]
+pub(crate) enum SnapshotChange {
+    InterfacesAdded {
+        path: OwnedObjectPath,
+        interfaces: HashMap<String, HashMap<String, OwnedValue>>,
+    },
+    InterfacesRemoved {
+        path: OwnedObjectPath,
+        interfaces: Vec<String>,
+    },
+    PropertiesChanged {
+        path: OwnedObjectPath,
+        interface: String,
+        changed: HashMap<String, OwnedValue>,
+        invalidated: Vec<String>,
+    },
+}
+
+impl SnapshotChange {
+    /// Decodes a signal into a change, or `None` for a signal that does not
+    /// describe the object graph (NetworkManager's legacy per-interface
+    /// `StateChanged`/`PropertiesChanged` signals share the match rule).
+    fn from_message(msg: &Message) -> Option<Self> {
+        let header = msg.header();
+        let interface = header.interface()?.as_str();
+        let member = header.member()?.as_str();
+        let body = msg.body();
+
+        match (interface, member) {
+            (OBJECT_MANAGER_INTERFACE, "InterfacesAdded") => {
+                let (path, interfaces) = body.deserialize().ok()?;
+                Some(Self::InterfacesAdded { path, interfaces })
+            }
+            (OBJECT_MANAGER_INTERFACE, "InterfacesRemoved") => {
+                let (path, interfaces) = body.deserialize().ok()?;
+                Some(Self::InterfacesRemoved { path, interfaces })
+            }
+            (PROPERTIES_INTERFACE, "PropertiesChanged") => {
+                let path = OwnedObjectPath::from(header.path()?.to_owned());
+                let (interface, changed, invalidated) = body.deserialize().ok()?;
+                Some(Self::PropertiesChanged {
+                    path,
+                    interface,
+                    changed,
+                    invalidated,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Every change NetworkManager announces to its object graph, in the order it
+/// sent them. Subscribed before this returns, so nothing sent afterwards is
+/// missed.
+pub(crate) async fn changes(
+    client: &NMClient,
+) -> Result<impl Stream<Item = SnapshotChange> + Send + Unpin + use<>> {
+    // One rule covers both the ObjectManager (at `/org/freedesktop`) and
+    // every object beneath it, so signals arrive in the order they were
+    // sent — a property update can never overtake the object it belongs to.
+    let rule = MatchRule::builder()
+        .msg_type(MessageType::Signal)
+        .sender(NM_BUS_NAME)?
+        .path_namespace(OBJECT_MANAGER_PATH)?
+        .build();
+    let signals = MessageStream::for_match_rule(rule, client.connection(), None).await?;
+
+    Ok(signals.filter_map(|msg| {
+        std::future::ready(msg.ok().and_then(|msg| SnapshotChange::from_message(&msg)))
+    }))
+}
+
+/// A snapshot kept current by NetworkManager's own change signals instead of
+/// being re-read on a timer.
+///
+/// A full `GetManagedObjects` is still the starting point, but after that only
+/// `InterfacesAdded`, `InterfacesRemoved` and `PropertiesChanged` cross the
+/// bus, so an idle machine generates no traffic at all and a change made by
+/// nmcli or a desktop applet lands as soon as NetworkManager announces it.
+///
+/// `notify` is called when the graph changes. Bursts (a scan landing dozens of
+/// access points at once) are coalesced: it fires once, and not again until a
+/// reader has taken a fresh [`Watch::snapshot`].
+///
+/// Readers get the published graph as an `Arc` and never lock anything while
+/// they hold it. A change is patched into the published graph in place when
+/// no reader holds it, and into a copy that replaces it otherwise, so a slow
+/// reader costs one copy and never stalls the signal stream: zbus stops
+/// reading the socket once a stream's queue is full.
+pub struct NmWatcher {
+    snapshot: Arc<Mutex<Arc<NmSnapshot>>>,
+    pending: Arc<AtomicBool>,
+    task: JoinHandle<()>,
+}
+
+impl NmWatcher {
+    pub async fn spawn<F>(client: &NMClient, notify: F) -> Result<Self>
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        // Subscribe before the initial read: a change racing the fetch is then
+        // replayed on top of it rather than lost.
+        let mut changes = changes(client).await?;
+        let snapshot = Arc::new(Mutex::new(Arc::new(NmSnapshot::fetch(client).await?)));
+        let pending = Arc::new(AtomicBool::new(false));
+
+        let task = tokio::spawn({
+            let snapshot = Arc::clone(&snapshot);
+            let pending = Arc::clone(&pending);
+            async move {
+                while let Some(change) = changes.next().await {
+                    let changed = {
+                        let mut published = lock(&snapshot);
+                        if Arc::get_mut(&mut published).is_none() {
+                            *published = Arc::new(published.copy());
+                        }
+                        Arc::get_mut(&mut published)
+                            .expect("a fresh copy has no other owner")
+                            .apply(change)
+                    };
+                    if changed && !pending.swap(true, Ordering::AcqRel) {
+                        notify();
+                    }
+                }
+            }
+        });
+
+        Ok(Self {
+            snapshot,
+            pending,
+            task,
+        })
+    }
+
+    /// The current object graph. It never changes under its holder, so a
+    /// refresh always reads one consistent instant, and changes arriving
+    /// meanwhile go into the next one.
+    ///
+    /// Taking a snapshot re-arms the change notification: anything patched in
+    /// after this call is announced again.
+    pub fn snapshot(&self) -> Arc<NmSnapshot> {
+        self.pending.store(false, Ordering::Release);
+        Arc::clone(&lock(&self.snapshot))
+    }
+}
+
+/// The watcher only holds the lock to swap or patch the graph, never across
+/// an await, so a poisoned lock still guards a whole graph.
+fn lock(snapshot: &Mutex<Arc<NmSnapshot>>) -> MutexGuard<'_, Arc<NmSnapshot>> {
+    snapshot
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[async_trait]
+impl Watch for NmWatcher {
+    async fn snapshot(&self) -> SharedSnapshot {
+        NmWatcher::snapshot(self)
+    }
+}
+
+impl Drop for NmWatcher {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -431,7 +726,7 @@ mod tests {
     /// The list is compared as a whole to decide whether saved profiles changed,
     /// so it has to be ordered independently of the map's iteration order.
     #[test]
-    fn connection_versions_are_sorted() {
+    fn profile_versions_are_sorted() {
         let snapshot = snapshot_of(vec![
             (
                 "/settings/2",
@@ -446,7 +741,7 @@ mod tests {
         ]);
 
         assert_eq!(
-            snapshot.connection_versions(),
+            snapshot.profile_versions(),
             vec![
                 ("/settings/1".to_string(), Some(3)),
                 ("/settings/2".to_string(), Some(9)),
@@ -457,7 +752,7 @@ mod tests {
     /// An unreadable version must stay distinguishable from a real one, since
     /// it is the key that decides whether cached profiles are still valid.
     #[test]
-    fn connection_versions_keep_unreadable_versions_unknown() {
+    fn profile_versions_keep_unreadable_versions_unknown() {
         let snapshot = snapshot_of(vec![(
             "/settings/1",
             interface::SETTINGS_CONNECTION,
@@ -465,20 +760,20 @@ mod tests {
         )]);
 
         assert_eq!(
-            snapshot.connection_versions(),
+            snapshot.profile_versions(),
             vec![("/settings/1".to_string(), None)]
         );
     }
 
     #[test]
-    fn connection_versions_ignore_non_profile_objects() {
+    fn profile_versions_ignore_non_profile_objects() {
         let snapshot = snapshot_of(vec![(
             "/ap/1",
             interface::ACCESS_POINT,
             access_point_props("home", 50),
         )]);
 
-        assert!(snapshot.connection_versions().is_empty());
+        assert!(snapshot.profile_versions().is_empty());
     }
 
     /// Adapter rows are diffed positionally each refresh, so this must follow
@@ -559,4 +854,124 @@ mod tests {
         assert_eq!(info.connection_type, "802-3-ethernet");
         assert_eq!(info.devices, vec!["/dev/2".to_string()]);
     }
+
+    fn added(path: &str, interface: &str, props: HashMap<String, OwnedValue>) -> SnapshotChange {
+        SnapshotChange::InterfacesAdded {
+            path: object_path(path),
+            interfaces: HashMap::from([(interface.to_string(), props)]),
+        }
+    }
+
+    fn properties_changed(path: &str, interface: &str, key: &str, v: OwnedValue) -> SnapshotChange {
+        SnapshotChange::PropertiesChanged {
+            path: object_path(path),
+            interface: interface.to_string(),
+            changed: HashMap::from([(key.to_string(), v)]),
+            invalidated: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn apply_interfaces_added_inserts_new_objects() {
+        let mut snapshot = snapshot_of(vec![]);
+
+        assert!(snapshot.apply(added(
+            "/dev/1",
+            interface::DEVICE,
+            HashMap::from([("Interface".to_string(), value("wlan0"))]),
+        )));
+        assert_eq!(
+            snapshot.device_interface("/dev/1").as_deref(),
+            Some("wlan0")
+        );
+    }
+
+    /// The watcher subscribes before its initial fetch, so signals that raced
+    /// the fetch are replayed onto a graph that already holds them. Replaying
+    /// must not be reported as a change, or startup would refresh for nothing.
+    #[test]
+    fn apply_reports_no_change_for_a_replayed_signal() {
+        let props = || HashMap::from([("Interface".to_string(), value("wlan0"))]);
+        let mut snapshot = snapshot_of(vec![("/dev/1", interface::DEVICE, props())]);
+
+        assert!(!snapshot.apply(added("/dev/1", interface::DEVICE, props())));
+        assert!(!snapshot.apply(properties_changed(
+            "/dev/1",
+            interface::DEVICE,
+            "Interface",
+            value("wlan0"),
+        )));
+    }
+
+    #[test]
+    fn apply_properties_changed_patches_in_place() {
+        let mut snapshot = snapshot_of(vec![(
+            "/ap/1",
+            interface::ACCESS_POINT,
+            access_point_props("home", 40),
+        )]);
+
+        assert!(snapshot.apply(properties_changed(
+            "/ap/1",
+            interface::ACCESS_POINT,
+            "Strength",
+            value(90u8),
+        )));
+
+        let ap = snapshot.access_point(&object_path("/ap/1")).expect("kept");
+        assert_eq!(ap.strength, 90);
+        // Untouched properties survive the patch.
+        assert_eq!(ap.ssid, "home");
+    }
+
+    #[test]
+    fn apply_ignores_properties_for_unknown_objects() {
+        let mut snapshot = snapshot_of(vec![]);
+
+        assert!(!snapshot.apply(properties_changed(
+            "/ap/9",
+            interface::ACCESS_POINT,
+            "Strength",
+            value(90u8),
+        )));
+        assert!(snapshot.access_point(&object_path("/ap/9")).is_none());
+    }
+
+    #[test]
+    fn apply_interfaces_removed_drops_emptied_objects() {
+        let mut snapshot = snapshot_of(vec![(
+            "/ap/1",
+            interface::ACCESS_POINT,
+            access_point_props("home", 40),
+        )]);
+
+        assert!(snapshot.apply(SnapshotChange::InterfacesRemoved {
+            path: object_path("/ap/1"),
+            interfaces: vec![interface::ACCESS_POINT.to_string()],
+        }));
+        assert!(snapshot.objects.is_empty());
+
+        // Removing it again is not a change.
+        assert!(!snapshot.apply(SnapshotChange::InterfacesRemoved {
+            path: object_path("/ap/1"),
+            interfaces: vec![interface::ACCESS_POINT.to_string()],
+        }));
+    }
+
+    #[test]
+    fn apply_treats_invalidated_properties_as_absent() {
+        let mut snapshot = snapshot_of(vec![(
+            "/dev/1",
+            interface::DEVICE,
+            HashMap::from([("State".to_string(), value(100u32))]),
+        )]);
+
+        assert!(snapshot.apply(SnapshotChange::PropertiesChanged {
+            path: object_path("/dev/1"),
+            interface: interface::DEVICE.to_string(),
+            changed: HashMap::new(),
+            invalidated: vec!["State".to_string()],
+        }));
+        assert!(snapshot.device_state("/dev/1").is_none());
+    }
 }
@@ -1,7 +1,11 @@
 // NetworkManager types and enums
 
+use std::collections::BTreeMap;
 use std::fmt;
 use std::net::IpAddr;
+use std::path::PathBuf;
+
+use zeroize::Zeroizing;
 
 /// A WireGuard peer parsed from a `.conf` `[Peer]` section.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -14,15 +18,65 @@ pub struct WgPeerConfig {
 }
 
 /// A WireGuard tunnel parsed from a `.conf` file, ready to be turned into a
-/// NetworkManager `wireguard` connection. Holds a single peer (the common case
-/// for hosted providers like Proton/Mullvad).
+/// NetworkManager `wireguard` connection. Hosted providers hand out a single
+/// peer; site-to-site tunnels list several.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct WgConfig {
-    pub private_key: String,
+    /// Wiped when the config is dropped.
+    pub private_key: Zeroizing<String>,
     /// Interface addresses as (ip, prefix); may mix IPv4 and IPv6.
     pub addresses: Vec<(IpAddr, u8)>,
     pub dns: Vec<IpAddr>,
-    pub peer: WgPeerConfig,
+    /// Search domains, the non-address entries of wg-quick's `DNS`.
+    pub dns_search: Vec<String>,
+    /// `None` lets the kernel pick a port.
+    pub listen_port: Option<u16>,
+    pub mtu: Option<u32>,
+    pub fwmark: Option<u32>,
+    pub table: WgRouteTable,
+    pub peers: Vec<WgPeerConfig>,
+}
+
+/// Where the routes to the peers' `AllowedIPs` go, per wg-quick's `Table`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WgRouteTable {
+    /// The main table, as wg-quick does by default.
+    #[default]
+    Auto,
+    /// No routes at all; something else routes into the tunnel.
+    Off,
+    /// A numbered routing table.
+    Id(u32),
+}
+
+/// An OpenVPN client profile parsed from an `.ovpn` file, its inline keys and
+/// certificates already written out, ready to be turned into a NetworkManager
+/// `vpn` connection for the OpenVPN plugin.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OvpnConfig {
+    /// Chosen before saving, so the directory holding the written-out keys
+    /// can be named after the profile.
+    pub uuid: String,
+    /// `host:port:proto` entries, tried in order.
+    pub remotes: Vec<String>,
+    /// `tun` or `tap`; `None` leaves the plugin's default (`tun`).
+    pub dev_type: Option<String>,
+    pub ca: PathBuf,
+    pub cert: Option<PathBuf>,
+    pub key: Option<PathBuf>,
+    pub tls_auth: Option<PathBuf>,
+    pub key_direction: Option<u8>,
+    pub tls_crypt: Option<PathBuf>,
+    /// `auth-user-pass`: the server wants a username and password.
+    pub user_pass: bool,
+    pub username: Option<String>,
+    /// Stored with the profile when the `.ovpn` supplied it; otherwise asked
+    /// for on every connect.
+    pub password: Option<String>,
+    pub cipher: Option<String>,
+    pub data_ciphers: Option<String>,
+    pub auth: Option<String>,
+    pub remote_cert_tls: Option<String>,
 }
 
 /// Summary of an active wired (802-3-ethernet) connection. Tracked
@@ -82,6 +136,72 @@ pub struct Ip4Info {
     pub nameservers: Vec<String>,
 }
 
+/// Active IPv6 configuration pulled from an NM device's `Ip6Config` object.
+/// Unlike IPv4, a link-local address is always present, so presence of the
+/// config alone says nothing; see [`Ip6Info::global_addresses`].
+#[derive(Debug, Clone, Default)]
+pub struct Ip6Info {
+    pub addresses: Vec<(String, u32)>,
+    pub gateway: Option<String>,
+    pub nameservers: Vec<String>,
+}
+
+impl Ip6Info {
+    /// Addresses that can reach the internet, i.e. everything except
+    /// link-local (`fe80::/10`) and unique-local (`fc00::/7`) ones. Empty on an
+    /// IPv4-only network.
+    pub fn global_addresses(&self) -> impl Iterator<Item = &(String, u32)> {
+        self.addresses.iter().filter(|(addr, _)| {
+            addr.parse::<std::net::Ipv6Addr>().is_ok_and(|ip| {
+                let first = ip.segments()[0];
+                first & 0xffc0 != 0xfe80 && first & 0xfe00 != 0xfc00
+            })
+        })
+    }
+}
+
+/// The DHCPv4 lease behind a device's address, from its `Dhcp4Config` object.
+/// NM exposes the client's raw options as strings (`dhcp_server_identifier`,
+/// `dhcp_lease_time`, `expiry`, `routers`, ...); the ones the doctor reads are
+/// lifted out, and the full set is kept for display.
+#[derive(Debug, Clone, Default)]
+pub struct Dhcp4Lease {
+    pub server: Option<String>,
+    /// Lease length in seconds, as granted.
+    pub lease_time: Option<u64>,
+    /// Unix time the lease runs out.
+    pub expiry: Option<i64>,
+    pub options: BTreeMap<String, String>,
+}
+
+impl Dhcp4Lease {
+    pub fn from_options(options: BTreeMap<String, String>) -> Self {
+        Self {
+            server: options.get("dhcp_server_identifier").cloned(),
+            lease_time: options.get("dhcp_lease_time").and_then(|v| v.parse().ok()),
+            expiry: options.get("expiry").and_then(|v| v.parse().ok()),
+            options,
+        }
+    }
+
+    /// Seconds left on the lease at unix time `now`; `None` when NM reports no
+    /// expiry (e.g. an infinite lease).
+    pub fn remaining(&self, now: i64) -> Option<u64> {
+        self.expiry
+            .map(|expiry| expiry.saturating_sub(now).max(0) as u64)
+    }
+
+    /// Whether the lease is in its last eighth. NM renews at half-life and
+    /// rebinds at seven eighths, so by then both have gone unanswered.
+    pub fn is_expiring(&self, now: i64) -> bool {
+        match (self.remaining(now), self.lease_time) {
+            (Some(left), Some(total)) => left < (total / 8).max(60),
+            (Some(left), None) => left < 60,
+            _ => false,
+        }
+    }
+}
+
 /// NetworkManager connectivity state. Mirrors `NM_CONNECTIVITY_*` values.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Connectivity {
@@ -343,24 +463,44 @@ pub struct AccessPointInfo {
     pub mode: WifiMode,
 }
 
+/// The 6 GHz band, in MHz; it starts just above the top of the 5 GHz one.
+const BAND_6GHZ: std::ops::RangeInclusive<u32> = 5925..=7125;
+
+/// The band a frequency in MHz falls in (2.4, 5 or 6 GHz)
+pub fn frequency_band(frequency: u32) -> &'static str {
+    if frequency < 3000 {
+        "2.4 GHz"
+    } else if BAND_6GHZ.contains(&frequency) {
+        "6 GHz"
+    } else {
+        "5 GHz"
+    }
+}
+
 impl AccessPointInfo {
-    /// Get frequency band (2.4GHz or 5GHz)
-    pub fn band(&self) -> &str {
-        if self.frequency < 3000 {
-            "2.4 GHz"
-        } else {
-            "5 GHz"
-        }
+    /// Get frequency band (2.4, 5 or 6 GHz)
+    pub fn band(&self) -> &'static str {
+        frequency_band(self.frequency)
     }
 
-    /// Get channel from frequency
+    /// Get channel from frequency, `0` when the frequency is unknown (iwd
+    /// only reports it for the network it is connected to)
     pub fn channel(&self) -> u32 {
         if self.frequency < 3000 {
             // 2.4 GHz
-            (self.frequency - 2407) / 5
+            self.frequency.saturating_sub(2407) / 5
+        } else if self.frequency == 5935 {
+            // 6 GHz channel 2 sits off the band's 5 MHz grid
+            2
+        } else if BAND_6GHZ.contains(&self.frequency) {
+            // Off-grid frequencies below channel 1 have no number
+            self.frequency.saturating_sub(5950) / 5
+        } else if self.frequency < 5000 {
+            // 4.9 GHz (Japan), numbered from 4000 MHz
+            self.frequency.saturating_sub(4000) / 5
         } else {
             // 5 GHz
-            (self.frequency - 5000) / 5
+            self.frequency.saturating_sub(5000) / 5
         }
     }
 }
@@ -451,6 +591,9 @@ pub enum ActivationFailureReason {
     SsidNotFound,
     /// Activation did not reach a terminal state within our wait window.
     Timeout,
+    /// The network needed secrets and no agent was registered to ask for
+    /// them (iwd's `NoAgent`); nothing was tried, so nothing was wrong.
+    NoAgent,
     /// Any other terminal failure; carries the raw NM reason code so callers
     /// can log it without inventing a name for every value.
     Other(u32),
@@ -656,6 +799,14 @@ mod tests {
             mode: WifiMode::Infrastructure,
         };
         assert_eq!(ap_5g.band(), "5 GHz");
+
+        let ap_6g = AccessPointInfo {
+            frequency: 6115,
+            ..ap_5g
+        };
+        assert_eq!(ap_6g.band(), "6 GHz");
+        assert_eq!(ap_6g.channel(), 33);
+        assert_eq!(frequency_band(5885), "5 GHz");
     }
 
     #[test]
@@ -670,6 +821,21 @@ mod tests {
             mode: WifiMode::Infrastructure,
         };
         assert_eq!(ap.channel(), 1);
+
+        let japan = AccessPointInfo {
+            frequency: 4920,
+            ..ap.clone()
+        };
+        assert_eq!(japan.channel(), 184);
+
+        let below_6ghz_grid = AccessPointInfo {
+            frequency: 5930,
+            ..ap.clone()
+        };
+        assert_eq!(below_6ghz_grid.channel(), 0);
+
+        let unknown = AccessPointInfo { frequency: 0, ..ap };
+        assert_eq!(unknown.channel(), 0);
     }
 
     #[test]
@@ -795,4 +961,48 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn ip6_global_addresses_drop_link_local_and_ula() {
+        let info = Ip6Info {
+            addresses: vec![
+                ("fe80::1c2d:3eff:fe4f:5a6b".into(), 64),
+                ("fd12:3456:789a::10".into(), 64),
+                ("2001:db8::10".into(), 64),
+            ],
+            ..Default::default()
+        };
+        let global: Vec<_> = info.global_addresses().map(|(a, _)| a.as_str()).collect();
+        assert_eq!(global, ["2001:db8::10"]);
+    }
+
+    fn lease(lease_time: &str, expiry: &str) -> Dhcp4Lease {
+        Dhcp4Lease::from_options(BTreeMap::from([
+            (
+                "dhcp_server_identifier".to_string(),
+                "192.168.1.1".to_string(),
+            ),
+            ("dhcp_lease_time".to_string(), lease_time.to_string()),
+            ("expiry".to_string(), expiry.to_string()),
+        ]))
+    }
+
+    #[test]
+    fn dhcp4_lease_reads_server_and_times() {
+        let l = lease("3600", "10000");
+        assert_eq!(l.server.as_deref(), Some("192.168.1.1"));
+        assert_eq!(l.lease_time, Some(3600));
+        assert_eq!(l.remaining(9000), Some(1000));
+        assert_eq!(l.remaining(20000), Some(0));
+    }
+
+    #[test]
+    fn dhcp4_lease_expiring_in_last_eighth() {
+        let l = lease("3600", "10000");
+        assert!(!l.is_expiring(10000 - 451));
+        assert!(l.is_expiring(10000 - 449));
+        // Short leases still get a minute of grace.
+        assert!(lease("120", "10000").is_expiring(10000 - 59));
+        assert!(!Dhcp4Lease::default().is_expiring(0));
+    }
 }
@@ -1,12 +1,13 @@
 // WiFi-specific helpers for NetworkManager
 
 use super::{AccessPointInfo, NMClient, NmSnapshot};
+use crate::backend::Snapshot;
 use anyhow::Result;
 
 impl NMClient {
     /// Get all visible networks, deduplicated by SSID.
     ///
-    /// Prefer [`NmSnapshot::visible_networks`] when the caller already holds a
+    /// Prefer [`Snapshot::visible_networks`] when the caller already holds a
     /// snapshot; this reads a fresh one.
     pub async fn get_visible_networks(&self, device_path: &str) -> Result<Vec<AccessPointInfo>> {
         let snapshot = NmSnapshot::fetch(self).await?;
@@ -0,0 +1,344 @@
+// A recording played back as a `Backend`. Reads come from the replayed state;
+// anything that would change it fails, since there is nothing to change.
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use async_trait::async_trait;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+use super::{Profiles, Recording, Step};
+use crate::backend::{Backend, EnterpriseCredentials, SharedSnapshot, Snapshot, Watch};
+use crate::nm::snapshot::{ManagedObjects, interface, prop};
+use crate::nm::{
+    self, AccessPointInfo, ActivationOutcome, ActiveConnectionInfo, ConnectionInfo, DeviceState,
+    LinkKind, NmSnapshot, OvpnConfig, SecurityType, VpnConnectionInfo, WgConfig, dump,
+};
+
+const READ_ONLY: &str = "This is a replayed recording; nothing can be changed";
+
+/// What the recording says NetworkManager looked like at the current point of
+/// playback.
+struct State {
+    graph: NmSnapshot,
+    profiles: Profiles,
+}
+
+impl State {
+    fn apply(&mut self, step: Step) -> bool {
+        match step {
+            Step::Graph(objects) => {
+                self.graph = NmSnapshot::from_objects(objects);
+                true
+            }
+            // Announced by the change that follows it.
+            Step::Profiles(profiles) => {
+                self.profiles = profiles;
+                false
+            }
+            Step::Change(change) => self.graph.apply(change),
+        }
+    }
+}
+
+/// Plays a [`Recording`] back through the `Backend` the TUI normally drives
+/// NetworkManager with.
+///
+/// Everything before the recording's first change signal is the starting
+/// state. The first [`Backend::watch`] starts playback, at the pace the
+/// changes were recorded, and each change reaches the watcher's `notify`
+/// exactly as a live one would.
+pub struct ReplayBackend {
+    state: Arc<Mutex<State>>,
+    /// The frames still to play, until the first watcher takes them.
+    pending: Mutex<Option<Vec<(Duration, Step)>>>,
+}
+
+impl ReplayBackend {
+    pub fn new(recording: Recording) -> Self {
+        let (start, rest) = recording.split();
+        let mut state = State {
+            graph: NmSnapshot::from_objects(ManagedObjects::new()),
+            profiles: Profiles::new(),
+        };
+        for step in start {
+            state.apply(step);
+        }
+
+        Self {
+            state: Arc::new(Mutex::new(state)),
+            pending: Mutex::new(Some(rest)),
+        }
+    }
+
+    /// A copy of the current graph, so a reader never holds up playback.
+    fn current(&self) -> NmSnapshot {
+        lock(&self.state).graph.copy()
+    }
+}
+
+impl fmt::Debug for ReplayBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReplayBackend").finish_non_exhaustive()
+    }
+}
+
+/// Playback only ever holds the lock to apply one step, so a poisoned lock
+/// still guards a whole state.
+fn lock(state: &Mutex<State>) -> MutexGuard<'_, State> {
+    state
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// The replayed state, re-read by each [`Watch::snapshot`].
+struct ReplayWatcher {
+    state: Arc<Mutex<State>>,
+    pending: Arc<AtomicBool>,
+    task: Option<JoinHandle<()>>,
+}
+
+#[async_trait]
+impl Watch for ReplayWatcher {
+    async fn snapshot(&self) -> SharedSnapshot {
+        self.pending.store(false, Ordering::Release);
+        Arc::new(lock(&self.state).graph.copy())
+    }
+}
+
+impl Drop for ReplayWatcher {
+    fn drop(&mut self) {
+        if let Some(task) = &self.task {
+            task.abort();
+        }
+    }
+}
+
+#[async_trait]
+impl Backend for ReplayBackend {
+    async fn get_wifi_devices(&self) -> Result<Vec<String>> {
+        Ok(self.current().wifi_devices())
+    }
+
+    async fn get_device_interface(&self, device: &str) -> Result<String> {
+        self.current()
+            .device_interface(device)
+            .with_context(|| format!("The recording has no device {device}"))
+    }
+
+    async fn get_device_hw_address(&self, device: &str) -> Result<String> {
+        let graph = self.current();
+        graph
+            .interface_props(device, interface::DEVICE)
+            .and_then(|props| prop(props, "HwAddress"))
+            .with_context(|| format!("The recording has no device {device}"))
+    }
+
+    async fn get_device_state(&self, device: &str) -> Result<DeviceState> {
+        self.current()
+            .device_state(device)
+            .with_context(|| format!("The recording has no device {device}"))
+    }
+
+    async fn is_wireless_enabled(&self) -> Result<bool> {
+        Ok(self.current().wireless_enabled().unwrap_or(false))
+    }
+
+    async fn set_wireless_enabled(&self, _enabled: bool) -> Result<()> {
+        bail!(READ_ONLY)
+    }
+
+    async fn snapshot(&self) -> Result<SharedSnapshot> {
+        Ok(Arc::new(self.current()))
+    }
+
+    /// Starts playback. Only the first watcher is notified; a later one reads
+    /// the same playback rather than starting another.
+    async fn watch(&self, notify: Box<dyn Fn() + Send + Sync>) -> Result<Box<dyn Watch>> {
+        let pending = Arc::new(AtomicBool::new(false));
+        let steps = self
+            .pending
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .take();
+
+        let task = steps.map(|steps| {
+            let state = Arc::clone(&self.state);
+            let pending = Arc::clone(&pending);
+            let started = Instant::now();
+            tokio::spawn(async move {
+                for (at, step) in steps {
+                    tokio::time::sleep_until(started + at).await;
+                    if lock(&state).apply(step) && !pending.swap(true, Ordering::AcqRel) {
+                        notify();
+                    }
+                }
+            })
+        });
+
+        Ok(Box::new(ReplayWatcher {
+            state: Arc::clone(&self.state),
+            pending,
+            task,
+        }))
+    }
+
+    async fn request_scan(&self, _device: &str) -> Result<()> {
+        bail!(READ_ONLY)
+    }
+
+    async fn get_active_access_point(&self, device: &str) -> Result<Option<String>> {
+        Ok(self.current().active_access_point(device).map(|ap| ap.path))
+    }
+
+    async fn get_access_point_info(&self, access_point: &str) -> Result<AccessPointInfo> {
+        let graph = self.current();
+        let props = graph
+            .interface_props(access_point, interface::ACCESS_POINT)
+            .with_context(|| format!("The recording has no access point {access_point}"))?;
+        Ok(nm::snapshot::access_point_info(access_point, props))
+    }
+
+    /// The recorded profiles `snapshot` lists, read the way `NMClient` reads
+    /// live ones.
+    async fn wifi_connections(&self, snapshot: &dyn Snapshot) -> Result<Arc<[ConnectionInfo]>> {
+        let state = lock(&self.state);
+        let mut connections: Vec<ConnectionInfo> = snapshot
+            .profile_versions()
+            .iter()
+            .filter_map(|(path, _)| nm::wifi_connection_info(path, state.profiles.get(path)?))
+            .collect();
+
+        connections.sort_by_key(|c| std::cmp::Reverse(c.timestamp));
+        Ok(connections.into())
+    }
+
+    /// Secrets are never recorded.
+    async fn get_wifi_psk(&self, _profile: &str) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    async fn get_vpn_connections(&self) -> Result<Vec<VpnConnectionInfo>> {
+        let state = lock(&self.state);
+        let mut vpns: Vec<VpnConnectionInfo> = state
+            .profiles
+            .iter()
+            .filter_map(|(path, settings)| nm::vpn_connection_info(path, settings))
+            .collect();
+
+        vpns.sort_by_key(|v| v.id.to_lowercase());
+        Ok(vpns)
+    }
+
+    async fn profile_json(&self, profile: &str) -> Result<serde_json::Value> {
+        let state = lock(&self.state);
+        let settings = state
+            .profiles
+            .get(profile)
+            .with_context(|| format!("The recording has no profile {profile}"))?;
+        Ok(dump::settings_json(settings))
+    }
+
+    /// Keys are secrets, which are never recorded.
+    async fn wireguard_config(&self, _profile: &str) -> Result<WgConfig> {
+        bail!("A recording holds no keys, so its tunnels cannot be exported")
+    }
+
+    async fn add_wireguard_connection(
+        &self,
+        _id: &str,
+        _interface: &str,
+        _cfg: &WgConfig,
+    ) -> Result<String> {
+        bail!(READ_ONLY)
+    }
+
+    async fn add_openvpn_connection(&self, _id: &str, _cfg: &OvpnConfig) -> Result<String> {
+        bail!(READ_ONLY)
+    }
+
+    async fn add_enterprise_connection(
+        &self,
+        _ssid: &str,
+        _credentials: EnterpriseCredentials<'_>,
+    ) -> Result<String> {
+        bail!(READ_ONLY)
+    }
+
+    async fn set_connection_autoconnect(&self, _profile: &str, _autoconnect: bool) -> Result<()> {
+        bail!(READ_ONLY)
+    }
+
+    async fn delete_connection(&self, _profile: &str) -> Result<()> {
+        bail!(READ_ONLY)
+    }
+
+    async fn activate_connection(&self, _profile: &str, _device: &str) -> Result<String> {
+        bail!(READ_ONLY)
+    }
+
+    async fn add_and_activate_connection(
+        &self,
+        _device: &str,
+        _access_point: &str,
+        _password: Option<&str>,
+    ) -> Result<String> {
+        bail!(READ_ONLY)
+    }
+
+    async fn add_and_activate_hidden_connection(
+        &self,
+        _device: &str,
+        _ssid: &str,
+        _security: SecurityType,
+        _password: Option<&str>,
+    ) -> Result<String> {
+        bail!(READ_ONLY)
+    }
+
+    async fn await_activation(&self, _active: &str, _device: &str) -> Result<ActivationOutcome> {
+        bail!(READ_ONLY)
+    }
+
+    async fn disconnect_device(&self, _device: &str) -> Result<()> {
+        bail!(READ_ONLY)
+    }
+
+    async fn deactivate_connection(&self, _active: &str) -> Result<()> {
+        bail!(READ_ONLY)
+    }
+
+    async fn get_active_connections(&self) -> Result<Vec<ActiveConnectionInfo>> {
+        Ok(self.current().active_connections())
+    }
+
+    /// Addresses live on `IP4Config` objects, which the recording has but
+    /// nothing here reads; the VPN modal shows the tunnel without one.
+    async fn active_connection_ipv4(&self, _active: &str) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    async fn create_hotspot(&self, _device: &str, _ssid: &str, _password: &str) -> Result<String> {
+        bail!(READ_ONLY)
+    }
+
+    async fn active_hotspot(&self, device: &str) -> Result<Option<ActiveConnectionInfo>> {
+        let actives = self.current().active_connections();
+        let state = lock(&self.state);
+        Ok(actives.into_iter().find(|info| {
+            info.devices.iter().any(|d| d == device)
+                && state
+                    .profiles
+                    .get(&info.connection_path)
+                    .is_some_and(nm::is_hotspot_profile)
+        }))
+    }
+
+    async fn prefer_internet(&self, _kind: LinkKind) -> Result<()> {
+        bail!(READ_ONLY)
+    }
+}
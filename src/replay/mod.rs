@@ -0,0 +1,530 @@
+//! Recordings of NetworkManager's state, for reproducing a UI bug on a machine
+//! that does not have the hardware which showed it.
+//!
+//! `wlctl record <file>` writes the object graph NetworkManager exports and
+//! the settings of every saved profile, then each change signal as it
+//! arrives. `wlctl --replay <file>` runs the TUI on a [`ReplayBackend`] that
+//! plays those back at the pace they were recorded, so `App::tick` sees the
+//! same sequence of snapshots the reporter's did.
+//!
+//! A recording is JSON lines: a header, then one [`Frame`] per line. Values
+//! keep their D-Bus signature so they read back with the types the snapshot
+//! expects. Secrets are redacted on the way in, by the same rule diagnostics
+//! bundles use; SSIDs and profile names are kept, since the bugs worth
+//! recording are usually about them.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{Context, Result, anyhow, bail};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as Json;
+use zbus::zvariant::{
+    Array, Dict, ObjectPath, OwnedObjectPath, OwnedValue, Signature, StructureBuilder, Value,
+};
+
+use crate::nm::dump::{REDACTED, is_secret};
+use crate::nm::snapshot::{ManagedObjects, SnapshotChange};
+
+mod backend;
+mod record;
+
+pub use backend::ReplayBackend;
+pub use record::{record, run};
+
+/// Bumped whenever a frame changes shape; older builds refuse newer files
+/// rather than misreading them.
+const FORMAT_VERSION: u32 = 1;
+
+/// A profile's `GetSettings` reply: section -> key -> value.
+pub(crate) type Settings = HashMap<String, HashMap<String, OwnedValue>>;
+
+/// Every saved profile's settings, by profile path.
+pub(crate) type Profiles = HashMap<String, Settings>;
+
+/// Property name -> encoded value.
+type EncodedProps = BTreeMap<String, Json>;
+
+/// Interface (or settings section) name -> its properties.
+type EncodedInterfaces = BTreeMap<String, EncodedProps>;
+
+/// One line of a recording. `at_ms` counts from the start of the recording.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum Frame {
+    Header {
+        version: u32,
+        wlctl: String,
+        started: String,
+    },
+    /// The whole object graph, as `GetManagedObjects` returned it.
+    Graph {
+        at_ms: u64,
+        objects: BTreeMap<String, EncodedInterfaces>,
+    },
+    /// Every saved profile's settings. Written before the signal that
+    /// announced a profile change, so a replay never sees a profile it has no
+    /// settings for.
+    Profiles {
+        at_ms: u64,
+        profiles: BTreeMap<String, EncodedInterfaces>,
+    },
+    InterfacesAdded {
+        at_ms: u64,
+        path: String,
+        interfaces: EncodedInterfaces,
+    },
+    InterfacesRemoved {
+        at_ms: u64,
+        path: String,
+        interfaces: Vec<String>,
+    },
+    PropertiesChanged {
+        at_ms: u64,
+        path: String,
+        interface: String,
+        changed: EncodedProps,
+        invalidated: Vec<String>,
+    },
+}
+
+/// A decoded frame, ready to apply.
+pub(crate) enum Step {
+    Graph(ManagedObjects),
+    Profiles(Profiles),
+    Change(SnapshotChange),
+}
+
+impl Frame {
+    fn header() -> Self {
+        Frame::Header {
+            version: FORMAT_VERSION,
+            wlctl: env!("CARGO_PKG_VERSION").to_string(),
+            started: chrono::Local::now().to_rfc3339(),
+        }
+    }
+
+    fn graph(at: Duration, objects: &ManagedObjects) -> Self {
+        Frame::Graph {
+            at_ms: millis(at),
+            objects: objects
+                .iter()
+                .map(|(path, interfaces)| (path.to_string(), encode_interfaces(interfaces)))
+                .collect(),
+        }
+    }
+
+    fn profiles(at: Duration, profiles: &Profiles) -> Self {
+        Frame::Profiles {
+            at_ms: millis(at),
+            profiles: profiles
+                .iter()
+                .map(|(path, settings)| (path.clone(), encode_interfaces(settings)))
+                .collect(),
+        }
+    }
+
+    fn change(at: Duration, change: &SnapshotChange) -> Self {
+        let at_ms = millis(at);
+        match change {
+            SnapshotChange::InterfacesAdded { path, interfaces } => Frame::InterfacesAdded {
+                at_ms,
+                path: path.to_string(),
+                interfaces: encode_interfaces(interfaces),
+            },
+            SnapshotChange::InterfacesRemoved { path, interfaces } => Frame::InterfacesRemoved {
+                at_ms,
+                path: path.to_string(),
+                interfaces: interfaces.clone(),
+            },
+            SnapshotChange::PropertiesChanged {
+                path,
+                interface,
+                changed,
+                invalidated,
+            } => Frame::PropertiesChanged {
+                at_ms,
+                path: path.to_string(),
+                interface: interface.clone(),
+                changed: encode_props(changed),
+                invalidated: invalidated.clone(),
+            },
+        }
+    }
+
+    /// The frame's offset and decoded content; `None` for the header.
+    fn into_step(self) -> Result<Option<(Duration, Step)>> {
+        let (at_ms, step) = match self {
+            Frame::Header { .. } => return Ok(None),
+            Frame::Graph { at_ms, objects } => {
+                let objects = objects
+                    .into_iter()
+                    .map(|(path, interfaces)| {
+                        Ok((object_path(path)?, decode_interfaces(interfaces)?))
+                    })
+                    .collect::<Result<_>>()?;
+                (at_ms, Step::Graph(objects))
+            }
+            Frame::Profiles { at_ms, profiles } => {
+                let profiles = profiles
+                    .into_iter()
+                    .map(|(path, settings)| Ok((path, decode_interfaces(settings)?)))
+                    .collect::<Result<_>>()?;
+                (at_ms, Step::Profiles(profiles))
+            }
+            Frame::InterfacesAdded {
+                at_ms,
+                path,
+                interfaces,
+            } => (
+                at_ms,
+                Step::Change(SnapshotChange::InterfacesAdded {
+                    path: object_path(path)?,
+                    interfaces: decode_interfaces(interfaces)?,
+                }),
+            ),
+            Frame::InterfacesRemoved {
+                at_ms,
+                path,
+                interfaces,
+            } => (
+                at_ms,
+                Step::Change(SnapshotChange::InterfacesRemoved {
+                    path: object_path(path)?,
+                    interfaces,
+                }),
+            ),
+            Frame::PropertiesChanged {
+                at_ms,
+                path,
+                interface,
+                changed,
+                invalidated,
+            } => (
+                at_ms,
+                Step::Change(SnapshotChange::PropertiesChanged {
+                    path: object_path(path)?,
+                    interface,
+                    changed: decode_props(changed)?,
+                    invalidated,
+                }),
+            ),
+        };
+        Ok(Some((Duration::from_millis(at_ms), step)))
+    }
+}
+
+/// A recording read back from disk, in the order it was written.
+pub struct Recording {
+    steps: Vec<(Duration, Step)>,
+}
+
+impl Recording {
+    pub fn load(path: &Path) -> Result<Self> {
+        let file =
+            File::open(path).with_context(|| format!("Could not open {}", path.display()))?;
+        Self::read(BufReader::new(file))
+            .with_context(|| format!("{} is not a readable recording", path.display()))
+    }
+
+    fn read(reader: impl BufRead) -> Result<Self> {
+        let mut lines = reader.lines();
+
+        let first = lines.next().ok_or_else(|| anyhow!("The file is empty"))??;
+        match serde_json::from_str(&first) {
+            Ok(Frame::Header { version, .. }) if version == FORMAT_VERSION => {}
+            Ok(Frame::Header { version, wlctl, .. }) => {
+                bail!(
+                    "It was written by wlctl {wlctl} in format {version}; this build reads {FORMAT_VERSION}"
+                )
+            }
+            _ => bail!("It does not start with a wlctl recording header"),
+        }
+
+        let mut steps = Vec::new();
+        for (number, line) in lines.enumerate() {
+            let line = line?;
+            // A recording cut short mid-write ends in a partial line; what
+            // came before it is still worth replaying.
+            let Ok(frame) = serde_json::from_str::<Frame>(&line) else {
+                break;
+            };
+            let step = frame
+                .into_step()
+                .with_context(|| format!("Line {}", number + 2))?;
+            steps.extend(step);
+        }
+
+        if !matches!(steps.first(), Some((_, Step::Graph(_)))) {
+            bail!("It holds no object graph to start from");
+        }
+        Ok(Self { steps })
+    }
+
+    /// The state to start from (every frame before the first change signal),
+    /// and the frames to play after it.
+    pub(crate) fn split(self) -> (Vec<Step>, Vec<(Duration, Step)>) {
+        let mut steps = self.steps.into_iter().peekable();
+        let mut start = Vec::new();
+        while let Some((_, step)) = steps.next_if(|(_, step)| !matches!(step, Step::Change(_))) {
+            start.push(step);
+        }
+        (start, steps.collect())
+    }
+}
+
+fn millis(at: Duration) -> u64 {
+    u64::try_from(at.as_millis()).unwrap_or(u64::MAX)
+}
+
+fn object_path(path: String) -> Result<OwnedObjectPath> {
+    OwnedObjectPath::try_from(path.clone()).map_err(|_| anyhow!("{path} is not an object path"))
+}
+
+fn encode_interfaces(
+    interfaces: &HashMap<String, HashMap<String, OwnedValue>>,
+) -> EncodedInterfaces {
+    interfaces
+        .iter()
+        .map(|(name, props)| (name.clone(), encode_props(props)))
+        .collect()
+}
+
+/// Properties as `{"signature", "value"}` objects, zvariant's own JSON form.
+/// Secrets become the string `"<redacted>"`, which no reader mistakes for the
+/// value it replaced.
+fn encode_props(props: &HashMap<String, OwnedValue>) -> EncodedProps {
+    props
+        .iter()
+        .filter_map(|(key, value)| {
+            let encoded = if is_secret(key) {
+                serde_json::to_value(Value::from(REDACTED))
+            } else {
+                serde_json::to_value(value)
+            };
+            // Only file descriptors fail to encode, and no property that
+            // describes state carries one.
+            Some((key.clone(), encoded.ok()?))
+        })
+        .collect()
+}
+
+fn decode_interfaces(
+    interfaces: EncodedInterfaces,
+) -> Result<HashMap<String, HashMap<String, OwnedValue>>> {
+    interfaces
+        .into_iter()
+        .map(|(name, props)| Ok((name, decode_props(props)?)))
+        .collect()
+}
+
+fn decode_props(props: EncodedProps) -> Result<HashMap<String, OwnedValue>> {
+    props
+        .into_iter()
+        .map(|(key, value)| {
+            let value = decode_variant(&value).with_context(|| format!("Property {key}"))?;
+            Ok((key, OwnedValue::try_from(value)?))
+        })
+        .collect()
+}
+
+/// Reads back one `{"signature", "value"}` object. zvariant writes this form
+/// but cannot parse it from JSON, where every number looks alike; the
+/// signature says which type each one was.
+fn decode_variant(json: &Json) -> Result<Value<'static>> {
+    let signature: Signature = json
+        .get("signature")
+        .and_then(Json::as_str)
+        .ok_or_else(|| anyhow!("A value has no signature"))?
+        .parse()
+        .map_err(|e| anyhow!("{e}"))?;
+    let value = json
+        .get("value")
+        .ok_or_else(|| anyhow!("A value is missing"))?;
+    decode(&signature, value)
+}
+
+fn decode(signature: &Signature, json: &Json) -> Result<Value<'static>> {
+    let mismatch = || anyhow!("{json} is not a {signature}");
+    let int = || json.as_i64().ok_or_else(mismatch);
+    let uint = || json.as_u64().ok_or_else(mismatch);
+    let text = || json.as_str().ok_or_else(mismatch);
+
+    Ok(match signature {
+        Signature::U8 => Value::U8(u8::try_from(uint()?)?),
+        Signature::Bool => Value::Bool(json.as_bool().ok_or_else(mismatch)?),
+        Signature::I16 => Value::I16(i16::try_from(int()?)?),
+        Signature::U16 => Value::U16(u16::try_from(uint()?)?),
+        Signature::I32 => Value::I32(i32::try_from(int()?)?),
+        Signature::U32 => Value::U32(u32::try_from(uint()?)?),
+        Signature::I64 => Value::I64(int()?),
+        Signature::U64 => Value::U64(uint()?),
+        Signature::F64 => Value::F64(json.as_f64().ok_or_else(mismatch)?),
+        Signature::Str => Value::from(text()?.to_string()),
+        Signature::Signature => {
+            Value::from(text()?.parse::<Signature>().map_err(|e| anyhow!("{e}"))?)
+        }
+        Signature::ObjectPath => Value::from(ObjectPath::try_from(text()?.to_string())?),
+        Signature::Variant => Value::Value(Box::new(decode_variant(json)?)),
+        Signature::Array(element) => {
+            let mut array = Array::new(element);
+            for item in json.as_array().ok_or_else(mismatch)? {
+                array.append(decode(element, item)?)?;
+            }
+            Value::Array(array)
+        }
+        Signature::Dict { key, value } => {
+            let mut dict = Dict::new(key, value);
+            for (k, v) in json.as_object().ok_or_else(mismatch)? {
+                // JSON keys are always strings; numbers and booleans were
+                // written as their text.
+                let k = match **key {
+                    Signature::Str | Signature::ObjectPath | Signature::Signature => {
+                        Json::String(k.clone())
+                    }
+                    _ => serde_json::from_str(k)?,
+                };
+                dict.append(decode(key, &k)?, decode(value, v)?)?;
+            }
+            Value::Dict(dict)
+        }
+        Signature::Structure(fields) => {
+            let items = json.as_array().ok_or_else(mismatch)?;
+            if items.len() != fields.len() {
+                return Err(mismatch());
+            }
+            let mut structure = StructureBuilder::new();
+            for (field, item) in fields.iter().zip(items) {
+                structure = structure.append_field(decode(field, item)?);
+            }
+            Value::Structure(structure.build()?)
+        }
+        _ => bail!("{signature} values cannot be replayed"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn owned(value: Value) -> OwnedValue {
+        value.try_to_owned().unwrap()
+    }
+
+    fn round_trip(value: Value) -> OwnedValue {
+        let props = HashMap::from([("Key".to_string(), owned(value))]);
+        let line = serde_json::to_string(&encode_props(&props)).unwrap();
+        let mut back = decode_props(serde_json::from_str(&line).unwrap()).unwrap();
+        back.remove("Key").unwrap()
+    }
+
+    /// Every number is just a number in JSON; the signature has to bring the
+    /// width back or `prop::<u8>` would read a `u64` and give up.
+    #[test]
+    fn values_keep_their_types() {
+        for value in [
+            Value::U8(71),
+            Value::U32(2437),
+            Value::I64(-5),
+            Value::Bool(true),
+            Value::from("wlan0"),
+            Value::from(b"home".to_vec()),
+            Value::from(vec![
+                ObjectPath::try_from("/ap/1").unwrap(),
+                ObjectPath::try_from("/ap/2").unwrap(),
+            ]),
+            Value::from(HashMap::from([(1u32, "one")])),
+        ] {
+            let expected = owned(value.try_clone().unwrap());
+            assert_eq!(round_trip(value), expected);
+        }
+    }
+
+    #[test]
+    fn nested_variants_and_structures_round_trip() {
+        let address: HashMap<&str, Value> = HashMap::from([
+            ("address", Value::from("10.0.0.2")),
+            ("prefix", Value::from(24u32)),
+        ]);
+        let value = Value::from(vec![address]);
+        let expected = owned(value.try_clone().unwrap());
+        assert_eq!(round_trip(value), expected);
+
+        let structure = Value::from(zbus::zvariant::Structure::from((1u32, "x")));
+        let expected = owned(structure.try_clone().unwrap());
+        assert_eq!(round_trip(structure), expected);
+    }
+
+    #[test]
+    fn secrets_are_redacted() {
+        let props = HashMap::from([
+            ("psk".to_string(), owned(Value::from("hunter22"))),
+            ("psk-flags".to_string(), owned(Value::U32(0))),
+        ]);
+
+        let back = decode_props(encode_props(&props)).unwrap();
+        assert_eq!(back["psk"], owned(Value::from(REDACTED)));
+        assert_eq!(back["psk-flags"], owned(Value::U32(0)));
+    }
+
+    fn recording(lines: &[String]) -> Result<Recording> {
+        Recording::read(lines.join("\n").as_bytes())
+    }
+
+    fn header() -> String {
+        serde_json::to_string(&Frame::header()).unwrap()
+    }
+
+    #[test]
+    fn starts_from_everything_before_the_first_change() {
+        let graph = Frame::graph(Duration::ZERO, &ManagedObjects::new());
+        let profiles = Frame::profiles(Duration::ZERO, &Profiles::new());
+        let change = Frame::InterfacesRemoved {
+            at_ms: 1500,
+            path: "/ap/1".to_string(),
+            interfaces: vec!["org.freedesktop.NetworkManager.AccessPoint".to_string()],
+        };
+        let lines: Vec<String> = [&graph, &profiles, &change]
+            .into_iter()
+            .map(|frame| serde_json::to_string(frame).unwrap())
+            .collect();
+
+        let (start, rest) = recording(&[vec![header()], lines].concat())
+            .unwrap()
+            .split();
+        assert_eq!(start.len(), 2);
+        assert_eq!(rest.len(), 1);
+        assert_eq!(rest[0].0, Duration::from_millis(1500));
+    }
+
+    #[test]
+    fn a_partial_last_line_is_dropped() {
+        let graph =
+            serde_json::to_string(&Frame::graph(Duration::ZERO, &ManagedObjects::new())).unwrap();
+        let cut = r#"{"kind":"properties_changed","at_ms":12,"pa"#.to_string();
+
+        let (start, rest) = recording(&[header(), graph, cut]).unwrap().split();
+        assert_eq!(start.len(), 1);
+        assert!(rest.is_empty());
+    }
+
+    #[test]
+    fn refuses_other_files_and_newer_formats() {
+        assert!(recording(&["{}".to_string()]).is_err());
+
+        let newer = serde_json::to_string(&Frame::Header {
+            version: FORMAT_VERSION + 1,
+            wlctl: "9.9.9".to_string(),
+            started: String::new(),
+        })
+        .unwrap();
+        let err = recording(&[newer]).err().unwrap();
+        assert!(format!("{err:#}").contains("9.9.9"));
+
+        // A header alone has nothing to replay.
+        assert!(recording(&[header()]).is_err());
+    }
+}
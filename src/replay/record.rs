@@ -0,0 +1,144 @@
+// `wlctl record`: the object graph, the saved profiles' settings, then every
+// change signal, written as they arrive.
+
+use std::fs::File;
+use std::future::Future;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use futures::StreamExt;
+use tokio::time::Instant;
+
+use super::{Frame, Profiles};
+use crate::backend::{BackendKind, Snapshot};
+use crate::nm::snapshot::changes;
+use crate::nm::{NMClient, NmSnapshot};
+
+/// Records until Ctrl-C, or for `duration` when one is given. Returns the
+/// process exit status.
+pub async fn run(path: &Path, duration: Option<Duration>, backend: BackendKind) -> Result<i32> {
+    if backend == BackendKind::Iwd {
+        bail!("Recording reads NetworkManager's object graph; iwd cannot be recorded");
+    }
+    let client = NMClient::new()
+        .await
+        .context("Could not reach NetworkManager over D-Bus")?;
+
+    let stop = async {
+        match duration {
+            Some(duration) => {
+                tokio::select! {
+                    _ = tokio::time::sleep(duration) => {}
+                    _ = tokio::signal::ctrl_c() => {}
+                }
+            }
+            None => {
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    };
+
+    eprintln!("Recording to {}; press Ctrl-C to stop", path.display());
+    let changes = record(&client, path, stop).await?;
+    eprintln!("Recorded {} changes to {}", changes, path.display());
+    Ok(0)
+}
+
+/// Writes a recording of `client`'s state to `path` until `stop` completes.
+/// Returns how many change signals it holds.
+///
+/// Each frame is flushed as it is written, so a recording killed outright
+/// still replays up to its last whole line.
+pub async fn record(
+    client: &NMClient,
+    path: &Path,
+    stop: impl Future<Output = ()>,
+) -> Result<usize> {
+    // Subscribed before the graph is read, as `NmWatcher` does, so a change
+    // racing the read is recorded after it rather than lost.
+    let mut changes = changes(client).await?;
+    let mut graph = NmSnapshot::fetch(client).await?;
+
+    let mut out = Writer::create(path)?;
+    out.write(&Frame::graph(out.elapsed(), graph.objects()))?;
+    let mut versions = graph.profile_versions();
+    out.write(&Frame::profiles(
+        out.elapsed(),
+        &read_profiles(client, &versions).await,
+    ))?;
+
+    tokio::pin!(stop);
+    let mut count = 0;
+    loop {
+        // Signals already received are written before stopping, so a stop
+        // right after a change still records it.
+        let change = tokio::select! {
+            biased;
+            change = changes.next() => match change {
+                Some(change) => change,
+                None => break,
+            },
+            _ = &mut stop => break,
+        };
+
+        let at = out.elapsed();
+        let frame = Frame::change(at, &change);
+        graph.apply(change);
+
+        // A profile added, removed or edited: its settings go out first, so
+        // the replayed change never names a profile the replay cannot read.
+        let current = graph.profile_versions();
+        if current != versions {
+            out.write(&Frame::profiles(at, &read_profiles(client, &current).await))?;
+            versions = current;
+        }
+
+        out.write(&frame)?;
+        count += 1;
+    }
+
+    Ok(count)
+}
+
+/// Settings of every profile in `versions`. One that vanishes before it is
+/// read is left out, as `NMClient::wifi_connections` does.
+async fn read_profiles(client: &NMClient, versions: &[(String, Option<u64>)]) -> Profiles {
+    let mut profiles = Profiles::new();
+    for (path, _) in versions {
+        if let Ok(settings) = client.get_connection_settings(path).await {
+            profiles.insert(path.clone(), settings);
+        }
+    }
+    profiles
+}
+
+struct Writer {
+    out: BufWriter<File>,
+    started: Instant,
+}
+
+impl Writer {
+    fn create(path: &Path) -> Result<Self> {
+        let file =
+            File::create(path).with_context(|| format!("Could not create {}", path.display()))?;
+        let mut writer = Self {
+            out: BufWriter::new(file),
+            started: Instant::now(),
+        };
+        writer.write(&Frame::header())?;
+        Ok(writer)
+    }
+
+    fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    fn write(&mut self, frame: &Frame) -> Result<()> {
+        serde_json::to_writer(&mut self.out, frame)?;
+        self.out.write_all(b"\n")?;
+        self.out.flush()?;
+        Ok(())
+    }
+}
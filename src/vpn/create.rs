@@ -0,0 +1,336 @@
+//! The form behind "new tunnel": a keypair generated up front, the handful of
+//! fields a client tunnel needs, and a preview before anything is saved.
+
+use std::net::IpAddr;
+
+use anyhow::{Result, anyhow, bail};
+use tui_input::Input;
+
+use super::keys::{self, KeyPair};
+use super::wg;
+use crate::nm::{WgConfig, WgPeerConfig, WgRouteTable};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CreateField {
+    Name,
+    Address,
+    Dns,
+    PeerPublicKey,
+    Endpoint,
+    AllowedIps,
+}
+
+impl CreateField {
+    pub const ALL: [CreateField; 6] = [
+        CreateField::Name,
+        CreateField::Address,
+        CreateField::Dns,
+        CreateField::PeerPublicKey,
+        CreateField::Endpoint,
+        CreateField::AllowedIps,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            CreateField::Name => "Name",
+            CreateField::Address => "Address",
+            CreateField::Dns => "DNS",
+            CreateField::PeerPublicKey => "Peer public key",
+            CreateField::Endpoint => "Endpoint",
+            CreateField::AllowedIps => "Allowed IPs",
+        }
+    }
+
+    /// Shown in an empty field.
+    pub fn placeholder(self) -> &'static str {
+        match self {
+            CreateField::Name => "optional; named after the endpoint",
+            CreateField::Address => "10.0.0.2/32",
+            CreateField::Dns => "optional, e.g. 10.0.0.1",
+            CreateField::PeerPublicKey => "the server's key",
+            CreateField::Endpoint => "vpn.example.com:51820",
+            CreateField::AllowedIps => "0.0.0.0/0, ::/0",
+        }
+    }
+}
+
+/// A WireGuard tunnel being written from scratch.
+#[derive(Debug)]
+pub struct CreateForm {
+    /// Ours; the public half goes to whoever runs the server.
+    pub keys: KeyPair,
+    pub name: Input,
+    pub address: Input,
+    pub dns: Input,
+    pub peer_public_key: Input,
+    pub endpoint: Input,
+    pub allowed_ips: Input,
+    pub focused_field: CreateField,
+    /// The config the fields describe, once they have been checked. Saving
+    /// only happens from the preview.
+    pub preview: Option<WgConfig>,
+}
+
+impl CreateForm {
+    /// A blank form with a new keypair. Allowed IPs start out routing
+    /// everything through the tunnel, the usual client setup.
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            keys: KeyPair::generate()?,
+            name: Input::default(),
+            address: Input::default(),
+            dns: Input::default(),
+            peer_public_key: Input::default(),
+            endpoint: Input::default(),
+            allowed_ips: Input::new("0.0.0.0/0, ::/0".to_string()),
+            focused_field: CreateField::Name,
+            preview: None,
+        })
+    }
+
+    pub fn input(&self, field: CreateField) -> &Input {
+        match field {
+            CreateField::Name => &self.name,
+            CreateField::Address => &self.address,
+            CreateField::Dns => &self.dns,
+            CreateField::PeerPublicKey => &self.peer_public_key,
+            CreateField::Endpoint => &self.endpoint,
+            CreateField::AllowedIps => &self.allowed_ips,
+        }
+    }
+
+    /// The input under the cursor.
+    pub fn focused_input(&mut self) -> &mut Input {
+        match self.focused_field {
+            CreateField::Name => &mut self.name,
+            CreateField::Address => &mut self.address,
+            CreateField::Dns => &mut self.dns,
+            CreateField::PeerPublicKey => &mut self.peer_public_key,
+            CreateField::Endpoint => &mut self.endpoint,
+            CreateField::AllowedIps => &mut self.allowed_ips,
+        }
+    }
+
+    /// Appends pasted text to the focused field. Line breaks are dropped, as
+    /// every field is a single line.
+    pub fn paste(&mut self, text: &str) {
+        let text: String = text.chars().filter(|c| !c.is_control()).collect();
+        let input = self.focused_input();
+        let value = format!("{}{}", input.value(), text.trim());
+        *input = Input::new(value);
+    }
+
+    pub fn next_field(&mut self) {
+        self.move_focus(1);
+    }
+
+    pub fn prev_field(&mut self) {
+        self.move_focus(CreateField::ALL.len() - 1);
+    }
+
+    fn move_focus(&mut self, step: usize) {
+        let at = CreateField::ALL
+            .iter()
+            .position(|&f| f == self.focused_field)
+            .unwrap_or(0);
+        self.focused_field = CreateField::ALL[(at + step) % CreateField::ALL.len()];
+    }
+
+    /// The profile name to save under; `None` to derive one from the config.
+    pub fn name(&self) -> Option<&str> {
+        Some(self.name.value().trim()).filter(|name| !name.is_empty())
+    }
+
+    /// Checks the fields and, when they describe a working tunnel, shows the
+    /// preview.
+    pub fn check(&mut self) -> Result<()> {
+        self.preview = Some(self.config()?);
+        Ok(())
+    }
+
+    /// The config the fields describe, built from the values themselves so
+    /// nothing typed into one field can end up read as another key.
+    fn config(&self) -> Result<WgConfig> {
+        let field = |input: &Input| input.value().trim().to_string();
+        let (address, dns, peer, endpoint, allowed_ips) = (
+            field(&self.address),
+            field(&self.dns),
+            field(&self.peer_public_key),
+            field(&self.endpoint),
+            field(&self.allowed_ips),
+        );
+        for (value, f) in [
+            (&address, CreateField::Address),
+            (&peer, CreateField::PeerPublicKey),
+            (&endpoint, CreateField::Endpoint),
+            (&allowed_ips, CreateField::AllowedIps),
+        ] {
+            if value.is_empty() {
+                bail!("{} is required", f.label());
+            }
+        }
+        keys::decode_key(&peer)?;
+        if peer == self.keys.public_key {
+            bail!("The peer public key is this tunnel's own key");
+        }
+
+        check_endpoint(&endpoint)?;
+
+        let addresses = list(&address)
+            .map(wg::parse_cidr)
+            .collect::<Result<Vec<_>>>()?;
+        let dns = list(&dns)
+            .map(|item| {
+                item.parse::<IpAddr>()
+                    .map_err(|_| anyhow!("invalid DNS server '{item}'"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let allowed_ips = list(&allowed_ips)
+            .map(|item| Ok(wg::format_cidr(wg::network(wg::parse_cidr(item)?))))
+            .collect::<Result<Vec<_>>>()?;
+        if addresses.is_empty() || allowed_ips.is_empty() {
+            bail!("Address and Allowed IPs need at least one entry");
+        }
+
+        Ok(WgConfig {
+            private_key: self.keys.private_key.clone().into(),
+            addresses,
+            dns,
+            dns_search: Vec::new(),
+            listen_port: None,
+            mtu: None,
+            fwmark: None,
+            table: WgRouteTable::Auto,
+            peers: vec![WgPeerConfig {
+                public_key: peer,
+                endpoint: Some(endpoint),
+                allowed_ips,
+                preshared_key: None,
+                persistent_keepalive: None,
+            }],
+        })
+    }
+
+    /// The previewed config as wg-quick text, with the private key left out:
+    /// it never needs to leave this machine.
+    pub fn preview_text(&self) -> Option<String> {
+        let cfg = self.preview.as_ref()?;
+        Some(
+            wg::render(cfg)
+                .lines()
+                .map(|line| {
+                    if line.starts_with("PrivateKey") {
+                        "PrivateKey = (generated)".to_string()
+                    } else {
+                        line.to_string()
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        )
+    }
+}
+
+/// The non-empty entries of a comma-separated field.
+fn list(value: &str) -> impl Iterator<Item = &str> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+}
+
+/// An endpoint is `host:port`, with IPv6 hosts in brackets.
+fn check_endpoint(endpoint: &str) -> Result<()> {
+    let invalid = || anyhow!("Endpoint '{endpoint}' is not host:port");
+    let (host, port) = endpoint.rsplit_once(':').ok_or_else(invalid)?;
+    let host = match host.strip_prefix('[') {
+        Some(v6) => v6.strip_suffix(']').ok_or_else(invalid)?,
+        None => host,
+    };
+    let hostname = |c: char| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | ':');
+    if host.is_empty() || !host.chars().all(hostname) || port.parse::<u16>().is_err() {
+        return Err(invalid());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SERVER: &str = "xTIBA5rboUvnH4htodjb6e697QjLERt1NAB4mZqp8Dg=";
+
+    fn filled() -> CreateForm {
+        let mut form = CreateForm::new().unwrap();
+        form.address = Input::new("10.0.0.2/32".into());
+        form.dns = Input::new("10.0.0.1".into());
+        form.peer_public_key = Input::new(SERVER.into());
+        form.endpoint = Input::new("vpn.example.com:51820".into());
+        form
+    }
+
+    #[test]
+    fn a_filled_form_previews_without_the_private_key() {
+        let mut form = filled();
+        form.check().unwrap();
+
+        let cfg = form.preview.as_ref().unwrap();
+        assert_eq!(*cfg.private_key, form.keys.private_key);
+        assert_eq!(cfg.peers[0].public_key, SERVER);
+        assert_eq!(cfg.peers[0].allowed_ips, ["0.0.0.0/0", "::/0"]);
+
+        let text = form.preview_text().unwrap();
+        assert!(text.contains("PrivateKey = (generated)"));
+        assert!(!text.contains(&form.keys.private_key));
+        assert!(text.contains("Endpoint = vpn.example.com:51820"));
+    }
+
+    #[test]
+    fn check_names_what_is_wrong() {
+        let mut form = filled();
+        form.endpoint = Input::default();
+        let err = form.check().unwrap_err();
+        assert_eq!(err.to_string(), "Endpoint is required");
+        assert!(form.preview.is_none());
+
+        let mut form = filled();
+        form.peer_public_key = Input::new("not a key".into());
+        assert!(form.check().is_err());
+
+        let mut form = filled();
+        form.address = Input::new("10.0.0.300/32".into());
+        assert!(form.check().is_err());
+    }
+
+    #[test]
+    fn field_values_cannot_smuggle_in_other_keys() {
+        let mut form = filled();
+        form.endpoint = Input::new("vpn.example.com:51820 # PostUp = x".into());
+        assert!(form.check().is_err());
+
+        let mut form = filled();
+        form.dns = Input::new("10.0.0.1; 10.0.0.2".into());
+        assert!(form.check().is_err());
+
+        let mut form = filled();
+        form.endpoint = Input::new("[fd00::1]:51820".into());
+        form.allowed_ips = Input::new("10.0.0.7/24".into());
+        form.check().unwrap();
+        let peer = &form.preview.as_ref().unwrap().peers[0];
+        assert_eq!(peer.endpoint.as_deref(), Some("[fd00::1]:51820"));
+        assert_eq!(peer.allowed_ips, ["10.0.0.0/24"]);
+    }
+
+    #[test]
+    fn focus_wraps_and_paste_stays_on_one_line() {
+        let mut form = CreateForm::new().unwrap();
+        form.prev_field();
+        assert_eq!(form.focused_field, CreateField::AllowedIps);
+        form.next_field();
+        form.next_field();
+        assert_eq!(form.focused_field, CreateField::Address);
+
+        form.paste("10.0.0.2/32\n");
+        assert_eq!(form.address.value(), "10.0.0.2/32");
+    }
+}
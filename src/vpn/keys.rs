@@ -0,0 +1,93 @@
+//! WireGuard keys: X25519 keypairs in the base64 form wg-quick configs and
+//! NetworkManager use. Private key material is wiped when it is dropped.
+
+use std::fmt;
+
+use anyhow::{Result, anyhow, bail};
+use base64ct::{Base64, Encoding};
+use curve25519_dalek::scalar::clamp_integer;
+use x25519_dalek::{PublicKey, StaticSecret};
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+/// A freshly generated WireGuard keypair, both halves base64-encoded.
+#[derive(Clone, Zeroize, ZeroizeOnDrop)]
+pub struct KeyPair {
+    pub private_key: String,
+    pub public_key: String,
+}
+
+impl KeyPair {
+    /// Generates a keypair from the kernel's random number generator.
+    pub fn generate() -> Result<Self> {
+        let mut bytes = Zeroizing::new([0u8; 32]);
+        getrandom::fill(&mut *bytes).map_err(|e| anyhow!("Could not read random bytes: {e}"))?;
+        // Clamped before it is stored, as `wg genkey` does, so the saved key
+        // reads the same to every tool.
+        let secret = StaticSecret::from(clamp_integer(*bytes));
+        Ok(Self {
+            private_key: Base64::encode_string(secret.as_bytes()),
+            public_key: Base64::encode_string(PublicKey::from(&secret).as_bytes()),
+        })
+    }
+}
+
+impl fmt::Debug for KeyPair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeyPair")
+            .field("public_key", &self.public_key)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The public key for a base64 private key, as `wg pubkey` prints it.
+#[cfg(test)]
+pub fn public_key(private_key: &str) -> Result<String> {
+    let secret = StaticSecret::from(*decode_key(private_key)?);
+    Ok(Base64::encode_string(PublicKey::from(&secret).as_bytes()))
+}
+
+/// Decodes a base64 WireGuard key, which must hold exactly 32 bytes. The
+/// decoding runs in constant time, as the key may be a private one.
+pub fn decode_key(key: &str) -> Result<Zeroizing<[u8; 32]>> {
+    let mut bytes = Zeroizing::new([0u8; 32]);
+    match Base64::decode(key.trim(), &mut *bytes).map(<[u8]>::len) {
+        Ok(32) => Ok(bytes),
+        Ok(_) | Err(base64ct::Error::InvalidLength) => {
+            bail!("The key is not a 32-byte WireGuard key")
+        }
+        Err(base64ct::Error::InvalidEncoding) => bail!("The key is not base64"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_keys_pair_up() {
+        let pair = KeyPair::generate().unwrap();
+        assert_eq!(pair.private_key.len(), 44);
+        assert_eq!(public_key(&pair.private_key).unwrap(), pair.public_key);
+        assert_ne!(KeyPair::generate().unwrap().private_key, pair.private_key);
+        assert!(!format!("{pair:?}").contains(&pair.private_key));
+        // A key from a real `wg genkey | wg pubkey`.
+        assert_eq!(
+            public_key("yAnz5TF+lXXJte14tji3zlMNq+hd2rYUIgJBgB3fBmk=").unwrap(),
+            "HIgo9xNzJMWLKASShiTqIybxZ0U3wGLiUeJ1PKf8ykw="
+        );
+    }
+
+    #[test]
+    fn only_32_byte_base64_keys_decode() {
+        assert!(decode_key(" HIgo9xNzJMWLKASShiTqIybxZ0U3wGLiUeJ1PKf8ykw= ").is_ok());
+        let short = decode_key("Zm9v").unwrap_err().to_string();
+        assert!(short.contains("32-byte"), "{short}");
+        // The text may be a private key, so it never reaches the message.
+        assert!(!short.contains("Zm9v"), "{short}");
+        let garbled = decode_key("HIgo9xNzJMWLKASShiTqIybxZ0U3wGLiUeJ1PKf8yk*=")
+            .unwrap_err()
+            .to_string();
+        assert!(garbled.contains("base64"), "{garbled}");
+        assert!(!garbled.contains("HIgo"), "{garbled}");
+    }
+}
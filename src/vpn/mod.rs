@@ -1,18 +1,30 @@
 //! VPN connections modal — lists saved VPN / WireGuard profiles and toggles
-//! them on or off, mirroring `nmtui`'s connection list for the common case of
-//! pre-configured tunnels with saved credentials. Rendering lives in `render`;
-//! this module owns the modal state and NM orchestration.
-
+//! them on or off, mirroring `nmtui`'s connection list. Passwords and one-time
+//! codes a tunnel needs while coming up are prompted for by the secret agent
+//! in `nm::agent`. WireGuard tunnels can also be imported from and exported to
+//! wg-quick configs, and OpenVPN profiles imported from `.ovpn` files.
+//! Rendering lives in `render`; this module owns the modal state and NM
+//! orchestration.
+
+mod create;
+mod keys;
+mod ovpn;
 mod render;
 mod wg;
 
+pub use create::{CreateField, CreateForm};
 pub use render::render_modal;
 
 use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
+use qrcode::{EcLevel, QrCode};
 
-use crate::nm::{ActiveConnectionState, NMClient, VpnConnectionInfo, WgConfig};
+use crate::backend::Backend;
+use crate::nm::{ActiveConnectionState, VpnConnectionInfo, VpnKind, WgConfig};
 
 /// A saved VPN profile paired with its live activation state.
 #[derive(Debug, Clone)]
@@ -52,7 +64,7 @@ impl VpnEntry {
 }
 
 /// Formats a span of seconds compactly: "45s", "14m", "2h 3m", "1d 4h".
-fn format_duration(secs: u64) -> String {
+pub(crate) fn format_duration(secs: u64) -> String {
     let (d, h, m, s) = (secs / 86_400, secs / 3_600 % 24, secs / 60 % 60, secs % 60);
     if d > 0 {
         format!("{d}d {h}h")
@@ -73,10 +85,69 @@ pub enum VpnPrompt {
     ConfirmDelete { path: String, id: String },
     /// Capturing import input: a pasted WireGuard config or a `.conf` path.
     Import(String),
+    /// Showing a WireGuard profile as a config to scan or save.
+    Export(Export),
+    /// Filling in a new WireGuard tunnel.
+    Create(Box<CreateForm>),
+    /// A tunnel was just created; its public key stays on show until
+    /// dismissed, so it can be copied to the server.
+    Created { id: String, public_key: String },
+}
+
+/// Numbered copies `Export::save` tries before giving up.
+const MAX_EXPORT_SUFFIX: u32 = 100;
+
+/// A saved WireGuard profile rendered as a wg-quick config, for moving the
+/// tunnel to another device.
+pub struct Export {
+    pub id: String,
+    /// The config text; it holds the private key.
+    pub text: String,
+    pub qr_code: QrCode,
+}
+
+impl Export {
+    /// The file [`save`](Self::save) tries first: the profile name as a
+    /// `.conf`.
+    pub fn file_name(&self) -> String {
+        format!("{}.conf", self.id.replace('/', "-"))
+    }
+
+    /// Writes the config to `dir`, readable by the owner only. An existing
+    /// file is never overwritten: a name already taken gets a `-2`, `-3`, ...
+    /// suffix instead. Returns the path written.
+    pub fn save(&self, dir: &Path) -> Result<PathBuf> {
+        let stem = self.id.replace('/', "-");
+        for n in 1..=MAX_EXPORT_SUFFIX {
+            let path = match n {
+                1 => dir.join(self.file_name()),
+                n => dir.join(format!("{stem}-{n}.conf")),
+            };
+            let mut file = match std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .mode(0o600)
+                .open(&path)
+            {
+                Ok(file) => file,
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e).with_context(|| format!("writing {}", path.display())),
+            };
+            file.write_all(self.text.as_bytes())
+                .with_context(|| format!("writing {}", path.display()))?;
+            return Ok(path);
+        }
+        bail!(
+            "{} and {MAX_EXPORT_SUFFIX} numbered copies already exist in {}",
+            self.file_name(),
+            dir.display()
+        )
+    }
 }
 
 /// Interactive modal state: the profile list, a selection cursor, and an
-/// optional active prompt (delete confirmation or import input).
+/// optional active prompt (delete confirmation, import input, an export or a
+/// new tunnel).
 pub struct VpnModal {
     pub entries: Vec<VpnEntry>,
     pub selected: usize,
@@ -85,9 +156,9 @@ pub struct VpnModal {
 
 impl VpnModal {
     /// Lists saved VPN profiles and resolves which are currently active.
-    pub async fn load(nm: &NMClient) -> Result<Self> {
+    pub async fn load(backend: &dyn Backend) -> Result<Self> {
         Ok(Self {
-            entries: list_entries(nm).await?,
+            entries: list_entries(backend).await?,
             selected: 0,
             prompt: None,
         })
@@ -101,6 +172,37 @@ impl VpnModal {
         }
     }
 
+    /// The export on show, when there is one.
+    pub fn export(&self) -> Option<&Export> {
+        match &self.prompt {
+            Some(VpnPrompt::Export(export)) => Some(export),
+            _ => None,
+        }
+    }
+
+    /// The new-tunnel form, when it is open.
+    pub fn create_form(&self) -> Option<&CreateForm> {
+        match &self.prompt {
+            Some(VpnPrompt::Create(form)) => Some(form),
+            _ => None,
+        }
+    }
+
+    pub fn create_form_mut(&mut self) -> Option<&mut CreateForm> {
+        match &mut self.prompt {
+            Some(VpnPrompt::Create(form)) => Some(form),
+            _ => None,
+        }
+    }
+
+    /// The just-created tunnel's name and public key, while they are shown.
+    pub fn created(&self) -> Option<(&str, &str)> {
+        match &self.prompt {
+            Some(VpnPrompt::Created { id, public_key }) => Some((id, public_key)),
+            _ => None,
+        }
+    }
+
     /// The profile pending deletion, when the confirm prompt is active.
     pub fn pending_delete(&self) -> Option<&str> {
         match &self.prompt {
@@ -128,22 +230,25 @@ impl VpnModal {
 
     /// Re-fetches profiles and active state, clamping the cursor if the list
     /// shrank. Profiles are sorted by name, so the cursor stays meaningful.
-    pub async fn refresh(&mut self, nm: &NMClient) -> Result<()> {
-        self.entries = list_entries(nm).await?;
+    pub async fn refresh(&mut self, backend: &dyn Backend) -> Result<()> {
+        self.entries = list_entries(backend).await?;
         self.selected = self.selected.min(self.entries.len().saturating_sub(1));
         Ok(())
     }
 
     /// Flips autoconnect on the selected profile and re-reads state so the UI
     /// reflects the change. No-op on an empty list.
-    pub async fn toggle_autoconnect(&mut self, nm: &NMClient) -> Result<Option<(String, bool)>> {
+    pub async fn toggle_autoconnect(
+        &mut self,
+        backend: &dyn Backend,
+    ) -> Result<Option<(String, bool)>> {
         let Some(entry) = self.selected_entry() else {
             return Ok(None);
         };
         let next = !entry.info.autoconnect;
         let (path, id) = (entry.info.path.clone(), entry.info.id.clone());
-        nm.set_connection_autoconnect(&path, next).await?;
-        self.refresh(nm).await?;
+        backend.set_connection_autoconnect(&path, next).await?;
+        self.refresh(backend).await?;
         Ok(Some((id, next)))
     }
 
@@ -159,6 +264,54 @@ impl VpnModal {
         }
     }
 
+    /// Reads the selected WireGuard profile back from the backend and shows it
+    /// as a config. No-op on an empty list.
+    pub async fn begin_export(&mut self, backend: &dyn Backend) -> Result<()> {
+        let Some(entry) = self.selected_entry() else {
+            return Ok(());
+        };
+        if entry.info.kind != VpnKind::WireGuard {
+            bail!("Only WireGuard profiles can be exported");
+        }
+        let id = entry.info.id.clone();
+        let cfg = backend.wireguard_config(&entry.info.path).await?;
+        let text = wg::render(&cfg);
+        // The lowest error correction keeps a multi-peer config scannable on
+        // a terminal-sized code.
+        let qr_code = QrCode::with_error_correction_level(&text, EcLevel::L)?;
+        self.prompt = Some(VpnPrompt::Export(Export { id, text, qr_code }));
+        Ok(())
+    }
+
+    /// Opens a blank new-tunnel form with a freshly generated keypair.
+    pub fn begin_create(&mut self) -> Result<()> {
+        self.prompt = Some(VpnPrompt::Create(Box::new(CreateForm::new()?)));
+        Ok(())
+    }
+
+    /// Saves the previewed tunnel from the new-tunnel form (without activating
+    /// it) and swaps the form for the new tunnel's public key. Returns the new
+    /// profile's name, or `None` when nothing has been previewed yet.
+    pub async fn save_created(&mut self, backend: &dyn Backend) -> Result<Option<String>> {
+        let Some(form) = self.create_form() else {
+            return Ok(None);
+        };
+        let Some(cfg) = &form.preview else {
+            return Ok(None);
+        };
+        let base = form
+            .name()
+            .map_or_else(|| name_from_config(cfg), str::to_string);
+        let public_key = form.keys.public_key.clone();
+        let id = create_profile(backend, &base, cfg).await?;
+        self.prompt = Some(VpnPrompt::Created {
+            id: id.clone(),
+            public_key,
+        });
+        self.refresh(backend).await?;
+        Ok(Some(id))
+    }
+
     /// Opens the import prompt with an empty buffer.
     pub fn begin_import(&mut self) {
         self.prompt = Some(VpnPrompt::Import(String::new()));
@@ -196,12 +349,22 @@ impl VpnModal {
 
     /// Deletes the profile captured by the active confirm prompt. Returns the
     /// removed profile's name. No-op when no delete is pending.
-    pub async fn delete_confirmed(&mut self, nm: &NMClient) -> Result<Option<String>> {
+    pub async fn delete_confirmed(&mut self, backend: &dyn Backend) -> Result<Option<String>> {
         let Some(VpnPrompt::ConfirmDelete { path, id }) = self.prompt.take() else {
             return Ok(None);
         };
-        nm.delete_connection(&path).await?;
-        self.refresh(nm).await?;
+        let openvpn = self
+            .entries
+            .iter()
+            .find(|e| e.info.path == path && e.info.kind == VpnKind::Vpn)
+            .map(|e| e.info.uuid.clone());
+        backend.delete_connection(&path).await?;
+        self.refresh(backend).await?;
+        // The keys an OpenVPN import wrote out go with the profile.
+        if let Some(uuid) = openvpn {
+            remove_openvpn_files(&openvpn_dir()?, &uuid)
+                .with_context(|| format!("{id} was deleted, but not its keys"))?;
+        }
         Ok(Some(id))
     }
 }
@@ -209,48 +372,142 @@ impl VpnModal {
 /// Activates the entry if it is down, deactivates it if up. VPNs attach to no
 /// specific device, so activation passes the null device path (`/`) and lets
 /// NetworkManager pick.
-pub async fn toggle(nm: &NMClient, entry: &VpnEntry) -> Result<()> {
+pub async fn toggle(backend: &dyn Backend, entry: &VpnEntry) -> Result<()> {
     match &entry.active_path {
-        Some(active) => nm.deactivate_connection(active).await,
+        Some(active) => backend.deactivate_connection(active).await,
         None => {
-            nm.activate_connection(&entry.info.path, "/").await?;
+            backend.activate_connection(&entry.info.path, "/").await?;
             Ok(())
         }
     }
 }
 
-/// Reads a WireGuard `.conf` from `path`, parses it, and creates a matching
-/// NetworkManager profile (without activating it). Returns the new profile's
-/// display name (derived from the file name). `~` is expanded to `$HOME`.
-pub async fn import_from_file(nm: &NMClient, path: &str) -> Result<String> {
+/// A profile created from an imported config.
+#[derive(Debug)]
+pub struct Imported {
+    /// The new profile's display name.
+    pub id: String,
+    /// What in the config NetworkManager will not honour.
+    pub warnings: Vec<String>,
+}
+
+/// Imports what was typed or pasted into the import prompt: a pasted
+/// WireGuard or OpenVPN config, or otherwise a path to a `.conf` or `.ovpn`
+/// file.
+pub async fn import(backend: &dyn Backend, input: &str) -> Result<Imported> {
+    if input.to_ascii_lowercase().contains("[interface]") || ovpn::looks_like(input) {
+        import_from_text(backend, input).await
+    } else {
+        import_from_file(backend, input).await
+    }
+}
+
+/// Reads a WireGuard `.conf` or OpenVPN `.ovpn` from `path`, parses it, and
+/// creates a matching NetworkManager profile (without activating it). The
+/// profile is named after the file. `~` is expanded to `$HOME`.
+pub async fn import_from_file(backend: &dyn Backend, path: &str) -> Result<Imported> {
     let expanded = expand_tilde(path);
     let text = tokio::fs::read_to_string(&expanded)
         .await
         .with_context(|| format!("reading {expanded}"))?;
-    let cfg = wg::parse(&text)?;
 
-    let base = std::path::Path::new(&expanded)
+    let path = Path::new(&expanded);
+    let base = path
         .file_stem()
         .and_then(|s| s.to_str())
-        .filter(|s| !s.is_empty())
-        .unwrap_or("wireguard");
+        .filter(|s| !s.is_empty());
+
+    let is_ovpn = path.extension().is_some_and(|ext| ext == "ovpn");
+    if is_ovpn || (!is_wireguard(&text) && ovpn::looks_like(&text)) {
+        let profile = ovpn::parse(&text)?;
+        let base = base.map_or_else(|| profile.name(), str::to_string);
+        // NetworkManager needs absolute paths for the files the profile names.
+        let absolute = std::path::absolute(path)?;
+        return import_openvpn(backend, &base, absolute.parent(), profile, &openvpn_dir()?).await;
+    }
+
+    let parsed = wg::parse(&text)?;
+    let id = create_profile(backend, base.unwrap_or("wireguard"), &parsed.config).await?;
+    Ok(Imported {
+        id,
+        warnings: parsed.warnings,
+    })
+}
+
+/// Parses a pasted WireGuard or OpenVPN config and creates a NetworkManager
+/// profile (without activating it). The display name is derived from the
+/// peer endpoint or remote since pasted text carries no file name.
+pub async fn import_from_text(backend: &dyn Backend, text: &str) -> Result<Imported> {
+    if !is_wireguard(text) && ovpn::looks_like(text) {
+        let profile = ovpn::parse(text)?;
+        let base = profile.name();
+        return import_openvpn(backend, &base, None, profile, &openvpn_dir()?).await;
+    }
+
+    let parsed = wg::parse(text)?;
+    let base = name_from_config(&parsed.config);
+    let id = create_profile(backend, &base, &parsed.config).await?;
+    Ok(Imported {
+        id,
+        warnings: parsed.warnings,
+    })
+}
+
+fn is_wireguard(text: &str) -> bool {
+    text.to_ascii_lowercase().contains("[interface]")
+}
+
+/// Where the certificates and keys of imported OpenVPN profiles are kept, one
+/// directory per profile.
+fn openvpn_dir() -> Result<PathBuf> {
+    let data = dirs::data_dir().context("No data directory to keep OpenVPN keys in")?;
+    Ok(data.join("wlctl").join("openvpn"))
+}
 
-    create_profile(nm, base, &cfg).await
+/// Saves an OpenVPN profile under a name no other profile has. Its inline
+/// keys and certificates go to `root/<uuid>`, which is removed again if the
+/// profile cannot be saved.
+async fn import_openvpn(
+    backend: &dyn Backend,
+    base_name: &str,
+    base_dir: Option<&Path>,
+    mut profile: ovpn::Profile,
+    root: &Path,
+) -> Result<Imported> {
+    let existing = backend.get_vpn_connections().await?;
+    let ids: HashSet<&str> = existing.iter().map(|v| v.id.as_str()).collect();
+    let id = dedupe(&ids, base_name);
+
+    let warnings = std::mem::take(&mut profile.warnings);
+    let cfg = profile.store(base_dir, root)?;
+    if let Err(e) = backend.add_openvpn_connection(&id, &cfg).await {
+        // Made by `store` just now, so nothing else is in it.
+        let _ = std::fs::remove_dir_all(root.join(&cfg.uuid));
+        return Err(e);
+    }
+    Ok(Imported { id, warnings })
 }
 
-/// Parses pasted WireGuard config text and creates a NetworkManager profile
-/// (without activating it). The display name is derived from the peer endpoint
-/// since pasted text carries no file name. Returns the new profile's name.
-pub async fn import_from_text(nm: &NMClient, text: &str) -> Result<String> {
-    let cfg = wg::parse(text)?;
-    let base = name_from_config(&cfg);
-    create_profile(nm, &base, &cfg).await
+/// Removes the directory an OpenVPN import wrote the profile `uuid`'s keys
+/// to, if it has one.
+fn remove_openvpn_files(root: &Path, uuid: &str) -> Result<()> {
+    // Only ever a plain directory name under `root`.
+    if uuid.is_empty() || Path::new(uuid).file_name() != Some(uuid.as_ref()) {
+        return Ok(());
+    }
+    let dir = root.join(uuid);
+    match std::fs::remove_dir_all(&dir) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(e).with_context(|| format!("removing {}", dir.display()))
+        }
+        _ => Ok(()),
+    }
 }
 
 /// Creates the profile under a display name and interface name that don't
 /// collide with existing saved profiles. A single fetch backs both dedup checks.
-async fn create_profile(nm: &NMClient, base_name: &str, cfg: &WgConfig) -> Result<String> {
-    let existing = nm.get_vpn_connections().await?;
+async fn create_profile(backend: &dyn Backend, base_name: &str, cfg: &WgConfig) -> Result<String> {
+    let existing = backend.get_vpn_connections().await?;
     let ids: HashSet<&str> = existing.iter().map(|v| v.id.as_str()).collect();
     let ifaces: HashSet<&str> = existing
         .iter()
@@ -260,7 +517,9 @@ async fn create_profile(nm: &NMClient, base_name: &str, cfg: &WgConfig) -> Resul
 
     let id = dedupe(&ids, base_name);
     let interface = unique_interface(&ifaces, &sanitize_ifname(base_name));
-    nm.add_wireguard_connection(&id, &interface, cfg).await?;
+    backend
+        .add_wireguard_connection(&id, &interface, cfg)
+        .await?;
     Ok(id)
 }
 
@@ -299,10 +558,11 @@ fn unique_interface(taken: &HashSet<&str>, base: &str) -> String {
     }
 }
 
-/// Derives a friendly profile name from the peer endpoint (e.g. `wg-nl247` from
-/// a hostname, `wg-185-185-50-27` from an IP). Falls back to `wg-imported`.
+/// Derives a friendly profile name from the first peer endpoint (e.g.
+/// `wg-nl247` from a hostname, `wg-185-185-50-27` from an IP). Falls back to
+/// `wg-imported`.
 fn name_from_config(cfg: &WgConfig) -> String {
-    let Some(endpoint) = &cfg.peer.endpoint else {
+    let Some(endpoint) = cfg.peers.iter().find_map(|p| p.endpoint.as_ref()) else {
         return "wg-imported".to_string();
     };
 
@@ -357,15 +617,13 @@ fn sanitize_ifname(name: &str) -> String {
 
 /// Joins saved VPN profiles with the set of active connections so each entry
 /// carries its current state and (when up) its active-connection path.
-async fn list_entries(nm: &NMClient) -> Result<Vec<VpnEntry>> {
-    let profiles = nm.get_vpn_connections().await?;
+async fn list_entries(backend: &dyn Backend) -> Result<Vec<VpnEntry>> {
+    let profiles = backend.get_vpn_connections().await?;
 
     // Map saved-profile path -> (active-connection path, state) for what's up.
     let mut active_by_profile: HashMap<String, (String, ActiveConnectionState)> = HashMap::new();
-    for active_path in nm.get_active_connections().await? {
-        if let Ok(info) = nm.get_active_connection_info(active_path.as_str()).await {
-            active_by_profile.insert(info.connection_path, (info.path, info.state));
-        }
+    for info in backend.get_active_connections().await? {
+        active_by_profile.insert(info.connection_path, (info.path, info.state));
     }
 
     let mut entries = Vec::with_capacity(profiles.len());
@@ -373,7 +631,11 @@ async fn list_entries(nm: &NMClient) -> Result<Vec<VpnEntry>> {
         match active_by_profile.get(&info.path) {
             Some((active_path, state)) => {
                 // Best-effort: a missing lease shouldn't drop the whole entry.
-                let ipv4 = nm.active_connection_ipv4(active_path).await.ok().flatten();
+                let ipv4 = backend
+                    .active_connection_ipv4(active_path)
+                    .await
+                    .ok()
+                    .flatten();
                 entries.push(VpnEntry {
                     info,
                     active_path: Some(active_path.clone()),
@@ -492,16 +754,21 @@ mod tests {
     #[test]
     fn name_from_config_derives_from_endpoint() {
         let make = |endpoint: Option<&str>| WgConfig {
-            private_key: "k=".into(),
+            private_key: String::from("k=").into(),
             addresses: vec![],
             dns: vec![],
-            peer: crate::nm::WgPeerConfig {
+            dns_search: vec![],
+            listen_port: None,
+            mtu: None,
+            fwmark: None,
+            table: Default::default(),
+            peers: vec![crate::nm::WgPeerConfig {
                 public_key: "p=".into(),
                 endpoint: endpoint.map(str::to_string),
                 allowed_ips: vec![],
                 preshared_key: None,
                 persistent_keepalive: None,
-            },
+            }],
         };
         assert_eq!(
             name_from_config(&make(Some("185.185.50.27:51820"))),
@@ -564,4 +831,139 @@ mod tests {
         assert_eq!(m.selected, 0);
         assert!(m.selected_entry().is_none());
     }
+
+    #[tokio::test]
+    async fn modal_drives_autoconnect_the_tunnel_and_delete_through_the_backend() {
+        let fake = crate::backend::fake::FakeBackend::default();
+        fake.add_wireguard_profile("work");
+
+        let mut modal = VpnModal::load(&fake).await.unwrap();
+        assert_eq!(modal.entries.len(), 1);
+        assert!(!modal.entries[0].is_active());
+
+        let toggled = modal.toggle_autoconnect(&fake).await.unwrap();
+        assert_eq!(toggled, Some(("work".to_string(), true)));
+        assert!(modal.entries[0].info.autoconnect);
+
+        toggle(&fake, &modal.entries[0]).await.unwrap();
+        modal.refresh(&fake).await.unwrap();
+        assert!(modal.entries[0].is_active());
+        assert_eq!(modal.entries[0].ipv4.as_deref(), Some("10.0.0.2/24"));
+        // VPNs are brought up without a device.
+        assert!(fake.active_connections()[0].devices.is_empty());
+
+        toggle(&fake, &modal.entries[0]).await.unwrap();
+        modal.refresh(&fake).await.unwrap();
+        assert!(!modal.entries[0].is_active());
+
+        modal.begin_delete();
+        assert_eq!(
+            modal.delete_confirmed(&fake).await.unwrap().as_deref(),
+            Some("work")
+        );
+        assert!(modal.is_empty());
+        assert!(VpnModal::load(&fake).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn export_renders_the_saved_tunnel_and_saves_it_privately() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let fake = crate::backend::fake::FakeBackend::default();
+        let conf = "[Interface]\nPrivateKey = k=\nAddress = 10.0.0.2/32\n\n[Peer]\nPublicKey = p=\nAllowedIPs = 0.0.0.0/0\nEndpoint = vpn.example.com:51820\n";
+        let imported = import_from_text(&fake, conf).await.unwrap();
+
+        let mut modal = VpnModal::load(&fake).await.unwrap();
+        modal.begin_export(&fake).await.unwrap();
+        let export = modal.export().expect("export on show");
+        assert_eq!(export.id, imported.id);
+        assert_eq!(export.text, conf);
+
+        let dir = std::env::temp_dir().join(format!("wlctl-export-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let saved = export.save(&dir);
+        let again = export.save(&dir);
+        let contents = saved.as_ref().ok().map(std::fs::read_to_string);
+        let mode = saved
+            .as_ref()
+            .ok()
+            .map(|path| std::fs::metadata(path).unwrap().permissions().mode());
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(saved.unwrap(), dir.join("wg-vpn.conf"));
+        assert_eq!(contents.unwrap().unwrap(), conf);
+        assert_eq!(mode.unwrap() & 0o777, 0o600);
+        // An existing file is never overwritten; the next name is used.
+        assert_eq!(again.unwrap(), dir.join("wg-vpn-2.conf"));
+
+        modal.cancel_prompt();
+        assert!(modal.export().is_none());
+    }
+
+    #[tokio::test]
+    async fn a_created_tunnel_is_saved_with_its_generated_key() {
+        let fake = crate::backend::fake::FakeBackend::default();
+        let mut modal = VpnModal::load(&fake).await.unwrap();
+        modal.begin_create().unwrap();
+
+        // Nothing is saved before the preview.
+        assert_eq!(modal.save_created(&fake).await.unwrap(), None);
+
+        let form = modal.create_form_mut().unwrap();
+        form.address = tui_input::Input::new("10.0.0.2/32".into());
+        form.peer_public_key =
+            tui_input::Input::new("xTIBA5rboUvnH4htodjb6e697QjLERt1NAB4mZqp8Dg=".into());
+        form.endpoint = tui_input::Input::new("home.example.com:51820".into());
+        form.check().unwrap();
+        let public_key = form.keys.public_key.clone();
+
+        let id = modal.save_created(&fake).await.unwrap();
+        assert_eq!(id.as_deref(), Some("wg-home"));
+        assert!(modal.create_form().is_none());
+        assert_eq!(modal.created(), Some(("wg-home", public_key.as_str())));
+        assert_eq!(modal.entries.len(), 1);
+
+        let cfg = fake
+            .wireguard_config(&modal.entries[0].info.path)
+            .await
+            .unwrap();
+        assert_eq!(keys::public_key(&cfg.private_key).unwrap(), public_key);
+    }
+
+    #[tokio::test]
+    async fn an_openvpn_import_keeps_its_inline_keys_beside_the_profile() {
+        let fake = crate::backend::fake::FakeBackend::default();
+        let root = std::env::temp_dir().join(format!("wlctl-openvpn-{}", std::process::id()));
+        let text = "client\nremote vpn.example.com 1194\nauth-user-pass\n<ca>\nCA\n</ca>\n";
+
+        // Someone else's directory under a plain name is left alone.
+        let theirs = root.join("ovpn-vpn");
+        std::fs::create_dir_all(&theirs).unwrap();
+        std::fs::write(theirs.join("ca.crt"), "THEIRS").unwrap();
+
+        let mut ids = Vec::new();
+        for _ in 0..2 {
+            let profile = ovpn::parse(text).unwrap();
+            let name = profile.name();
+            let imported = import_openvpn(&fake, &name, None, profile, &root)
+                .await
+                .unwrap();
+            ids.push(imported.id);
+        }
+        let vpns = fake.get_vpn_connections().await.unwrap();
+        let ca = std::fs::read_to_string(root.join(&vpns[1].uuid).join("ca.crt"));
+        let untouched = std::fs::read_to_string(theirs.join("ca.crt"));
+        remove_openvpn_files(&root, &vpns[0].uuid).unwrap();
+        let removed = !root.join(&vpns[0].uuid).exists();
+        remove_openvpn_files(&root, "..").unwrap();
+        let kept = root.join(&vpns[1].uuid).exists();
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(ids, ["ovpn-vpn", "ovpn-vpn-2"]);
+        assert_eq!(ca.unwrap(), "CA\n");
+        assert_eq!(untouched.unwrap(), "THEIRS");
+        assert!(vpns.iter().all(|v| v.kind == VpnKind::Vpn));
+        assert_ne!(vpns[0].uuid, vpns[1].uuid);
+        assert!(removed && kept);
+    }
 }
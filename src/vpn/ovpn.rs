@@ -0,0 +1,576 @@
+//! OpenVPN `.ovpn` parser — the client profiles VPN providers and corporate
+//! admins hand out. Keys and certificates may be inline `<ca>`-style blocks or
+//! files next to the profile; NetworkManager's OpenVPN plugin only takes
+//! paths, so inline ones are written out before the [`OvpnConfig`] is
+//! built.
+
+use std::io::Write;
+use std::net::IpAddr;
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow, bail};
+
+use crate::nm::OvpnConfig;
+
+/// OpenVPN's defaults when neither `remote` nor `port`/`proto` say otherwise.
+const DEFAULT_PORT: u16 = 1194;
+const DEFAULT_PROTO: &str = "udp";
+
+/// Directives that only matter to the `openvpn` command line or are what
+/// NetworkManager's plugin does anyway; skipping them changes nothing.
+const HARMLESS: [&str; 15] = [
+    "client",
+    "tls-client",
+    "pull",
+    "nobind",
+    "persist-key",
+    "persist-tun",
+    "resolv-retry",
+    "auth-nocache",
+    "auth-retry",
+    "verb",
+    "mute",
+    "mute-replay-warnings",
+    "explicit-exit-notify",
+    "setenv",
+    "script-security",
+];
+
+/// Key or certificate material: inline in the profile, or a file it names.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Material {
+    Inline(String),
+    File(PathBuf),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Remote {
+    host: String,
+    port: Option<u16>,
+    proto: Option<String>,
+}
+
+/// A parsed `.ovpn` profile, before its material is written out.
+#[derive(Debug, Default)]
+pub struct Profile {
+    remotes: Vec<Remote>,
+    port: Option<u16>,
+    proto: Option<String>,
+    dev_type: Option<String>,
+    ca: Option<Material>,
+    cert: Option<Material>,
+    key: Option<Material>,
+    tls_auth: Option<Material>,
+    key_direction: Option<u8>,
+    tls_crypt: Option<Material>,
+    /// `auth-user-pass`: `Some(None)` asks at connect time, `Some(Some(_))`
+    /// names the username and password.
+    auth_user_pass: Option<Option<Material>>,
+    cipher: Option<String>,
+    data_ciphers: Option<String>,
+    auth: Option<String>,
+    remote_cert_tls: Option<String>,
+    /// What in the profile NetworkManager will not honour, one sentence each.
+    pub warnings: Vec<String>,
+}
+
+/// Whether `text` reads as an OpenVPN profile rather than anything else.
+pub fn looks_like(text: &str) -> bool {
+    text.lines().any(|line| {
+        let line = line.trim_start();
+        line.starts_with("remote ") || line == "client" || line.starts_with("<ca>")
+    })
+}
+
+/// Parses `.ovpn` text. Directives NetworkManager has no use for are skipped;
+/// a profile without a `remote`, a `ca`, or a way to authenticate is an
+/// error.
+pub fn parse(text: &str) -> Result<Profile> {
+    let mut profile = Profile::default();
+    let mut skipped: Vec<String> = Vec::new();
+    let mut skip = |directive: &str| {
+        if !skipped.iter().any(|s| s == directive) {
+            skipped.push(directive.to_string());
+        }
+    };
+    let mut lines = text.lines();
+
+    while let Some(raw) = lines.next() {
+        let line = raw.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+
+        // An inline block: `<ca>` … `</ca>`.
+        if let Some(tag) = line.strip_prefix('<').and_then(|s| s.strip_suffix('>')) {
+            let close = format!("</{tag}>");
+            let mut body = String::new();
+            loop {
+                let Some(raw) = lines.next() else {
+                    bail!("<{tag}> is never closed");
+                };
+                if raw.trim() == close {
+                    break;
+                }
+                body.push_str(raw.trim_end());
+                body.push('\n');
+            }
+            let inline = Some(Material::Inline(body));
+            match tag {
+                "ca" => profile.ca = inline,
+                "cert" => profile.cert = inline,
+                "key" => profile.key = inline,
+                "tls-auth" => profile.tls_auth = inline,
+                "tls-crypt" => profile.tls_crypt = inline,
+                "auth-user-pass" => profile.auth_user_pass = Some(inline),
+                _ => skip(&format!("<{tag}>")),
+            }
+            continue;
+        }
+
+        let words = tokenize(line)?;
+        let word = |n: usize| words.get(n).map(String::as_str);
+        let file = |n: usize| word(n).map(|w| Material::File(PathBuf::from(w)));
+        let value = |n: usize| words.get(n).cloned();
+
+        match words[0].as_str() {
+            "remote" => {
+                let host = value(1).ok_or_else(|| anyhow!("remote is missing a host"))?;
+                profile.remotes.push(Remote {
+                    host,
+                    port: word(2).map(parse_port).transpose()?,
+                    proto: word(3).map(normalize_proto),
+                });
+            }
+            "port" | "rport" => profile.port = word(1).map(parse_port).transpose()?,
+            "proto" => profile.proto = word(1).map(normalize_proto),
+            "dev" => {
+                profile.dev_type = word(1).and_then(|dev| {
+                    ["tun", "tap"]
+                        .into_iter()
+                        .find(|kind| dev.starts_with(kind))
+                        .map(str::to_string)
+                });
+            }
+            "dev-type" => profile.dev_type = value(1),
+            "ca" => profile.ca = file(1),
+            "cert" => profile.cert = file(1),
+            "key" => profile.key = file(1),
+            "tls-auth" => {
+                // Inline material is named `[inline]`, and the block follows.
+                if word(1) != Some("[inline]") {
+                    profile.tls_auth = file(1);
+                }
+                if let Some(dir) = word(2) {
+                    profile.key_direction = Some(parse_key_direction(dir)?);
+                }
+            }
+            "key-direction" => {
+                let dir = word(1).ok_or_else(|| anyhow!("key-direction is empty"))?;
+                profile.key_direction = Some(parse_key_direction(dir)?);
+            }
+            "tls-crypt" => {
+                if word(1) != Some("[inline]") {
+                    profile.tls_crypt = file(1);
+                }
+            }
+            "auth-user-pass" => {
+                profile.auth_user_pass = Some(file(1));
+            }
+            "cipher" => profile.cipher = value(1),
+            "data-ciphers" | "ncp-ciphers" => profile.data_ciphers = value(1),
+            "auth" => profile.auth = value(1),
+            "remote-cert-tls" => profile.remote_cert_tls = value(1),
+            "pkcs12" => bail!("pkcs12 bundles are not supported; split it into ca, cert and key"),
+            directive if HARMLESS.contains(&directive) => {}
+            directive => skip(directive),
+        }
+    }
+    if !skipped.is_empty() {
+        profile.warnings.push(format!(
+            "Not carried over to NetworkManager, so skipped: {}",
+            skipped.join(", ")
+        ));
+    }
+
+    if profile.remotes.is_empty() {
+        bail!("the profile has no remote");
+    }
+    if profile.ca.is_none() {
+        bail!("the profile has no ca");
+    }
+    if profile.cert.is_some() != profile.key.is_some() {
+        bail!("the profile needs both a cert and a key, or neither");
+    }
+    if profile.cert.is_none() && profile.auth_user_pass.is_none() {
+        bail!("the profile has neither a client certificate nor auth-user-pass");
+    }
+    Ok(profile)
+}
+
+impl Profile {
+    /// A profile name from the first remote, e.g. `ovpn-nl1` for
+    /// `nl1.example.com`.
+    pub fn name(&self) -> String {
+        let host = &self.remotes[0].host;
+        if host.parse::<IpAddr>().is_ok() {
+            format!("ovpn-{}", host.replace(['.', ':'], "-"))
+        } else {
+            format!("ovpn-{}", host.split('.').next().unwrap_or(host))
+        }
+    }
+
+    /// Writes inline material to `root/<uuid>`, a new directory named after
+    /// the profile's fresh UUID and readable by the owner only, and returns
+    /// the config NetworkManager needs. The directory is removed again if
+    /// anything fails. Relative file names are resolved against `base`, the
+    /// directory of the imported file; pasted text has none and must carry
+    /// its material inline.
+    pub fn store(self, base: Option<&Path>, root: &Path) -> Result<OvpnConfig> {
+        let uuid = new_uuid()?;
+        let dir = root.join(&uuid);
+        std::fs::create_dir_all(root).with_context(|| format!("creating {}", root.display()))?;
+        // `create`, not `create_all`: a directory that is already there
+        // belongs to someone else and must not be written into.
+        std::fs::DirBuilder::new()
+            .mode(0o700)
+            .create(&dir)
+            .with_context(|| format!("creating {}", dir.display()))?;
+        let stored = self.write_out(base, &dir, uuid);
+        if stored.is_err() {
+            let _ = std::fs::remove_dir_all(&dir);
+        }
+        stored
+    }
+
+    fn write_out(self, base: Option<&Path>, dir: &Path, uuid: String) -> Result<OvpnConfig> {
+        let place = |material: Option<Material>, name: &str| -> Result<Option<PathBuf>> {
+            match material {
+                None => Ok(None),
+                Some(Material::File(path)) => resolve(base, path).map(Some),
+                Some(Material::Inline(body)) => {
+                    let path = dir.join(name);
+                    write_private(&path, &body)?;
+                    Ok(Some(path))
+                }
+            }
+        };
+
+        let ca = place(self.ca, "ca.crt")?.expect("parse requires a ca");
+        let cert = place(self.cert, "client.crt")?;
+        let key = place(self.key, "client.key")?;
+        let tls_auth = place(self.tls_auth, "ta.key")?;
+        let tls_crypt = place(self.tls_crypt, "tls-crypt.key")?;
+
+        let (user_pass, username, password) = match self.auth_user_pass {
+            None => (false, None, None),
+            Some(None) => (true, None, None),
+            Some(Some(material)) => {
+                let text = match material {
+                    Material::Inline(body) => body,
+                    Material::File(path) => {
+                        let path = resolve(base, path)?;
+                        std::fs::read_to_string(&path)
+                            .with_context(|| format!("reading {}", path.display()))?
+                    }
+                };
+                let mut lines = text.lines().map(str::trim);
+                let username = lines.next().filter(|u| !u.is_empty()).map(str::to_string);
+                let password = lines.next().filter(|p| !p.is_empty()).map(str::to_string);
+                (true, username, password)
+            }
+        };
+
+        let (port, proto) = (
+            self.port.unwrap_or(DEFAULT_PORT),
+            self.proto.as_deref().unwrap_or(DEFAULT_PROTO),
+        );
+        let remotes = self
+            .remotes
+            .iter()
+            .map(|r| {
+                let host = if r.host.parse::<std::net::Ipv6Addr>().is_ok() {
+                    format!("[{}]", r.host)
+                } else {
+                    r.host.clone()
+                };
+                let proto = r.proto.as_deref().unwrap_or(proto);
+                format!("{host}:{}:{proto}", r.port.unwrap_or(port))
+            })
+            .collect();
+
+        Ok(OvpnConfig {
+            uuid,
+            remotes,
+            dev_type: self.dev_type,
+            ca,
+            cert,
+            key,
+            tls_auth,
+            key_direction: self.key_direction,
+            tls_crypt,
+            user_pass,
+            username,
+            password,
+            cipher: self.cipher,
+            data_ciphers: self.data_ciphers,
+            auth: self.auth,
+            remote_cert_tls: self.remote_cert_tls,
+        })
+    }
+}
+
+fn resolve(base: Option<&Path>, path: PathBuf) -> Result<PathBuf> {
+    if path.is_absolute() {
+        return Ok(path);
+    }
+    match base {
+        Some(base) => Ok(base.join(path)),
+        None => bail!(
+            "the profile refers to {}; import the .ovpn file instead of pasting it",
+            path.display()
+        ),
+    }
+}
+
+/// Splits a directive into its words the way `openvpn` does: double quotes
+/// group words and honour backslash escapes, single quotes group them
+/// verbatim, and a backslash outside quotes escapes the next character.
+fn tokenize(line: &str) -> Result<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' | '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some(q) if q == c => break,
+                        Some('\\') if c == '"' => word.extend(chars.next()),
+                        Some(other) => word.push(other),
+                        None => bail!("unterminated quote in '{line}'"),
+                    }
+                }
+            }
+            '\\' => word.get_or_insert_with(String::new).extend(chars.next()),
+            c if c.is_whitespace() => words.extend(word.take()),
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+/// A random (version 4) UUID for a new profile.
+fn new_uuid() -> Result<String> {
+    let mut bytes = [0u8; 16];
+    getrandom::fill(&mut bytes).map_err(|e| anyhow!("Could not read random bytes: {e}"))?;
+    bytes[6] = bytes[6] & 0x0f | 0x40;
+    bytes[8] = bytes[8] & 0x3f | 0x80;
+    let hex = hex::encode(bytes);
+    Ok(format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    ))
+}
+
+fn write_private(path: &Path, body: &str) -> Result<()> {
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)
+        .with_context(|| format!("writing {}", path.display()))?;
+    file.write_all(body.as_bytes())?;
+    Ok(())
+}
+
+fn parse_port(s: &str) -> Result<u16> {
+    s.parse().map_err(|_| anyhow!("invalid port '{s}'"))
+}
+
+fn parse_key_direction(s: &str) -> Result<u8> {
+    match s {
+        "0" => Ok(0),
+        "1" => Ok(1),
+        _ => bail!("invalid key-direction '{s}'"),
+    }
+}
+
+/// `tcp-client` and friends are client spellings of plain `tcp`.
+fn normalize_proto(proto: &str) -> String {
+    proto
+        .to_ascii_lowercase()
+        .trim_end_matches("-client")
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROVIDER: &str = "\
+client
+dev tun
+proto udp
+remote nl1.example.com 1194
+remote nl2.example.com 443 tcp-client
+remote 2001:db8::1
+auth-user-pass
+cipher AES-256-GCM
+remote-cert-tls server
+key-direction 1
+
+<ca>
+-----BEGIN CERTIFICATE-----
+MIIB
+-----END CERTIFICATE-----
+</ca>
+<tls-auth>
+-----BEGIN OpenVPN Static key V1-----
+abcd
+-----END OpenVPN Static key V1-----
+</tls-auth>
+";
+
+    fn temp_dir(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("wlctl-ovpn-{}-{}", name, std::process::id()))
+    }
+
+    #[test]
+    fn parses_a_provider_profile_and_writes_its_inline_keys() {
+        assert!(looks_like(PROVIDER));
+        let profile = parse(PROVIDER).unwrap();
+        assert_eq!(profile.name(), "ovpn-nl1");
+
+        let root = temp_dir("provider");
+        let cfg = profile.store(None, &root).unwrap();
+        let dir = root.join(&cfg.uuid);
+        let ca = std::fs::read_to_string(dir.join("ca.crt"));
+        let mode = |path: PathBuf| {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::metadata(path).map(|m| m.permissions().mode() & 0o777)
+        };
+        let modes = (mode(dir.join("ta.key")), mode(dir.clone()));
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(
+            cfg.remotes,
+            [
+                "nl1.example.com:1194:udp",
+                "nl2.example.com:443:tcp",
+                "[2001:db8::1]:1194:udp"
+            ]
+        );
+        assert_eq!(cfg.dev_type.as_deref(), Some("tun"));
+        assert_eq!(cfg.ca, dir.join("ca.crt"));
+        assert!(
+            ca.unwrap()
+                .starts_with("-----BEGIN CERTIFICATE-----\nMIIB\n")
+        );
+        assert_eq!(cfg.tls_auth, Some(dir.join("ta.key")));
+        assert_eq!(modes.0.unwrap(), 0o600);
+        assert_eq!(modes.1.unwrap(), 0o700);
+        assert_eq!(cfg.key_direction, Some(1));
+        assert!(cfg.user_pass && cfg.username.is_none() && cfg.password.is_none());
+        assert_eq!(cfg.cipher.as_deref(), Some("AES-256-GCM"));
+        assert_eq!(cfg.remote_cert_tls.as_deref(), Some("server"));
+        assert!(cfg.cert.is_none());
+    }
+
+    #[test]
+    fn file_references_resolve_next_to_the_imported_file() {
+        let dir = temp_dir("files");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("login.txt"), "alice\nhunter2\n").unwrap();
+        let profile = parse(
+            "remote vpn.example.com\nproto tcp\nport 443\nca ca.crt\ncert /etc/openvpn/me.crt\nkey me.key\ntls-auth ta.key 1\nauth-user-pass login.txt\n",
+        )
+        .unwrap();
+        let cfg = profile.store(Some(&dir), &dir.join("out"));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let cfg = cfg.unwrap();
+        assert_eq!(cfg.remotes, ["vpn.example.com:443:tcp"]);
+        assert_eq!(cfg.ca, dir.join("ca.crt"));
+        assert_eq!(cfg.cert, Some(PathBuf::from("/etc/openvpn/me.crt")));
+        assert_eq!(cfg.key, Some(dir.join("me.key")));
+        assert_eq!(cfg.key_direction, Some(1));
+        assert_eq!(cfg.username.as_deref(), Some("alice"));
+        assert_eq!(cfg.password.as_deref(), Some("hunter2"));
+    }
+
+    #[test]
+    fn pasted_text_cannot_refer_to_files() {
+        let profile = parse("remote vpn.example.com\nca ca.crt\nauth-user-pass\n").unwrap();
+        let root = temp_dir("pasted");
+        let err = profile.store(None, &root).unwrap_err();
+        // The key directory made for it is gone again.
+        let left = std::fs::read_dir(&root).map(|dir| dir.count());
+        let _ = std::fs::remove_dir_all(&root);
+        assert!(err.to_string().contains("ca.crt"), "{err}");
+        assert_eq!(left.unwrap(), 0);
+    }
+
+    #[test]
+    fn quoted_arguments_keep_their_spaces() {
+        assert_eq!(
+            tokenize(r#"ca "My Keys/ca.crt""#).unwrap(),
+            ["ca", "My Keys/ca.crt"]
+        );
+        assert_eq!(
+            tokenize(r#"auth-user-pass 'a b'  c\ d "e\"f""#).unwrap(),
+            ["auth-user-pass", "a b", "c d", "e\"f"]
+        );
+        assert!(tokenize(r#"ca "ca.crt"#).is_err());
+
+        let profile =
+            parse("remote vpn.example.com\nca \"My Keys/ca.crt\"\nauth-user-pass\n").unwrap();
+        let cfg = profile.store(Some(Path::new("/vpn")), &temp_dir("quoted"));
+        let _ = std::fs::remove_dir_all(temp_dir("quoted"));
+        assert_eq!(cfg.unwrap().ca, PathBuf::from("/vpn/My Keys/ca.crt"));
+    }
+
+    #[test]
+    fn skipped_directives_are_reported_once() {
+        let profile = parse(
+            "client\nnobind\nremote h\nca ca.crt\nauth-user-pass\nredirect-gateway def1\ncomp-lzo\nredirect-gateway ipv6\n<extra-certs>\nX\n</extra-certs>\n",
+        )
+        .unwrap();
+        assert_eq!(
+            profile.warnings,
+            [
+                "Not carried over to NetworkManager, so skipped: redirect-gateway, comp-lzo, <extra-certs>"
+            ]
+        );
+        assert!(parse(PROVIDER).unwrap().warnings.is_empty());
+    }
+
+    #[test]
+    fn rejects_profiles_that_cannot_connect() {
+        // No remote.
+        assert!(parse("ca ca.crt\nauth-user-pass\n").is_err());
+        // No ca.
+        assert!(parse("remote h\nauth-user-pass\n").is_err());
+        // No way to authenticate.
+        assert!(parse("remote h\nca ca.crt\n").is_err());
+        // A cert without its key.
+        assert!(parse("remote h\nca ca.crt\ncert me.crt\n").is_err());
+        // An unclosed block.
+        assert!(parse("remote h\nauth-user-pass\n<ca>\nMIIB\n").is_err());
+        assert!(parse("remote h 99999\nca ca.crt\nauth-user-pass\n").is_err());
+    }
+
+    #[test]
+    fn a_wireguard_config_does_not_look_like_openvpn() {
+        assert!(!looks_like(
+            "[Interface]\nPrivateKey = k=\n[Peer]\nEndpoint = remote.example.com:51820\n"
+        ));
+    }
+}
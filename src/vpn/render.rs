@@ -8,11 +8,23 @@ use ratatui::{
     },
 };
 
-use super::VpnModal;
+use tui_qrcode::{Colors, QrCodeWidget};
+
+use super::{CreateField, CreateForm, Export, VpnModal};
 use crate::nm::ActiveConnectionState;
 
 /// Draws the VPN modal centered on top of the current frame.
 pub fn render_modal(frame: &mut Frame, modal: &VpnModal) {
+    // An export needs more room than the modal has for its QR code.
+    if let Some(export) = modal.export() {
+        render_export(frame, export);
+        return;
+    }
+    if let Some(form) = modal.create_form() {
+        render_create(frame, form);
+        return;
+    }
+
     let area = popup_area(frame.area());
 
     let block = vpn_block();
@@ -46,7 +58,7 @@ pub fn render_modal(frame: &mut Frame, modal: &VpnModal) {
     frame.render_widget(hint(modal), chunks[2]);
 }
 
-/// Draws the WireGuard import view: instructions, a bordered paste/path box,
+/// Draws the import view: instructions, a bordered paste/path box,
 /// and the action hints. Replaces the list while importing.
 fn render_import(frame: &mut Frame, area: Rect, modal: &VpnModal) {
     let buf = modal.import_buffer().unwrap_or("");
@@ -61,8 +73,8 @@ fn render_import(frame: &mut Frame, area: Rect, modal: &VpnModal) {
         .split(area);
 
     let info = Paragraph::new(vec![
-        Line::from("Import a WireGuard tunnel".bold()),
-        Line::from("Paste a config, or type a path to a .conf file".fg(Color::DarkGray)),
+        Line::from("Import a WireGuard or OpenVPN tunnel".bold()),
+        Line::from("Paste a config, or type a path to a .conf or .ovpn file".fg(Color::DarkGray)),
     ])
     .alignment(Alignment::Center);
     frame.render_widget(info, chunks[0]);
@@ -109,9 +121,249 @@ fn render_import(frame: &mut Frame, area: Rect, modal: &VpnModal) {
     );
 }
 
+/// Draws an exported tunnel as a QR code for the WireGuard mobile app, over
+/// the whole frame, as the WiFi share popup does.
+fn render_export(frame: &mut Frame, export: &Export) {
+    let widget = QrCodeWidget::new(export.qr_code.clone()).colors(Colors::Inverted);
+    let size = widget.size(Rect::default());
+    let full = frame.area();
+
+    // Title, a blank line, the code, a blank line, two lines of hints, inside
+    // a border.
+    let needed = (size.width.max(44) + 4, size.height + 7);
+    let fits = needed.0 <= full.width && needed.1 <= full.height;
+    let (width, height) = if fits {
+        needed
+    } else {
+        (full.width.min(48), full.height.min(9))
+    };
+
+    let area = Rect::new(
+        full.x + (full.width - width) / 2,
+        full.y + (full.height - height) / 2,
+        width,
+        height,
+    );
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Thick)
+        .border_style(Style::default().fg(Color::Green))
+        .padding(Padding::horizontal(1));
+    let inner = block.inner(area);
+    frame.render_widget(Clear, area);
+    frame.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(2),
+            Constraint::Fill(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .split(inner);
+
+    frame.render_widget(
+        Paragraph::new(Line::from(export.id.clone()).bold()).alignment(Alignment::Center),
+        chunks[0],
+    );
+
+    if fits {
+        let qr = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Fill(1),
+                Constraint::Length(size.width),
+                Constraint::Fill(1),
+            ])
+            .split(chunks[1])[1];
+        frame.render_widget(widget, qr);
+    } else {
+        frame.render_widget(
+            Paragraph::new(format!(
+                "Enlarge the terminal to {}x{} to show the QR code",
+                needed.0, needed.1
+            ))
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: true }),
+            chunks[1],
+        );
+    }
+
+    frame.render_widget(
+        Paragraph::new("Holds the private key; share it with care")
+            .alignment(Alignment::Center)
+            .style(Style::default().fg(Color::DarkGray)),
+        chunks[2],
+    );
+    let actions = Line::from(vec![
+        Span::from("s").bold(),
+        Span::from(format!(" Save to ~/{}   ", export.file_name())),
+        Span::from("Esc").bold(),
+        Span::from(" Close"),
+    ]);
+    frame.render_widget(
+        Paragraph::new(actions)
+            .alignment(Alignment::Center)
+            .style(Style::default().fg(Color::Blue)),
+        chunks[3],
+    );
+}
+
+/// Draws the new-tunnel form over the whole frame: our public key on top, then
+/// either the fields or, once they check out, the config they make.
+fn render_create(frame: &mut Frame, form: &CreateForm) {
+    let preview = form.preview_text();
+    let body_height = match &preview {
+        Some(text) => text.lines().count() as u16,
+        None => CreateField::ALL.len() as u16 * 2 - 1,
+    };
+
+    let full = frame.area();
+    let width = full.width.min(72);
+    let height = full.height.min(body_height + 10);
+    let area = Rect::new(
+        full.x + (full.width - width) / 2,
+        full.y + (full.height - height) / 2,
+        width,
+        height,
+    );
+    let title = if preview.is_some() {
+        " New WireGuard tunnel: preview "
+    } else {
+        " New WireGuard tunnel "
+    };
+    let block = Block::default()
+        .title(title)
+        .title_alignment(Alignment::Center)
+        .title_style(Style::default().bold())
+        .borders(Borders::ALL)
+        .border_type(BorderType::Thick)
+        .border_style(Style::default().fg(Color::Green))
+        .padding(Padding::new(2, 2, 1, 0));
+    let inner = block.inner(area);
+    frame.render_widget(Clear, area);
+    frame.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(1), // our public key
+            Constraint::Length(1), // who it is for
+            Constraint::Length(1), // spacer
+            Constraint::Fill(1),   // fields or preview
+            Constraint::Length(1), // spacer
+            Constraint::Length(1), // hints
+        ])
+        .split(inner);
+
+    frame.render_widget(
+        Paragraph::new(Line::from(vec![
+            Span::from("Public key  ").bold(),
+            Span::from(form.keys.public_key.clone())
+                .fg(Color::Green)
+                .bold(),
+        ])),
+        chunks[0],
+    );
+    frame.render_widget(
+        Paragraph::new("Give this key to whoever runs the server").fg(Color::DarkGray),
+        chunks[1],
+    );
+
+    let hints = match &preview {
+        Some(text) => {
+            frame.render_widget(Paragraph::new(text.clone()), chunks[3]);
+            vec![
+                Span::from("⏎").bold(),
+                Span::from(" Save   "),
+                Span::from("Esc").bold(),
+                Span::from(" Edit"),
+            ]
+        }
+        None => {
+            render_create_fields(frame, chunks[3], form);
+            vec![
+                Span::from("Tab").bold(),
+                Span::from(" Next   "),
+                Span::from("⏎").bold(),
+                Span::from(" Preview   "),
+                Span::from("Esc").bold(),
+                Span::from(" Cancel"),
+            ]
+        }
+    };
+    frame.render_widget(
+        Paragraph::new(Line::from(hints))
+            .alignment(Alignment::Center)
+            .style(Style::default().fg(Color::Blue)),
+        chunks[5],
+    );
+}
+
+/// One labelled input per row, with the cursor in the focused one.
+fn render_create_fields(frame: &mut Frame, area: Rect, form: &CreateForm) {
+    const LABEL_WIDTH: u16 = 17;
+
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(CreateField::ALL.map(|_| Constraint::Length(2)))
+        .split(area);
+
+    for (field, row) in CreateField::ALL.into_iter().zip(rows.iter()) {
+        let focused = field == form.focused_field;
+        let [label, input] = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Length(LABEL_WIDTH), Constraint::Fill(1)])
+            .areas(Rect { height: 1, ..*row });
+
+        let marker = if focused {
+            Span::from(" *").fg(Color::Green)
+        } else {
+            Span::from("")
+        };
+        frame.render_widget(
+            Paragraph::new(Line::from(vec![Span::from(field.label()).bold(), marker])),
+            label,
+        );
+
+        let value = form.input(field).value();
+        let style = if focused {
+            Style::default().fg(Color::White).bg(Color::DarkGray)
+        } else {
+            Style::default().fg(Color::Gray).bg(Color::DarkGray)
+        };
+        // Keep the cursor in view when the value outgrows the box.
+        let scroll = form
+            .input(field)
+            .visual_scroll(input.width.saturating_sub(1) as usize);
+        let text = if value.is_empty() && !focused {
+            Line::from(Span::from(field.placeholder()).dim())
+        } else {
+            Line::from(value.to_string())
+        };
+        frame.render_widget(
+            Paragraph::new(text).style(style).scroll((0, scroll as u16)),
+            input,
+        );
+
+        if focused {
+            let cursor = form.input(field).visual_cursor().saturating_sub(scroll);
+            frame.set_cursor_position((input.x + cursor as u16, input.y));
+        }
+    }
+}
+
 /// One-line detail for the selected entry: its assigned IPv4 and uptime while
 /// up. Blank when nothing is selected or the tunnel is down.
 fn detail(modal: &VpnModal) -> Paragraph<'static> {
+    // A new tunnel's key is the one thing its server still needs.
+    if let Some((id, _)) = modal.created() {
+        return Paragraph::new(format!("Saved '{id}'. Give the server this public key:"))
+            .alignment(Alignment::Center)
+            .style(Style::default().fg(Color::DarkGray));
+    }
+
     let text = modal
         .selected_entry()
         .filter(|e| e.is_active())
@@ -134,7 +386,7 @@ fn detail(modal: &VpnModal) -> Paragraph<'static> {
 
 fn popup_area(full: Rect) -> Rect {
     let modal_h = full.height.saturating_sub(4).clamp(8, 18);
-    let modal_w = full.width.saturating_sub(4).clamp(40, 64);
+    let modal_w = full.width.saturating_sub(4).clamp(40, 76);
 
     let vertical = Layout::default()
         .direction(Direction::Vertical)
@@ -171,7 +423,7 @@ fn render_empty(frame: &mut Frame, area: Rect) {
     let p = Paragraph::new(vec![
         Line::from("No VPN connections configured.").centered(),
         Line::from(""),
-        Line::from("Press 'i' to import a WireGuard config (paste or file path).")
+        Line::from("Press 'i' to import a WireGuard or OpenVPN config or 'n' to create one.")
             .centered()
             .style(Style::default().fg(Color::DarkGray)),
     ])
@@ -239,20 +491,30 @@ fn hint(modal: &VpnModal) -> Paragraph<'static> {
             .alignment(Alignment::Center)
             .style(Style::default().fg(Color::Red));
     }
+    if let Some((_, public_key)) = modal.created() {
+        let line = Line::from(vec![
+            Span::from(public_key.to_string()).fg(Color::Green).bold(),
+            Span::from("   "),
+            Span::from("⏎").bold().fg(Color::Blue),
+            Span::from(" Done").fg(Color::Blue),
+        ]);
+        return Paragraph::new(line).alignment(Alignment::Center);
+    }
 
     let spans = if modal.is_empty() {
         vec![
             Span::from("i").bold(),
             Span::from(" Import"),
             Span::from(" | "),
+            Span::from("n").bold(),
+            Span::from(" New"),
+            Span::from(" | "),
             Span::from("Esc").bold(),
             Span::from(" Close"),
         ]
     } else {
+        // Moving with ↑↓ goes without saying; the line is full.
         vec![
-            Span::from("↑↓").bold(),
-            Span::from(" Move"),
-            Span::from(" | "),
             Span::from("⏎").bold(),
             Span::from(" Toggle"),
             Span::from(" | "),
@@ -265,6 +527,12 @@ fn hint(modal: &VpnModal) -> Paragraph<'static> {
             Span::from("i").bold(),
             Span::from(" Import"),
             Span::from(" | "),
+            Span::from("n").bold(),
+            Span::from(" New"),
+            Span::from(" | "),
+            Span::from("e").bold(),
+            Span::from(" Export"),
+            Span::from(" | "),
             Span::from("Esc").bold(),
             Span::from(" Close"),
         ]
@@ -1,31 +1,59 @@
-//! Minimal WireGuard `.conf` parser — enough to import the single-peer tunnels
-//! that hosted providers (Proton, Mullvad, …) hand out. Turns the INI-ish text
-//! into an [`nm::WgConfig`] that the NM layer maps onto a `wireguard` profile.
+//! WireGuard `.conf` parser — the single-peer tunnels hosted providers
+//! (Proton, Mullvad, …) hand out as well as multi-peer site-to-site configs.
+//! Turns the INI-ish text into an [`nm::WgConfig`] that the NM layer maps onto
+//! a `wireguard` profile, noting the wg-quick keys NetworkManager cannot honour,
+//! and renders a [`WgConfig`] back to text for export.
 
-use std::net::IpAddr;
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::str::FromStr;
 
 use anyhow::{Result, anyhow, bail};
 
-use crate::nm::{WgConfig, WgPeerConfig};
+use crate::nm::{WgConfig, WgPeerConfig, WgRouteTable};
+
+/// wg-quick runs these around bringing the interface up and down; NM has no
+/// equivalent.
+const HOOKS: [&str; 4] = ["preup", "postup", "predown", "postdown"];
+
+/// A parsed config and what in it NetworkManager will not do.
+#[derive(Debug)]
+pub struct Parsed {
+    pub config: WgConfig,
+    /// One sentence each, ready to show to the user.
+    pub warnings: Vec<String>,
+}
+
+/// A `[Peer]` section as read, before its required keys are checked.
+#[derive(Default)]
+struct PeerDraft {
+    public_key: Option<String>,
+    endpoint: Option<String>,
+    allowed_ips: Vec<(IpAddr, u8)>,
+    preshared_key: Option<String>,
+    keepalive: Option<u32>,
+}
 
 /// Parses WireGuard config text into a [`WgConfig`]. Section and key names are
 /// matched case-insensitively; comments (`#`/`;`) and blank lines are ignored.
-/// Exactly one `[Interface]` and one `[Peer]` are expected.
-pub fn parse(text: &str) -> Result<WgConfig> {
+/// Exactly one `[Interface]` and at least one `[Peer]` are expected.
+pub fn parse(text: &str) -> Result<Parsed> {
     let mut section: Option<String> = None;
 
     let mut private_key: Option<String> = None;
     let mut addresses: Vec<(IpAddr, u8)> = Vec::new();
     let mut dns: Vec<IpAddr> = Vec::new();
-
-    let mut public_key: Option<String> = None;
-    let mut endpoint: Option<String> = None;
-    let mut allowed_ips: Vec<String> = Vec::new();
-    let mut preshared_key: Option<String> = None;
-    let mut keepalive: Option<u32> = None;
+    let mut dns_search: Vec<String> = Vec::new();
+    let mut listen_port: Option<u16> = None;
+    let mut mtu: Option<u32> = None;
+    let mut fwmark: Option<u32> = None;
+    let mut table = WgRouteTable::Auto;
     let mut interface_count = 0;
-    let mut peer_count = 0;
+
+    let mut peers: Vec<PeerDraft> = Vec::new();
+    let mut warnings: Vec<String> = Vec::new();
+    let mut hooks: Vec<&str> = Vec::new();
 
     for raw in text.lines() {
         // Strip inline comments and surrounding whitespace.
@@ -43,12 +71,7 @@ pub fn parse(text: &str) -> Result<WgConfig> {
                         bail!("multiple [Interface] sections are not supported");
                     }
                 }
-                "peer" => {
-                    peer_count += 1;
-                    if peer_count > 1 {
-                        bail!("multiple [Peer] sections are not supported");
-                    }
-                }
+                "peer" => peers.push(PeerDraft::default()),
                 _ => {}
             }
             section = Some(name);
@@ -72,34 +95,71 @@ pub fn parse(text: &str) -> Result<WgConfig> {
                 }
             }
             (Some("interface"), "dns") => {
-                // DNS may also list search domains; keep only IP entries.
-                for item in value.split(',') {
-                    if let Ok(ip) = IpAddr::from_str(item.trim()) {
-                        dns.push(ip);
+                // wg-quick takes every entry that is not an address as a
+                // search domain.
+                for item in value.split(',').map(str::trim).filter(|i| !i.is_empty()) {
+                    match IpAddr::from_str(item) {
+                        Ok(ip) => dns.push(ip),
+                        Err(_) => dns_search.push(item.to_string()),
                     }
                 }
             }
-            (Some("peer"), "publickey") => public_key = Some(value),
-            (Some("peer"), "endpoint") => endpoint = Some(value),
-            (Some("peer"), "allowedips") => {
-                for item in value.split(',') {
-                    let item = item.trim();
-                    if item.is_empty() {
-                        continue;
-                    }
-                    // Validate the CIDR; keep the original string for NM.
-                    parse_cidr(item)?;
-                    allowed_ips.push(item.to_string());
-                }
+            (Some("interface"), "listenport") => {
+                listen_port = Some(
+                    value
+                        .parse()
+                        .map_err(|_| anyhow!("invalid ListenPort '{value}'"))?,
+                );
             }
-            (Some("peer"), "presharedkey") => preshared_key = Some(value),
-            (Some("peer"), "persistentkeepalive") => {
-                keepalive = Some(
+            (Some("interface"), "mtu") => {
+                mtu = Some(
                     value
                         .parse()
-                        .map_err(|_| anyhow!("invalid PersistentKeepalive '{value}'"))?,
+                        .map_err(|_| anyhow!("invalid MTU '{value}'"))?,
                 );
             }
+            (Some("interface"), "fwmark") => fwmark = parse_fwmark(&value)?,
+            (Some("interface"), "table") => match value.to_ascii_lowercase().as_str() {
+                "auto" => table = WgRouteTable::Auto,
+                "off" => table = WgRouteTable::Off,
+                _ => match value.parse() {
+                    Ok(id) => table = WgRouteTable::Id(id),
+                    // wg-quick looks names up in /etc/iproute2/rt_tables.
+                    Err(_) => warnings.push(format!(
+                        "Table '{value}' is not a number; NetworkManager needs a table id, so routes go to the main table"
+                    )),
+                },
+            },
+            (Some("interface"), key) if HOOKS.contains(&key) => {
+                let name = hook_name(key);
+                if !hooks.contains(&name) {
+                    hooks.push(name);
+                }
+            }
+            (Some("interface"), "saveconfig") => {
+                warnings.push("SaveConfig is wg-quick only and was ignored".to_string());
+            }
+            (Some("peer"), key) => {
+                let peer = peers.last_mut().expect("a [Peer] section was opened");
+                match key {
+                    "publickey" => peer.public_key = Some(value),
+                    "endpoint" => peer.endpoint = Some(value),
+                    "allowedips" => {
+                        for item in value.split(',') {
+                            let item = item.trim();
+                            if item.is_empty() {
+                                continue;
+                            }
+                            peer.allowed_ips.push(network(parse_cidr(item)?));
+                        }
+                    }
+                    "presharedkey" => peer.preshared_key = Some(value),
+                    "persistentkeepalive" => {
+                        peer.keepalive = parse_keepalive(&value)?;
+                    }
+                    _ => {}
+                }
+            }
             _ => {}
         }
     }
@@ -108,32 +168,231 @@ pub fn parse(text: &str) -> Result<WgConfig> {
     if addresses.is_empty() {
         bail!("[Interface] is missing Address");
     }
-    let public_key = public_key.ok_or_else(|| anyhow!("[Peer] is missing PublicKey"))?;
-    if endpoint.is_none() {
-        bail!("[Peer] is missing Endpoint");
+    if peers.is_empty() {
+        bail!("[Peer] is missing");
     }
-    if allowed_ips.is_empty() {
-        // A tunnel that routes nothing is almost certainly a mistake.
-        bail!("[Peer] is missing AllowedIPs");
+    if !hooks.is_empty() {
+        warnings.push(format!(
+            "NetworkManager does not run wg-quick hooks; {} skipped",
+            hooks.join(", ")
+        ));
     }
 
-    Ok(WgConfig {
-        private_key,
-        addresses,
-        dns,
-        peer: WgPeerConfig {
-            public_key,
-            endpoint,
-            allowed_ips,
-            preshared_key,
-            persistent_keepalive: keepalive,
+    let peers = finish_peers(peers, listen_port.is_some(), &mut warnings)?;
+
+    // NM disables IPv6 on a tunnel without an IPv6 address, so routes for
+    // IPv6 AllowedIPs would never be installed and that traffic bypasses it.
+    let has_v6_address = addresses.iter().any(|(ip, _)| ip.is_ipv6());
+    let routes_v6 = peers
+        .iter()
+        .flat_map(|p| &p.allowed_ips)
+        .any(|ip| ip.contains(':'));
+    if routes_v6 && !has_v6_address && table != WgRouteTable::Off {
+        warnings.push(
+            "AllowedIPs lists IPv6 but [Interface] has no IPv6 Address; IPv6 traffic will not go through the tunnel"
+                .to_string(),
+        );
+    }
+
+    Ok(Parsed {
+        config: WgConfig {
+            private_key: private_key.into(),
+            addresses,
+            dns,
+            dns_search,
+            listen_port,
+            mtu,
+            fwmark,
+            table,
+            peers,
         },
+        warnings,
     })
 }
 
+/// Checks each peer's required keys and how the peers fit together. Errors
+/// name the peer by position when there is more than one.
+fn finish_peers(
+    drafts: Vec<PeerDraft>,
+    listening: bool,
+    warnings: &mut Vec<String>,
+) -> Result<Vec<WgPeerConfig>> {
+    let several = drafts.len() > 1;
+    let label = |n: usize| {
+        if several {
+            format!("[Peer] {}", n + 1)
+        } else {
+            "[Peer]".to_string()
+        }
+    };
+
+    let mut keys: HashSet<String> = HashSet::new();
+    let mut claimed: HashSet<(IpAddr, u8)> = HashSet::new();
+    let mut shared: Vec<String> = Vec::new();
+    let mut peers = Vec::with_capacity(drafts.len());
+
+    for (n, draft) in drafts.into_iter().enumerate() {
+        let public_key = draft
+            .public_key
+            .ok_or_else(|| anyhow!("{} is missing PublicKey", label(n)))?;
+        if !keys.insert(public_key.clone()) {
+            bail!("{} repeats the PublicKey of an earlier peer", label(n));
+        }
+        if draft.allowed_ips.is_empty() {
+            // A peer that routes nothing is almost certainly a mistake.
+            bail!("{} is missing AllowedIPs", label(n));
+        }
+        for &cidr in &draft.allowed_ips {
+            if !claimed.insert(cidr) {
+                shared.push(format_cidr(cidr));
+            }
+        }
+
+        peers.push(WgPeerConfig {
+            public_key,
+            endpoint: draft.endpoint,
+            allowed_ips: draft.allowed_ips.into_iter().map(format_cidr).collect(),
+            preshared_key: draft.preshared_key,
+            persistent_keepalive: draft.keepalive,
+        });
+    }
+
+    // Peers that wait to be contacted are fine on a listening end, one peer
+    // or many, but something has to start the handshake.
+    if !listening && peers.iter().all(|p| p.endpoint.is_none()) {
+        if several {
+            bail!("no [Peer] has an Endpoint and [Interface] has no ListenPort");
+        }
+        bail!("[Peer] is missing Endpoint and [Interface] has no ListenPort");
+    }
+    if !shared.is_empty() {
+        warnings.push(format!(
+            "AllowedIPs {} listed for more than one peer; WireGuard routes each to the last peer only",
+            shared.join(", ")
+        ));
+    }
+
+    Ok(peers)
+}
+
+/// wg-quick's spelling of a hook key, for warnings.
+fn hook_name(key: &str) -> &'static str {
+    match key {
+        "preup" => "PreUp",
+        "postup" => "PostUp",
+        "predown" => "PreDown",
+        _ => "PostDown",
+    }
+}
+
+/// `FwMark` is a number, hex with `0x`, or `off`.
+fn parse_fwmark(value: &str) -> Result<Option<u32>> {
+    let invalid = || anyhow!("invalid FwMark '{value}'");
+    if value.eq_ignore_ascii_case("off") {
+        return Ok(None);
+    }
+    let mark = match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => u32::from_str_radix(hex, 16).map_err(|_| invalid())?,
+        None => value.parse().map_err(|_| invalid())?,
+    };
+    Ok((mark != 0).then_some(mark))
+}
+
+/// `PersistentKeepalive` is seconds, with `off` (or 0) turning it off.
+fn parse_keepalive(value: &str) -> Result<Option<u32>> {
+    if value.eq_ignore_ascii_case("off") {
+        return Ok(None);
+    }
+    let secs: u32 = value
+        .parse()
+        .map_err(|_| anyhow!("invalid PersistentKeepalive '{value}'"))?;
+    Ok((secs != 0).then_some(secs))
+}
+
+/// The network an `AllowedIPs` entry names: host bits cleared, as the kernel
+/// stores it, so `10.0.0.5/24` and `10.0.0.0/24` count as the same route.
+pub(super) fn network((ip, prefix): (IpAddr, u8)) -> (IpAddr, u8) {
+    let ip = match ip {
+        IpAddr::V4(v4) => {
+            let mask = u32::MAX.checked_shl(32 - u32::from(prefix)).unwrap_or(0);
+            IpAddr::V4(Ipv4Addr::from(u32::from(v4) & mask))
+        }
+        IpAddr::V6(v6) => {
+            let mask = u128::MAX.checked_shl(128 - u32::from(prefix)).unwrap_or(0);
+            IpAddr::V6(Ipv6Addr::from(u128::from(v6) & mask))
+        }
+    };
+    (ip, prefix)
+}
+
+pub(super) fn format_cidr((ip, prefix): (IpAddr, u8)) -> String {
+    format!("{ip}/{prefix}")
+}
+
+/// Renders `cfg` as wg-quick config text, the inverse of [`parse`].
+pub fn render(cfg: &WgConfig) -> String {
+    let list = |items: Vec<String>| items.join(", ");
+    // Writing to a `String` cannot fail.
+    let mut out = String::from("[Interface]\n");
+    let _ = writeln!(out, "PrivateKey = {}", *cfg.private_key);
+    let _ = writeln!(
+        out,
+        "Address = {}",
+        list(cfg.addresses.iter().copied().map(format_cidr).collect())
+    );
+    if !cfg.dns.is_empty() || !cfg.dns_search.is_empty() {
+        let _ = writeln!(
+            out,
+            "DNS = {}",
+            list(
+                cfg.dns
+                    .iter()
+                    .map(IpAddr::to_string)
+                    .chain(cfg.dns_search.iter().cloned())
+                    .collect()
+            )
+        );
+    }
+    if let Some(port) = cfg.listen_port {
+        let _ = writeln!(out, "ListenPort = {port}");
+    }
+    if let Some(mtu) = cfg.mtu {
+        let _ = writeln!(out, "MTU = {mtu}");
+    }
+    if let Some(mark) = cfg.fwmark {
+        let _ = writeln!(out, "FwMark = {mark:#x}");
+    }
+    match cfg.table {
+        WgRouteTable::Auto => {}
+        WgRouteTable::Off => out.push_str("Table = off\n"),
+        WgRouteTable::Id(id) => {
+            let _ = writeln!(out, "Table = {id}");
+        }
+    }
+
+    for peer in &cfg.peers {
+        out.push_str("\n[Peer]\n");
+        let _ = writeln!(out, "PublicKey = {}", peer.public_key);
+        if let Some(psk) = &peer.preshared_key {
+            let _ = writeln!(out, "PresharedKey = {psk}");
+        }
+        let _ = writeln!(out, "AllowedIPs = {}", list(peer.allowed_ips.clone()));
+        if let Some(endpoint) = &peer.endpoint {
+            let _ = writeln!(out, "Endpoint = {endpoint}");
+        }
+        if let Some(secs) = peer.persistent_keepalive {
+            let _ = writeln!(out, "PersistentKeepalive = {secs}");
+        }
+    }
+    out
+}
+
 /// Parses `addr/prefix`, defaulting the prefix to the address family's full
 /// width (`/32` for IPv4, `/128` for IPv6) when omitted.
-fn parse_cidr(s: &str) -> Result<(IpAddr, u8)> {
+pub(super) fn parse_cidr(s: &str) -> Result<(IpAddr, u8)> {
     let (addr_part, prefix_part) = match s.split_once('/') {
         Some((a, p)) => (a, Some(p)),
         None => (s, None),
@@ -178,16 +437,20 @@ Endpoint = 1.2.3.4:51820
 
     #[test]
     fn parses_a_typical_single_peer_conf() {
-        let cfg = parse(PROTON).unwrap();
-        assert_eq!(cfg.private_key, "aPrivateKey=");
+        let parsed = parse(PROTON).unwrap();
+        assert!(parsed.warnings.is_empty());
+        let cfg = parsed.config;
+        assert_eq!(cfg.private_key.as_str(), "aPrivateKey=");
         assert_eq!(
             cfg.addresses,
             vec![(IpAddr::V4(Ipv4Addr::new(10, 2, 0, 2)), 32)]
         );
         assert_eq!(cfg.dns, vec![IpAddr::V4(Ipv4Addr::new(10, 2, 0, 1))]);
-        assert_eq!(cfg.peer.public_key, "aPublicKey=");
-        assert_eq!(cfg.peer.endpoint.as_deref(), Some("1.2.3.4:51820"));
-        assert_eq!(cfg.peer.allowed_ips, vec!["0.0.0.0/0"]);
+        assert_eq!(cfg.table, WgRouteTable::Auto);
+        assert_eq!(cfg.peers.len(), 1);
+        assert_eq!(cfg.peers[0].public_key, "aPublicKey=");
+        assert_eq!(cfg.peers[0].endpoint.as_deref(), Some("1.2.3.4:51820"));
+        assert_eq!(cfg.peers[0].allowed_ips, vec!["0.0.0.0/0"]);
     }
 
     #[test]
@@ -202,7 +465,8 @@ Endpoint = h:1
 AllowedIPs = 0.0.0.0/0, ::/0
 ",
         )
-        .unwrap();
+        .unwrap()
+        .config;
         assert_eq!(
             cfg.addresses,
             vec![
@@ -210,7 +474,7 @@ AllowedIPs = 0.0.0.0/0, ::/0
                 (IpAddr::V6(Ipv6Addr::new(0xfd00, 0, 0, 0, 0, 0, 0, 2)), 128),
             ]
         );
-        assert_eq!(cfg.peer.allowed_ips, vec!["0.0.0.0/0", "::/0"]);
+        assert_eq!(cfg.peers[0].allowed_ips, vec!["0.0.0.0/0", "::/0"]);
     }
 
     #[test]
@@ -220,9 +484,161 @@ AllowedIPs = 0.0.0.0/0, ::/0
     }
 
     #[test]
-    fn rejects_multiple_peers() {
-        let two = "[Interface]\nPrivateKey=k=\nAddress=10.0.0.2/32\n[Peer]\nPublicKey=p=\nEndpoint=h:1\nAllowedIPs=0.0.0.0/0\n[Peer]\nPublicKey=q=\nEndpoint=h:2\nAllowedIPs=0.0.0.0/0\n";
-        assert!(parse(two).is_err());
+    fn parses_several_peers_in_order() {
+        let parsed = parse(
+            "[Interface]
+PrivateKey = k=
+Address = 10.0.0.1/24
+ListenPort = 51820
+[Peer]
+PublicKey = branch-a=
+Endpoint = a.example.com:51820
+AllowedIPs = 10.0.0.2/32, 192.168.10.0/24
+[Peer]
+PublicKey = branch-b=
+AllowedIPs = 10.0.0.3/32, 192.168.20.0/24
+PresharedKey = psk=
+PersistentKeepalive = 25
+",
+        )
+        .unwrap();
+        assert!(parsed.warnings.is_empty());
+        let peers = parsed.config.peers;
+        assert_eq!(peers.len(), 2);
+        assert_eq!(peers[0].public_key, "branch-a=");
+        assert_eq!(peers[0].allowed_ips, vec!["10.0.0.2/32", "192.168.10.0/24"]);
+        assert_eq!(peers[1].public_key, "branch-b=");
+        assert_eq!(peers[1].endpoint, None);
+        assert_eq!(peers[1].preshared_key.as_deref(), Some("psk="));
+        assert_eq!(peers[1].persistent_keepalive, Some(25));
+    }
+
+    #[test]
+    fn rejects_peers_that_cannot_work_together() {
+        let base = "[Interface]\nPrivateKey=k=\nAddress=10.0.0.1/24\n";
+        // The second peer has no AllowedIPs; the error says which one.
+        let err = parse(&format!(
+            "{base}[Peer]\nPublicKey=p=\nEndpoint=h:1\nAllowedIPs=10.0.0.2/32\n[Peer]\nPublicKey=q=\n"
+        ))
+        .unwrap_err();
+        assert!(err.to_string().contains("[Peer] 2"), "{err}");
+        // Two peers with one key.
+        assert!(parse(&format!(
+            "{base}[Peer]\nPublicKey=p=\nEndpoint=h:1\nAllowedIPs=10.0.0.2/32\n[Peer]\nPublicKey=p=\nEndpoint=h:2\nAllowedIPs=10.0.0.3/32\n"
+        ))
+        .is_err());
+        // Nobody would ever start a handshake.
+        assert!(parse(&format!(
+            "{base}[Peer]\nPublicKey=p=\nAllowedIPs=10.0.0.2/32\n[Peer]\nPublicKey=q=\nAllowedIPs=10.0.0.3/32\n"
+        ))
+        .is_err());
+        assert!(
+            parse(&format!(
+                "{base}[Peer]\nPublicKey=p=\nAllowedIPs=10.0.0.2/32\n"
+            ))
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn a_listening_end_may_wait_for_its_only_peer() {
+        let parsed = parse(
+            "[Interface]\nPrivateKey=k=\nAddress=10.0.0.1/24\nListenPort=51820\n[Peer]\nPublicKey=p=\nAllowedIPs=10.0.0.2/32\n",
+        )
+        .unwrap();
+        assert_eq!(parsed.config.peers[0].endpoint, None);
+    }
+
+    #[test]
+    fn reads_interface_keys() {
+        let cfg = parse(
+            "[Interface]\nPrivateKey=k=\nAddress=10.0.0.2/32\nListenPort=51821\nMTU=1380\nFwMark=0xca6c\nTable=1234\n[Peer]\nPublicKey=p=\nEndpoint=h:1\nAllowedIPs=0.0.0.0/0\n",
+        )
+        .unwrap()
+        .config;
+        assert_eq!(cfg.listen_port, Some(51821));
+        assert_eq!(cfg.mtu, Some(1380));
+        assert_eq!(cfg.fwmark, Some(0xca6c));
+        assert_eq!(cfg.table, WgRouteTable::Id(1234));
+
+        let off = parse(
+            "[Interface]\nPrivateKey=k=\nAddress=10.0.0.2/32\nFwMark=off\nTable=off\n[Peer]\nPublicKey=p=\nEndpoint=h:1\nAllowedIPs=0.0.0.0/0\n",
+        )
+        .unwrap()
+        .config;
+        assert_eq!(off.fwmark, None);
+        assert_eq!(off.table, WgRouteTable::Off);
+
+        assert!(parse("[Interface]\nPrivateKey=k=\nAddress=10.0.0.2/32\nListenPort=70000\n[Peer]\nPublicKey=p=\nEndpoint=h:1\nAllowedIPs=0.0.0.0/0\n").is_err());
+    }
+
+    #[test]
+    fn warns_about_what_network_manager_cannot_honour() {
+        let parsed = parse(
+            "[Interface]
+PrivateKey = k=
+Address = 10.0.0.2/32
+Table = vpn
+PostUp = iptables -A FORWARD -i %i -j ACCEPT
+PostDown = iptables -D FORWARD -i %i -j ACCEPT
+PostUp = echo up
+SaveConfig = true
+[Peer]
+PublicKey = p=
+Endpoint = h:1
+AllowedIPs = 0.0.0.0/0, ::/0
+",
+        )
+        .unwrap();
+        assert_eq!(parsed.config.table, WgRouteTable::Auto);
+        let warnings = parsed.warnings.join("\n");
+        assert!(warnings.contains("Table 'vpn'"), "{warnings}");
+        assert!(warnings.contains("PostUp, PostDown skipped"), "{warnings}");
+        assert!(warnings.contains("SaveConfig"), "{warnings}");
+        assert!(warnings.contains("no IPv6 Address"), "{warnings}");
+        assert_eq!(parsed.warnings.len(), 4);
+    }
+
+    #[test]
+    fn normalizes_allowed_ips_and_flags_overlaps() {
+        let parsed = parse(
+            "[Interface]\nPrivateKey=k=\nAddress=10.0.0.1/24, fd00::1/64\n[Peer]\nPublicKey=p=\nEndpoint=h:1\nAllowedIPs=10.0.0.5/24, fd00:0:0:0::7/64, 10.9.9.9\n[Peer]\nPublicKey=q=\nEndpoint=h:2\nAllowedIPs=10.0.0.0/24\n",
+        )
+        .unwrap();
+        assert_eq!(
+            parsed.config.peers[0].allowed_ips,
+            vec!["10.0.0.0/24", "fd00::/64", "10.9.9.9/32"]
+        );
+        assert_eq!(parsed.warnings.len(), 1);
+        assert!(parsed.warnings[0].contains("10.0.0.0/24"));
+    }
+
+    #[test]
+    fn render_round_trips_through_parse() {
+        let text = "\
+[Interface]
+PrivateKey = k=
+Address = 10.0.0.1/24, fd00::1/64
+DNS = 10.0.0.53, fd00::53, corp.example
+ListenPort = 51820
+MTU = 1380
+FwMark = 0xca6c
+Table = off
+
+[Peer]
+PublicKey = p=
+PresharedKey = s=
+AllowedIPs = 10.0.0.2/32, fd00::2/128
+Endpoint = [2001:db8::1]:51820
+PersistentKeepalive = 25
+
+[Peer]
+PublicKey = q=
+AllowedIPs = 192.168.20.0/24
+";
+        let cfg = parse(text).unwrap().config;
+        assert_eq!(render(&cfg), text);
+        assert_eq!(parse(&render(&cfg)).unwrap().config, cfg);
     }
 
     #[test]
@@ -237,8 +653,9 @@ AllowedIPs = 0.0.0.0/0, ::/0
         let cfg = parse(
             "[Interface]\nPrivateKey=k=\nAddress=10.0.0.2/32\n[Peer]\nPublicKey=p=\nEndpoint=h:1\nAllowedIPs=0.0.0.0/0, ::/0,\n",
         )
-        .unwrap();
-        assert_eq!(cfg.peer.allowed_ips, vec!["0.0.0.0/0", "::/0"]);
+        .unwrap()
+        .config;
+        assert_eq!(cfg.peers[0].allowed_ips, vec!["0.0.0.0/0", "::/0"]);
 
         // A non-CIDR entry is rejected.
         assert!(parse(
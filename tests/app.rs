@@ -0,0 +1,178 @@
+//! The TUI's state machine (`App`, `Station`, `VpnModal`) driven by the fake
+//! NetworkManager in `common::nm`.
+
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use common::nm::{AccessPoint, Activation, MockNm, Security, wifi_profile, wireguard_profile};
+use tokio::sync::mpsc::{UnboundedReceiver, unbounded_channel};
+use wlctl::app::App;
+use wlctl::config::Config;
+use wlctl::event::Event;
+use wlctl::nm::{Mode, NMClient};
+use wlctl::vpn::{self, VpnModal};
+
+/// Long enough for any signal round trip on a loaded machine.
+const WAIT: Duration = Duration::from_secs(5);
+
+/// Waits for the next event matching `pick`, dropping the others.
+async fn next<T>(rx: &mut UnboundedReceiver<Event>, pick: impl Fn(Event) -> Option<T>) -> T {
+    tokio::time::timeout(WAIT, async {
+        loop {
+            let event = rx.recv().await.expect("sender alive");
+            if let Some(found) = pick(event) {
+                return found;
+            }
+        }
+    })
+    .await
+    .expect("event arrived in time")
+}
+
+fn notification(event: Event) -> Option<String> {
+    match event {
+        Event::Notification(n) => Some(n.message),
+        _ => None,
+    }
+}
+
+/// Ticks on every change the watcher reports until `done` holds, as the main
+/// loop would. One change can arrive as several signals (an access point,
+/// then the device's list of them), so a single tick is not enough.
+async fn tick_until(app: &mut App, rx: &mut UnboundedReceiver<Event>, done: impl Fn(&App) -> bool) {
+    while !done(app) {
+        next(rx, |e| matches!(e, Event::NmChanged).then_some(())).await;
+        app.tick().await.unwrap();
+    }
+}
+
+fn new_networks(app: &App) -> Vec<&str> {
+    names(
+        &app.device
+            .station
+            .as_ref()
+            .expect("station mode")
+            .new_networks,
+    )
+}
+
+fn names(networks: &[(wlctl::mode::station::network::Network, i16)]) -> Vec<&str> {
+    networks.iter().map(|(n, _)| n.name.as_str()).collect()
+}
+
+#[tokio::test]
+async fn app_starts_on_the_station_with_known_and_new_networks() {
+    let nm = MockNm::start().await;
+    let device = nm.add_wifi_device("wlan0").await;
+    nm.add_access_point(&device, AccessPoint::new("home", 80, Security::Wpa2))
+        .await;
+    nm.add_access_point(&device, AccessPoint::new("cafe", 50, Security::Open))
+        .await;
+    let home = nm
+        .add_profile(wifi_profile("home", "home", Some("hunter22")))
+        .await;
+    nm.add_profile(wifi_profile("faraway", "faraway", None))
+        .await;
+    nm.activate_now(&home, &device).await;
+
+    let (tx, _rx) = unbounded_channel();
+    let app = App::new(tx, Arc::new(Config::default()), Mode::Station)
+        .await
+        .unwrap();
+
+    assert_eq!(app.device.name, "wlan0");
+    assert_eq!(app.adapters.len(), 1);
+    let station = app.device.station.as_ref().expect("station mode");
+    assert_eq!(
+        station.connected_network.as_ref().map(|n| n.name.as_str()),
+        Some("home")
+    );
+    assert_eq!(names(&station.known_networks), ["home"]);
+    assert_eq!(names(&station.new_networks), ["cafe"]);
+    assert_eq!(station.unavailable_known_networks.len(), 1);
+    assert_eq!(station.unavailable_known_networks[0].name, "faraway");
+    // Building the station asked for fresh scan results.
+    assert!(nm.scans() >= 1);
+}
+
+#[tokio::test]
+async fn tick_picks_up_access_points_reported_by_the_watcher() {
+    let nm = MockNm::start().await;
+    let device = nm.add_wifi_device("wlan0").await;
+    let (tx, mut rx) = unbounded_channel();
+    let mut app = App::new(tx, Arc::new(Config::default()), Mode::Station)
+        .await
+        .unwrap();
+    assert!(new_networks(&app).is_empty());
+
+    let ap = nm
+        .add_access_point(&device, AccessPoint::new("cafe", 50, Security::Open))
+        .await;
+    tick_until(&mut app, &mut rx, |app| !new_networks(app).is_empty()).await;
+    assert_eq!(new_networks(&app), ["cafe"]);
+
+    nm.remove_access_point(&device, &ap).await;
+    tick_until(&mut app, &mut rx, |app| new_networks(app).is_empty()).await;
+}
+
+#[tokio::test]
+async fn connecting_from_the_station_reports_the_outcome() {
+    let nm = MockNm::start().await;
+    let device = nm.add_wifi_device("wlan0").await;
+    nm.add_access_point(&device, AccessPoint::new("home", 80, Security::Wpa2))
+        .await;
+    let (tx, mut rx) = unbounded_channel();
+    let app = App::new(tx.clone(), Arc::new(Config::default()), Mode::Station)
+        .await
+        .unwrap();
+    let network = app.device.station.as_ref().unwrap().new_networks[0]
+        .0
+        .clone();
+
+    nm.set_activation(Activation::Fail(7));
+    network.connect(tx.clone(), Some("wrong")).await.unwrap();
+    assert_eq!(next(&mut rx, notification).await, "Associating with home…");
+    assert_eq!(next(&mut rx, notification).await, "Wrong password for home");
+
+    nm.set_activation(Activation::Succeed);
+    network.connect(tx, Some("hunter22")).await.unwrap();
+    assert_eq!(next(&mut rx, notification).await, "Associating with home…");
+    assert_eq!(next(&mut rx, notification).await, "Connected to home");
+}
+
+#[tokio::test]
+async fn vpn_modal_toggles_autoconnect_and_the_tunnel() {
+    let nm = MockNm::start().await;
+    nm.add_wifi_device("wlan0").await;
+    nm.add_profile(wireguard_profile("work", "wg-work")).await;
+    nm.add_profile(wifi_profile("home", "home", None)).await;
+    let client = NMClient::new().await.unwrap();
+
+    let mut modal = VpnModal::load(&client).await.unwrap();
+    assert_eq!(modal.entries.len(), 1);
+    assert_eq!(modal.entries[0].info.id, "work");
+    assert!(!modal.entries[0].is_active());
+
+    let toggled = modal.toggle_autoconnect(&client).await.unwrap();
+    assert_eq!(toggled, Some(("work".to_string(), true)));
+    assert!(modal.entries[0].info.autoconnect);
+
+    vpn::toggle(&client, &modal.entries[0]).await.unwrap();
+    modal.refresh(&client).await.unwrap();
+    assert!(modal.entries[0].active_path.is_some());
+    assert_eq!(nm.actives()[0].device, None);
+
+    vpn::toggle(&client, &modal.entries[0]).await.unwrap();
+    modal.refresh(&client).await.unwrap();
+    assert!(modal.entries[0].active_path.is_none());
+
+    modal.begin_delete();
+    assert_eq!(
+        modal.delete_confirmed(&client).await.unwrap().as_deref(),
+        Some("work")
+    );
+    assert!(modal.is_empty());
+    assert_eq!(nm.profiles().len(), 1);
+}
@@ -0,0 +1,88 @@
+//! A throwaway `dbus-daemon` for one test, listening on a socket in its own
+//! temporary directory.
+
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const CONFIG: &str = r#"<!DOCTYPE busconfig PUBLIC "-//freedesktop//DTD D-Bus Bus Configuration 1.0//EN"
+ "http://www.freedesktop.org/standards/dbus/1.0/busconfig.dtd">
+<busconfig>
+  <type>session</type>
+  <listen>unix:path=SOCKET</listen>
+  <auth>EXTERNAL</auth>
+  <policy context="default">
+    <allow send_destination="*" eavesdrop="true"/>
+    <allow eavesdrop="true"/>
+    <allow own="*"/>
+  </policy>
+</busconfig>
+"#;
+
+/// A running private bus. The daemon is killed and its directory removed on
+/// drop.
+pub struct Bus {
+    daemon: Child,
+    dir: PathBuf,
+    pub address: String,
+}
+
+impl Bus {
+    /// Starts a bus. A missing `dbus-daemon` fails the test rather than
+    /// letting it pass without having run.
+    pub fn start() -> Self {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+        let dir = std::env::temp_dir().join(format!(
+            "wlctl-test-bus-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::create_dir_all(&dir).expect("create bus directory");
+        let config = dir.join("bus.conf");
+        let socket = dir.join("socket");
+        std::fs::write(
+            &config,
+            CONFIG.replace("SOCKET", &socket.display().to_string()),
+        )
+        .expect("write bus config");
+
+        let mut daemon = match Command::new("dbus-daemon")
+            .arg(format!("--config-file={}", config.display()))
+            .args(["--nofork", "--print-address=1"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+        {
+            Ok(daemon) => daemon,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let _ = std::fs::remove_dir_all(&dir);
+                panic!("dbus-daemon is not installed; the D-Bus tests need it");
+            }
+            Err(e) => panic!("could not start dbus-daemon: {e}"),
+        };
+
+        // The daemon prints its address once it is listening.
+        let mut address = String::new();
+        BufReader::new(daemon.stdout.take().expect("piped stdout"))
+            .read_line(&mut address)
+            .expect("read bus address");
+        let address = address.trim().to_string();
+        assert!(!address.is_empty(), "dbus-daemon exited without an address");
+
+        Self {
+            daemon,
+            dir,
+            address,
+        }
+    }
+}
+
+impl Drop for Bus {
+    fn drop(&mut self) {
+        let _ = self.daemon.kill();
+        let _ = self.daemon.wait();
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
@@ -0,0 +1,522 @@
+//! A fake iwd: the part of `net.connman.iwd` wlctl talks to, served with zbus
+//! on a private bus.
+//!
+//! Like the fake NetworkManager, all state lives in one [`State`] behind a
+//! mutex and the exported objects are views over it. `Network.Connect` asks
+//! the registered agent for a passphrase exactly as iwd does, so a test
+//! exercises wlctl's agent end to end.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use tokio::sync::{Mutex as AsyncMutex, MutexGuard as AsyncMutexGuard};
+use zbus::message::Header;
+use zbus::zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, Value};
+use zbus::{Connection, fdo, interface};
+
+use super::bus::Bus;
+
+const BUS_NAME: &str = "net.connman.iwd";
+const MANAGER_PATH: &str = "/net/connman/iwd";
+const DEVICE_PATH: &str = "/net/connman/iwd/0/4";
+
+const STATION: &str = "net.connman.iwd.Station";
+const NETWORK: &str = "net.connman.iwd.Network";
+
+#[derive(Debug, zbus::DBusError)]
+#[zbus(prefix = "net.connman.iwd")]
+enum IwdError {
+    #[zbus(error)]
+    ZBus(zbus::Error),
+    Failed(String),
+    NoAgent(String),
+    NotFound(String),
+}
+
+#[derive(Debug, Clone)]
+struct Network {
+    path: String,
+    name: String,
+    kind: String,
+    /// 100 * dBm.
+    signal: i16,
+    passphrase: Option<String>,
+    known: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Known {
+    pub path: String,
+    pub name: String,
+    pub autoconnect: bool,
+}
+
+struct State {
+    powered: bool,
+    station: String,
+    connected: Option<String>,
+    networks: Vec<Network>,
+    known: Vec<Known>,
+    agent: Option<(String, OwnedObjectPath)>,
+    scans: usize,
+}
+
+type Shared = Arc<Mutex<State>>;
+
+fn lock(state: &Shared) -> MutexGuard<'_, State> {
+    state.lock().expect("fake iwd state lock poisoned")
+}
+
+fn object_path(path: &str) -> OwnedObjectPath {
+    OwnedObjectPath::try_from(path.to_string()).expect("valid object path")
+}
+
+fn value<'a>(v: impl Into<Value<'a>>) -> OwnedValue {
+    OwnedValue::try_from(v.into()).expect("value is convertible")
+}
+
+/// iwd leaves optional object properties out; the fake reports them as `/`.
+fn optional_path(path: &Option<String>) -> OwnedObjectPath {
+    object_path(path.as_deref().unwrap_or("/"))
+}
+
+async fn properties_changed(
+    conn: &Connection,
+    path: &str,
+    interface: &str,
+    changed: Vec<(&str, OwnedValue)>,
+) {
+    let changed: HashMap<&str, OwnedValue> = changed.into_iter().collect();
+    conn.emit_signal(
+        None::<&str>,
+        path,
+        "org.freedesktop.DBus.Properties",
+        "PropertiesChanged",
+        &(interface, changed, Vec::<String>::new()),
+    )
+    .await
+    .expect("emit signal");
+}
+
+/// A fake iwd with one powered, disconnected station on its own bus, with
+/// `DBUS_SYSTEM_BUS_ADDRESS` pointing at it.
+pub struct MockIwd {
+    conn: Connection,
+    state: Shared,
+    _bus: Bus,
+    _exclusive: AsyncMutexGuard<'static, ()>,
+}
+
+impl MockIwd {
+    /// Starts the fake; panics when `dbus-daemon` is not installed.
+    pub async fn start() -> Self {
+        // `DBUS_SYSTEM_BUS_ADDRESS` is process-wide, so the tests of one
+        // binary take turns.
+        static EXCLUSIVE: AsyncMutex<()> = AsyncMutex::const_new(());
+        let exclusive = EXCLUSIVE.lock().await;
+
+        let bus = Bus::start();
+        // SAFETY: only tests holding `EXCLUSIVE` read or write the variable,
+        // and none of them is running while this one holds it.
+        unsafe { std::env::set_var("DBUS_SYSTEM_BUS_ADDRESS", &bus.address) };
+
+        let state: Shared = Arc::new(Mutex::new(State {
+            powered: true,
+            station: "disconnected".to_string(),
+            connected: None,
+            networks: Vec::new(),
+            known: Vec::new(),
+            agent: None,
+            scans: 0,
+        }));
+
+        let conn = zbus::connection::Builder::address(bus.address.as_str())
+            .expect("bus address")
+            .serve_at("/", fdo::ObjectManager)
+            .expect("serve object manager")
+            .serve_at(MANAGER_PATH, AgentManager(state.clone()))
+            .expect("serve agent manager")
+            .serve_at(DEVICE_PATH, Device(state.clone()))
+            .expect("serve device")
+            .serve_at(DEVICE_PATH, Station(state.clone()))
+            .expect("serve station")
+            .name(BUS_NAME)
+            .expect("bus name")
+            .build()
+            .await
+            .expect("start fake iwd");
+
+        Self {
+            conn,
+            state,
+            _bus: bus,
+            _exclusive: exclusive,
+        }
+    }
+
+    pub fn device(&self) -> &'static str {
+        DEVICE_PATH
+    }
+
+    /// Adds a network in range. `passphrase` is what `Connect` accepts for a
+    /// `psk` network. Returns its path.
+    pub async fn add_network(
+        &self,
+        name: &str,
+        kind: &str,
+        signal: i16,
+        passphrase: Option<&str>,
+    ) -> String {
+        let path = format!("{DEVICE_PATH}/{}_{kind}", hex::encode(name));
+        lock(&self.state).networks.push(Network {
+            path: path.clone(),
+            name: name.to_string(),
+            kind: kind.to_string(),
+            signal,
+            passphrase: passphrase.map(str::to_string),
+            known: None,
+        });
+        self.conn
+            .object_server()
+            .at(
+                path.as_str(),
+                NetworkObject {
+                    state: self.state.clone(),
+                    path: path.clone(),
+                },
+            )
+            .await
+            .expect("serve network");
+        path
+    }
+
+    /// Marks the network called `name` as known. Returns the known network's
+    /// path.
+    pub async fn add_known(&self, name: &str) -> String {
+        add_known(&self.conn, &self.state, name).await
+    }
+
+    /// Changes the station's state the way iwd would announce it.
+    pub async fn set_station_state(&self, state: &str) {
+        lock(&self.state).station = state.to_string();
+        properties_changed(
+            &self.conn,
+            DEVICE_PATH,
+            STATION,
+            vec![("State", value(state))],
+        )
+        .await;
+    }
+
+    pub fn known(&self) -> Vec<Known> {
+        lock(&self.state).known.clone()
+    }
+
+    pub fn connected(&self) -> Option<String> {
+        lock(&self.state).connected.clone()
+    }
+
+    pub fn powered(&self) -> bool {
+        lock(&self.state).powered
+    }
+
+    pub fn scans(&self) -> usize {
+        lock(&self.state).scans
+    }
+}
+
+async fn add_known(conn: &Connection, state: &Shared, name: &str) -> String {
+    let path = {
+        let mut st = lock(state);
+        let network = st
+            .networks
+            .iter_mut()
+            .find(|n| n.name == name)
+            .expect("known network is in range");
+        let path = format!("{MANAGER_PATH}/{}_{}", hex::encode(name), network.kind);
+        network.known = Some(path.clone());
+        st.known.push(Known {
+            path: path.clone(),
+            name: name.to_string(),
+            autoconnect: true,
+        });
+        path
+    };
+    conn.object_server()
+        .at(
+            path.as_str(),
+            KnownNetwork {
+                state: state.clone(),
+                path: path.clone(),
+            },
+        )
+        .await
+        .expect("serve known network");
+    path
+}
+
+struct AgentManager(Shared);
+
+#[interface(name = "net.connman.iwd.AgentManager")]
+impl AgentManager {
+    fn register_agent(&self, #[zbus(header)] header: Header<'_>, path: OwnedObjectPath) {
+        let sender = header
+            .sender()
+            .expect("method call has a sender")
+            .to_string();
+        lock(&self.0).agent = Some((sender, path));
+    }
+
+    fn unregister_agent(&self, _path: OwnedObjectPath) {
+        lock(&self.0).agent = None;
+    }
+}
+
+struct Device(Shared);
+
+#[interface(name = "net.connman.iwd.Device")]
+impl Device {
+    #[zbus(property)]
+    fn name(&self) -> String {
+        "wlan0".to_string()
+    }
+
+    #[zbus(property)]
+    fn address(&self) -> String {
+        "00:11:22:33:44:55".to_string()
+    }
+
+    #[zbus(property)]
+    fn mode(&self) -> String {
+        "station".to_string()
+    }
+
+    #[zbus(property)]
+    fn powered(&self) -> bool {
+        lock(&self.0).powered
+    }
+
+    #[zbus(property)]
+    fn set_powered(&mut self, powered: bool) {
+        lock(&self.0).powered = powered;
+    }
+}
+
+struct Station(Shared);
+
+#[interface(name = "net.connman.iwd.Station")]
+impl Station {
+    fn scan(&self) {
+        lock(&self.0).scans += 1;
+    }
+
+    async fn disconnect(&self, #[zbus(connection)] conn: &Connection) {
+        let network = {
+            let mut st = lock(&self.0);
+            st.station = "disconnected".to_string();
+            st.connected.take()
+        };
+        properties_changed(
+            conn,
+            DEVICE_PATH,
+            STATION,
+            vec![
+                ("State", value("disconnected")),
+                ("ConnectedNetwork", value(object_path("/"))),
+            ],
+        )
+        .await;
+        if let Some(network) = network {
+            properties_changed(conn, &network, NETWORK, vec![("Connected", value(false))]).await;
+        }
+    }
+
+    fn get_ordered_networks(&self) -> Vec<(OwnedObjectPath, i16)> {
+        let mut networks = lock(&self.0).networks.clone();
+        networks.sort_by_key(|n| std::cmp::Reverse(n.signal));
+        networks
+            .into_iter()
+            .map(|n| (object_path(&n.path), n.signal))
+            .collect()
+    }
+
+    #[zbus(property)]
+    fn state(&self) -> String {
+        lock(&self.0).station.clone()
+    }
+
+    #[zbus(property)]
+    fn connected_network(&self) -> OwnedObjectPath {
+        optional_path(&lock(&self.0).connected)
+    }
+
+    #[zbus(property)]
+    fn scanning(&self) -> bool {
+        false
+    }
+}
+
+struct NetworkObject {
+    state: Shared,
+    path: String,
+}
+
+impl NetworkObject {
+    fn network(&self) -> Network {
+        lock(&self.state)
+            .networks
+            .iter()
+            .find(|n| n.path == self.path)
+            .cloned()
+            .expect("network exists")
+    }
+}
+
+#[interface(name = "net.connman.iwd.Network")]
+impl NetworkObject {
+    /// Asks the agent for a passphrase when the network is secured and not yet
+    /// known, then connects; a wrong passphrase fails the way iwd's 4-way
+    /// handshake does.
+    async fn connect(&self, #[zbus(connection)] conn: &Connection) -> Result<(), IwdError> {
+        let network = self.network();
+        if network.kind == "psk" && network.known.is_none() {
+            let Some((agent, agent_path)) = lock(&self.state).agent.clone() else {
+                return Err(IwdError::NoAgent("No agent registered".to_string()));
+            };
+            let reply = conn
+                .call_method(
+                    Some(agent.as_str()),
+                    agent_path.as_str(),
+                    Some("net.connman.iwd.Agent"),
+                    "RequestPassphrase",
+                    &(ObjectPath::try_from(self.path.as_str()).expect("valid path"),),
+                )
+                .await;
+            let passphrase: String = match reply {
+                Ok(reply) => reply.body().deserialize()?,
+                Err(_) => return Err(IwdError::Failed("Operation failed".to_string())),
+            };
+            if Some(passphrase) != network.passphrase {
+                return Err(IwdError::Failed("Operation failed".to_string()));
+            }
+            add_known(conn, &self.state, &network.name).await;
+        }
+
+        {
+            let mut st = lock(&self.state);
+            st.station = "connected".to_string();
+            st.connected = Some(self.path.clone());
+        }
+        properties_changed(
+            conn,
+            DEVICE_PATH,
+            STATION,
+            vec![
+                ("State", value("connected")),
+                ("ConnectedNetwork", value(object_path(&self.path))),
+            ],
+        )
+        .await;
+        properties_changed(conn, &self.path, NETWORK, vec![("Connected", value(true))]).await;
+        Ok(())
+    }
+
+    #[zbus(property)]
+    fn name(&self) -> String {
+        self.network().name
+    }
+
+    #[zbus(property, name = "Type")]
+    fn kind(&self) -> String {
+        self.network().kind
+    }
+
+    #[zbus(property)]
+    fn connected(&self) -> bool {
+        lock(&self.state).connected.as_deref() == Some(self.path.as_str())
+    }
+
+    #[zbus(property)]
+    fn device(&self) -> OwnedObjectPath {
+        object_path(DEVICE_PATH)
+    }
+
+    #[zbus(property)]
+    fn known_network(&self) -> OwnedObjectPath {
+        optional_path(&self.network().known)
+    }
+}
+
+struct KnownNetwork {
+    state: Shared,
+    path: String,
+}
+
+impl KnownNetwork {
+    fn read<T>(&self, f: impl FnOnce(&Known) -> T) -> fdo::Result<T> {
+        lock(&self.state)
+            .known
+            .iter()
+            .find(|k| k.path == self.path)
+            .map(f)
+            .ok_or_else(|| fdo::Error::UnknownObject(self.path.clone()))
+    }
+}
+
+#[interface(name = "net.connman.iwd.KnownNetwork")]
+impl KnownNetwork {
+    async fn forget(&self, #[zbus(connection)] conn: &Connection) -> Result<(), IwdError> {
+        {
+            let mut st = lock(&self.state);
+            let Some(index) = st.known.iter().position(|k| k.path == self.path) else {
+                return Err(IwdError::NotFound("Not found".to_string()));
+            };
+            st.known.remove(index);
+            for network in &mut st.networks {
+                if network.known.as_deref() == Some(self.path.as_str()) {
+                    network.known = None;
+                }
+            }
+        }
+        conn.object_server()
+            .remove::<KnownNetwork, _>(self.path.as_str())
+            .await?;
+        Ok(())
+    }
+
+    #[zbus(property)]
+    fn name(&self) -> fdo::Result<String> {
+        self.read(|k| k.name.clone())
+    }
+
+    #[zbus(property, name = "Type")]
+    fn kind(&self) -> String {
+        "psk".to_string()
+    }
+
+    #[zbus(property)]
+    fn hidden(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn last_connected_time(&self) -> String {
+        "2026-01-02T03:04:05Z".to_string()
+    }
+
+    #[zbus(property)]
+    fn auto_connect(&self) -> fdo::Result<bool> {
+        self.read(|k| k.autoconnect)
+    }
+
+    #[zbus(property)]
+    fn set_auto_connect(&mut self, autoconnect: bool) {
+        if let Some(known) = lock(&self.state)
+            .known
+            .iter_mut()
+            .find(|k| k.path == self.path)
+        {
+            known.autoconnect = autoconnect;
+        }
+    }
+}
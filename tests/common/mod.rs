@@ -0,0 +1,9 @@
+//! Shared scaffolding for the integration tests: a private bus and a fake
+//! NetworkManager or iwd on it.
+
+// Each test binary compiles this module and uses a different part of it.
+#![allow(dead_code)]
+
+pub mod bus;
+pub mod iwd;
+pub mod nm;
@@ -0,0 +1,1502 @@
+//! A fake NetworkManager: the part of `org.freedesktop.NetworkManager` wlctl
+//! talks to, served with zbus on a private bus.
+//!
+//! All state lives in one [`State`] behind a mutex and the exported objects
+//! are thin views over it, so a test and a D-Bus client always see the same
+//! thing. Every change a real NetworkManager announces is announced here too
+//! (`PropertiesChanged`, `InterfacesAdded`/`InterfacesRemoved` and the
+//! per-object `StateChanged` signals), which is what `NmWatcher` and
+//! `await_activation` depend on.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use tokio::sync::{Mutex as AsyncMutex, MutexGuard as AsyncMutexGuard};
+use zbus::zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, Value};
+use zbus::{Connection, fdo, interface};
+
+use super::bus::Bus;
+
+/// Connection settings as NetworkManager passes them: section -> key -> value.
+pub type Settings = HashMap<String, HashMap<String, OwnedValue>>;
+
+const BUS_NAME: &str = "org.freedesktop.NetworkManager";
+const NM_PATH: &str = "/org/freedesktop/NetworkManager";
+const SETTINGS_PATH: &str = "/org/freedesktop/NetworkManager/Settings";
+const AGENT_MANAGER_PATH: &str = "/org/freedesktop/NetworkManager/AgentManager";
+const SECRET_AGENT_PATH: &str = "/org/freedesktop/NetworkManager/SecretAgent";
+
+const MANAGER: &str = "org.freedesktop.NetworkManager";
+const SETTINGS: &str = "org.freedesktop.NetworkManager.Settings";
+const SETTINGS_CONNECTION: &str = "org.freedesktop.NetworkManager.Settings.Connection";
+const DEVICE: &str = "org.freedesktop.NetworkManager.Device";
+const WIRELESS: &str = "org.freedesktop.NetworkManager.Device.Wireless";
+const ACTIVE: &str = "org.freedesktop.NetworkManager.Connection.Active";
+const SECRET_AGENT: &str = "org.freedesktop.NetworkManager.SecretAgent";
+
+/// How long an activation takes to settle. Long enough for a client to
+/// subscribe to the new active connection before it changes state.
+const SETTLE: Duration = Duration::from_millis(100);
+
+/// Keys NetworkManager leaves out of `GetSettings` and only hands out through
+/// `GetSecrets`.
+const SECRETS: [&str; 6] = [
+    "psk",
+    "password",
+    "wep-key0",
+    "private-key",
+    "private-key-password",
+    "preshared-key",
+];
+
+/// `NMDeviceState` and `NMActiveConnectionState` values.
+pub mod state {
+    pub const UNAVAILABLE: u32 = 20;
+    pub const DISCONNECTED: u32 = 30;
+    pub const PREPARE: u32 = 40;
+    pub const ACTIVATED: u32 = 100;
+    pub const FAILED: u32 = 120;
+
+    pub const ACTIVE_ACTIVATING: u32 = 1;
+    pub const ACTIVE_ACTIVATED: u32 = 2;
+    pub const ACTIVE_DEACTIVATED: u32 = 4;
+}
+
+/// `NMActiveConnectionStateReason` values.
+const REASON_USER_DISCONNECTED: u32 = 2;
+const REASON_DEVICE_DISCONNECTED: u32 = 3;
+
+/// How the next activations end.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Activation {
+    /// The device and the active connection reach `Activated`.
+    #[default]
+    Succeed,
+    /// The device fails with this `NMDeviceStateReason` (7 no secrets, 53
+    /// SSID not found, ...) and the active connection deactivates.
+    Fail(u32),
+    /// Nothing happens; the connection stays `Activating`.
+    Hang,
+}
+
+/// Security an access point advertises.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Security {
+    Open,
+    Wpa2,
+    Wpa3,
+    Enterprise,
+}
+
+/// A scan result to put in range of a device.
+#[derive(Debug, Clone)]
+pub struct AccessPoint {
+    pub ssid: String,
+    pub bssid: String,
+    pub strength: u8,
+    pub frequency: u32,
+    pub security: Security,
+}
+
+impl AccessPoint {
+    pub fn new(ssid: &str, strength: u8, security: Security) -> Self {
+        // A stable BSSID per SSID keeps test output readable.
+        let hash = ssid
+            .bytes()
+            .fold(0u32, |h, b| h.wrapping_mul(31) ^ b as u32);
+        let [a, b, c, d] = hash.to_be_bytes();
+        Self {
+            ssid: ssid.to_string(),
+            bssid: format!("02:00:{a:02X}:{b:02X}:{c:02X}:{d:02X}"),
+            strength,
+            frequency: 2437,
+            security,
+        }
+    }
+
+    pub fn frequency(mut self, frequency: u32) -> Self {
+        self.frequency = frequency;
+        self
+    }
+
+    /// `(Flags, WpaFlags, RsnFlags)` as NetworkManager reports them.
+    fn flags(&self) -> (u32, u32, u32) {
+        const PRIVACY: u32 = 0x1;
+        const PAIR_CCMP: u32 = 0x8;
+        const GROUP_CCMP: u32 = 0x80;
+        const KEY_MGMT_PSK: u32 = 0x100;
+        const KEY_MGMT_802_1X: u32 = 0x200;
+        const KEY_MGMT_SAE: u32 = 0x400;
+
+        let ccmp = PAIR_CCMP | GROUP_CCMP;
+        match self.security {
+            Security::Open => (0, 0, 0),
+            Security::Wpa2 => (PRIVACY, 0, ccmp | KEY_MGMT_PSK),
+            Security::Wpa3 => (PRIVACY, 0, ccmp | KEY_MGMT_SAE),
+            Security::Enterprise => (PRIVACY, 0, ccmp | KEY_MGMT_802_1X),
+        }
+    }
+}
+
+/// An active connection as the fake sees it.
+#[derive(Debug, Clone)]
+pub struct Active {
+    pub path: String,
+    pub connection: String,
+    pub device: Option<String>,
+    pub id: String,
+    pub uuid: String,
+    pub kind: String,
+    pub state: u32,
+}
+
+#[derive(Debug)]
+struct Device {
+    path: String,
+    interface: String,
+    hw_address: String,
+    state: u32,
+    active_connection: Option<String>,
+    access_points: Vec<String>,
+    active_access_point: Option<String>,
+    last_scan: i64,
+}
+
+#[derive(Debug)]
+struct Profile {
+    path: String,
+    settings: Settings,
+    version: u64,
+}
+
+#[derive(Debug)]
+struct State {
+    counters: HashMap<&'static str, u32>,
+    wireless_enabled: bool,
+    devices: Vec<Device>,
+    access_points: Vec<(String, AccessPoint)>,
+    profiles: Vec<Profile>,
+    actives: Vec<Active>,
+    primary: Option<String>,
+    activation: Activation,
+    scans: usize,
+    /// Bus names of the registered secret agents.
+    agents: Vec<String>,
+}
+
+impl State {
+    fn next_path(&mut self, kind: &'static str) -> String {
+        let n = self.counters.entry(kind).or_insert(0);
+        *n += 1;
+        format!("{NM_PATH}/{kind}/{n}")
+    }
+
+    fn device(&self, path: &str) -> Option<&Device> {
+        self.devices.iter().find(|d| d.path == path)
+    }
+
+    fn device_mut(&mut self, path: &str) -> Option<&mut Device> {
+        self.devices.iter_mut().find(|d| d.path == path)
+    }
+
+    fn access_point(&self, path: &str) -> Option<&AccessPoint> {
+        self.access_points
+            .iter()
+            .find(|(p, _)| p == path)
+            .map(|(_, ap)| ap)
+    }
+
+    fn profile(&self, path: &str) -> Option<&Profile> {
+        self.profiles.iter().find(|p| p.path == path)
+    }
+
+    fn active(&self, path: &str) -> Option<&Active> {
+        self.actives.iter().find(|a| a.path == path)
+    }
+
+    fn active_mut(&mut self, path: &str) -> Option<&mut Active> {
+        self.actives.iter_mut().find(|a| a.path == path)
+    }
+
+    fn active_paths(&self) -> Vec<OwnedObjectPath> {
+        self.actives.iter().map(|a| object_path(&a.path)).collect()
+    }
+}
+
+type Shared = Arc<Mutex<State>>;
+
+fn lock(state: &Shared) -> MutexGuard<'_, State> {
+    state
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn object_path(path: &str) -> OwnedObjectPath {
+    OwnedObjectPath::try_from(path.to_string()).expect("valid object path")
+}
+
+fn optional_path(path: &Option<String>) -> OwnedObjectPath {
+    object_path(path.as_deref().unwrap_or("/"))
+}
+
+/// A D-Bus value in owned form, for building [`Settings`].
+pub fn value<'a>(v: impl Into<Value<'a>>) -> OwnedValue {
+    v.into().try_to_owned().expect("value is convertible")
+}
+
+fn copy_settings(settings: &Settings) -> Settings {
+    settings
+        .iter()
+        .map(|(section, keys)| {
+            let keys = keys
+                .iter()
+                .map(|(k, v)| (k.clone(), v.try_clone().expect("no file descriptors")))
+                .collect();
+            (section.clone(), keys)
+        })
+        .collect()
+}
+
+fn setting_str(settings: &Settings, section: &str, key: &str) -> Option<String> {
+    let value = settings.get(section)?.get(key)?.try_clone().ok()?;
+    String::try_from(value).ok()
+}
+
+fn ssid_of(settings: &Settings) -> Option<String> {
+    let value = settings
+        .get("802-11-wireless")?
+        .get("ssid")?
+        .try_clone()
+        .ok()?;
+    let bytes = Vec::<u8>::try_from(value).ok()?;
+    Some(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// A saved WiFi profile, with a PSK when `psk` is given.
+pub fn wifi_profile(id: &str, ssid: &str, psk: Option<&str>) -> Settings {
+    let mut settings = Settings::new();
+    settings.insert(
+        "connection".into(),
+        HashMap::from([
+            ("id".to_string(), value(id)),
+            ("type".to_string(), value("802-11-wireless")),
+        ]),
+    );
+    settings.insert(
+        "802-11-wireless".into(),
+        HashMap::from([("ssid".to_string(), value(ssid.as_bytes().to_vec()))]),
+    );
+    if let Some(psk) = psk {
+        settings.insert(
+            "802-11-wireless-security".into(),
+            HashMap::from([
+                ("key-mgmt".to_string(), value("wpa-psk")),
+                ("psk".to_string(), value(psk)),
+            ]),
+        );
+    }
+    settings
+}
+
+/// A saved OpenVPN profile that asks for its password at connect time.
+pub fn openvpn_profile(id: &str, username: &str) -> Settings {
+    let mut settings = Settings::new();
+    settings.insert(
+        "connection".into(),
+        HashMap::from([
+            ("id".to_string(), value(id)),
+            ("type".to_string(), value("vpn")),
+        ]),
+    );
+    let data = HashMap::from([
+        ("connection-type".to_string(), "password".to_string()),
+        ("username".to_string(), username.to_string()),
+        ("password-flags".to_string(), "2".to_string()),
+    ]);
+    settings.insert(
+        "vpn".into(),
+        HashMap::from([
+            (
+                "service-type".to_string(),
+                value("org.freedesktop.NetworkManager.openvpn"),
+            ),
+            ("data".to_string(), value(data)),
+        ]),
+    );
+    settings
+}
+
+/// A saved WireGuard profile for `interface`.
+pub fn wireguard_profile(id: &str, interface: &str) -> Settings {
+    let mut settings = Settings::new();
+    settings.insert(
+        "connection".into(),
+        HashMap::from([
+            ("id".to_string(), value(id)),
+            ("type".to_string(), value("wireguard")),
+            ("interface-name".to_string(), value(interface)),
+            ("autoconnect".to_string(), value(false)),
+        ]),
+    );
+    settings.insert(
+        "wireguard".into(),
+        HashMap::from([(
+            "private-key".to_string(),
+            value("yAnz5TF+lXXJte14tji3zlMNq+hd2rYUIgJBgB3fBmk="),
+        )]),
+    );
+    settings
+}
+
+async fn emit<B>(conn: &Connection, path: &str, interface: &str, signal: &str, body: &B)
+where
+    B: serde::Serialize + zbus::zvariant::DynamicType,
+{
+    conn.emit_signal(None::<&str>, path, interface, signal, body)
+        .await
+        .expect("emit signal");
+}
+
+async fn properties_changed(
+    conn: &Connection,
+    path: &str,
+    interface: &str,
+    changed: Vec<(&str, OwnedValue)>,
+) {
+    let changed: HashMap<&str, OwnedValue> = changed.into_iter().collect();
+    emit(
+        conn,
+        path,
+        "org.freedesktop.DBus.Properties",
+        "PropertiesChanged",
+        &(interface, changed, Vec::<String>::new()),
+    )
+    .await;
+}
+
+async fn announce_actives(conn: &Connection, state: &Shared) {
+    let (actives, primary) = {
+        let st = lock(state);
+        (st.active_paths(), optional_path(&st.primary))
+    };
+    properties_changed(
+        conn,
+        NM_PATH,
+        MANAGER,
+        vec![
+            ("ActiveConnections", value(actives)),
+            ("PrimaryConnection", value(primary)),
+        ],
+    )
+    .await;
+}
+
+/// Moves a device to `new`, emitting `StateChanged` with `reason` and the
+/// properties that follow the state.
+async fn set_device_state(conn: &Connection, state: &Shared, device: &str, new: u32, reason: u32) {
+    let (old, active, ap) = {
+        let mut st = lock(state);
+        let Some(dev) = st.device_mut(device) else {
+            return;
+        };
+        let old = std::mem::replace(&mut dev.state, new);
+        if new != state::ACTIVATED {
+            dev.active_access_point = None;
+        }
+        if new <= state::DISCONNECTED || new == state::FAILED {
+            dev.active_connection = None;
+        }
+        (
+            old,
+            optional_path(&dev.active_connection),
+            optional_path(&dev.active_access_point),
+        )
+    };
+
+    emit(conn, device, DEVICE, "StateChanged", &(new, old, reason)).await;
+    properties_changed(
+        conn,
+        device,
+        DEVICE,
+        vec![("State", value(new)), ("ActiveConnection", value(active))],
+    )
+    .await;
+    properties_changed(
+        conn,
+        device,
+        WIRELESS,
+        vec![("ActiveAccessPoint", value(ap))],
+    )
+    .await;
+}
+
+async fn set_active_state(conn: &Connection, state: &Shared, active: &str, new: u32, reason: u32) {
+    if let Some(ac) = lock(state).active_mut(active) {
+        ac.state = new;
+    }
+    emit(conn, active, ACTIVE, "StateChanged", &(new, reason)).await;
+    properties_changed(conn, active, ACTIVE, vec![("State", value(new))]).await;
+}
+
+async fn add_profile(conn: &Connection, state: &Shared, mut settings: Settings) -> String {
+    let path = {
+        let mut st = lock(state);
+        let path = st.next_path("Settings");
+        let n = st.profiles.len() + 1;
+        let connection = settings.entry("connection".into()).or_default();
+        connection
+            .entry("uuid".into())
+            .or_insert_with(|| value(format!("00000000-0000-4000-8000-{n:012x}")));
+        st.profiles.push(Profile {
+            path: path.clone(),
+            settings,
+            version: 1,
+        });
+        path
+    };
+
+    conn.object_server()
+        .at(
+            path.as_str(),
+            SettingsConnection {
+                state: state.clone(),
+                path: path.clone(),
+            },
+        )
+        .await
+        .expect("export profile");
+    emit(
+        conn,
+        SETTINGS_PATH,
+        SETTINGS,
+        "NewConnection",
+        &(object_path(&path),),
+    )
+    .await;
+    path
+}
+
+/// Starts activating `profile` on `device` (`/` for a VPN). The outcome
+/// follows the configured [`Activation`] after `settle`, or right away when
+/// `settle` is `None`.
+async fn activate(
+    conn: &Connection,
+    state: &Shared,
+    profile: &str,
+    device: &str,
+    settle: Option<Duration>,
+) -> fdo::Result<String> {
+    let device = (device != "/").then(|| device.to_string());
+    let (path, previous, activation) = {
+        let mut st = lock(state);
+        let settings = &st
+            .profile(profile)
+            .ok_or_else(|| fdo::Error::UnknownObject(format!("No profile at {profile}")))?
+            .settings;
+        let id = setting_str(settings, "connection", "id").unwrap_or_default();
+        let uuid = setting_str(settings, "connection", "uuid").unwrap_or_default();
+        let kind = setting_str(settings, "connection", "type").unwrap_or_default();
+        let previous = match &device {
+            Some(device) => st
+                .device(device)
+                .ok_or_else(|| fdo::Error::UnknownObject(format!("No device at {device}")))?
+                .active_connection
+                .clone(),
+            None => None,
+        };
+
+        let path = st.next_path("ActiveConnection");
+        st.actives.push(Active {
+            path: path.clone(),
+            connection: profile.to_string(),
+            device: device.clone(),
+            id,
+            uuid,
+            kind,
+            state: state::ACTIVE_ACTIVATING,
+        });
+        (path, previous, st.activation)
+    };
+
+    // Activating over an existing connection replaces it, as NM does.
+    if let Some(previous) = previous {
+        deactivate(conn, state, &previous, REASON_USER_DISCONNECTED).await;
+    }
+
+    conn.object_server()
+        .at(
+            path.as_str(),
+            ActiveConnection {
+                state: state.clone(),
+                path: path.clone(),
+            },
+        )
+        .await?;
+    if let Some(device) = &device {
+        if let Some(dev) = lock(state).device_mut(device) {
+            dev.active_connection = Some(path.clone());
+        }
+        set_device_state(conn, state, device, state::PREPARE, 0).await;
+    }
+    announce_actives(conn, state).await;
+
+    match settle {
+        None => finish(conn, state, &path, activation).await,
+        Some(_) if activation == Activation::Hang => {}
+        Some(after) => {
+            let (conn, state, path) = (conn.clone(), state.clone(), path.clone());
+            tokio::spawn(async move {
+                tokio::time::sleep(after).await;
+                finish(&conn, &state, &path, activation).await;
+            });
+        }
+    }
+    Ok(path)
+}
+
+/// Ends an activation the way `activation` says.
+async fn finish(conn: &Connection, state: &Shared, active: &str, activation: Activation) {
+    let Some(ac) = lock(state).active(active).cloned() else {
+        return;
+    };
+
+    match activation {
+        Activation::Succeed => {
+            if let Some(device) = &ac.device {
+                let mut st = lock(state);
+                let ssid = st
+                    .profile(&ac.connection)
+                    .and_then(|p| ssid_of(&p.settings));
+                let ap = st.device(device).and_then(|dev| {
+                    dev.access_points
+                        .iter()
+                        .filter(|path| st.access_point(path).map(|ap| &ap.ssid) == ssid.as_ref())
+                        .max_by_key(|path| st.access_point(path).map(|ap| ap.strength))
+                        .cloned()
+                });
+                if let Some(dev) = st.device_mut(device) {
+                    dev.active_access_point = ap;
+                }
+                st.primary = Some(ac.path.clone());
+            }
+            if let Some(device) = &ac.device {
+                set_device_state(conn, state, device, state::ACTIVATED, 0).await;
+                // The state change above clears the AP for any other state,
+                // so set it again now that the device is activated.
+                let ap = lock(state)
+                    .device(device)
+                    .map(|dev| optional_path(&dev.active_access_point));
+                if let Some(ap) = ap {
+                    properties_changed(
+                        conn,
+                        device,
+                        WIRELESS,
+                        vec![("ActiveAccessPoint", value(ap))],
+                    )
+                    .await;
+                }
+            }
+            set_active_state(conn, state, active, state::ACTIVE_ACTIVATED, 0).await;
+            announce_actives(conn, state).await;
+        }
+        Activation::Fail(reason) => {
+            if let Some(device) = &ac.device {
+                set_device_state(conn, state, device, state::FAILED, reason).await;
+            }
+            set_active_state(
+                conn,
+                state,
+                active,
+                state::ACTIVE_DEACTIVATED,
+                REASON_DEVICE_DISCONNECTED,
+            )
+            .await;
+            remove_active(conn, state, active).await;
+            if let Some(device) = &ac.device {
+                set_device_state(conn, state, device, state::DISCONNECTED, reason).await;
+            }
+        }
+        Activation::Hang => {}
+    }
+}
+
+async fn deactivate(conn: &Connection, state: &Shared, active: &str, reason: u32) {
+    let Some(ac) = lock(state).active(active).cloned() else {
+        return;
+    };
+    set_active_state(conn, state, active, state::ACTIVE_DEACTIVATED, reason).await;
+    remove_active(conn, state, active).await;
+    if let Some(device) = &ac.device {
+        set_device_state(conn, state, device, state::DISCONNECTED, 39).await;
+    }
+}
+
+async fn remove_active(conn: &Connection, state: &Shared, active: &str) {
+    {
+        let mut st = lock(state);
+        st.actives.retain(|a| a.path != active);
+        if st.primary.as_deref() == Some(active) {
+            st.primary = None;
+        }
+    }
+    let _ = conn
+        .object_server()
+        .remove::<ActiveConnection, _>(active)
+        .await;
+    announce_actives(conn, state).await;
+}
+
+/// A fake NetworkManager on its own bus, with `DBUS_SYSTEM_BUS_ADDRESS`
+/// pointing at it so `NMClient::new` finds it.
+///
+/// Starts with wireless enabled and nothing else: add devices, access points
+/// and profiles to set the scene.
+pub struct MockNm {
+    conn: Connection,
+    state: Shared,
+    _bus: Bus,
+    _exclusive: AsyncMutexGuard<'static, ()>,
+}
+
+impl MockNm {
+    /// Starts the fake; panics when `dbus-daemon` is not installed.
+    pub async fn start() -> Self {
+        // `DBUS_SYSTEM_BUS_ADDRESS` is process-wide, so the tests of one
+        // binary take turns.
+        static EXCLUSIVE: AsyncMutex<()> = AsyncMutex::const_new(());
+        let exclusive = EXCLUSIVE.lock().await;
+
+        let bus = Bus::start();
+        // SAFETY: only tests holding `EXCLUSIVE` read or write the variable,
+        // and none of them is running while this one holds it.
+        unsafe { std::env::set_var("DBUS_SYSTEM_BUS_ADDRESS", &bus.address) };
+
+        let state: Shared = Arc::new(Mutex::new(State {
+            counters: HashMap::new(),
+            wireless_enabled: true,
+            devices: Vec::new(),
+            access_points: Vec::new(),
+            profiles: Vec::new(),
+            actives: Vec::new(),
+            primary: None,
+            activation: Activation::default(),
+            scans: 0,
+            agents: Vec::new(),
+        }));
+
+        let conn = zbus::connection::Builder::address(bus.address.as_str())
+            .expect("bus address")
+            .serve_at("/org/freedesktop", fdo::ObjectManager)
+            .expect("serve object manager")
+            .serve_at(NM_PATH, Manager(state.clone()))
+            .expect("serve manager")
+            .serve_at(SETTINGS_PATH, SettingsManager(state.clone()))
+            .expect("serve settings")
+            .serve_at(AGENT_MANAGER_PATH, AgentManager(state.clone()))
+            .expect("serve agent manager")
+            .name(BUS_NAME)
+            .expect("bus name")
+            .build()
+            .await
+            .expect("start fake NetworkManager");
+
+        Self {
+            conn,
+            state,
+            _bus: bus,
+            _exclusive: exclusive,
+        }
+    }
+
+    /// Adds a disconnected WiFi device called `interface`. Returns its path.
+    pub async fn add_wifi_device(&self, interface: &str) -> String {
+        let path = {
+            let mut st = lock(&self.state);
+            let path = st.next_path("Devices");
+            let n = st.devices.len() + 1;
+            st.devices.push(Device {
+                path: path.clone(),
+                interface: interface.to_string(),
+                hw_address: format!("02:00:00:00:00:{n:02X}"),
+                state: state::DISCONNECTED,
+                active_connection: None,
+                access_points: Vec::new(),
+                active_access_point: None,
+                last_scan: -1,
+            });
+            path
+        };
+
+        let server = self.conn.object_server();
+        server
+            .at(
+                path.as_str(),
+                WifiDevice {
+                    state: self.state.clone(),
+                    path: path.clone(),
+                },
+            )
+            .await
+            .expect("export device");
+        server
+            .at(
+                path.as_str(),
+                Wireless {
+                    state: self.state.clone(),
+                    path: path.clone(),
+                },
+            )
+            .await
+            .expect("export wireless");
+        self.announce_devices().await;
+        path
+    }
+
+    /// Puts `ap` in range of `device`. Returns the access point's path.
+    pub async fn add_access_point(&self, device: &str, ap: AccessPoint) -> String {
+        let path = {
+            let mut st = lock(&self.state);
+            let path = st.next_path("AccessPoint");
+            st.access_points.push((path.clone(), ap.clone()));
+            st.device_mut(device)
+                .expect("device exists")
+                .access_points
+                .push(path.clone());
+            path
+        };
+
+        self.conn
+            .object_server()
+            .at(path.as_str(), AccessPointObject(ap))
+            .await
+            .expect("export access point");
+        emit(
+            &self.conn,
+            device,
+            WIRELESS,
+            "AccessPointAdded",
+            &(object_path(&path),),
+        )
+        .await;
+        self.announce_access_points(device).await;
+        path
+    }
+
+    /// Takes an access point out of range.
+    pub async fn remove_access_point(&self, device: &str, ap: &str) {
+        {
+            let mut st = lock(&self.state);
+            st.access_points.retain(|(path, _)| path != ap);
+            if let Some(dev) = st.device_mut(device) {
+                dev.access_points.retain(|path| path != ap);
+            }
+        }
+        let _ = self
+            .conn
+            .object_server()
+            .remove::<AccessPointObject, _>(ap)
+            .await;
+        emit(
+            &self.conn,
+            device,
+            WIRELESS,
+            "AccessPointRemoved",
+            &(object_path(ap),),
+        )
+        .await;
+        self.announce_access_points(device).await;
+    }
+
+    /// Saves a profile. Returns its path.
+    pub async fn add_profile(&self, settings: Settings) -> String {
+        add_profile(&self.conn, &self.state, settings).await
+    }
+
+    /// Activates `profile` on `device` and settles it before returning, as if
+    /// it had been connected before the test started. Returns the active
+    /// connection's path.
+    pub async fn activate_now(&self, profile: &str, device: &str) -> String {
+        activate(&self.conn, &self.state, profile, device, None)
+            .await
+            .expect("activate profile")
+    }
+
+    /// How the next activations end.
+    pub fn set_activation(&self, activation: Activation) {
+        lock(&self.state).activation = activation;
+    }
+
+    /// A saved profile's settings, secrets included.
+    pub fn profile(&self, path: &str) -> Option<Settings> {
+        lock(&self.state)
+            .profile(path)
+            .map(|p| copy_settings(&p.settings))
+    }
+
+    /// Paths of every saved profile, oldest first.
+    pub fn profiles(&self) -> Vec<String> {
+        lock(&self.state)
+            .profiles
+            .iter()
+            .map(|p| p.path.clone())
+            .collect()
+    }
+
+    pub fn actives(&self) -> Vec<Active> {
+        lock(&self.state).actives.clone()
+    }
+
+    pub fn device_state(&self, device: &str) -> u32 {
+        lock(&self.state)
+            .device(device)
+            .map(|dev| dev.state)
+            .unwrap_or(state::UNAVAILABLE)
+    }
+
+    pub fn wireless_enabled(&self) -> bool {
+        lock(&self.state).wireless_enabled
+    }
+
+    /// How many scans clients have requested.
+    /// Asks the most recently registered secret agent for the `setting`
+    /// secrets of the profile at `path`, as an activation that needs them
+    /// does.
+    pub async fn get_secrets(
+        &self,
+        settings: &Settings,
+        path: &str,
+        setting: &str,
+        hints: &[&str],
+        flags: u32,
+    ) -> zbus::Result<Settings> {
+        let agent = self.agent().await?;
+        let reply = agent
+            .call_method(
+                "GetSecrets",
+                &(settings, object_path(path), setting, hints, flags),
+            )
+            .await?;
+        reply.body().deserialize()
+    }
+
+    /// Withdraws a `get_secrets` request still waiting for an answer.
+    pub async fn cancel_get_secrets(&self, path: &str, setting: &str) -> zbus::Result<()> {
+        let agent = self.agent().await?;
+        agent
+            .call_method("CancelGetSecrets", &(object_path(path), setting))
+            .await?;
+        Ok(())
+    }
+
+    async fn agent(&self) -> zbus::Result<zbus::Proxy<'static>> {
+        let name = lock(&self.state)
+            .agents
+            .last()
+            .cloned()
+            .ok_or_else(|| zbus::Error::Failure("no secret agent registered".into()))?;
+        zbus::Proxy::new(&self.conn, name, SECRET_AGENT_PATH, SECRET_AGENT).await
+    }
+
+    pub fn scans(&self) -> usize {
+        lock(&self.state).scans
+    }
+
+    async fn announce_devices(&self) {
+        let devices: Vec<OwnedObjectPath> = lock(&self.state)
+            .devices
+            .iter()
+            .map(|d| object_path(&d.path))
+            .collect();
+        properties_changed(
+            &self.conn,
+            NM_PATH,
+            MANAGER,
+            vec![
+                ("Devices", value(devices.clone())),
+                ("AllDevices", value(devices)),
+            ],
+        )
+        .await;
+    }
+
+    async fn announce_access_points(&self, device: &str) {
+        let aps: Vec<OwnedObjectPath> = lock(&self.state)
+            .device(device)
+            .map(|dev| dev.access_points.iter().map(|p| object_path(p)).collect())
+            .unwrap_or_default();
+        properties_changed(
+            &self.conn,
+            device,
+            WIRELESS,
+            vec![("AccessPoints", value(aps))],
+        )
+        .await;
+    }
+}
+
+struct Manager(Shared);
+
+#[interface(name = "org.freedesktop.NetworkManager")]
+impl Manager {
+    async fn get_devices(&self) -> Vec<OwnedObjectPath> {
+        self.devices()
+    }
+
+    async fn get_all_devices(&self) -> Vec<OwnedObjectPath> {
+        self.devices()
+    }
+
+    async fn activate_connection(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        connection: ObjectPath<'_>,
+        device: ObjectPath<'_>,
+        _specific_object: ObjectPath<'_>,
+    ) -> fdo::Result<OwnedObjectPath> {
+        let active = activate(conn, &self.0, &connection, &device, Some(SETTLE)).await?;
+        Ok(object_path(&active))
+    }
+
+    async fn add_and_activate_connection(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        mut connection: Settings,
+        device: ObjectPath<'_>,
+        specific_object: ObjectPath<'_>,
+    ) -> fdo::Result<(OwnedObjectPath, OwnedObjectPath)> {
+        // NM fills the SSID in from the access point when it is left out.
+        if ssid_of(&connection).is_none()
+            && let Some(ap) = lock(&self.0).access_point(&specific_object)
+        {
+            connection
+                .entry("802-11-wireless".into())
+                .or_default()
+                .insert("ssid".into(), value(ap.ssid.as_bytes().to_vec()));
+        }
+        let profile = add_profile(conn, &self.0, connection).await;
+        let active = activate(conn, &self.0, &profile, &device, Some(SETTLE)).await?;
+        Ok((object_path(&profile), object_path(&active)))
+    }
+
+    async fn deactivate_connection(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        active_connection: ObjectPath<'_>,
+    ) -> fdo::Result<()> {
+        if lock(&self.0).active(&active_connection).is_none() {
+            return Err(fdo::Error::Failed(format!(
+                "{active_connection} is not active"
+            )));
+        }
+        deactivate(conn, &self.0, &active_connection, REASON_USER_DISCONNECTED).await;
+        Ok(())
+    }
+
+    /// Always full connectivity.
+    async fn check_connectivity(&self) -> u32 {
+        4
+    }
+
+    #[zbus(property)]
+    fn version(&self) -> String {
+        "1.48.0-fake".to_string()
+    }
+
+    #[zbus(property)]
+    fn devices(&self) -> Vec<OwnedObjectPath> {
+        lock(&self.0)
+            .devices
+            .iter()
+            .map(|d| object_path(&d.path))
+            .collect()
+    }
+
+    #[zbus(property)]
+    fn all_devices(&self) -> Vec<OwnedObjectPath> {
+        self.devices()
+    }
+
+    #[zbus(property)]
+    fn active_connections(&self) -> Vec<OwnedObjectPath> {
+        lock(&self.0).active_paths()
+    }
+
+    #[zbus(property)]
+    fn primary_connection(&self) -> OwnedObjectPath {
+        optional_path(&lock(&self.0).primary)
+    }
+
+    #[zbus(property)]
+    fn wireless_enabled(&self) -> bool {
+        lock(&self.0).wireless_enabled
+    }
+
+    #[zbus(property)]
+    fn set_wireless_enabled(&self, enabled: bool) {
+        lock(&self.0).wireless_enabled = enabled;
+    }
+
+    #[zbus(property)]
+    fn wireless_hardware_enabled(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn networking_enabled(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn connectivity(&self) -> u32 {
+        4
+    }
+
+    #[zbus(property)]
+    fn connectivity_check_uri(&self) -> String {
+        String::new()
+    }
+}
+
+struct SettingsManager(Shared);
+
+#[interface(name = "org.freedesktop.NetworkManager.Settings")]
+impl SettingsManager {
+    async fn list_connections(&self) -> Vec<OwnedObjectPath> {
+        self.connections()
+    }
+
+    async fn add_connection(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        connection: Settings,
+    ) -> OwnedObjectPath {
+        object_path(&add_profile(conn, &self.0, connection).await)
+    }
+
+    #[zbus(property)]
+    fn connections(&self) -> Vec<OwnedObjectPath> {
+        lock(&self.0)
+            .profiles
+            .iter()
+            .map(|p| object_path(&p.path))
+            .collect()
+    }
+
+    #[zbus(property)]
+    fn hostname(&self) -> String {
+        "fake".to_string()
+    }
+}
+
+struct AgentManager(Shared);
+
+#[interface(name = "org.freedesktop.NetworkManager.AgentManager")]
+impl AgentManager {
+    async fn register(
+        &self,
+        #[zbus(header)] header: zbus::message::Header<'_>,
+        _identifier: String,
+    ) -> fdo::Result<()> {
+        let sender = header
+            .sender()
+            .ok_or_else(|| fdo::Error::Failed("no sender".into()))?;
+        lock(&self.0).agents.push(sender.to_string());
+        Ok(())
+    }
+}
+
+struct SettingsConnection {
+    state: Shared,
+    path: String,
+}
+
+#[interface(name = "org.freedesktop.NetworkManager.Settings.Connection")]
+impl SettingsConnection {
+    /// The settings without their secrets.
+    async fn get_settings(&self) -> fdo::Result<Settings> {
+        let st = lock(&self.state);
+        let mut settings = copy_settings(&self.profile(&st)?.settings);
+        for keys in settings.values_mut() {
+            keys.retain(|key, _| !SECRETS.contains(&key.as_str()));
+        }
+        Ok(settings)
+    }
+
+    async fn get_secrets(&self, setting_name: &str) -> fdo::Result<Settings> {
+        let st = lock(&self.state);
+        let settings = &self.profile(&st)?.settings;
+        let secrets = settings
+            .get(setting_name)
+            .map(|keys| {
+                keys.iter()
+                    .filter(|(key, _)| SECRETS.contains(&key.as_str()))
+                    .map(|(k, v)| (k.clone(), v.try_clone().expect("no file descriptors")))
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok(HashMap::from([(setting_name.to_string(), secrets)]))
+    }
+
+    async fn update(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        properties: Settings,
+    ) -> fdo::Result<()> {
+        let version = {
+            let mut st = lock(&self.state);
+            let profile = st
+                .profiles
+                .iter_mut()
+                .find(|p| p.path == self.path)
+                .ok_or_else(|| fdo::Error::UnknownObject(self.path.clone()))?;
+            profile.settings = properties;
+            profile.version += 1;
+            profile.version
+        };
+        emit(conn, &self.path, SETTINGS_CONNECTION, "Updated", &()).await;
+        properties_changed(
+            conn,
+            &self.path,
+            SETTINGS_CONNECTION,
+            vec![("VersionId", value(version))],
+        )
+        .await;
+        Ok(())
+    }
+
+    async fn delete(&self, #[zbus(connection)] conn: &Connection) -> fdo::Result<()> {
+        let actives: Vec<String> = {
+            let mut st = lock(&self.state);
+            st.profiles.retain(|p| p.path != self.path);
+            st.actives
+                .iter()
+                .filter(|a| a.connection == self.path)
+                .map(|a| a.path.clone())
+                .collect()
+        };
+        // Deleting a profile takes down its connection.
+        for active in actives {
+            deactivate(conn, &self.state, &active, REASON_USER_DISCONNECTED).await;
+        }
+        emit(conn, &self.path, SETTINGS_CONNECTION, "Removed", &()).await;
+        emit(
+            conn,
+            SETTINGS_PATH,
+            SETTINGS,
+            "ConnectionRemoved",
+            &(object_path(&self.path),),
+        )
+        .await;
+        conn.object_server()
+            .remove::<Self, _>(self.path.as_str())
+            .await?;
+        Ok(())
+    }
+
+    #[zbus(property)]
+    fn version_id(&self) -> u64 {
+        lock(&self.state)
+            .profile(&self.path)
+            .map(|p| p.version)
+            .unwrap_or(0)
+    }
+
+    #[zbus(property)]
+    fn unsaved(&self) -> bool {
+        false
+    }
+}
+
+impl SettingsConnection {
+    fn profile<'a>(&self, st: &'a State) -> fdo::Result<&'a Profile> {
+        st.profile(&self.path)
+            .ok_or_else(|| fdo::Error::UnknownObject(self.path.clone()))
+    }
+}
+
+struct WifiDevice {
+    state: Shared,
+    path: String,
+}
+
+impl WifiDevice {
+    fn read<T>(&self, f: impl FnOnce(&Device) -> T) -> T
+    where
+        T: Default,
+    {
+        lock(&self.state)
+            .device(&self.path)
+            .map(f)
+            .unwrap_or_default()
+    }
+}
+
+#[interface(name = "org.freedesktop.NetworkManager.Device")]
+impl WifiDevice {
+    async fn disconnect(&self, #[zbus(connection)] conn: &Connection) -> fdo::Result<()> {
+        let active = self.read(|dev| dev.active_connection.clone());
+        let Some(active) = active else {
+            return Err(fdo::Error::Failed("This device is not active".into()));
+        };
+        deactivate(conn, &self.state, &active, REASON_USER_DISCONNECTED).await;
+        Ok(())
+    }
+
+    #[zbus(property)]
+    fn interface(&self) -> String {
+        self.read(|dev| dev.interface.clone())
+    }
+
+    #[zbus(property)]
+    fn hw_address(&self) -> String {
+        self.read(|dev| dev.hw_address.clone())
+    }
+
+    #[zbus(property)]
+    fn device_type(&self) -> u32 {
+        2
+    }
+
+    #[zbus(property)]
+    fn driver(&self) -> String {
+        "fake".to_string()
+    }
+
+    #[zbus(property)]
+    fn managed(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn state(&self) -> u32 {
+        self.read(|dev| dev.state)
+    }
+
+    #[zbus(property)]
+    fn active_connection(&self) -> OwnedObjectPath {
+        optional_path(&self.read(|dev| dev.active_connection.clone()))
+    }
+
+    #[zbus(property)]
+    fn ip4_config(&self) -> OwnedObjectPath {
+        object_path("/")
+    }
+
+    #[zbus(property)]
+    fn ip6_config(&self) -> OwnedObjectPath {
+        object_path("/")
+    }
+
+    #[zbus(property)]
+    fn dhcp4_config(&self) -> OwnedObjectPath {
+        object_path("/")
+    }
+}
+
+struct Wireless {
+    state: Shared,
+    path: String,
+}
+
+impl Wireless {
+    fn read<T>(&self, f: impl FnOnce(&Device) -> T) -> T
+    where
+        T: Default,
+    {
+        lock(&self.state)
+            .device(&self.path)
+            .map(f)
+            .unwrap_or_default()
+    }
+}
+
+#[interface(name = "org.freedesktop.NetworkManager.Device.Wireless")]
+impl Wireless {
+    async fn get_all_access_points(&self) -> Vec<OwnedObjectPath> {
+        self.access_points()
+    }
+
+    async fn get_access_points(&self) -> Vec<OwnedObjectPath> {
+        self.access_points()
+    }
+
+    async fn request_scan(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        _options: HashMap<String, OwnedValue>,
+    ) {
+        let last_scan = {
+            let mut st = lock(&self.state);
+            st.scans += 1;
+            let scans = st.scans as i64;
+            let Some(dev) = st.device_mut(&self.path) else {
+                return;
+            };
+            dev.last_scan = scans * 1000;
+            dev.last_scan
+        };
+        properties_changed(
+            conn,
+            &self.path,
+            WIRELESS,
+            vec![("LastScan", value(last_scan))],
+        )
+        .await;
+    }
+
+    #[zbus(property)]
+    fn hw_address(&self) -> String {
+        self.read(|dev| dev.hw_address.clone())
+    }
+
+    /// Infrastructure mode.
+    #[zbus(property)]
+    fn mode(&self) -> u32 {
+        2
+    }
+
+    #[zbus(property)]
+    fn access_points(&self) -> Vec<OwnedObjectPath> {
+        self.read(|dev| dev.access_points.iter().map(|p| object_path(p)).collect())
+    }
+
+    #[zbus(property)]
+    fn active_access_point(&self) -> OwnedObjectPath {
+        optional_path(&self.read(|dev| dev.active_access_point.clone()))
+    }
+
+    #[zbus(property)]
+    fn bitrate(&self) -> u32 {
+        if self.read(|dev| dev.active_access_point.is_some()) {
+            144_000
+        } else {
+            0
+        }
+    }
+
+    #[zbus(property)]
+    fn last_scan(&self) -> i64 {
+        self.read(|dev| dev.last_scan)
+    }
+}
+
+struct AccessPointObject(AccessPoint);
+
+#[interface(name = "org.freedesktop.NetworkManager.AccessPoint")]
+impl AccessPointObject {
+    #[zbus(property)]
+    fn ssid(&self) -> Vec<u8> {
+        self.0.ssid.as_bytes().to_vec()
+    }
+
+    #[zbus(property)]
+    fn strength(&self) -> u8 {
+        self.0.strength
+    }
+
+    #[zbus(property)]
+    fn frequency(&self) -> u32 {
+        self.0.frequency
+    }
+
+    #[zbus(property)]
+    fn hw_address(&self) -> String {
+        self.0.bssid.clone()
+    }
+
+    #[zbus(property)]
+    fn flags(&self) -> u32 {
+        self.0.flags().0
+    }
+
+    #[zbus(property)]
+    fn wpa_flags(&self) -> u32 {
+        self.0.flags().1
+    }
+
+    #[zbus(property)]
+    fn rsn_flags(&self) -> u32 {
+        self.0.flags().2
+    }
+
+    #[zbus(property)]
+    fn mode(&self) -> u32 {
+        2
+    }
+
+    #[zbus(property)]
+    fn max_bitrate(&self) -> u32 {
+        866_000
+    }
+
+    #[zbus(property)]
+    fn last_seen(&self) -> i32 {
+        1
+    }
+}
+
+struct ActiveConnection {
+    state: Shared,
+    path: String,
+}
+
+impl ActiveConnection {
+    fn read<T>(&self, f: impl FnOnce(&Active) -> T) -> T
+    where
+        T: Default,
+    {
+        lock(&self.state)
+            .active(&self.path)
+            .map(f)
+            .unwrap_or_default()
+    }
+}
+
+#[interface(name = "org.freedesktop.NetworkManager.Connection.Active")]
+impl ActiveConnection {
+    #[zbus(property)]
+    fn id(&self) -> String {
+        self.read(|ac| ac.id.clone())
+    }
+
+    #[zbus(property)]
+    fn uuid(&self) -> String {
+        self.read(|ac| ac.uuid.clone())
+    }
+
+    #[zbus(property, name = "Type")]
+    fn kind(&self) -> String {
+        self.read(|ac| ac.kind.clone())
+    }
+
+    #[zbus(property)]
+    fn state(&self) -> u32 {
+        self.read(|ac| ac.state)
+    }
+
+    #[zbus(property)]
+    fn connection(&self) -> OwnedObjectPath {
+        object_path(&self.read(|ac| ac.connection.clone()))
+    }
+
+    #[zbus(property)]
+    fn devices(&self) -> Vec<OwnedObjectPath> {
+        self.read(|ac| ac.device.iter().map(|d| object_path(d)).collect())
+    }
+
+    #[zbus(property)]
+    fn vpn(&self) -> bool {
+        self.read(|ac| ac.kind == "vpn")
+    }
+
+    #[zbus(property)]
+    fn specific_object(&self) -> OwnedObjectPath {
+        object_path("/")
+    }
+
+    #[zbus(property)]
+    fn ip4_config(&self) -> OwnedObjectPath {
+        object_path("/")
+    }
+}
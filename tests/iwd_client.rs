@@ -0,0 +1,174 @@
+//! `IwdClient` against the fake iwd in `common::iwd`.
+
+mod common;
+
+use std::time::Duration;
+
+use common::iwd::MockIwd;
+use tokio::sync::mpsc::unbounded_channel;
+use wlctl::backend::{self, Backend, BackendKind};
+use wlctl::iwd::IwdClient;
+use wlctl::nm::{ActivationFailureReason, ActivationOutcome, DeviceState, SecurityType};
+
+/// Starts the fake.
+macro_rules! fake_iwd {
+    () => {{
+        let iwd = MockIwd::start().await;
+        let client = IwdClient::new().await.expect("connect to the fake");
+        (iwd, client)
+    }};
+}
+
+#[tokio::test]
+async fn maps_the_station_onto_devices_and_access_points() {
+    let (iwd, client) = fake_iwd!();
+    iwd.add_network("cafe", "open", -7000, None).await;
+    iwd.add_network("home", "psk", -5500, Some("hunter22"))
+        .await;
+    iwd.add_known("home").await;
+    let device = iwd.device();
+
+    assert_eq!(client.get_wifi_devices().await.unwrap(), [device]);
+    assert_eq!(client.get_device_interface(device).await.unwrap(), "wlan0");
+    assert_eq!(
+        client.get_device_state(device).await.unwrap(),
+        DeviceState::Disconnected
+    );
+
+    let snapshot = client.snapshot().await.unwrap();
+    let networks = snapshot.visible_networks(device);
+    let names: Vec<&str> = networks.iter().map(|n| n.ssid.as_str()).collect();
+    assert_eq!(names, ["home", "cafe"]);
+    assert_eq!(networks[0].security, SecurityType::WPA2);
+    assert_eq!(networks[0].strength, 90);
+
+    let known = client.wifi_connections(&*snapshot).await.unwrap();
+    assert_eq!(known.len(), 1);
+    assert_eq!(known[0].ssid, "home");
+
+    client.request_scan(device).await.unwrap();
+    assert_eq!(iwd.scans(), 1);
+
+    client.set_wireless_enabled(false).await.unwrap();
+    assert!(!iwd.powered());
+}
+
+/// iwd will not take a passphrase as an argument; it asks the agent, which
+/// answers with the one the connect was given.
+#[tokio::test]
+async fn new_network_gets_its_passphrase_through_the_agent() {
+    let (iwd, client) = fake_iwd!();
+    let network = iwd
+        .add_network("home", "psk", -5500, Some("hunter22"))
+        .await;
+    let device = iwd.device();
+
+    let active = client
+        .add_and_activate_connection(device, &network, Some("hunter22"))
+        .await
+        .unwrap();
+    assert_eq!(
+        client.await_activation(&active, device).await.unwrap(),
+        ActivationOutcome::Activated
+    );
+    assert_eq!(iwd.connected().as_deref(), Some(network.as_str()));
+
+    let snapshot = client.snapshot().await.unwrap();
+    assert_eq!(
+        snapshot.active_access_point(device).map(|ap| ap.ssid),
+        Some("home".to_string())
+    );
+    let actives = snapshot.active_connections();
+    assert_eq!(actives.len(), 1);
+    assert_eq!(actives[0].connection_path, iwd.known()[0].path);
+}
+
+#[tokio::test]
+async fn wrong_passphrase_reads_as_bad_secrets() {
+    let (iwd, client) = fake_iwd!();
+    let network = iwd
+        .add_network("home", "psk", -5500, Some("hunter22"))
+        .await;
+    let device = iwd.device();
+
+    let active = client
+        .add_and_activate_connection(device, &network, Some("wrong-one"))
+        .await
+        .unwrap();
+    assert_eq!(
+        client.await_activation(&active, device).await.unwrap(),
+        ActivationOutcome::Failed(ActivationFailureReason::BadSecrets)
+    );
+    assert!(iwd.known().is_empty());
+}
+
+#[tokio::test]
+async fn known_network_connects_toggles_autoconnect_and_forgets() {
+    let (iwd, client) = fake_iwd!();
+    iwd.add_network("home", "psk", -5500, Some("hunter22"))
+        .await;
+    let known = iwd.add_known("home").await;
+    let device = iwd.device();
+
+    let active = client.activate_connection(&known, device).await.unwrap();
+    assert_eq!(
+        client.await_activation(&active, device).await.unwrap(),
+        ActivationOutcome::Activated
+    );
+
+    client
+        .set_connection_autoconnect(&known, false)
+        .await
+        .unwrap();
+    assert!(!iwd.known()[0].autoconnect);
+
+    client.disconnect_device(device).await.unwrap();
+    assert_eq!(iwd.connected(), None);
+
+    client.delete_connection(&known).await.unwrap();
+    assert!(iwd.known().is_empty());
+    let snapshot = client.snapshot().await.unwrap();
+    assert!(
+        client
+            .wifi_connections(&*snapshot)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[tokio::test]
+async fn watcher_rereads_after_a_change() {
+    let (iwd, client) = fake_iwd!();
+    let device = iwd.device();
+
+    let (tx, mut rx) = unbounded_channel();
+    let watcher = client
+        .watch(Box::new(move || {
+            let _ = tx.send(());
+        }))
+        .await
+        .unwrap();
+    assert_eq!(
+        watcher.snapshot().await.device_state(device),
+        Some(DeviceState::Disconnected)
+    );
+
+    iwd.set_station_state("connecting").await;
+    tokio::time::timeout(Duration::from_secs(5), rx.recv())
+        .await
+        .expect("notified of the change");
+    assert_eq!(
+        watcher.snapshot().await.device_state(device),
+        Some(DeviceState::Prepare)
+    );
+}
+
+#[tokio::test]
+async fn auto_picks_iwd_when_networkmanager_is_absent() {
+    let iwd = MockIwd::start().await;
+
+    let client = backend::connect(BackendKind::Auto).await.unwrap();
+    assert_eq!(client.get_wifi_devices().await.unwrap(), [iwd.device()]);
+    assert!(format!("{client:?}").starts_with("IwdClient"));
+}
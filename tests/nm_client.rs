@@ -0,0 +1,437 @@
+//! `NMClient` against the fake NetworkManager in `common::nm`.
+
+mod common;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use common::nm::{AccessPoint, Activation, MockNm, Security, openvpn_profile, state, wifi_profile};
+use tokio::sync::mpsc::{UnboundedReceiver, unbounded_channel};
+use wlctl::agent::AuthAgent;
+use wlctl::backend::Snapshot;
+use wlctl::event::Event;
+use wlctl::mode::station::known_network::KnownNetwork;
+use wlctl::nm::{
+    ActivationFailureReason, ActivationOutcome, NMClient, NmSnapshot, NmWatcher, SecurityType,
+    WgRouteTable,
+};
+
+/// Starts the fake with one WiFi device.
+macro_rules! fake_nm {
+    () => {{
+        let nm = MockNm::start().await;
+        let device = nm.add_wifi_device("wlan0").await;
+        let client = NMClient::new().await.expect("connect to the fake");
+        (nm, device, client)
+    }};
+}
+
+fn messages(rx: &mut UnboundedReceiver<Event>) -> Vec<String> {
+    let mut out = Vec::new();
+    while let Ok(event) = rx.try_recv() {
+        if let Event::Notification(n) = event {
+            out.push(n.message);
+        }
+    }
+    out
+}
+
+#[tokio::test]
+async fn finds_the_wifi_device_and_requests_scans() {
+    let (nm, device, client) = fake_nm!();
+
+    assert_eq!(client.get_wifi_device().await.unwrap().as_str(), device);
+    assert_eq!(client.get_device_interface(&device).await.unwrap(), "wlan0");
+    assert!(client.is_wireless_enabled().await.unwrap());
+
+    client.request_scan(&device).await.unwrap();
+    assert_eq!(nm.scans(), 1);
+
+    client.set_wireless_enabled(false).await.unwrap();
+    assert!(!nm.wireless_enabled());
+}
+
+#[tokio::test]
+async fn snapshot_sees_access_points_and_profiles() {
+    let (nm, device, client) = fake_nm!();
+    nm.add_access_point(&device, AccessPoint::new("cafe", 40, Security::Open))
+        .await;
+    nm.add_access_point(
+        &device,
+        AccessPoint::new("home", 80, Security::Wpa2).frequency(5180),
+    )
+    .await;
+    nm.add_access_point(
+        &device,
+        AccessPoint::new("office", 60, Security::Enterprise),
+    )
+    .await;
+    nm.add_profile(wifi_profile("home", "home", Some("hunter22")))
+        .await;
+
+    let snapshot = NmSnapshot::fetch(&client).await.unwrap();
+    let aps = snapshot.access_points(&device);
+    let find = |ssid: &str| aps.iter().find(|ap| ap.ssid == ssid).unwrap();
+    assert_eq!(aps.len(), 3);
+    assert_eq!(find("cafe").security, SecurityType::Open);
+    assert_eq!(find("home").security, SecurityType::WPA2);
+    assert_eq!(find("home").frequency, 5180);
+    assert_eq!(find("office").security, SecurityType::Enterprise);
+
+    let profiles = client.wifi_connections(&snapshot).await.unwrap();
+    assert_eq!(profiles.len(), 1);
+    assert_eq!(profiles[0].ssid, "home");
+    assert!(profiles[0].autoconnect);
+
+    // Secrets only come out of GetSecrets.
+    let settings = client
+        .get_connection_settings(&profiles[0].path)
+        .await
+        .unwrap();
+    assert!(!settings["802-11-wireless-security"].contains_key("psk"));
+    assert_eq!(
+        client.get_wifi_psk(&profiles[0].path).await.unwrap(),
+        Some("hunter22".to_string())
+    );
+}
+
+#[tokio::test]
+async fn a_held_snapshot_does_not_hold_back_changes() {
+    let (nm, device, client) = fake_nm!();
+    let (tx, mut changed) = unbounded_channel();
+    let watcher = NmWatcher::spawn(&client, move || {
+        let _ = tx.send(());
+    })
+    .await
+    .unwrap();
+
+    // Kept across a burst of more signals than a zbus stream queues.
+    let held = watcher.snapshot();
+    for n in 0..80 {
+        nm.add_access_point(
+            &device,
+            AccessPoint::new(&format!("ap{n}"), 50, Security::Open),
+        )
+        .await;
+    }
+    tokio::time::timeout(std::time::Duration::from_secs(5), async {
+        while watcher.snapshot().access_points(&device).len() < 80 {
+            changed.recv().await.expect("watcher alive");
+        }
+    })
+    .await
+    .expect("the changes arrived while a snapshot was held");
+    assert!(held.access_points(&device).is_empty());
+}
+
+#[tokio::test]
+async fn connecting_to_a_new_network_activates_it() {
+    let (nm, device, client) = fake_nm!();
+    let ap = nm
+        .add_access_point(&device, AccessPoint::new("home", 80, Security::Wpa2))
+        .await;
+
+    let active = client
+        .add_and_activate_connection(&device, &ap, Some("hunter22"))
+        .await
+        .unwrap();
+    let outcome = client.await_activation(&active, &device).await.unwrap();
+
+    assert_eq!(outcome, ActivationOutcome::Activated);
+    assert_eq!(nm.device_state(&device), state::ACTIVATED);
+    let snapshot = NmSnapshot::fetch(&client).await.unwrap();
+    assert_eq!(
+        snapshot.active_access_point(&device).map(|ap| ap.ssid),
+        Some("home".to_string())
+    );
+    // The new profile kept the password it was given.
+    let profile = nm.profiles().pop().unwrap();
+    assert_eq!(
+        client.get_wifi_psk(&profile).await.unwrap().as_deref(),
+        Some("hunter22")
+    );
+}
+
+#[tokio::test]
+async fn activation_failures_carry_the_device_reason() {
+    let (nm, device, client) = fake_nm!();
+    nm.add_access_point(&device, AccessPoint::new("home", 80, Security::Wpa2))
+        .await;
+    let profile = nm
+        .add_profile(wifi_profile("home", "home", Some("wrong")))
+        .await;
+
+    for (reason, expected) in [
+        (7, ActivationFailureReason::BadSecrets),
+        (53, ActivationFailureReason::SsidNotFound),
+        (36, ActivationFailureReason::Other(36)),
+    ] {
+        nm.set_activation(Activation::Fail(reason));
+        let active = client.activate_connection(&profile, &device).await.unwrap();
+        let outcome = client.await_activation(&active, &device).await.unwrap();
+
+        assert_eq!(
+            outcome,
+            ActivationOutcome::Failed(expected),
+            "reason {reason}"
+        );
+        assert_eq!(nm.device_state(&device), state::DISCONNECTED);
+        assert!(nm.actives().is_empty());
+    }
+}
+
+#[tokio::test]
+async fn forgetting_and_autoconnect_edit_the_profile() {
+    let (nm, _device, client) = fake_nm!();
+    let client = Arc::new(client);
+    let path = nm
+        .add_profile(wifi_profile("home", "home", Some("hunter22")))
+        .await;
+    let info = client
+        .get_wifi_connections()
+        .await
+        .unwrap()
+        .first()
+        .cloned()
+        .unwrap();
+    let mut known = KnownNetwork::from_connection_info(client.clone(), info);
+    let (tx, mut rx) = unbounded_channel();
+
+    known.toggle_autoconnect(tx.clone()).await.unwrap();
+    assert!(!known.is_autoconnect);
+    let autoconnect = nm.profile(&path).unwrap()["connection"]["autoconnect"]
+        .try_clone()
+        .unwrap();
+    assert!(!bool::try_from(autoconnect).unwrap());
+    // The edit is visible through a fresh snapshot despite the profile cache.
+    assert!(!client.get_wifi_connections().await.unwrap()[0].autoconnect);
+
+    known.forget(tx).await.unwrap();
+    assert!(nm.profiles().is_empty());
+    assert!(client.get_wifi_connections().await.unwrap().is_empty());
+    assert_eq!(
+        messages(&mut rx),
+        [
+            "Disable Autoconnect for: home",
+            "The Network home is removed"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn hotspot_starts_and_stops() {
+    let (nm, device, client) = fake_nm!();
+
+    let active = client
+        .create_hotspot(&device, "wlctl-ap", "password123")
+        .await
+        .unwrap();
+    assert_eq!(
+        client
+            .await_activation(active.as_str(), &device)
+            .await
+            .unwrap(),
+        ActivationOutcome::Activated
+    );
+    let profile = nm.profile(&nm.actives()[0].connection).unwrap();
+    let mode = profile["802-11-wireless"]["mode"].try_clone().unwrap();
+    assert_eq!(String::try_from(mode).unwrap(), "ap");
+
+    client.deactivate_connection(active.as_str()).await.unwrap();
+    assert!(nm.actives().is_empty());
+    assert_eq!(nm.device_state(&device), state::DISCONNECTED);
+}
+
+#[tokio::test]
+async fn a_site_to_site_import_keeps_every_peer() {
+    let (nm, _device, client) = fake_nm!();
+    let conf = "\
+[Interface]
+PrivateKey = yAnz5TF+lXXJte14tji3zlMNq+hd2rYUIgJBgB3fBmk=
+Address = 10.0.0.1/24
+DNS = 10.0.0.53, corp.example
+ListenPort = 51820
+MTU = 1380
+Table = 100
+PostUp = sysctl -w net.ipv4.ip_forward=1
+
+[Peer]
+PublicKey = xTIBA5rboUvnH4htodjb6e697QjLERt1NAB4mZqp8Dg=
+Endpoint = branch-a.example.com:51820
+AllowedIPs = 10.0.0.2/32, 192.168.10.0/24
+
+[Peer]
+PublicKey = TrMvSoP4jYQlY6RIzBgbssQqY3vxI2Pi+y71lOWWXX0=
+AllowedIPs = 10.0.0.3/32, 192.168.20.0/24
+PersistentKeepalive = 25
+";
+
+    let imported = wlctl::vpn::import_from_text(&client, conf).await.unwrap();
+    assert_eq!(imported.id, "wg-branch-a");
+    assert_eq!(imported.warnings.len(), 1);
+    assert!(imported.warnings[0].contains("PostUp"));
+
+    let profile = nm.profile(&nm.profiles()[0]).unwrap();
+    let wireguard = &profile["wireguard"];
+    let number = |value: &zbus::zvariant::OwnedValue| u32::try_from(value.try_clone().unwrap());
+    assert_eq!(number(&wireguard["listen-port"]).unwrap(), 51820);
+    assert_eq!(number(&wireguard["mtu"]).unwrap(), 1380);
+    assert_eq!(number(&profile["ipv4"]["route-table"]).unwrap(), 100);
+
+    let peers = <Vec<HashMap<String, zbus::zvariant::OwnedValue>>>::try_from(
+        wireguard["peers"].try_clone().unwrap(),
+    )
+    .unwrap();
+    assert_eq!(peers.len(), 2);
+    let keys: Vec<String> = peers
+        .iter()
+        .map(|peer| String::try_from(peer["public-key"].try_clone().unwrap()).unwrap())
+        .collect();
+    assert_eq!(
+        keys,
+        [
+            "xTIBA5rboUvnH4htodjb6e697QjLERt1NAB4mZqp8Dg=",
+            "TrMvSoP4jYQlY6RIzBgbssQqY3vxI2Pi+y71lOWWXX0="
+        ]
+    );
+    assert!(!peers[1].contains_key("endpoint"));
+    assert_eq!(number(&peers[1]["persistent-keepalive"]).unwrap(), 25);
+
+    // Read back for export, the private key comes from the secrets.
+    let cfg = client
+        .get_wireguard_config(&nm.profiles()[0])
+        .await
+        .unwrap();
+    assert_eq!(
+        cfg.private_key.as_str(),
+        "yAnz5TF+lXXJte14tji3zlMNq+hd2rYUIgJBgB3fBmk="
+    );
+    assert_eq!(cfg.dns_search, ["corp.example"]);
+    assert_eq!(cfg.listen_port, Some(51820));
+    assert_eq!(cfg.table, WgRouteTable::Id(100));
+    assert_eq!(cfg.peers.len(), 2);
+    assert_eq!(cfg.peers[1].allowed_ips, ["10.0.0.3/32", "192.168.20.0/24"]);
+}
+
+/// Whether `reply` is the agent error `name`.
+fn agent_error<T>(reply: zbus::Result<T>, name: &str) -> bool {
+    matches!(
+        reply,
+        Err(zbus::Error::MethodError(error, _, _))
+            if error.as_str() == format!("org.freedesktop.NetworkManager.SecretAgent.{name}")
+    )
+}
+
+#[tokio::test]
+async fn vpn_secrets_are_asked_for_through_the_password_prompt() {
+    let (nm, _device, client) = fake_nm!();
+    let (tx, mut rx) = unbounded_channel();
+    client
+        .register_secret_agent(AuthAgent::new(tx))
+        .await
+        .unwrap();
+    let profile = openvpn_profile("work", "alice");
+    let path = "/org/freedesktop/NetworkManager/Settings/1";
+
+    // The password and a one-time code the server asked for, each typed
+    // into its own prompt.
+    let hints = [
+        "password",
+        "x-vpn-message:Enter your token code",
+        "challenge-response",
+    ];
+    let (reply, ()) = tokio::join!(nm.get_secrets(&profile, path, "vpn", &hints, 1), async {
+        for (expected, answer) in [("Password", "hunter2"), ("One-time code", "123456")] {
+            match rx.recv().await {
+                Some(Event::AuthRequestPassword {
+                    network_name,
+                    user_name,
+                    message,
+                    label,
+                    reply,
+                }) => {
+                    assert_eq!(network_name, "work");
+                    assert_eq!(user_name.as_deref(), Some("alice"));
+                    assert_eq!(message.as_deref(), Some("Enter your token code"));
+                    assert_eq!(label, expected);
+                    reply.send(answer.into());
+                }
+                other => panic!("expected a password prompt, got {other:?}"),
+            }
+        }
+    });
+    let secrets =
+        HashMap::<String, String>::try_from(reply.unwrap()["vpn"]["secrets"].try_clone().unwrap())
+            .unwrap();
+    assert_eq!(
+        secrets,
+        HashMap::from([
+            ("password".into(), "hunter2".into()),
+            ("challenge-response".into(), "123456".into())
+        ])
+    );
+
+    // NetworkManager gives up on one profile before the user answers: its
+    // prompt closes, and the request queued behind it is asked next.
+    let other = "/org/freedesktop/NetworkManager/Settings/2";
+    let (first, second, ()) = tokio::join!(
+        nm.get_secrets(&profile, path, "vpn", &[], 1),
+        async {
+            // Sent once the first prompt is up, so it queues behind it.
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            nm.get_secrets(&openvpn_profile("home", "bob"), other, "vpn", &[], 1)
+                .await
+        },
+        async {
+            // Kept open, as the prompt on screen would be.
+            let shown = rx.recv().await;
+            assert!(matches!(shown, Some(Event::AuthRequestPassword { .. })));
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            nm.cancel_get_secrets(path, "vpn").await.unwrap();
+            assert!(matches!(rx.recv().await, Some(Event::AuthRequestWithdrawn)));
+            match rx.recv().await {
+                Some(Event::AuthRequestPassword {
+                    network_name,
+                    reply,
+                    ..
+                }) => {
+                    assert_eq!(network_name, "home");
+                    reply.send("s3cret".into());
+                }
+                other => panic!("expected the queued prompt, got {other:?}"),
+            }
+        }
+    );
+    assert!(agent_error(first, "UserCanceled"));
+    assert!(second.is_ok());
+
+    // WiFi secrets, and requests that may not prompt, are left to others.
+    let wifi = wifi_profile("home", "home", None);
+    let reply = nm
+        .get_secrets(&wifi, path, "802-11-wireless-security", &[], 1)
+        .await;
+    assert!(agent_error(reply, "NoSecrets"));
+    assert!(agent_error(
+        nm.get_secrets(&profile, path, "vpn", &[], 0).await,
+        "NoSecrets"
+    ));
+    // OpenConnect logins need a web form: turned away, and the user told.
+    let mut openconnect = openvpn_profile("corp", "alice");
+    openconnect.get_mut("vpn").unwrap().insert(
+        "service-type".into(),
+        zbus::zvariant::OwnedValue::try_from(zbus::zvariant::Value::from(
+            "org.freedesktop.NetworkManager.openconnect",
+        ))
+        .unwrap(),
+    );
+    assert!(agent_error(
+        nm.get_secrets(&openconnect, other, "vpn", &[], 1).await,
+        "NoSecrets"
+    ));
+    match rx.recv().await {
+        Some(Event::Notification(notification)) => {
+            assert!(notification.message.contains("OpenConnect"));
+        }
+        other => panic!("expected a notification, got {other:?}"),
+    }
+}
@@ -0,0 +1,179 @@
+//! `wlctl record` against the fake NetworkManager in `common::nm`, played back
+//! into the TUI's `App` with no bus at all.
+
+mod common;
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use common::nm::{AccessPoint, MockNm, Security, wifi_profile};
+use tokio::sync::mpsc::{UnboundedReceiver, unbounded_channel};
+use tokio::sync::oneshot;
+use wlctl::app::App;
+use wlctl::backend::Backend;
+use wlctl::config::Config;
+use wlctl::event::Event;
+use wlctl::nm::{Mode, NMClient};
+use wlctl::replay::{self, Recording, ReplayBackend};
+
+/// Long enough for any signal round trip on a loaded machine.
+const WAIT: Duration = Duration::from_secs(5);
+
+/// Removes the recording when the test ends, however it ends.
+struct TempFile(PathBuf);
+
+impl TempFile {
+    fn new(name: &str) -> Self {
+        Self(std::env::temp_dir().join(format!("wlctl-test-{}-{}.jsonl", name, std::process::id())))
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// Ticks on every change the watcher reports until `done` holds, as the main
+/// loop would.
+async fn tick_until(app: &mut App, rx: &mut UnboundedReceiver<Event>, done: impl Fn(&App) -> bool) {
+    tokio::time::timeout(WAIT, async {
+        while !done(app) {
+            loop {
+                if let Event::NmChanged = rx.recv().await.expect("sender alive") {
+                    break;
+                }
+            }
+            app.tick().await.unwrap();
+        }
+    })
+    .await
+    .expect("the replay got there in time");
+}
+
+fn network_names(networks: &[(wlctl::mode::station::network::Network, i16)]) -> Vec<&str> {
+    networks.iter().map(|(n, _)| n.name.as_str()).collect()
+}
+
+fn new_networks(app: &App) -> Vec<&str> {
+    network_names(&app.device.station.as_ref().expect("station").new_networks)
+}
+
+fn known_networks(app: &App) -> Vec<&str> {
+    network_names(&app.device.station.as_ref().expect("station").known_networks)
+}
+
+#[tokio::test]
+async fn a_recording_replays_into_the_app_in_order() {
+    let nm = MockNm::start().await;
+    let device = nm.add_wifi_device("wlan0").await;
+    nm.add_access_point(&device, AccessPoint::new("cafe", 50, Security::Open))
+        .await;
+    let client = NMClient::new().await.expect("connect to the fake");
+    let file = TempFile::new("replay");
+
+    // Follow the changes alongside the recorder, on the same connection, to
+    // know when it has seen them too.
+    let (tx, mut seen) = unbounded_channel();
+    let watcher = Backend::watch(
+        &client,
+        Box::new(move || {
+            let _ = tx.send(());
+        }),
+    )
+    .await
+    .unwrap();
+
+    let (stop, stopped) = oneshot::channel::<()>();
+    let recorder = tokio::spawn({
+        let client = client.clone();
+        let path = file.0.clone();
+        async move {
+            replay::record(&client, &path, async {
+                let _ = stopped.await;
+            })
+            .await
+        }
+    });
+    // Let the recorder write its starting state (header, graph, profiles)
+    // before anything changes.
+    tokio::time::timeout(WAIT, async {
+        while std::fs::read_to_string(&file.0).map_or(0, |text| text.lines().count()) < 3 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("the recorder started");
+
+    nm.add_access_point(&device, AccessPoint::new("home", 80, Security::Wpa2))
+        .await;
+    nm.add_profile(wifi_profile("home", "home", Some("hunter22")))
+        .await;
+    tokio::time::timeout(WAIT, async {
+        loop {
+            let snapshot = watcher.snapshot().await;
+            if snapshot.visible_networks(&device).len() == 2
+                && snapshot.profile_versions().len() == 1
+            {
+                break;
+            }
+            drop(snapshot);
+            seen.recv().await;
+        }
+    })
+    .await
+    .expect("the changes arrived");
+
+    let _ = stop.send(());
+    let changes = recorder.await.unwrap().unwrap();
+    assert!(changes > 0);
+    drop(nm);
+
+    let text = std::fs::read_to_string(&file.0).unwrap();
+    assert!(!text.contains("hunter22"));
+
+    let backend = Arc::new(ReplayBackend::new(Recording::load(&file.0).unwrap()));
+    let (tx, mut rx) = unbounded_channel();
+    let mut app = App::with_backend(
+        tx,
+        backend.clone(),
+        Arc::new(Config::default()),
+        Mode::Station,
+    )
+    .await
+    .unwrap();
+
+    // The replay starts where the recording did, then catches up.
+    assert_eq!(app.device.name, "wlan0");
+    assert_eq!(new_networks(&app), ["cafe"]);
+    assert!(known_networks(&app).is_empty());
+
+    tick_until(&mut app, &mut rx, |app| known_networks(app) == ["home"]).await;
+    assert_eq!(new_networks(&app), ["cafe"]);
+
+    // Nothing in a recording can be changed.
+    let err = backend.request_scan(&device).await.unwrap_err();
+    assert!(err.to_string().contains("replayed recording"));
+}
+
+#[tokio::test]
+async fn record_stops_with_the_file_readable() {
+    let nm = MockNm::start().await;
+    nm.add_wifi_device("wlan0").await;
+    let client = NMClient::new().await.expect("connect to the fake");
+    let file = TempFile::new("empty");
+
+    let changes = replay::record(&client, &file.0, std::future::ready(()))
+        .await
+        .unwrap();
+    assert_eq!(changes, 0);
+
+    let backend = ReplayBackend::new(Recording::load(&file.0).unwrap());
+    let devices = backend.get_wifi_devices().await.unwrap();
+    assert_eq!(devices.len(), 1);
+    assert_eq!(
+        backend.get_device_interface(&devices[0]).await.unwrap(),
+        "wlan0"
+    );
+}